@@ -0,0 +1,223 @@
+use crate::color_management::is_inverted_decode;
+use crate::decode_raw_image::{decode_image, decode_raw_samples, image_data, RawSamples};
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GrayImage, Luma, RgbaImage};
+use pdf::enc::StreamFilter;
+use pdf::error::PdfError;
+use pdf::object::{ImageDict, ImageXObject, Ref, Resolve, Stream};
+use pdf::primitive::Primitive;
+
+///画像XObjectがSMaskもしくはMaskを持っていればtrueを返す。
+pub(crate) fn has_image_mask(img: &ImageXObject) -> bool {
+    img.smask.is_some() || img.mask.is_some()
+}
+
+///画像XObjectのSMask(ソフトマスク)もしくはMask(ステンシルマスク・カラーキーマスク)を適用し、
+///アルファチャンネル付きの画像を作成する。
+/// # Arguments
+/// * `img` - 画像XObject。
+/// * `base` - 展開済みの画像XObjectの画像。
+/// * `data` - `raw_image_data()`で取得した画像データ(カラーキーマスクの判定に使用する)。
+/// * `filter` - `raw_image_data()`で取得した未適用のフィルタ。
+/// * `resolve` - マスク画像の参照の解決に使用するリゾルバ。
+/// # Returns
+/// * 成功時:RGBAの画像。(マスクを持たない、もしくは展開できない形式のマスクの場合はNone。)
+/// * 失敗時:PdfError(マスク画像の取得もしくは展開失敗)。
+pub(crate) fn apply_image_mask(
+    img: &ImageXObject,
+    base: &DynamicImage,
+    data: &[u8],
+    filter: Option<&StreamFilter>,
+    resolve: &impl Resolve,
+) -> Result<Option<RgbaImage>, PdfError> {
+    //SMaskが指定されている場合はMaskを無視する。
+    let (alpha, filter_type): (GrayImage, FilterType) = if let Some(smask) = img.smask {
+        match get_soft_mask(smask, resolve)? {
+            Some(alpha) => (alpha, FilterType::Triangle),
+            None => return Ok(None),
+        }
+    } else {
+        match img.mask {
+            Some(Primitive::Reference(r)) => {
+                (get_stencil_mask(Ref::new(r), resolve)?, FilterType::Nearest)
+            }
+            Some(Primitive::Array(ref ranges)) => {
+                //カラーキーマスクは展開前のサンプル値で判定するため、JPEGなどの非可逆圧縮の画像には適用しない。
+                if !matches!(filter, None | Some(StreamFilter::FlateDecode(_))) {
+                    return Ok(None);
+                }
+                let ranges: Vec<u32> = ranges
+                    .iter()
+                    .map(|range| match range {
+                        Primitive::Integer(value) => Ok((*value).max(0) as u32),
+                        _ => Err(PdfError::Other {
+                            msg: format!("INVALID COLOR KEY MASK. MASK: {:?}", ranges),
+                        }),
+                    })
+                    .collect::<Result<Vec<u32>, PdfError>>()?;
+                let samples: RawSamples = decode_raw_samples(img, data, filter)?;
+                match color_key_alpha(&samples, &ranges, img.width, img.height) {
+                    Some(alpha) => (alpha, FilterType::Nearest),
+                    None => {
+                        return Err(PdfError::Other {
+                            msg: format!(
+                            "COLOR KEY MASK DOES NOT MATCH COMPONENTS. MASK: {:?} COMPONENTS: {}",
+                            ranges, samples.components
+                        ),
+                        })
+                    }
+                }
+            }
+            _ => return Ok(None),
+        }
+    };
+    Ok(Some(combine_alpha(base, &alpha, filter_type)))
+}
+
+///SMaskの画像を取得し、アルファ値の画像に変換する。
+/// # Returns
+/// * 成功時:アルファ値の画像。(JPXDecodeのSMaskは展開できないためNone。)
+/// * 失敗時:PdfError。
+fn get_soft_mask(
+    smask: Ref<Stream<ImageDict>>,
+    resolve: &impl Resolve,
+) -> Result<Option<GrayImage>, PdfError> {
+    let mask: ImageXObject = ImageXObject {
+        inner: (*resolve.get(smask)?).clone(),
+    };
+    let (mask_data, mask_filter) = image_data(&mask, resolve)?;
+    let mut alpha: GrayImage = match decode_image(&mask, &mask_data, mask_filter)? {
+        Some(image) => image.to_luma8(),
+        None => return Ok(None),
+    };
+    if is_inverted_decode(mask.decode.as_deref()) {
+        imageops::invert(&mut alpha);
+    }
+    Ok(Some(alpha))
+}
+
+///Maskに指定されたステンシルマスクの画像を取得し、アルファ値の画像に変換する。
+/// # Returns
+/// * 成功時:アルファ値の画像。
+/// * 失敗時:PdfError。
+fn get_stencil_mask(
+    mask: Ref<Stream<ImageDict>>,
+    resolve: &impl Resolve,
+) -> Result<GrayImage, PdfError> {
+    let mask: ImageXObject = ImageXObject {
+        inner: (*resolve.get(mask)?).clone(),
+    };
+    let (mask_data, mask_filter) = image_data(&mask, resolve)?;
+    let samples: RawSamples = decode_raw_samples(&mask, &mask_data, mask_filter)?;
+    Ok(stencil_alpha(
+        &samples.values,
+        mask.width,
+        mask.height,
+        is_inverted_decode(mask.decode.as_deref()),
+    ))
+}
+
+///ステンシルマスクのサンプル値からアルファ値の画像を作成する。
+///(Decode配列が[0 1]の場合はサンプル値1、[1 0]の場合はサンプル値0の部分が透明になる。)
+fn stencil_alpha(values: &[u16], width: u32, height: u32, inverted: bool) -> GrayImage {
+    let masked_value: u16 = if inverted { 0 } else { 1 };
+    GrayImage::from_fn(width, height, |x, y| {
+        let index: usize = y as usize * width as usize + x as usize;
+        match values.get(index) {
+            Some(value) if *value == masked_value => Luma([0]),
+            _ => Luma([255]),
+        }
+    })
+}
+
+///カラーキーマスクの範囲からアルファ値の画像を作成する。
+///(全ての色成分が範囲内に含まれるピクセルが透明になる。)
+/// # Returns
+/// * 成功時:アルファ値の画像。
+/// * 失敗時:None(範囲の数が色成分数の2倍ではない)。
+fn color_key_alpha(
+    samples: &RawSamples,
+    ranges: &[u32],
+    width: u32,
+    height: u32,
+) -> Option<GrayImage> {
+    let components: usize = samples.components as usize;
+    if ranges.len() != components * 2 {
+        return None;
+    }
+    let mut pixels = samples.values.chunks(components);
+    Some(GrayImage::from_fn(width, height, |_, _| {
+        let masked: bool = match pixels.next() {
+            Some(pixel) => pixel
+                .iter()
+                .zip(ranges.chunks(2))
+                .all(|(value, range)| (range[0]..=range[1]).contains(&(*value as u32))),
+            None => false,
+        };
+        if masked {
+            Luma([0])
+        } else {
+            Luma([255])
+        }
+    }))
+}
+
+///画像にアルファ値を合成する。(マスクの大きさが画像と異なる場合は画像の大きさに拡大・縮小する。)
+fn combine_alpha(base: &DynamicImage, alpha: &GrayImage, filter_type: FilterType) -> RgbaImage {
+    let mut rgba: RgbaImage = base.to_rgba8();
+    let resized: GrayImage;
+    let alpha: &GrayImage = if alpha.dimensions() == rgba.dimensions() {
+        alpha
+    } else {
+        resized = imageops::resize(alpha, rgba.width(), rgba.height(), filter_type);
+        &resized
+    };
+    for (pixel, a) in rgba.pixels_mut().zip(alpha.pixels()) {
+        pixel[3] = a[0];
+    }
+    rgba
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+
+    #[test]
+    fn test_stencil_alpha() {
+        let values: [u16; 4] = [0, 1, 1, 0];
+        assert_eq!(
+            stencil_alpha(&values, 2, 2, false).into_raw(),
+            vec![255, 0, 0, 255]
+        );
+        assert_eq!(
+            stencil_alpha(&values, 2, 2, true).into_raw(),
+            vec![0, 255, 255, 0]
+        );
+    }
+
+    #[test]
+    fn test_color_key_alpha() {
+        let samples = RawSamples {
+            values: vec![255, 255, 255, 10, 20, 30],
+            components: 3,
+            bits: 8,
+        };
+        let ranges: [u32; 6] = [250, 255, 250, 255, 250, 255];
+        let alpha = color_key_alpha(&samples, &ranges, 2, 1).unwrap();
+        assert_eq!(alpha.into_raw(), vec![0, 255]);
+        assert!(color_key_alpha(&samples, &ranges[..2], 2, 1).is_none());
+    }
+
+    #[test]
+    fn test_combine_alpha_resize() {
+        let base = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(4, 4, Rgb([10, 20, 30])));
+        let alpha = GrayImage::from_raw(2, 2, vec![0, 255, 255, 0]).unwrap();
+        let rgba = combine_alpha(&base, &alpha, FilterType::Nearest);
+        assert_eq!(rgba.dimensions(), (4, 4));
+        assert_eq!(rgba.get_pixel(0, 0).0, [10, 20, 30, 0]);
+        assert_eq!(rgba.get_pixel(3, 0).0, [10, 20, 30, 255]);
+        assert_eq!(rgba.get_pixel(3, 3).0, [10, 20, 30, 0]);
+    }
+}
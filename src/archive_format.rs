@@ -0,0 +1,59 @@
+use std::fmt;
+use std::str::FromStr;
+
+///画像ファイルとマニフェストをまとめて書き込むアーカイブの形式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArchiveFormat {
+    ///tar形式。(圧縮しない。)
+    #[default]
+    Tar,
+    ///zip形式。(画像データは圧縮済みのため、無圧縮で格納する。)
+    Zip,
+    ///コミック用のzip形式。(画像をページ番号の順に格納する。全ての画像をメモリ上に保持してから書き込む。)
+    Cbz,
+}
+
+impl ArchiveFormat {
+    ///アーカイブのファイルの拡張子を取得する。
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Tar => "tar",
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::Cbz => "cbz",
+        }
+    }
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "tar" => Ok(ArchiveFormat::Tar),
+            "zip" => Ok(ArchiveFormat::Zip),
+            "cbz" => Ok(ArchiveFormat::Cbz),
+            _ => Err(format!("UNSUPPORTED ARCHIVE FORMAT: {} (tar|zip|cbz)", s)),
+        }
+    }
+}
+
+impl fmt::Display for ArchiveFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_format_from_str() {
+        assert_eq!("tar".parse::<ArchiveFormat>(), Ok(ArchiveFormat::Tar));
+        assert_eq!("ZIP".parse::<ArchiveFormat>(), Ok(ArchiveFormat::Zip));
+        assert_eq!("Cbz".parse::<ArchiveFormat>(), Ok(ArchiveFormat::Cbz));
+        assert!("7z".parse::<ArchiveFormat>().is_err());
+        assert_eq!(ArchiveFormat::Zip.to_string(), "zip");
+        assert_eq!(ArchiveFormat::Cbz.extension(), "cbz");
+    }
+}
@@ -0,0 +1,279 @@
+use log::{info, log_enabled, Level};
+use pdf::content::Op;
+use pdf::error::PdfError;
+use pdf::object::{ColorSpace, ImageXObject, Page, PlainRef, RcRef, Resolve, Resources, XObject};
+use pdf::primitive::Primitive;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+///ページから収集した画像。
+#[derive(Clone)]
+pub(crate) enum PageImage {
+    ///リソースに登録された画像XObject。
+    XObject(RcRef<XObject>),
+    ///コンテンツストリーム内のインライン画像(BI ... ID ... EI)。
+    Inline(Arc<ImageXObject>),
+}
+
+impl PageImage {
+    ///画像XObjectを取得する。(画像以外のXObjectの場合はNone。)
+    pub(crate) fn image(&self) -> Option<&ImageXObject> {
+        match self {
+            PageImage::XObject(object) => match **object {
+                XObject::Image(ref image) => Some(image),
+                _ => None,
+            },
+            PageImage::Inline(image) => Some(image),
+        }
+    }
+
+    ///画像XObjectの参照を取得する。(インライン画像の場合はNone。)
+    pub(crate) fn object_ref(&self) -> Option<PlainRef> {
+        match self {
+            PageImage::XObject(object) => Some(object.get_ref().get_inner()),
+            PageImage::Inline(_) => None,
+        }
+    }
+}
+
+///ページ内の画像を収集する。
+///リソースの画像XObjectに加え、Form XObjectのリソースを再帰的に探索し、
+///ページとForm XObjectのコンテンツストリーム内のインライン画像も収集する。
+/// # Arguments
+/// * `page` - PDFファイルのページ。
+/// * `resources` - ページのリソース。
+/// * `resolve` - 間接参照の解決に使用するリゾルバ。
+/// * `images` - 収集した画像(名前と画像)の格納先。
+/// * `failures` - 取得に失敗したXObjectもしくはコンテンツストリーム(名前とエラー)の格納先。
+pub(crate) fn collect_page_images(
+    page: &Page,
+    resources: &Resources,
+    resolve: &impl Resolve,
+    images: &mut Vec<(String, PageImage)>,
+    failures: &mut Vec<(String, PdfError)>,
+) {
+    let mut visited: HashSet<PlainRef> = HashSet::new();
+    collect_xobject_images(resources, resolve, "", &mut visited, images, failures);
+
+    if let Some(ref contents) = page.contents {
+        match contents.operations(resolve) {
+            Ok(ops) => collect_inline_images(&ops, resources, "", images),
+            Err(e) => failures.push(("Contents".to_string(), e)),
+        }
+    }
+}
+
+///リソース内の画像XObjectを収集する。(Form XObjectのリソースとコンテンツストリームも再帰的に探索する。)
+/// # Arguments
+/// * `resources` - ページもしくはForm XObjectのリソース。
+/// * `resolve` - 間接参照の解決に使用するリゾルバ。
+/// * `name_prefix` - 画像名の先頭に付ける名前(Form XObjectの名前を"_"で連結したもの。ページ直下は空文字列)。
+/// * `visited` - 探索済みのXObjectの参照(循環参照と同じXObjectの重複を防ぐため)。
+/// * `images` - 収集した画像(名前と画像)の格納先。
+/// * `failures` - 取得に失敗したXObject(名前とエラー)の格納先。
+fn collect_xobject_images(
+    resources: &Resources,
+    resolve: &impl Resolve,
+    name_prefix: &str,
+    visited: &mut HashSet<PlainRef>,
+    images: &mut Vec<(String, PageImage)>,
+    failures: &mut Vec<(String, PdfError)>,
+) {
+    //出力されるファイル名が実行ごとに変わらないよう、名前順に探索する。
+    let mut entries: Vec<_> = resources.xobjects.iter().collect();
+    entries.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+
+    for (name, &r) in entries {
+        let full_name: String = join_image_name(name_prefix, name);
+        //探索済みのXObjectは無視する。
+        if !visited.insert(r.get_inner()) {
+            if log_enabled!(Level::Debug) {
+                info!(
+                    "XOBJECT ALREADY VISITED. IT IGNORED. XObject_Name: {}",
+                    full_name
+                );
+            }
+            continue;
+        }
+        let object: RcRef<XObject> = match resolve.get(r) {
+            Ok(object) => object,
+            Err(e) => {
+                failures.push((full_name, e));
+                continue;
+            }
+        };
+        match *object {
+            XObject::Image(_) => {
+                if log_enabled!(Level::Debug) {
+                    info!("XObject_Name: {}", full_name);
+                }
+                images.push((full_name, PageImage::XObject(object.clone())));
+            }
+            XObject::Form(ref form) => {
+                //リソースを持たないForm XObjectは親のリソースを使用するため、探索済みとなる。
+                let form_resources: &Resources = match form.dict().resources {
+                    Some(ref form_resources) => {
+                        collect_xobject_images(
+                            form_resources,
+                            resolve,
+                            &full_name,
+                            visited,
+                            images,
+                            failures,
+                        );
+                        form_resources
+                    }
+                    None => resources,
+                };
+                match form.operations(resolve) {
+                    Ok(ops) => collect_inline_images(&ops, form_resources, &full_name, images),
+                    Err(e) => failures.push((full_name, e)),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+///コンテンツストリームの命令列からインライン画像を収集する。
+///インライン画像の名前は"Inline"に出現順の番号を付けたものとする。(XObjectの名前と区別するため。)
+/// # Arguments
+/// * `ops` - コンテンツストリームの命令列。
+/// * `resources` - コンテンツストリームのリソース(名前付きの色空間の解決に使用する)。
+/// * `name_prefix` - 画像名の先頭に付ける名前。
+/// * `images` - 収集した画像(名前と画像)の格納先。
+fn collect_inline_images(
+    ops: &[Op],
+    resources: &Resources,
+    name_prefix: &str,
+    images: &mut Vec<(String, PageImage)>,
+) {
+    let mut inline_count: u32 = 0;
+    for op in ops {
+        if let Op::InlineImage { image } = op {
+            inline_count += 1;
+            let full_name: String =
+                join_image_name(name_prefix, &format!("Inline{}", inline_count));
+            if log_enabled!(Level::Debug) {
+                info!("INLINE IMAGE FOUND. NAME: {}", full_name);
+            }
+            images.push((
+                full_name,
+                PageImage::Inline(normalize_inline_image(image, resources)),
+            ));
+        }
+    }
+}
+
+///インライン画像の色空間を画像XObjectと同じ形式に変換する。
+///キーの省略形(W、H、BPC、CS、Fなど)はpdfクレートのパーサで展開されるため、
+///ここでは値として使用される色空間名の省略形とリソースに登録された色空間名を解決する。
+pub(crate) fn normalize_inline_image(
+    image: &Arc<ImageXObject>,
+    resources: &Resources,
+) -> Arc<ImageXObject> {
+    let color_space: ColorSpace = match image.color_space {
+        Some(ref color_space) => match resolve_inline_color_space(color_space, resources) {
+            Some(color_space) => color_space,
+            None => return Arc::clone(image),
+        },
+        None => return Arc::clone(image),
+    };
+    let mut normalized: ImageXObject = (**image).clone();
+    normalized.inner.info.info.color_space = Some(color_space);
+    Arc::new(normalized)
+}
+
+///インライン画像の色空間を解決する。
+/// # Returns
+/// * 変換が必要な場合:変換後の色空間。
+/// * 変換が不要もしくは解決できない場合:None。
+fn resolve_inline_color_space(
+    color_space: &ColorSpace,
+    resources: &Resources,
+) -> Option<ColorSpace> {
+    match color_space {
+        ColorSpace::Named(name) => match expand_inline_image_abbreviation(name.as_str()) {
+            "DeviceGray" => Some(ColorSpace::DeviceGray),
+            "DeviceRGB" => Some(ColorSpace::DeviceRGB),
+            "DeviceCMYK" => Some(ColorSpace::DeviceCMYK),
+            _ => resources.color_spaces.get(name).cloned(),
+        },
+        //[/I /RGB 255 <...>]形式のIndexed色空間。
+        ColorSpace::Other(array) => match array.as_slice() {
+            [Primitive::Name(kind), base, Primitive::Integer(hival), lookup]
+                if expand_inline_image_abbreviation(kind.as_str()) == "Indexed" =>
+            {
+                let base: ColorSpace = match base {
+                    Primitive::Name(base_name) => resolve_inline_color_space(
+                        &ColorSpace::Named(base_name.clone()),
+                        resources,
+                    )?,
+                    _ => return None,
+                };
+                let lookup: Arc<[u8]> = match lookup {
+                    Primitive::String(lookup) => lookup.as_bytes().into(),
+                    _ => return None,
+                };
+                Some(ColorSpace::Indexed(
+                    Box::new(base),
+                    (*hival).clamp(0, 255) as u8,
+                    lookup,
+                ))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+///インライン画像で使用される色空間名・フィルタ名の省略形を正式な名前に変換する。
+///(省略形でない名前はそのまま返す。)
+pub(crate) fn expand_inline_image_abbreviation(name: &str) -> &str {
+    match name {
+        "G" => "DeviceGray",
+        "RGB" => "DeviceRGB",
+        "CMYK" => "DeviceCMYK",
+        "I" => "Indexed",
+        "AHx" => "ASCIIHexDecode",
+        "A85" => "ASCII85Decode",
+        "LZW" => "LZWDecode",
+        "Fl" => "FlateDecode",
+        "RL" => "RunLengthDecode",
+        "CCF" => "CCITTFaxDecode",
+        "DCT" => "DCTDecode",
+        _ => name,
+    }
+}
+
+///親の名前と子の名前を"_"で連結する。
+fn join_image_name(name_prefix: &str, name: &str) -> String {
+    if name_prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}_{}", name_prefix, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_inline_image_abbreviation() {
+        assert_eq!(expand_inline_image_abbreviation("G"), "DeviceGray");
+        assert_eq!(expand_inline_image_abbreviation("RGB"), "DeviceRGB");
+        assert_eq!(expand_inline_image_abbreviation("CMYK"), "DeviceCMYK");
+        assert_eq!(expand_inline_image_abbreviation("I"), "Indexed");
+        assert_eq!(expand_inline_image_abbreviation("Fl"), "FlateDecode");
+        assert_eq!(expand_inline_image_abbreviation("CCF"), "CCITTFaxDecode");
+        assert_eq!(expand_inline_image_abbreviation("DeviceRGB"), "DeviceRGB");
+        assert_eq!(expand_inline_image_abbreviation("CS0"), "CS0");
+    }
+
+    #[test]
+    fn test_join_image_name() {
+        assert_eq!(join_image_name("", "Im1"), "Im1");
+        assert_eq!(join_image_name("Fm1", "Inline1"), "Fm1_Inline1");
+    }
+}
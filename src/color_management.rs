@@ -0,0 +1,388 @@
+use image::{DynamicImage, ImageBuffer, Luma, Rgb};
+use pdf::error::PdfError;
+use pdf::function::Function;
+use pdf::object::ColorSpace;
+use pdf::primitive::{Dictionary, Primitive};
+use std::sync::Arc;
+
+///画像のサンプル値をsRGBもしくはグレーに変換するための色空間。
+///(ICCBased色空間はプロファイルを適用せず、代替色空間もしくは色成分数に対応するデバイス色空間として扱う。)
+pub(crate) enum ImageColorSpace<'a> {
+    Gray,
+    Rgb,
+    Cmyk,
+    ///CIE L*a*b*色空間。(白色点はD65に合わせて変換する。)
+    Lab {
+        range: [f32; 4],
+    },
+    ///パレット(基底色空間の値の表)を参照するIndexed色空間。
+    Indexed {
+        base: Box<ImageColorSpace<'a>>,
+        hival: u8,
+        lookup: Arc<[u8]>,
+    },
+    ///Tint Transform関数で代替色空間の値に変換するSeparation・DeviceN色空間。
+    Tint {
+        components: usize,
+        alternate: Box<ImageColorSpace<'a>>,
+        function: &'a Function,
+    },
+}
+
+impl<'a> ImageColorSpace<'a> {
+    ///PDFの色空間から変換用の色空間を作成する。
+    /// # Arguments
+    /// * `color_space` - 画像XObjectの色空間。
+    /// # Returns
+    /// * 成功時:変換用の色空間。
+    /// * 失敗時:PdfError(未対応の色空間)。
+    pub(crate) fn new(color_space: &'a ColorSpace) -> Result<Self, PdfError> {
+        match color_space {
+            ColorSpace::DeviceGray | ColorSpace::CalGray(_) => Ok(ImageColorSpace::Gray),
+            ColorSpace::DeviceRGB | ColorSpace::CalRGB(_) => Ok(ImageColorSpace::Rgb),
+            ColorSpace::DeviceCMYK | ColorSpace::CalCMYK(_) => Ok(ImageColorSpace::Cmyk),
+            ColorSpace::Icc(icc) => match icc.alternate {
+                Some(ref alternate) => ImageColorSpace::new(alternate),
+                None => ImageColorSpace::from_components(icc.components as usize),
+            },
+            ColorSpace::Indexed(base, hival, lookup) => Ok(ImageColorSpace::Indexed {
+                base: Box::new(ImageColorSpace::new(base)?),
+                hival: *hival,
+                lookup: Arc::clone(lookup),
+            }),
+            ColorSpace::Separation(_, alternate, function) => Ok(ImageColorSpace::Tint {
+                components: 1,
+                alternate: Box::new(ImageColorSpace::new(alternate)?),
+                function,
+            }),
+            ColorSpace::DeviceN {
+                names, alt, tint, ..
+            } => Ok(ImageColorSpace::Tint {
+                components: names.len(),
+                alternate: Box::new(ImageColorSpace::new(alt)?),
+                function: tint,
+            }),
+            ColorSpace::Other(array) => match array.as_slice() {
+                [Primitive::Name(name), Primitive::Dictionary(dict)] if name.as_str() == "Lab" => {
+                    Ok(lab_from_dict(dict))
+                }
+                _ => Err(PdfError::Other {
+                    msg: format!("UNSUPPORTED COLOR SPACE. COLOR_SPACE: {:?}", color_space),
+                }),
+            },
+            _ => Err(PdfError::Other {
+                msg: format!("UNSUPPORTED COLOR SPACE. COLOR_SPACE: {:?}", color_space),
+            }),
+        }
+    }
+
+    ///色成分数に対応するデバイス色空間を作成する。(1:グレー、3:RGB、4:CMYK)
+    pub(crate) fn from_components(components: usize) -> Result<Self, PdfError> {
+        match components {
+            1 => Ok(ImageColorSpace::Gray),
+            3 => Ok(ImageColorSpace::Rgb),
+            4 => Ok(ImageColorSpace::Cmyk),
+            n => Err(PdfError::Other {
+                msg: format!("UNSUPPORTED COLOR COMPONENTS. N: {}", n),
+            }),
+        }
+    }
+
+    ///1ピクセルあたりの色成分数を取得する。
+    pub(crate) fn components(&self) -> usize {
+        match self {
+            ImageColorSpace::Gray | ImageColorSpace::Indexed { .. } => 1,
+            ImageColorSpace::Rgb | ImageColorSpace::Lab { .. } => 3,
+            ImageColorSpace::Cmyk => 4,
+            ImageColorSpace::Tint { components, .. } => *components,
+        }
+    }
+
+    ///変換後の画像がグレーになる色空間であればtrueを返す。
+    pub(crate) fn is_gray(&self) -> bool {
+        match self {
+            ImageColorSpace::Gray => true,
+            ImageColorSpace::Indexed { base, .. } => base.is_gray(),
+            ImageColorSpace::Tint { alternate, .. } => alternate.is_gray(),
+            _ => false,
+        }
+    }
+
+    ///Decode配列が指定されていない場合の各色成分の値の範囲を取得する。
+    /// # Arguments
+    /// * `bits` - 1成分あたりのビット数。
+    pub(crate) fn default_decode(&self, bits: u32) -> Vec<[f32; 2]> {
+        match self {
+            ImageColorSpace::Indexed { .. } => vec![[0.0, ((1u32 << bits) - 1) as f32]],
+            ImageColorSpace::Lab { range, .. } => {
+                vec![[0.0, 100.0], [range[0], range[1]], [range[2], range[3]]]
+            }
+            _ => vec![[0.0, 1.0]; self.components()],
+        }
+    }
+
+    ///色成分の値をsRGB(0.0-1.0)に変換する。(グレーになる色空間の場合は3成分とも同じ値になる。)
+    /// # Arguments
+    /// * `values` - Decode配列を適用した色成分の値。
+    /// # Returns
+    /// * 成功時:sRGBの値。
+    /// * 失敗時:PdfError(Tint Transform関数の実行失敗)。
+    pub(crate) fn to_rgb(&self, values: &[f32]) -> Result<[f32; 3], PdfError> {
+        match self {
+            ImageColorSpace::Gray => Ok([values[0]; 3]),
+            ImageColorSpace::Rgb => Ok([values[0], values[1], values[2]]),
+            ImageColorSpace::Cmyk => {
+                let k: f32 = 1.0 - values[3];
+                Ok([
+                    (1.0 - values[0]) * k,
+                    (1.0 - values[1]) * k,
+                    (1.0 - values[2]) * k,
+                ])
+            }
+            ImageColorSpace::Lab { .. } => Ok(lab_to_srgb(values[0], values[1], values[2])),
+            ImageColorSpace::Indexed {
+                base,
+                hival,
+                lookup,
+            } => {
+                //パレットの値(0-255)を基底色空間の値の範囲に変換する。
+                let index: usize = values[0].round().clamp(0.0, *hival as f32) as usize;
+                let base_components: usize = base.components();
+                let mut base_values: Vec<f32> = base
+                    .default_decode(8)
+                    .iter()
+                    .enumerate()
+                    .map(|(i, [min, max])| {
+                        let byte: u8 = lookup
+                            .get(index * base_components + i)
+                            .copied()
+                            .unwrap_or(0);
+                        min + byte as f32 * (max - min) / 255.0
+                    })
+                    .collect();
+                base_values.resize(base_components, 0.0);
+                base.to_rgb(&base_values)
+            }
+            ImageColorSpace::Tint {
+                alternate,
+                function,
+                ..
+            } => {
+                let mut alternate_values: Vec<f32> = vec![0.0; alternate.components()];
+                function.apply(values, &mut alternate_values)?;
+                alternate.to_rgb(&alternate_values)
+            }
+        }
+    }
+}
+
+///Decode配列が[1 0]のように値を反転させるものであればtrueを返す。
+pub(crate) fn is_inverted_decode(decode: Option<&[f32]>) -> bool {
+    matches!(decode, Some([min, max, ..]) if min > max)
+}
+
+///Lab色空間の辞書から変換用の色空間を作成する。
+fn lab_from_dict<'a>(dict: &Dictionary) -> ImageColorSpace<'a> {
+    let range: [f32; 4] = match get_numbers(dict, "Range").as_deref() {
+        Some([a_min, a_max, b_min, b_max]) => [*a_min, *a_max, *b_min, *b_max],
+        _ => [-100.0, 100.0, -100.0, 100.0],
+    };
+    ImageColorSpace::Lab { range }
+}
+
+///辞書から数値の配列を取得する。
+fn get_numbers(dict: &Dictionary, key: &str) -> Option<Vec<f32>> {
+    match dict.get(key) {
+        Some(Primitive::Array(array)) => array
+            .iter()
+            .map(|value| match value {
+                Primitive::Integer(value) => Some(*value as f32),
+                Primitive::Number(value) => Some(*value),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+///L*a*b*の値をsRGB(0.0-1.0)に変換する。
+fn lab_to_srgb(l: f32, a: f32, b: f32) -> [f32; 3] {
+    let fy: f32 = (l + 16.0) / 116.0;
+    let fx: f32 = fy + a / 500.0;
+    let fz: f32 = fy - b / 200.0;
+    let inverse = |t: f32| -> f32 {
+        if t > 6.0 / 29.0 {
+            t * t * t
+        } else {
+            3.0 * (6.0f32 / 29.0).powi(2) * (t - 4.0 / 29.0)
+        }
+    };
+    //白色点をD65として(白色点の違いを無視して)XYZをsRGBに変換する。
+    let x: f32 = inverse(fx) * 0.9505;
+    let y: f32 = inverse(fy);
+    let z: f32 = inverse(fz) * 1.089;
+    let linear: [f32; 3] = [
+        3.2406 * x - 1.5372 * y - 0.4986 * z,
+        -0.9689 * x + 1.8758 * y + 0.0415 * z,
+        0.0557 * x - 0.2040 * y + 1.0570 * z,
+    ];
+    linear.map(|c| {
+        let c: f32 = c.clamp(0.0, 1.0);
+        if c <= 0.0031308 {
+            12.92 * c
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        }
+    })
+}
+
+///サンプル値を色空間に従ってsRGBもしくはグレーの8bitの画像に変換する。
+/// # Arguments
+/// * `values` - 1成分ずつに分解したサンプル値。
+/// * `width` - 画像の幅。
+/// * `height` - 画像の高さ。
+/// * `bits` - 1成分あたりのビット数。
+/// * `color_space` - 画像の色空間。
+/// * `decode` - 画像XObjectのDecode配列。(Noneもしくは不正な場合は色空間の既定の範囲とする。)
+/// # Returns
+/// * 成功時:グレースケールもしくはRGBの画像。
+/// * 失敗時:PdfError(サンプル値の不足もしくはTint Transform関数の実行失敗)。
+pub(crate) fn samples_to_srgb(
+    values: &[u16],
+    width: u32,
+    height: u32,
+    bits: u32,
+    color_space: &ImageColorSpace,
+    decode: Option<&[f32]>,
+) -> Result<DynamicImage, PdfError> {
+    let components: usize = color_space.components();
+    let pixels: usize = width as usize * height as usize;
+    if values.len() < pixels * components {
+        return Err(PdfError::Other {
+            msg: format!(
+                "IMAGE DATA IS TOO SHORT. EXPECTED: {} ACTUAL: {}",
+                pixels * components,
+                values.len()
+            ),
+        });
+    }
+    let ranges: Vec<[f32; 2]> = match decode {
+        Some(decode) if decode.len() == components * 2 => {
+            decode.chunks(2).map(|range| [range[0], range[1]]).collect()
+        }
+        _ => color_space.default_decode(bits),
+    };
+    let max_value: f32 = ((1u32 << bits) - 1) as f32;
+    let convert = |pixel: &[u16]| -> Result<[u8; 3], PdfError> {
+        let decoded: Vec<f32> = pixel
+            .iter()
+            .zip(ranges.iter())
+            .map(|(value, [min, max])| min + *value as f32 * (max - min) / max_value)
+            .collect();
+        Ok(color_space
+            .to_rgb(&decoded)?
+            .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+    };
+
+    //1成分・8bit以下の画像は取り得る値が少ないため、先に全ての値を変換しておく。
+    let table: Option<Vec<[u8; 3]>> = if components == 1 && bits <= 8 {
+        Some(
+            (0..=max_value as u16)
+                .map(|value| convert(&[value]))
+                .collect::<Result<Vec<[u8; 3]>, PdfError>>()?,
+        )
+    } else {
+        None
+    };
+    let mut rgb: Vec<[u8; 3]> = Vec::with_capacity(pixels);
+    for pixel in values.chunks(components).take(pixels) {
+        match table {
+            Some(ref table) => rgb.push(table[pixel[0] as usize]),
+            None => rgb.push(convert(pixel)?),
+        }
+    }
+
+    let image: Option<DynamicImage> = if color_space.is_gray() {
+        ImageBuffer::<Luma<u8>, Vec<u8>>::from_raw(
+            width,
+            height,
+            rgb.iter().map(|c| c[0]).collect(),
+        )
+        .map(DynamicImage::ImageLuma8)
+    } else {
+        ImageBuffer::<Rgb<u8>, Vec<u8>>::from_raw(width, height, rgb.concat())
+            .map(DynamicImage::ImageRgb8)
+    };
+    image.ok_or_else(|| PdfError::Other {
+        msg: "COULD NOT CREATE IMAGE BUFFER.".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_inverted_decode() {
+        assert!(!is_inverted_decode(None));
+        assert!(!is_inverted_decode(Some(&[0.0, 1.0])));
+        assert!(is_inverted_decode(Some(&[1.0, 0.0])));
+    }
+
+    #[test]
+    fn test_samples_to_srgb_decode_array() {
+        //Decode配列[1 0]でグレーの値が反転することを確認する。
+        let values: [u16; 2] = [0, 255];
+        let image =
+            samples_to_srgb(&values, 2, 1, 8, &ImageColorSpace::Gray, Some(&[1.0, 0.0])).unwrap();
+        assert!(matches!(image, DynamicImage::ImageLuma8(_)));
+        assert_eq!(image.to_luma8().into_raw(), vec![255, 0]);
+    }
+
+    #[test]
+    fn test_samples_to_srgb_indexed() {
+        //2bitのインデックスで赤・緑・青のパレットを参照する。
+        let color_space = ImageColorSpace::Indexed {
+            base: Box::new(ImageColorSpace::Rgb),
+            hival: 2,
+            lookup: Arc::from(&[255, 0, 0, 0, 255, 0, 0, 0, 255][..]),
+        };
+        let values: [u16; 3] = [2, 0, 1];
+        let image = samples_to_srgb(&values, 3, 1, 2, &color_space, None).unwrap();
+        assert_eq!(
+            image.to_rgb8().into_raw(),
+            vec![0, 0, 255, 255, 0, 0, 0, 255, 0]
+        );
+    }
+
+    #[test]
+    fn test_samples_to_srgb_cmyk() {
+        let values: [u16; 8] = [255, 0, 0, 0, 0, 0, 0, 255];
+        let image = samples_to_srgb(&values, 2, 1, 8, &ImageColorSpace::Cmyk, None).unwrap();
+        assert_eq!(image.to_rgb8().into_raw(), vec![0, 255, 255, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_samples_to_srgb_too_short() {
+        let values: [u16; 5] = [0; 5];
+        assert!(samples_to_srgb(&values, 2, 1, 8, &ImageColorSpace::Rgb, None).is_err());
+    }
+
+    #[test]
+    fn test_lab_to_srgb() {
+        let white = lab_to_srgb(100.0, 0.0, 0.0);
+        let black = lab_to_srgb(0.0, 0.0, 0.0);
+        assert!(white.iter().all(|c| *c > 0.99));
+        assert!(black.iter().all(|c| *c < 0.01));
+        //a*が正の値の場合は赤みが強くなる。
+        let red = lab_to_srgb(50.0, 60.0, 0.0);
+        assert!(red[0] > red[1] && red[0] > red[2]);
+    }
+
+    #[test]
+    fn test_from_components() {
+        assert!(ImageColorSpace::from_components(1).unwrap().is_gray());
+        assert_eq!(ImageColorSpace::from_components(4).unwrap().components(), 4);
+        assert!(ImageColorSpace::from_components(2).is_err());
+    }
+}
@@ -0,0 +1,798 @@
+use std::convert::Infallible;
+
+use crate::color_management::is_inverted_decode;
+
+use fax::decoder::pels;
+use fax::encoder::Encoder;
+use fax::maps::{black, mode, white, Mode};
+use fax::{BitReader, Color, VecWriter};
+use pdf::enc::CCITTFaxDecodeParams;
+use pdf::error::PdfError;
+use pdf::object::ImageXObject;
+
+///展開できる画像のピクセル数の上限。(不正なデータによる過大なメモリ確保を防ぐ。)
+const MAX_PIXELS: u64 = 1 << 30;
+
+///EOL(000000000001)の値とビット数。
+const EOL: u16 = 1;
+const EOL_LENGTH: u8 = 12;
+
+///TIFFの圧縮方式。(CCITT 1D・T.4(Group 3)・T.6(Group 4)。)
+const TIFF_COMPRESSION_CCITT_RLE: u16 = 2;
+const TIFF_COMPRESSION_T4: u16 = 3;
+const TIFF_COMPRESSION_T6: u16 = 4;
+
+///CCITTFaxDecodeのパラメータ。(展開に使用するもののみ。)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FaxParams {
+    ///符号化方式。(負の場合はT.6、0の場合はT.4の1次元、正の場合はT.4の1次元と2次元の混在。)
+    k: i32,
+    end_of_line: bool,
+    encoded_byte_align: bool,
+    columns: u16,
+    black_is_1: bool,
+}
+
+impl FaxParams {
+    fn new(params: &CCITTFaxDecodeParams) -> Result<Self, String> {
+        let columns: u16 = match u16::try_from(params.columns) {
+            Ok(columns) if columns > 0 => columns,
+            _ => {
+                return Err(format!(
+                    "UNSUPPORTED CCITT FAX COLUMNS. COLUMNS: {}",
+                    params.columns
+                ))
+            }
+        };
+        Ok(FaxParams {
+            k: params.k,
+            end_of_line: params.end_of_line,
+            encoded_byte_align: params.encoded_byte_align,
+            columns,
+            black_is_1: params.black_is_1,
+        })
+    }
+
+    ///TIFFにそのまま格納できる場合は圧縮方式とT4Options・T6Optionsのタグと値を取得する。
+    ///(T.6で行ごとにバイト境界に揃えたもの、EOLのないT.4はTIFFで表現できないためNone。)
+    fn tiff_compression(&self) -> Option<(u16, u16, u32)> {
+        match (self.k, self.end_of_line, self.encoded_byte_align) {
+            (k, _, false) if k < 0 => Some((TIFF_COMPRESSION_T6, 293, 0)),
+            (0, false, true) => Some((TIFF_COMPRESSION_CCITT_RLE, 0, 0)),
+            (k, true, align) if k >= 0 => Some((
+                TIFF_COMPRESSION_T4,
+                292,
+                (k > 0) as u32 | if align { 4 } else { 0 },
+            )),
+            _ => None,
+        }
+    }
+}
+
+///CCITTの符号化データをビット単位で読み込む。(データの終端以降は0として扱う。)
+struct FaxReader<'a> {
+    data: &'a [u8],
+    ///読み込み位置。(ビット単位。)
+    position: usize,
+}
+
+impl<'a> FaxReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        FaxReader { data, position: 0 }
+    }
+
+    ///データの終端に達したか。
+    fn is_end(&self) -> bool {
+        self.position >= self.data.len() * 8
+    }
+
+    ///次のバイト境界まで読み飛ばす。
+    fn align(&mut self) {
+        self.position = self.position.div_ceil(8) * 8;
+    }
+
+    fn read_bit(&mut self) -> Option<u16> {
+        let bit: Option<u16> = self.peek(1);
+        self.position += 1;
+        bit
+    }
+}
+
+impl BitReader for FaxReader<'_> {
+    type Error = Infallible;
+
+    fn peek(&self, bits: u8) -> Option<u16> {
+        if bits > 16 || self.is_end() {
+            return None;
+        }
+        let byte: usize = self.position / 8;
+        let window: u32 = (0..3).fold(0, |window, i| {
+            (window << 8) | self.data.get(byte + i).copied().unwrap_or(0) as u32
+        });
+        let shift: u32 = 24 - (self.position % 8) as u32 - bits as u32;
+        Some(((window >> shift) & ((1u32 << bits) - 1)) as u16)
+    }
+
+    fn consume(&mut self, bits: u8) -> Result<(), Self::Error> {
+        self.position += bits as usize;
+        Ok(())
+    }
+
+    fn bits_to_byte_boundary(&self) -> u8 {
+        ((8 - self.position % 8) % 8) as u8
+    }
+}
+
+///指定した色のランの長さを読み込む。(メイクアップ符号と終端符号の合計。)
+fn run_length(reader: &mut FaxReader, color: Color) -> Result<u16, String> {
+    let mut length: u16 = 0;
+    loop {
+        let code: u16 = match color {
+            Color::White => white::decode(reader),
+            Color::Black => black::decode(reader),
+        }
+        .ok_or_else(|| {
+            format!(
+                "INVALID CCITT FAX RUN LENGTH CODE. BIT: {}",
+                reader.position
+            )
+        })?;
+        length = length.saturating_add(code);
+        if code < 64 {
+            return Ok(length);
+        }
+    }
+}
+
+///1行の色の変化位置を正規化する。(幅を超える位置を切り詰め、長さ0のランを取り除く。)
+fn push_change(changes: &mut Vec<u16>, position: u16) {
+    let position: u16 = position.max(changes.last().copied().unwrap_or(0));
+    if changes.last() == Some(&position) {
+        changes.pop();
+    } else {
+        changes.push(position);
+    }
+}
+
+///1次元符号化された1行を読み込む。
+/// # Returns
+/// * 成功時:色の変化位置。(白から始まり、白黒が交互に変わる。)
+/// * 失敗時:不正な符号。
+fn decode_1d_row(reader: &mut FaxReader, columns: u16) -> Result<Vec<u16>, String> {
+    let mut changes: Vec<u16> = Vec::new();
+    let mut a0: u16 = 0;
+    let mut color: Color = Color::White;
+    while a0 < columns {
+        a0 = a0.saturating_add(run_length(reader, color)?).min(columns);
+        push_change(&mut changes, a0);
+        color = !color;
+    }
+    Ok(changes)
+}
+
+///参照行からb1・b2を取得する。(a0より右にあり、a0と異なる色に変わる最初の変化位置とその次の変化位置。)
+fn reference_changes(reference: &[u16], a0: i32, color: Color, columns: u16) -> (u16, u16) {
+    //偶数番目は黒に、奇数番目は白に変わる位置。
+    let parity: usize = if color == Color::White { 0 } else { 1 };
+    let index: Option<usize> = reference
+        .iter()
+        .enumerate()
+        .position(|(i, &position)| position as i32 > a0 && i % 2 == parity);
+    match index {
+        Some(i) => (
+            reference[i],
+            reference.get(i + 1).copied().unwrap_or(columns),
+        ),
+        None => (columns, columns),
+    }
+}
+
+///2次元符号化された1行を読み込む。
+/// # Arguments
+/// * `reference` - 参照行(直前の行)の色の変化位置。
+/// # Returns
+/// * 成功時:色の変化位置。(白から始まり、白黒が交互に変わる。)
+/// * 失敗時:不正な符号もしくは未対応の拡張モード。
+fn decode_2d_row(
+    reader: &mut FaxReader,
+    reference: &[u16],
+    columns: u16,
+) -> Result<Vec<u16>, String> {
+    let mut changes: Vec<u16> = Vec::new();
+    //行頭の前の仮想的な白のピクセルを-1とする。
+    let mut a0: i32 = -1;
+    let mut color: Color = Color::White;
+    while a0 < columns as i32 {
+        let (b1, b2) = reference_changes(reference, a0, color, columns);
+        match mode::decode(reader) {
+            Some(Mode::Pass) => a0 = b2 as i32,
+            Some(Mode::Horizontal) => {
+                let start: u16 = a0.max(0) as u16;
+                let a1: u16 = start
+                    .saturating_add(run_length(reader, color)?)
+                    .min(columns);
+                let a2: u16 = a1.saturating_add(run_length(reader, !color)?).min(columns);
+                push_change(&mut changes, a1);
+                push_change(&mut changes, a2);
+                a0 = a2 as i32;
+            }
+            Some(Mode::Vertical(delta)) => {
+                let a1: i32 = b1 as i32 + delta as i32;
+                if a1 < 0 || a1 > columns as i32 {
+                    return Err(format!(
+                        "INVALID CCITT FAX VERTICAL MODE. BIT: {}",
+                        reader.position
+                    ));
+                }
+                push_change(&mut changes, a1 as u16);
+                color = !color;
+                a0 = a1;
+            }
+            Some(Mode::Extension) => {
+                return Err("CCITT FAX EXTENSION MODE IS NOT SUPPORTED.".to_string())
+            }
+            Some(Mode::EOF) | None => {
+                return Err(format!(
+                    "INVALID CCITT FAX MODE CODE. BIT: {}",
+                    reader.position
+                ))
+            }
+        }
+    }
+    Ok(changes)
+}
+
+///CCITTの符号化データを展開する。
+/// # Arguments
+/// * `data` - CCITTFaxDecodeの画像データ。
+/// * `params` - CCITTFaxDecodeのパラメータ。
+/// * `max_rows` - 展開する行数の上限。
+/// # Returns
+/// * 成功時:各行の色の変化位置。(EOFB・RTC・データの終端で終了する。途中の行が壊れている場合はその前の行まで。)
+/// * 失敗時:1行目から展開できない不正なデータ。
+fn decode_fax(data: &[u8], params: &FaxParams, max_rows: u32) -> Result<Vec<Vec<u16>>, String> {
+    let mut reader = FaxReader::new(data);
+    let mut rows: Vec<Vec<u16>> = Vec::new();
+    let mut reference: Vec<u16> = Vec::new();
+    while (rows.len() as u32) < max_rows {
+        //EOLがある場合は、EOLの終端がバイト境界になるように埋め草ビットが追加される。
+        if params.encoded_byte_align && (params.k < 0 || !params.end_of_line) {
+            reader.align();
+        }
+        //EOLと、EOLの前の埋め草ビットを読み飛ばす。(T.6ではEOFBの前半になる。)
+        let mut eol_count: u32 = 0;
+        loop {
+            match reader.peek(EOL_LENGTH) {
+                Some(EOL) => {
+                    reader.position += EOL_LENGTH as usize;
+                    eol_count += 1;
+                    if params.k < 0 || eol_count >= 2 {
+                        break;
+                    }
+                }
+                Some(0) if params.k >= 0 => reader.position += 1,
+                _ => break,
+            }
+        }
+        if (params.k < 0 && eol_count > 0) || eol_count >= 2 || reader.is_end() {
+            break;
+        }
+        //T.4の1次元と2次元の混在の場合は、各行の先頭のビットが1のときに1次元符号化。
+        let two_dimensional: bool = match params.k {
+            k if k < 0 => true,
+            0 => false,
+            _ => {
+                let tag: Option<u16> = reader.read_bit();
+                if eol_count > 0 && reader.peek(EOL_LENGTH) == Some(EOL) {
+                    break;
+                }
+                tag == Some(0)
+            }
+        };
+        let row: Result<Vec<u16>, String> = if two_dimensional {
+            decode_2d_row(&mut reader, &reference, params.columns)
+        } else {
+            decode_1d_row(&mut reader, params.columns)
+        };
+        match row {
+            Ok(row) => {
+                reference.clone_from(&row);
+                rows.push(row);
+            }
+            Err(e) if rows.is_empty() => return Err(e),
+            Err(_) => break,
+        }
+    }
+    Ok(rows)
+}
+
+///展開した行を画像データに変換する。
+/// # Arguments
+/// * `rows` - 各行の色の変化位置。
+/// * `params` - CCITTFaxDecodeのパラメータ。
+/// * `width` - 画像XObjectの幅。
+/// * `height` - 画像XObjectの高さ。
+/// # Returns
+/// * 1ピクセル1ビットの画像データ。(行ごとにバイト境界に揃え、BlackIs1がfalseの場合は0が黒。)
+///   (幅・高さが足りない部分は白で埋め、はみ出す部分は切り捨てる。)
+fn rows_to_samples(rows: &[Vec<u16>], params: &FaxParams, width: u32, height: u32) -> Vec<u8> {
+    let row_bytes: usize = width.div_ceil(8) as usize;
+    let (white, black): (u8, u8) = if params.black_is_1 { (0, 1) } else { (1, 0) };
+    let mut samples: Vec<u8> = vec![white * 0xFF; row_bytes * height as usize];
+    for (row, line) in samples.chunks_exact_mut(row_bytes).zip(rows) {
+        for (x, color) in pels(line, params.columns).enumerate().take(width as usize) {
+            if color == Color::Black {
+                let mask: u8 = 0x80 >> (x % 8);
+                row[x / 8] = if black == 1 {
+                    row[x / 8] | mask
+                } else {
+                    row[x / 8] & !mask
+                };
+            }
+        }
+    }
+    samples
+}
+
+///CCITTの符号化データを展開する。
+/// # Returns
+/// * 成功時:1ピクセル1ビットの画像データ。(`rows_to_samples()`を参照。)
+/// * 失敗時:未対応のパラメータもしくは不正なデータ。
+fn decode_ccitt(
+    data: &[u8],
+    params: &FaxParams,
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>, String> {
+    if width as u64 * height as u64 > MAX_PIXELS {
+        return Err(format!(
+            "CCITT FAX IMAGE IS TOO LARGE. SIZE: {}x{}",
+            width, height
+        ));
+    }
+    let rows: Vec<Vec<u16>> = decode_fax(data, params, height)?;
+    Ok(rows_to_samples(&rows, params, width, height))
+}
+
+///1ビットの画像をTIFFの1つのストリップとして格納する。
+/// # Arguments
+/// * `data` - 圧縮されたストリップのデータ。
+/// * `compression` - 圧縮方式。
+/// * `options` - T4OptionsもしくはT6Optionsのタグと値。(タグが0の場合は書き込まない。)
+/// * `photometric` - PhotometricInterpretation。(0の場合は0が白、1の場合は0が黒。)
+fn tiff_file(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    compression: u16,
+    options: (u16, u32),
+    photometric: u16,
+) -> Vec<u8> {
+    //(タグ・型・値。)型は3がSHORT、4がLONG、5がRATIONAL(値はデータ内の位置で後から設定する)。
+    let mut entries: Vec<(u16, u16, u32)> = vec![
+        (256, 4, width),
+        (257, 4, height),
+        (258, 3, 1),
+        (259, 3, compression as u32),
+        (262, 3, photometric as u32),
+        (273, 4, 0),
+        (277, 3, 1),
+        (278, 4, height),
+        (279, 4, data.len() as u32),
+        (282, 5, 0),
+        (283, 5, 0),
+        //解像度は不明のため、単位なしの1:1とする。
+        (296, 3, 1),
+    ];
+    if options.0 != 0 {
+        entries.push((options.0, 4, options.1));
+        entries.sort_by_key(|entry| entry.0);
+    }
+    let ifd_end: u32 = 8 + 2 + 12 * entries.len() as u32 + 4;
+    let data_offset: u32 = ifd_end + 16;
+    let mut file: Vec<u8> = Vec::with_capacity(data_offset as usize + data.len());
+    file.extend_from_slice(b"II*\0");
+    file.extend_from_slice(&8u32.to_le_bytes());
+    file.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    let mut rational_offset: u32 = ifd_end;
+    for (tag, value_type, value) in entries {
+        let value: u32 = match (tag, value_type) {
+            (273, _) => data_offset,
+            (_, 5) => {
+                rational_offset += 8;
+                rational_offset - 8
+            }
+            _ => value,
+        };
+        file.extend_from_slice(&tag.to_le_bytes());
+        file.extend_from_slice(&value_type.to_le_bytes());
+        file.extend_from_slice(&1u32.to_le_bytes());
+        file.extend_from_slice(&value.to_le_bytes());
+    }
+    file.extend_from_slice(&0u32.to_le_bytes());
+    for _ in 0..2 {
+        file.extend_from_slice(&1u32.to_le_bytes());
+        file.extend_from_slice(&1u32.to_le_bytes());
+    }
+    file.extend_from_slice(data);
+    file
+}
+
+///CCITTの符号化データをTIFFファイルに格納する。
+/// # Arguments
+/// * `data` - CCITTFaxDecodeの画像データ。
+/// * `params` - CCITTFaxDecodeのパラメータ。
+/// * `height` - 行数。
+/// * `black_is_dark` - 黒のランを黒で表示するか。(falseの場合は白黒を反転して表示する。)
+/// # Returns
+/// * 成功時:TIFFファイルのデータ。(TIFFで表現できない形式はT.6に再符号化する。)
+/// * 失敗時:再符号化のための展開に失敗した場合。
+fn fax_tiff(
+    data: &[u8],
+    params: &FaxParams,
+    height: u32,
+    black_is_dark: bool,
+) -> Result<Vec<u8>, String> {
+    let photometric: u16 = if black_is_dark { 0 } else { 1 };
+    let width: u32 = params.columns as u32;
+    if let Some((compression, options_tag, options)) = params.tiff_compression() {
+        return Ok(tiff_file(
+            data,
+            width,
+            height,
+            compression,
+            (options_tag, options),
+            photometric,
+        ));
+    }
+    if width as u64 * height as u64 > MAX_PIXELS {
+        return Err(format!(
+            "CCITT FAX IMAGE IS TOO LARGE. SIZE: {}x{}",
+            width, height
+        ));
+    }
+    let rows: Vec<Vec<u16>> = decode_fax(data, params, height)?;
+    let mut encoder = Encoder::new(VecWriter::new());
+    for y in 0..height as usize {
+        let line: &[u16] = rows.get(y).map(Vec::as_slice).unwrap_or_default();
+        encoder
+            .encode_line(pels(line, params.columns), params.columns)
+            .unwrap_or_else(|e| match e {});
+    }
+    let encoded: Vec<u8> = encoder.finish().unwrap_or_else(|e| match e {}).finish();
+    Ok(tiff_file(
+        &encoded,
+        width,
+        height,
+        TIFF_COMPRESSION_T6,
+        (293, 0),
+        photometric,
+    ))
+}
+
+///CCITTFaxDecodeの画像XObjectのデータを展開する。
+/// # Arguments
+/// * `img` - 画像XObject(Width/Heightの取得に使用する)。
+/// * `data` - `raw_image_data()`で取得した画像データ。
+/// * `params` - CCITTFaxDecodeのパラメータ。
+/// # Returns
+/// * 成功時:フィルタを適用していない画像と同じ形式の画像データ。(1ピクセル1ビット・BlackIs1に従う。)
+/// * 失敗時:PdfError(未対応のパラメータもしくは不正なデータ)。
+pub(crate) fn decode_ccitt_image(
+    img: &ImageXObject,
+    data: &[u8],
+    params: &CCITTFaxDecodeParams,
+) -> Result<Vec<u8>, PdfError> {
+    FaxParams::new(params)
+        .and_then(|fax_params| decode_ccitt(data, &fax_params, img.width, img.height))
+        .map_err(|msg| PdfError::Other { msg })
+}
+
+///CCITTFaxDecodeの画像XObjectのデータを展開せずにTIFFファイルに格納する。
+/// # Arguments
+/// * `img` - 画像XObject(Height/Decodeの取得に使用する)。
+/// * `data` - `raw_image_data()`で取得した画像データ。
+/// * `params` - CCITTFaxDecodeのパラメータ。(K・Columns・Rows・BlackIs1・EndOfLine・EncodedByteAlign。)
+/// # Returns
+/// * 成功時:TIFFファイルのデータ。(TIFFで表現できない形式は展開してT.6で再符号化する。)
+/// * 失敗時:PdfError(未対応のパラメータもしくは再符号化のための展開失敗)。
+pub(crate) fn ccitt_tiff(
+    img: &ImageXObject,
+    data: &[u8],
+    params: &CCITTFaxDecodeParams,
+) -> Result<Vec<u8>, PdfError> {
+    let height: u32 = if params.rows > 0 {
+        params.rows
+    } else {
+        img.height
+    };
+    //黒のランのサンプル値がDecode配列で黒(ImageMaskの場合は塗る部分)になるかを判定する。
+    let black_is_dark: bool = params.black_is_1 == is_inverted_decode(img.decode.as_deref());
+    FaxParams::new(params)
+        .and_then(|fax_params| fax_tiff(data, &fax_params, height, black_is_dark))
+        .map_err(|msg| PdfError::Other { msg })
+}
+
+///TIFFファイルの最初のIFDのタグと値を取得する。(値が1つのSHORT・LONGのタグのみ。テスト用。)
+#[cfg(test)]
+pub(crate) fn tiff_tags(tiff: &[u8]) -> std::collections::BTreeMap<u16, u32> {
+    let read_u16 = |at: usize| u16::from_le_bytes([tiff[at], tiff[at + 1]]);
+    let read_u32 =
+        |at: usize| u32::from_le_bytes([tiff[at], tiff[at + 1], tiff[at + 2], tiff[at + 3]]);
+    let ifd: usize = read_u32(4) as usize;
+    (0..read_u16(ifd) as usize)
+        .map(|i| ifd + 2 + 12 * i)
+        .filter_map(|entry| match read_u16(entry + 2) {
+            3 => Some((read_u16(entry), read_u16(entry + 8) as u32)),
+            4 => Some((read_u16(entry), read_u32(entry + 8))),
+            _ => None,
+        })
+        .collect()
+}
+
+///fax_tiff()で作成したTIFFファイルを、タグのみから復元したパラメータで展開する。(テスト用。)
+/// # Returns
+/// * 各行を黒く表示する画素は"#"、白く表示する画素は"."とした文字列。
+#[cfg(test)]
+pub(crate) fn fax_tiff_rows(tiff: &[u8]) -> Vec<String> {
+    let tags = tiff_tags(tiff);
+    let (width, height, compression) = (tags[&256], tags[&257], tags[&259] as u16);
+    let t4_options: u32 = tags.get(&292).copied().unwrap_or(0);
+    let params = FaxParams {
+        k: match compression {
+            TIFF_COMPRESSION_T6 => -1,
+            _ => (t4_options & 1) as i32,
+        },
+        end_of_line: compression == TIFF_COMPRESSION_T4,
+        encoded_byte_align: compression == TIFF_COMPRESSION_CCITT_RLE || t4_options & 4 != 0,
+        columns: width as u16,
+        black_is_1: false,
+    };
+    let strip: &[u8] = &tiff[tags[&273] as usize..][..tags[&279] as usize];
+    let samples: Vec<u8> = decode_ccitt(strip, &params, width, height).unwrap();
+    //黒のランは0。PhotometricInterpretationが0(WhiteIsZero)の場合は黒のランを黒で表示する。
+    let dark: u8 = if tags[&262] == 0 { 0 } else { 1 };
+    let row_bytes: usize = (width as usize).div_ceil(8);
+    samples
+        .chunks(row_bytes)
+        .map(|row| {
+            (0..width as usize)
+                .map(|x| {
+                    if (row[x / 8] >> (7 - x % 8)) & 1 == dark {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fax::{BitWriter, Bits};
+
+    ///ビット列を書き込む。
+    #[derive(Default)]
+    struct BitVec(Vec<bool>);
+
+    impl BitWriter for BitVec {
+        type Error = Infallible;
+
+        fn write(&mut self, bits: Bits) -> Result<(), Self::Error> {
+            for i in (0..bits.len).rev() {
+                self.0.push((bits.data >> i) & 1 == 1);
+            }
+            Ok(())
+        }
+    }
+
+    const ROWS: [&str; 6] = [
+        "....................",
+        "..####......####....",
+        "..#####....######...",
+        "#...................",
+        "...................#",
+        "####################",
+    ];
+
+    fn params(k: i32, end_of_line: bool, encoded_byte_align: bool) -> FaxParams {
+        FaxParams {
+            k,
+            end_of_line,
+            encoded_byte_align,
+            columns: 20,
+            black_is_1: false,
+        }
+    }
+
+    fn row_pels(row: &str) -> impl Iterator<Item = Color> + '_ {
+        row.chars()
+            .map(|c| if c == '#' { Color::Black } else { Color::White })
+    }
+
+    fn write_run(writer: &mut BitVec, color: Color, length: u16) {
+        let encode = |n: u16| match color {
+            Color::White => white::encode(n).unwrap(),
+            Color::Black => black::encode(n).unwrap(),
+        };
+        if length >= 64 {
+            writer.write(encode(length / 64 * 64)).unwrap();
+        }
+        writer.write(encode(length % 64)).unwrap();
+    }
+
+    ///faxクレートの符号化器で2次元符号化した1行を作成する。(参照行を符号化した後の部分のみを使用する。)
+    fn encode_2d_row(reference: Option<&str>, row: &str) -> Vec<bool> {
+        let encode_lines = |lines: &[&str]| -> Vec<bool> {
+            let mut encoder = Encoder::new(BitVec::default());
+            for line in lines {
+                encoder.encode_line(row_pels(line), 20).unwrap();
+            }
+            let mut bits: Vec<bool> = encoder.finish().unwrap().0;
+            //EOFBを取り除く。
+            bits.truncate(bits.len() - 24);
+            bits
+        };
+        match reference {
+            Some(reference) => {
+                let skip: usize = encode_lines(&[reference]).len();
+                encode_lines(&[reference, row]).split_off(skip)
+            }
+            None => encode_lines(&[row]),
+        }
+    }
+
+    ///T.4の1次元・2次元の混在もしくはT.6で符号化する。(2次元の行はfaxクレートの符号化器で作成する。)
+    fn encode(rows: &[&str], params: &FaxParams) -> Vec<u8> {
+        let eol = Bits {
+            data: EOL,
+            len: EOL_LENGTH,
+        };
+        let mut writer = BitVec::default();
+        for (y, row) in rows.iter().enumerate() {
+            let two_dimensional: bool =
+                params.k < 0 || (params.k > 0 && y % params.k as usize != 0);
+            if params.encoded_byte_align {
+                let eol_bits: usize = if params.end_of_line { 12 } else { 0 };
+                while !(writer.0.len() + eol_bits).is_multiple_of(8) {
+                    writer.0.push(false);
+                }
+            }
+            if params.end_of_line {
+                writer.write(eol).unwrap();
+            }
+            if params.k > 0 {
+                writer.0.push(!two_dimensional);
+            }
+            if two_dimensional {
+                let reference: Option<&str> = y.checked_sub(1).map(|y| rows[y]);
+                writer.0.extend(encode_2d_row(reference, row));
+            } else {
+                let mut color = Color::White;
+                let mut length: u16 = 0;
+                for pel in row_pels(row) {
+                    if pel != color {
+                        write_run(&mut writer, color, length);
+                        color = !color;
+                        length = 0;
+                    }
+                    length += 1;
+                }
+                write_run(&mut writer, color, length);
+            }
+        }
+        //T.6の場合はEOFB、T.4の場合はRTC。
+        for _ in 0..if params.k < 0 { 2 } else { 6 } {
+            writer.write(eol).unwrap();
+            if params.k > 0 {
+                writer.0.push(true);
+            }
+        }
+        writer
+            .0
+            .chunks(8)
+            .map(|bits| {
+                bits.iter()
+                    .enumerate()
+                    .fold(0u8, |byte, (i, &bit)| byte | ((bit as u8) << (7 - i)))
+            })
+            .collect()
+    }
+
+    fn samples_to_rows(samples: &[u8], width: usize, black: u8) -> Vec<String> {
+        let row_bytes: usize = width.div_ceil(8);
+        samples
+            .chunks(row_bytes)
+            .map(|row| {
+                (0..width)
+                    .map(|x| {
+                        if (row[x / 8] >> (7 - x % 8)) & 1 == black {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_decode_ccitt() {
+        for (k, end_of_line, encoded_byte_align) in [
+            (-1, false, false),
+            (-1, false, true),
+            (0, false, false),
+            (0, true, false),
+            (0, true, true),
+            (0, false, true),
+            (2, true, false),
+            (3, false, true),
+        ] {
+            let params = params(k, end_of_line, encoded_byte_align);
+            let data: Vec<u8> = encode(&ROWS, &params);
+            let samples: Vec<u8> = decode_ccitt(&data, &params, 20, 6).unwrap();
+            assert_eq!(samples_to_rows(&samples, 20, 0), ROWS, "{:?}", params);
+        }
+    }
+
+    #[test]
+    fn test_decode_ccitt_size_and_black_is_1() {
+        let params = FaxParams {
+            black_is_1: true,
+            ..params(-1, false, false)
+        };
+        let data: Vec<u8> = encode(&ROWS[..4], &params);
+        //高さが足りない行は白で埋め、幅がはみ出す部分は切り捨てる。
+        let samples: Vec<u8> = decode_ccitt(&data, &params, 8, 5).unwrap();
+        assert_eq!(
+            samples_to_rows(&samples, 8, 1),
+            ["........", "..####..", "..#####.", "#.......", "........"]
+        );
+        assert!(decode_ccitt(&[0x02, 0x00, 0x00], &params, 20, 6).is_err());
+    }
+
+    #[test]
+    fn test_fax_tiff() {
+        for (k, end_of_line, encoded_byte_align, compression, options) in [
+            (-1, false, false, TIFF_COMPRESSION_T6, Some((293, 0))),
+            (0, false, true, TIFF_COMPRESSION_CCITT_RLE, None),
+            //T4Optionsは2次元符号化(1)とEOLのバイト境界への整列(4)。
+            (2, true, true, TIFF_COMPRESSION_T4, Some((292, 5))),
+            (0, true, false, TIFF_COMPRESSION_T4, Some((292, 0))),
+            //TIFFで表現できない形式はT.6に再符号化する。
+            (-1, false, true, TIFF_COMPRESSION_T6, Some((293, 0))),
+            (0, false, false, TIFF_COMPRESSION_T6, Some((293, 0))),
+        ] {
+            let params = params(k, end_of_line, encoded_byte_align);
+            let data: Vec<u8> = encode(&ROWS, &params);
+            let tiff: Vec<u8> = fax_tiff(&data, &params, 6, true).unwrap();
+            let tags = tiff_tags(&tiff);
+            assert_eq!(tags[&256], 20, "{:?}", params);
+            assert_eq!(tags[&257], 6, "{:?}", params);
+            assert_eq!(tags[&259], compression as u32, "{:?}", params);
+            assert_eq!(tags[&262], 0, "{:?}", params);
+            let options_tag: Option<(u16, u32)> = tags
+                .iter()
+                .find(|(tag, _)| matches!(tag, 292 | 293))
+                .map(|(tag, value)| (*tag, *value));
+            assert_eq!(options_tag, options, "{:?}", params);
+            let strip: &[u8] = &tiff[tags[&273] as usize..];
+            assert_eq!(strip.len(), tags[&279] as usize, "{:?}", params);
+            //格納した符号化データをTIFFのタグのみから復元したパラメータで展開する。
+            assert_eq!(fax_tiff_rows(&tiff), ROWS, "{:?}", params);
+            //T.6のTIFFはimageクレートでも読み込める。
+            if compression == TIFF_COMPRESSION_T6 {
+                let image = image::load_from_memory(&tiff).unwrap().to_luma8();
+                let rows: Vec<String> = image
+                    .rows()
+                    .map(|row| row.map(|p| if p.0[0] < 128 { '#' } else { '.' }).collect())
+                    .collect();
+                assert_eq!(rows, ROWS, "{:?}", params);
+            }
+        }
+    }
+}
@@ -0,0 +1,1891 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::jbig2_arithmetic::{ArithmeticDecoder, Context, IntegerContexts, IntegerType};
+use crate::jbig2_huffman::{BitReader, HuffmanTable};
+
+use fax::decoder::{decode_g4, pels};
+use fax::Color;
+use pdf::enc::JBIG2DecodeParams;
+use pdf::error::PdfError;
+use pdf::object::{ImageXObject, Resolve};
+
+///展開できる画像のピクセル数の上限。(不正なデータによる過大なメモリ確保を防ぐ。)
+const MAX_PIXELS: u64 = 1 << 30;
+
+///単独のJBIG2ファイルのヘッダー。(ID文字列・シーケンシャル形式・1ページ。)
+const JBIG2_FILE_HEADER: [u8; 13] = [
+    0x97, 0x4A, 0x42, 0x32, 0x0D, 0x0A, 0x1A, 0x0A, 0x01, 0x00, 0x00, 0x00, 0x01,
+];
+
+///JBIG2の2値画像。(1ピクセル1バイト。1が黒、0が白。)
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Bitmap {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+///領域を合成する演算子。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CombinationOperator {
+    Or,
+    And,
+    Xor,
+    Xnor,
+    Replace,
+}
+
+impl CombinationOperator {
+    fn new(value: u8) -> Self {
+        match value {
+            1 => CombinationOperator::And,
+            2 => CombinationOperator::Xor,
+            3 => CombinationOperator::Xnor,
+            4 => CombinationOperator::Replace,
+            _ => CombinationOperator::Or,
+        }
+    }
+
+    fn apply(&self, dest: u8, src: u8) -> u8 {
+        match self {
+            CombinationOperator::Or => dest | src,
+            CombinationOperator::And => dest & src,
+            CombinationOperator::Xor => dest ^ src,
+            CombinationOperator::Xnor => 1 ^ dest ^ src,
+            CombinationOperator::Replace => src,
+        }
+    }
+}
+
+impl Bitmap {
+    ///全て白の画像を作成する。
+    fn new(width: u32, height: u32) -> Result<Self, String> {
+        if width as u64 * height as u64 > MAX_PIXELS {
+            return Err(format!(
+                "JBIG2 BITMAP IS TOO LARGE. SIZE: {}x{}",
+                width, height
+            ));
+        }
+        Ok(Bitmap {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize],
+        })
+    }
+
+    ///ピクセルの値を取得する。(画像の範囲外は0。)
+    fn get(&self, x: i64, y: i64) -> u8 {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            0
+        } else {
+            self.pixels[y as usize * self.width as usize + x as usize]
+        }
+    }
+
+    fn set(&mut self, x: u32, y: u32, value: u8) {
+        self.pixels[y as usize * self.width as usize + x as usize] = value;
+    }
+
+    ///行を別の行の値で上書きする。
+    fn copy_row(&mut self, from: u32, to: u32) {
+        let width: usize = self.width as usize;
+        self.pixels.copy_within(
+            from as usize * width..(from as usize + 1) * width,
+            to as usize * width,
+        );
+    }
+
+    ///画像の高さを広げる。(広げた行はvalueで埋める。)
+    fn extend_height(&mut self, height: u32, value: u8) -> Result<(), String> {
+        if height <= self.height {
+            return Ok(());
+        }
+        if self.width as u64 * height as u64 > MAX_PIXELS {
+            return Err(format!(
+                "JBIG2 BITMAP IS TOO LARGE. SIZE: {}x{}",
+                self.width, height
+            ));
+        }
+        self.pixels
+            .resize(self.width as usize * height as usize, value);
+        self.height = height;
+        Ok(())
+    }
+
+    ///指定された範囲を切り出す。(画像の範囲外は0。)
+    fn crop(&self, x: i64, y: i64, width: u32, height: u32) -> Result<Bitmap, String> {
+        let mut bitmap = Bitmap::new(width, height)?;
+        for row in 0..height {
+            for column in 0..width {
+                bitmap.set(column, row, self.get(x + column as i64, y + row as i64));
+            }
+        }
+        Ok(bitmap)
+    }
+
+    ///別の画像を指定された位置に合成する。
+    fn compose(&mut self, other: &Bitmap, x: i64, y: i64, operator: CombinationOperator) {
+        for row in 0..other.height as i64 {
+            let dest_y: i64 = y + row;
+            if dest_y < 0 || dest_y >= self.height as i64 {
+                continue;
+            }
+            for column in 0..other.width as i64 {
+                let dest_x: i64 = x + column;
+                if dest_x < 0 || dest_x >= self.width as i64 {
+                    continue;
+                }
+                let index: usize = dest_y as usize * self.width as usize + dest_x as usize;
+                self.pixels[index] = operator.apply(
+                    self.pixels[index],
+                    other.pixels[row as usize * other.width as usize + column as usize],
+                );
+            }
+        }
+    }
+
+    ///PDFの画像データ(1ピクセル1ビット・行ごとにバイト境界に揃える・0が黒)に変換する。
+    ///(指定された大きさに満たない部分は白、超える部分は切り捨てる。)
+    fn to_samples(&self, width: u32, height: u32) -> Vec<u8> {
+        let stride: usize = (width as usize).div_ceil(8);
+        let mut samples: Vec<u8> = vec![0xFF; stride * height as usize];
+        for y in 0..height.min(self.height) {
+            for x in 0..width.min(self.width) {
+                if self.get(x as i64, y as i64) == 1 {
+                    samples[y as usize * stride + x as usize / 8] &= !(0x80 >> (x % 8));
+                }
+            }
+        }
+        samples
+    }
+}
+
+///バイト単位でセグメントのデータを読み込む。
+struct ByteReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        ByteReader { data, position: 0 }
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], String> {
+        match self
+            .data
+            .get(self.position..self.position.saturating_add(length))
+        {
+            Some(bytes) => {
+                self.position += length;
+                Ok(bytes)
+            }
+            None => Err("JBIG2 SEGMENT DATA IS TOO SHORT.".to_string()),
+        }
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn i8(&mut self) -> Result<i8, String> {
+        Ok(self.u8()? as i8)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes: &[u8] = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes: &[u8] = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        Ok(self.u32()? as i32)
+    }
+
+    ///残りのデータを取得する。
+    fn rest(&self) -> &'a [u8] {
+        &self.data[self.position.min(self.data.len())..]
+    }
+}
+
+///汎用領域の復号のパラメータ。(T.88 6.2.2。)
+struct GenericRegionParams<'a> {
+    width: u32,
+    height: u32,
+    template: u8,
+    tpgdon: bool,
+    ///適応テンプレートのピクセルの位置。(テンプレート0は4つ、それ以外は1つ目のみ使用する。)
+    at: [(i32, i32); 4],
+    ///復号を省略するピクセル。(ハーフトーン領域で使用する。)
+    skip: Option<&'a Bitmap>,
+}
+
+///適応テンプレートのピクセルの既定の位置。(パターン辞書・ハーフトーン領域で使用する。)
+fn default_at(template: u8) -> [(i32, i32); 4] {
+    let x: i32 = if template <= 1 { 3 } else { 2 };
+    [(x, -1), (-3, -1), (2, -2), (-2, -2)]
+}
+
+///汎用領域の各ピクセルのコンテキストを計算する。(T.88 6.2.5.3。)
+fn generic_context(bitmap: &Bitmap, x: i64, y: i64, template: u8, at: &[(i32, i32); 4]) -> usize {
+    let p = |dx: i64, dy: i64| bitmap.get(x + dx, y + dy) as usize;
+    let a = |i: usize| p(at[i].0 as i64, at[i].1 as i64);
+    match template {
+        0 => {
+            p(-1, 0)
+                | p(-2, 0) << 1
+                | p(-3, 0) << 2
+                | p(-4, 0) << 3
+                | a(0) << 4
+                | p(2, -1) << 5
+                | p(1, -1) << 6
+                | p(0, -1) << 7
+                | p(-1, -1) << 8
+                | p(-2, -1) << 9
+                | a(1) << 10
+                | a(2) << 11
+                | p(1, -2) << 12
+                | p(0, -2) << 13
+                | p(-1, -2) << 14
+                | a(3) << 15
+        }
+        1 => {
+            p(-1, 0)
+                | p(-2, 0) << 1
+                | p(-3, 0) << 2
+                | a(0) << 3
+                | p(2, -1) << 4
+                | p(1, -1) << 5
+                | p(0, -1) << 6
+                | p(-1, -1) << 7
+                | p(-2, -1) << 8
+                | p(2, -2) << 9
+                | p(1, -2) << 10
+                | p(0, -2) << 11
+                | p(-1, -2) << 12
+        }
+        2 => {
+            p(-1, 0)
+                | p(-2, 0) << 1
+                | a(0) << 2
+                | p(1, -1) << 3
+                | p(0, -1) << 4
+                | p(-1, -1) << 5
+                | p(-2, -1) << 6
+                | p(1, -2) << 7
+                | p(0, -2) << 8
+                | p(-1, -2) << 9
+        }
+        _ => {
+            p(-1, 0)
+                | p(-2, 0) << 1
+                | p(-3, 0) << 2
+                | p(-4, 0) << 3
+                | a(0) << 4
+                | p(1, -1) << 5
+                | p(0, -1) << 6
+                | p(-1, -1) << 7
+                | p(-2, -1) << 8
+                | p(-3, -1) << 9
+        }
+    }
+}
+
+///汎用領域のコンテキストを作成する。(全てのテンプレートで共通の大きさ。)
+fn generic_contexts() -> Vec<Context> {
+    vec![Context::default(); 1 << 16]
+}
+
+///算術符号化された汎用領域を復号する。(T.88 6.2.5.7。)
+/// # Arguments
+/// * `decoder` - 算術符号の復号器。
+/// * `contexts` - 汎用領域のコンテキスト。(シンボル辞書では全てのシンボルで共有する。)
+/// * `params` - 汎用領域の復号のパラメータ。
+fn decode_generic_region(
+    decoder: &mut ArithmeticDecoder,
+    contexts: &mut [Context],
+    params: &GenericRegionParams,
+) -> Result<Bitmap, String> {
+    let mut bitmap = Bitmap::new(params.width, params.height)?;
+    //典型的な予測で前の行と同じ行であることを示すコンテキスト。
+    let sltp_context: usize = match params.template {
+        0 => 0x9B25,
+        1 => 0x0795,
+        2 => 0x00E5,
+        _ => 0x0195,
+    };
+    let mut ltp: u8 = 0;
+    for y in 0..params.height {
+        if params.tpgdon {
+            ltp ^= decoder.decode(&mut contexts[sltp_context]);
+            if ltp == 1 {
+                if y > 0 {
+                    bitmap.copy_row(y - 1, y);
+                }
+                continue;
+            }
+        }
+        for x in 0..params.width {
+            if params
+                .skip
+                .is_some_and(|skip| skip.get(x as i64, y as i64) == 1)
+            {
+                continue;
+            }
+            let context: usize =
+                generic_context(&bitmap, x as i64, y as i64, params.template, &params.at);
+            let bit: u8 = decoder.decode(&mut contexts[context]);
+            if bit == 1 {
+                bitmap.set(x, y, 1);
+            }
+        }
+    }
+    Ok(bitmap)
+}
+
+///MMR(G4)で符号化された汎用領域を復号する。(T.88 6.2.6。)
+fn decode_mmr_region(data: &[u8], width: u32, height: u32) -> Result<Bitmap, String> {
+    let mut bitmap = Bitmap::new(width, height)?;
+    if width == 0 || height == 0 {
+        return Ok(bitmap);
+    }
+    let (mmr_width, mmr_height) = match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(mmr_width), Ok(mmr_height)) => (mmr_width, mmr_height),
+        _ => {
+            return Err(format!(
+                "JBIG2 MMR REGION IS TOO LARGE. SIZE: {}x{}",
+                width, height
+            ))
+        }
+    };
+    let mut y: u32 = 0;
+    decode_g4(
+        data.iter().copied(),
+        mmr_width,
+        Some(mmr_height),
+        |transitions| {
+            if y < height {
+                for (x, color) in pels(transitions, mmr_width).enumerate() {
+                    if color == Color::Black {
+                        bitmap.set(x as u32, y, 1);
+                    }
+                }
+            }
+            y += 1;
+        },
+    )
+    .ok_or_else(|| "COULD NOT DECODE JBIG2 MMR DATA.".to_string())?;
+    Ok(bitmap)
+}
+
+///リファインメント領域の復号のパラメータ。(T.88 6.3.2。)
+struct RefinementParams<'a> {
+    width: u32,
+    height: u32,
+    template: u8,
+    reference: &'a Bitmap,
+    ///参照画像の位置のずれ。
+    dx: i32,
+    dy: i32,
+    tpgron: bool,
+    ///適応テンプレートのピクセルの位置。(テンプレート0のみ使用する。1つ目は復号中の画像、2つ目は参照画像。)
+    at: [(i32, i32); 2],
+}
+
+///リファインメント領域の各ピクセルのコンテキストを計算する。(T.88 6.3.5.3。)
+fn refinement_context(bitmap: &Bitmap, x: i64, y: i64, params: &RefinementParams) -> usize {
+    let p = |dx: i64, dy: i64| bitmap.get(x + dx, y + dy) as usize;
+    let rx: i64 = x - params.dx as i64;
+    let ry: i64 = y - params.dy as i64;
+    let r = |dx: i64, dy: i64| params.reference.get(rx + dx, ry + dy) as usize;
+    if params.template == 0 {
+        p(-1, 0)
+            | p(1, -1) << 1
+            | p(0, -1) << 2
+            | p(params.at[0].0 as i64, params.at[0].1 as i64) << 3
+            | r(1, 1) << 4
+            | r(0, 1) << 5
+            | r(-1, 1) << 6
+            | r(1, 0) << 7
+            | r(0, 0) << 8
+            | r(-1, 0) << 9
+            | r(1, -1) << 10
+            | r(0, -1) << 11
+            | r(params.at[1].0 as i64, params.at[1].1 as i64) << 12
+    } else {
+        p(-1, 0)
+            | p(1, -1) << 1
+            | p(0, -1) << 2
+            | p(-1, -1) << 3
+            | r(1, 1) << 4
+            | r(0, 1) << 5
+            | r(1, 0) << 6
+            | r(0, 0) << 7
+            | r(-1, 0) << 8
+            | r(0, -1) << 9
+    }
+}
+
+///リファインメント領域のコンテキストを作成する。(全てのテンプレートで共通の大きさ。)
+fn refinement_contexts() -> Vec<Context> {
+    vec![Context::default(); 1 << 13]
+}
+
+///リファインメント領域を復号する。(T.88 6.3.5。)
+/// # Arguments
+/// * `decoder` - 算術符号の復号器。
+/// * `contexts` - リファインメント領域のコンテキスト。
+/// * `params` - リファインメント領域の復号のパラメータ。
+fn decode_refinement_region(
+    decoder: &mut ArithmeticDecoder,
+    contexts: &mut [Context],
+    params: &RefinementParams,
+) -> Result<Bitmap, String> {
+    let mut bitmap = Bitmap::new(params.width, params.height)?;
+    //典型的な予測の判定に使用するコンテキスト。(参照画像の中心のピクセルのみが1の場合。)
+    let sltp_context: usize = if params.template == 0 { 0x100 } else { 0x80 };
+    let mut ltp: u8 = 0;
+    for y in 0..params.height {
+        if params.tpgron {
+            ltp ^= decoder.decode(&mut contexts[sltp_context]);
+        }
+        for x in 0..params.width {
+            //参照画像の周囲3x3のピクセルが全て同じ値であれば、その値とする。
+            if ltp == 1 {
+                let rx: i64 = x as i64 - params.dx as i64;
+                let ry: i64 = y as i64 - params.dy as i64;
+                let value: u8 = params.reference.get(rx, ry);
+                let uniform: bool = (-1..=1)
+                    .all(|dy| (-1..=1).all(|dx| params.reference.get(rx + dx, ry + dy) == value));
+                if uniform {
+                    bitmap.set(x, y, value);
+                    continue;
+                }
+            }
+            let context: usize = refinement_context(&bitmap, x as i64, y as i64, params);
+            let bit: u8 = decoder.decode(&mut contexts[context]);
+            if bit == 1 {
+                bitmap.set(x, y, 1);
+            }
+        }
+    }
+    Ok(bitmap)
+}
+
+///シンボルIDのビット数を計算する。(シンボル数の2を底とする対数の切り上げ。)
+fn symbol_code_length(symbol_count: u64) -> u32 {
+    let mut length: u32 = 0;
+    while (1u64 << length) < symbol_count {
+        length += 1;
+    }
+    length
+}
+
+///Huffman表を選択する。
+/// # Arguments
+/// * `selector` - セグメントのフラグで指定された値。
+/// * `standard` - 値ごとの標準のHuffman表の番号。
+/// * `custom_selector` - テーブルセグメントで定義された表を使用することを示す値。
+/// * `custom` - 参照しているテーブルセグメントの表。(使用した表は取り除かれる。)
+fn select_table<'t>(
+    selector: u16,
+    standard: &[u32],
+    custom_selector: u16,
+    custom: &mut impl Iterator<Item = &'t HuffmanTable>,
+) -> Result<HuffmanTable, String> {
+    if selector == custom_selector {
+        return custom
+            .next()
+            .cloned()
+            .ok_or_else(|| "JBIG2 CUSTOM HUFFMAN TABLE NOT FOUND.".to_string());
+    }
+    standard
+        .get(selector as usize)
+        .map(|number| HuffmanTable::standard(*number))
+        .ok_or_else(|| format!("INVALID JBIG2 HUFFMAN TABLE SELECTION. VALUE: {}", selector))
+}
+
+///テキスト領域のHuffman表。
+struct TextHuffmanTables {
+    fs: HuffmanTable,
+    ds: HuffmanTable,
+    dt: HuffmanTable,
+    rdw: HuffmanTable,
+    rdh: HuffmanTable,
+    rdx: HuffmanTable,
+    rdy: HuffmanTable,
+    rsize: HuffmanTable,
+    ///シンボルIDの表。(Noneの場合はシンボルIDのビット数の固定長。)
+    symbol_id: Option<HuffmanTable>,
+}
+
+impl TextHuffmanTables {
+    ///整数の種類に対応する表を取得する。
+    fn table(&self, integer_type: IntegerType) -> Result<&HuffmanTable, String> {
+        match integer_type {
+            IntegerType::Iafs => Ok(&self.fs),
+            IntegerType::Iads => Ok(&self.ds),
+            IntegerType::Iadt => Ok(&self.dt),
+            IntegerType::Iardw => Ok(&self.rdw),
+            IntegerType::Iardh => Ok(&self.rdh),
+            IntegerType::Iardx => Ok(&self.rdx),
+            IntegerType::Iardy => Ok(&self.rdy),
+            _ => Err(format!(
+                "JBIG2 HUFFMAN TABLE IS NOT DEFINED. TYPE: {:?}",
+                integer_type
+            )),
+        }
+    }
+}
+
+///テキスト領域の符号化方式と復号の状態。
+enum TextCoding<'a, 'b> {
+    Arithmetic {
+        decoder: &'b mut ArithmeticDecoder<'a>,
+        integers: &'b mut IntegerContexts,
+    },
+    Huffman {
+        reader: &'b mut BitReader<'a>,
+        tables: &'b TextHuffmanTables,
+    },
+}
+
+impl TextCoding<'_, '_> {
+    ///整数を復号する。(OOBの場合はNone。)
+    fn integer(&mut self, integer_type: IntegerType) -> Result<Option<i32>, String> {
+        match self {
+            TextCoding::Arithmetic { decoder, integers } => {
+                Ok(integers.decode(decoder, integer_type))
+            }
+            TextCoding::Huffman { reader, tables } => tables.table(integer_type)?.decode(reader),
+        }
+    }
+
+    ///整数を復号する。(OOBを許容しない場合。)
+    fn value(&mut self, integer_type: IntegerType) -> Result<i32, String> {
+        self.integer(integer_type)?
+            .ok_or_else(|| format!("UNEXPECTED JBIG2 OOB. TYPE: {:?}", integer_type))
+    }
+
+    ///シンボルIDを復号する。
+    fn symbol_id(&mut self, code_length: u32) -> Result<u32, String> {
+        match self {
+            TextCoding::Arithmetic { decoder, integers } => Ok(integers.decode_id(decoder)),
+            TextCoding::Huffman { reader, tables } => match tables.symbol_id {
+                Some(ref table) => table.decode_value(reader).map(|id| id as u32),
+                None => reader.read_bits(code_length),
+            },
+        }
+    }
+
+    ///ストリップ内のT座標を復号する。
+    fn strip_t(&mut self, log_strips: u32) -> Result<i32, String> {
+        if log_strips == 0 {
+            return Ok(0);
+        }
+        match self {
+            TextCoding::Arithmetic { .. } => self.value(IntegerType::Iait),
+            TextCoding::Huffman { reader, .. } => Ok(reader.read_bits(log_strips)? as i32),
+        }
+    }
+
+    ///シンボルをリファインメントするかを復号する。
+    fn refinement_flag(&mut self) -> Result<bool, String> {
+        match self {
+            TextCoding::Arithmetic { .. } => Ok(self.value(IntegerType::Iari)? != 0),
+            TextCoding::Huffman { reader, .. } => Ok(reader.read_bit()? != 0),
+        }
+    }
+
+    ///リファインメントした画像を復号する。(Huffman符号化の場合はRSIZEのバイト数の算術符号化されたデータを復号する。)
+    fn refine(
+        &mut self,
+        contexts: &mut [Context],
+        params: &RefinementParams,
+    ) -> Result<Bitmap, String> {
+        match self {
+            TextCoding::Arithmetic { decoder, .. } => {
+                decode_refinement_region(decoder, contexts, params)
+            }
+            TextCoding::Huffman { reader, tables } => {
+                let size: i32 = tables.rsize.decode_value(reader)?;
+                reader.align();
+                let mut decoder = ArithmeticDecoder::new(reader.remaining());
+                let bitmap: Bitmap = decode_refinement_region(&mut decoder, contexts, params)?;
+                reader.skip_bytes(size.max(0) as usize);
+                Ok(bitmap)
+            }
+        }
+    }
+}
+
+///テキスト領域の復号のパラメータ。(T.88 6.4.2。)
+struct TextRegionParams<'a> {
+    width: u32,
+    height: u32,
+    refine: bool,
+    ///ストリップの大きさの2を底とする対数。
+    log_strips: u32,
+    ///シンボルの基準点。(0:左下・1:左上・2:右下・3:右上。)
+    ref_corner: u8,
+    transposed: bool,
+    combination_operator: CombinationOperator,
+    default_pixel: u8,
+    ds_offset: i32,
+    refinement_template: u8,
+    refinement_at: [(i32, i32); 2],
+    instances: u32,
+    symbols: &'a [&'a Bitmap],
+    symbol_code_length: u32,
+}
+
+///テキスト領域を復号する。(T.88 6.4.5。)
+/// # Arguments
+/// * `params` - テキスト領域の復号のパラメータ。
+/// * `coding` - 符号化方式と復号の状態。
+/// * `refinement_contexts` - シンボルのリファインメントに使用するコンテキスト。
+fn decode_text_region(
+    params: &TextRegionParams,
+    coding: &mut TextCoding,
+    refinement_contexts: &mut [Context],
+) -> Result<Bitmap, String> {
+    let mut bitmap = Bitmap::new(params.width, params.height)?;
+    if params.default_pixel == 1 {
+        bitmap.pixels.fill(1);
+    }
+    let strips: i64 = 1 << params.log_strips;
+    let mut strip_t: i64 = -(coding.value(IntegerType::Iadt)? as i64) * strips;
+    let mut first_s: i64 = 0;
+    let mut instances: u32 = 0;
+    while instances < params.instances {
+        strip_t += coding.value(IntegerType::Iadt)? as i64 * strips;
+        first_s += coding.value(IntegerType::Iafs)? as i64;
+        let mut current_s: i64 = first_s;
+        loop {
+            let t: i64 = strip_t + coding.strip_t(params.log_strips)? as i64;
+            let id: u32 = coding.symbol_id(params.symbol_code_length)?;
+            let symbol: &Bitmap = params
+                .symbols
+                .get(id as usize)
+                .ok_or_else(|| format!("JBIG2 SYMBOL NOT FOUND. ID: {}", id))?;
+            let refined: Option<Bitmap> = if params.refine && coding.refinement_flag()? {
+                let rdw: i32 = coding.value(IntegerType::Iardw)?;
+                let rdh: i32 = coding.value(IntegerType::Iardh)?;
+                let rdx: i32 = coding.value(IntegerType::Iardx)?;
+                let rdy: i32 = coding.value(IntegerType::Iardy)?;
+                let refinement = RefinementParams {
+                    width: (symbol.width as i64 + rdw as i64).max(0) as u32,
+                    height: (symbol.height as i64 + rdh as i64).max(0) as u32,
+                    template: params.refinement_template,
+                    reference: symbol,
+                    dx: (rdw >> 1) + rdx,
+                    dy: (rdh >> 1) + rdy,
+                    tpgron: false,
+                    at: params.refinement_at,
+                };
+                Some(coding.refine(refinement_contexts, &refinement)?)
+            } else {
+                None
+            };
+            let symbol: &Bitmap = refined.as_ref().unwrap_or(symbol);
+            let width: i64 = symbol.width as i64;
+            let height: i64 = symbol.height as i64;
+            //基準点が右側(転置時は下側)の場合は、配置する前にシンボルの幅(転置時は高さ)だけ進める。
+            if !params.transposed && params.ref_corner >= 2 {
+                current_s += width - 1;
+            } else if params.transposed && params.ref_corner & 1 == 0 {
+                current_s += height - 1;
+            }
+            let (s, t) = if params.transposed {
+                (t, current_s)
+            } else {
+                (current_s, t)
+            };
+            let x: i64 = if params.ref_corner >= 2 {
+                s - width + 1
+            } else {
+                s
+            };
+            let y: i64 = if params.ref_corner & 1 == 0 {
+                t - height + 1
+            } else {
+                t
+            };
+            bitmap.compose(symbol, x, y, params.combination_operator);
+            if !params.transposed && params.ref_corner < 2 {
+                current_s += width - 1;
+            } else if params.transposed && params.ref_corner & 1 == 1 {
+                current_s += height - 1;
+            }
+            instances += 1;
+            match coding.integer(IntegerType::Iads)? {
+                Some(ds) => current_s += ds as i64 + params.ds_offset as i64,
+                None => break,
+            }
+            if instances >= params.instances {
+                break;
+            }
+        }
+    }
+    Ok(bitmap)
+}
+
+///シンボル辞書を復号する。(T.88 6.5・7.4.2。)
+/// # Arguments
+/// * `data` - セグメントのデータ。
+/// * `input_symbols` - 参照しているシンボル辞書がエクスポートしたシンボル。
+/// * `tables` - 参照しているテーブルセグメントの表。
+/// # Returns
+/// * 成功時:エクスポートするシンボル。
+/// * 失敗時:未対応の形式もしくは不正なデータ。
+fn decode_symbol_dictionary(
+    data: &[u8],
+    input_symbols: &[Bitmap],
+    tables: &[&HuffmanTable],
+) -> Result<Vec<Bitmap>, String> {
+    let mut reader = ByteReader::new(data);
+    let flags: u16 = reader.u16()?;
+    let huffman: bool = flags & 1 != 0;
+    let refine_aggregate: bool = flags & 2 != 0;
+    let template: u8 = ((flags >> 10) & 3) as u8;
+    let refinement_template: u8 = ((flags >> 12) & 1) as u8;
+    if flags & 0x0100 != 0 {
+        return Err("JBIG2 BITMAP CODING CONTEXT REUSE IS NOT SUPPORTED.".to_string());
+    }
+    let mut at: [(i32, i32); 4] = default_at(template);
+    if !huffman {
+        for pixel in at.iter_mut().take(if template == 0 { 4 } else { 1 }) {
+            *pixel = (reader.i8()? as i32, reader.i8()? as i32);
+        }
+    }
+    let mut refinement_at: [(i32, i32); 2] = [(-1, -1), (-1, -1)];
+    if refine_aggregate && refinement_template == 0 {
+        for pixel in refinement_at.iter_mut() {
+            *pixel = (reader.i8()? as i32, reader.i8()? as i32);
+        }
+    }
+    let exported_count: u32 = reader.u32()?;
+    let new_count: u32 = reader.u32()?;
+    let code_length: u32 = symbol_code_length(input_symbols.len() as u64 + new_count as u64);
+
+    let mut custom = tables.iter().copied();
+    let huffman_tables: Option<[HuffmanTable; 4]> = if huffman {
+        Some([
+            select_table((flags >> 2) & 3, &[4, 5], 3, &mut custom)?,
+            select_table((flags >> 4) & 3, &[2, 3], 3, &mut custom)?,
+            select_table((flags >> 6) & 1, &[1], 1, &mut custom)?,
+            select_table((flags >> 7) & 1, &[1], 1, &mut custom)?,
+        ])
+    } else {
+        None
+    };
+
+    let mut arithmetic_decoder = ArithmeticDecoder::new(reader.rest());
+    let mut bit_reader = BitReader::new(reader.rest());
+    let mut integers = IntegerContexts::new(code_length);
+    let mut generic_contexts: Vec<Context> = generic_contexts();
+    let mut refinement_contexts: Vec<Context> = refinement_contexts();
+    //集約したシンボルのテキスト領域をHuffman符号化で復号する場合の表。
+    let aggregate_tables: Option<TextHuffmanTables> = huffman.then(|| TextHuffmanTables {
+        fs: HuffmanTable::standard(6),
+        ds: HuffmanTable::standard(8),
+        dt: HuffmanTable::standard(11),
+        rdw: HuffmanTable::standard(15),
+        rdh: HuffmanTable::standard(15),
+        rdx: HuffmanTable::standard(15),
+        rdy: HuffmanTable::standard(15),
+        rsize: HuffmanTable::standard(1),
+        symbol_id: None,
+    });
+
+    let mut new_symbols: Vec<Bitmap> = Vec::new();
+    let mut height_class: i64 = 0;
+    while (new_symbols.len() as u32) < new_count {
+        let delta_height: i32 = match huffman_tables {
+            Some(ref tables) => tables[0].decode_value(&mut bit_reader)?,
+            None => integers
+                .decode(&mut arithmetic_decoder, IntegerType::Iadh)
+                .ok_or_else(|| "UNEXPECTED JBIG2 OOB. TYPE: Iadh".to_string())?,
+        };
+        height_class += delta_height as i64;
+        let height: u32 = u32::try_from(height_class)
+            .map_err(|_| format!("INVALID JBIG2 SYMBOL HEIGHT. HEIGHT: {}", height_class))?;
+        let mut symbol_width: i64 = 0;
+        //Huffman符号化でまとめて符号化された高さクラスのシンボルの幅。
+        let mut collective_widths: Vec<u32> = Vec::new();
+        loop {
+            let delta_width: Option<i32> = match huffman_tables {
+                Some(ref tables) => tables[1].decode(&mut bit_reader)?,
+                None => integers.decode(&mut arithmetic_decoder, IntegerType::Iadw),
+            };
+            let delta_width: i32 = match delta_width {
+                Some(delta_width) => delta_width,
+                None => break,
+            };
+            if new_symbols.len() as u32 + collective_widths.len() as u32 >= new_count {
+                return Err("TOO MANY JBIG2 SYMBOLS IN HEIGHT CLASS.".to_string());
+            }
+            symbol_width += delta_width as i64;
+            let width: u32 = u32::try_from(symbol_width)
+                .map_err(|_| format!("INVALID JBIG2 SYMBOL WIDTH. WIDTH: {}", symbol_width))?;
+            if huffman && !refine_aggregate {
+                collective_widths.push(width);
+                continue;
+            }
+            let symbol: Bitmap = if !refine_aggregate {
+                let params = GenericRegionParams {
+                    width,
+                    height,
+                    template,
+                    tpgdon: false,
+                    at,
+                    skip: None,
+                };
+                decode_generic_region(&mut arithmetic_decoder, &mut generic_contexts, &params)?
+            } else {
+                let instances: i32 = match huffman_tables {
+                    Some(ref tables) => tables[3].decode_value(&mut bit_reader)?,
+                    None => integers
+                        .decode(&mut arithmetic_decoder, IntegerType::Iaai)
+                        .ok_or_else(|| "UNEXPECTED JBIG2 OOB. TYPE: Iaai".to_string())?,
+                };
+                let symbols: Vec<&Bitmap> =
+                    input_symbols.iter().chain(new_symbols.iter()).collect();
+                let mut coding: TextCoding = match aggregate_tables {
+                    Some(ref tables) => TextCoding::Huffman {
+                        reader: &mut bit_reader,
+                        tables,
+                    },
+                    None => TextCoding::Arithmetic {
+                        decoder: &mut arithmetic_decoder,
+                        integers: &mut integers,
+                    },
+                };
+                if instances > 1 {
+                    let params = TextRegionParams {
+                        width,
+                        height,
+                        refine: true,
+                        log_strips: 0,
+                        ref_corner: 1,
+                        transposed: false,
+                        combination_operator: CombinationOperator::Or,
+                        default_pixel: 0,
+                        ds_offset: 0,
+                        refinement_template,
+                        refinement_at,
+                        instances: instances as u32,
+                        symbols: &symbols,
+                        symbol_code_length: code_length,
+                    };
+                    decode_text_region(&params, &mut coding, &mut refinement_contexts)?
+                } else {
+                    let id: u32 = coding.symbol_id(code_length)?;
+                    let rdx: i32 = coding.value(IntegerType::Iardx)?;
+                    let rdy: i32 = coding.value(IntegerType::Iardy)?;
+                    let reference: &Bitmap = symbols
+                        .get(id as usize)
+                        .ok_or_else(|| format!("JBIG2 SYMBOL NOT FOUND. ID: {}", id))?;
+                    let params = RefinementParams {
+                        width,
+                        height,
+                        template: refinement_template,
+                        reference,
+                        dx: rdx,
+                        dy: rdy,
+                        tpgron: false,
+                        at: refinement_at,
+                    };
+                    coding.refine(&mut refinement_contexts, &params)?
+                }
+            };
+            new_symbols.push(symbol);
+        }
+        //Huffman符号化で集約しない場合は、高さクラスのシンボルをまとめた画像を分割する。
+        if let Some(ref tables) = huffman_tables {
+            if refine_aggregate {
+                continue;
+            }
+            let size: i32 = tables[2].decode_value(&mut bit_reader)?;
+            bit_reader.align();
+            let total_width: u32 = collective_widths.iter().sum();
+            let collective: Bitmap = if size == 0 {
+                let stride: usize = (total_width as usize).div_ceil(8);
+                let bytes: &[u8] = bit_reader
+                    .remaining()
+                    .get(..stride * height as usize)
+                    .ok_or_else(|| "JBIG2 SYMBOL DICTIONARY DATA IS TOO SHORT.".to_string())?;
+                let mut collective = Bitmap::new(total_width, height)?;
+                for y in 0..height {
+                    for x in 0..total_width {
+                        let byte: u8 = bytes[y as usize * stride + x as usize / 8];
+                        collective.set(x, y, (byte >> (7 - x % 8)) & 1);
+                    }
+                }
+                bit_reader.skip_bytes(bytes.len());
+                collective
+            } else {
+                let bytes: &[u8] = bit_reader
+                    .remaining()
+                    .get(..size as usize)
+                    .ok_or_else(|| "JBIG2 SYMBOL DICTIONARY DATA IS TOO SHORT.".to_string())?;
+                bit_reader.skip_bytes(bytes.len());
+                decode_mmr_region(bytes, total_width, height)?
+            };
+            let mut x: i64 = 0;
+            for width in collective_widths {
+                new_symbols.push(collective.crop(x, 0, width, height)?);
+                x += width as i64;
+            }
+        }
+    }
+
+    //エクスポートするシンボルを復号する。(エクスポートしないシンボルとするシンボルの連続数が交互に並ぶ。)
+    let total: usize = input_symbols.len() + new_symbols.len();
+    let mut exported: Vec<Bitmap> = Vec::new();
+    let mut index: usize = 0;
+    let mut export: bool = false;
+    while index < total {
+        let run: i32 = match huffman_tables {
+            Some(_) => HuffmanTable::standard(1).decode_value(&mut bit_reader)?,
+            None => integers
+                .decode(&mut arithmetic_decoder, IntegerType::Iaex)
+                .ok_or_else(|| "UNEXPECTED JBIG2 OOB. TYPE: Iaex".to_string())?,
+        };
+        let end: usize = index.saturating_add(run.max(0) as usize).min(total);
+        if export {
+            for i in index..end {
+                exported.push(match input_symbols.get(i) {
+                    Some(symbol) => symbol.clone(),
+                    None => new_symbols[i - input_symbols.len()].clone(),
+                });
+            }
+        }
+        index = end;
+        export = !export;
+    }
+    if exported.len() != exported_count as usize {
+        return Err(format!(
+            "JBIG2 EXPORTED SYMBOL COUNT MISMATCH. EXPECTED: {} DECODED: {}",
+            exported_count,
+            exported.len()
+        ));
+    }
+    Ok(exported)
+}
+
+///領域セグメントの情報。(T.88 7.4.1。)
+#[derive(Debug, Clone, Copy)]
+struct RegionInfo {
+    width: u32,
+    height: u32,
+    x: u32,
+    y: u32,
+    combination_operator: CombinationOperator,
+}
+
+impl RegionInfo {
+    fn parse(reader: &mut ByteReader) -> Result<Self, String> {
+        Ok(RegionInfo {
+            width: reader.u32()?,
+            height: reader.u32()?,
+            x: reader.u32()?,
+            y: reader.u32()?,
+            combination_operator: CombinationOperator::new(reader.u8()? & 7),
+        })
+    }
+}
+
+///テキスト領域のセグメントを復号する。(T.88 7.4.3。)
+fn decode_text_region_segment(
+    reader: &mut ByteReader,
+    info: &RegionInfo,
+    symbols: &[&Bitmap],
+    tables: &[&HuffmanTable],
+) -> Result<Bitmap, String> {
+    let flags: u16 = reader.u16()?;
+    let huffman: bool = flags & 1 != 0;
+    let refine: bool = flags & 2 != 0;
+    let refinement_template: u8 = ((flags >> 15) & 1) as u8;
+    let huffman_flags: u16 = if huffman { reader.u16()? } else { 0 };
+    let mut refinement_at: [(i32, i32); 2] = [(-1, -1), (-1, -1)];
+    if refine && refinement_template == 0 {
+        for pixel in refinement_at.iter_mut() {
+            *pixel = (reader.i8()? as i32, reader.i8()? as i32);
+        }
+    }
+    let instances: u32 = reader.u32()?;
+    //SBDSOFFSETは5ビットの符号付き整数。
+    let ds_offset: i32 = (((flags >> 10) & 0x1F) as i32 ^ 0x10) - 0x10;
+    let code_length: u32 = symbol_code_length(symbols.len() as u64);
+    let params = TextRegionParams {
+        width: info.width,
+        height: info.height,
+        refine,
+        log_strips: ((flags >> 2) & 3) as u32,
+        ref_corner: ((flags >> 4) & 3) as u8,
+        transposed: flags & 0x40 != 0,
+        combination_operator: CombinationOperator::new(((flags >> 7) & 3) as u8),
+        default_pixel: ((flags >> 9) & 1) as u8,
+        ds_offset,
+        refinement_template,
+        refinement_at,
+        instances,
+        symbols,
+        symbol_code_length: code_length,
+    };
+    let mut refinement_contexts: Vec<Context> = refinement_contexts();
+    if huffman {
+        let mut custom = tables.iter().copied();
+        let fs = select_table(huffman_flags & 3, &[6, 7], 3, &mut custom)?;
+        let ds = select_table((huffman_flags >> 2) & 3, &[8, 9, 10], 3, &mut custom)?;
+        let dt = select_table((huffman_flags >> 4) & 3, &[11, 12, 13], 3, &mut custom)?;
+        let rdw = select_table((huffman_flags >> 6) & 3, &[14, 15], 3, &mut custom)?;
+        let rdh = select_table((huffman_flags >> 8) & 3, &[14, 15], 3, &mut custom)?;
+        let rdx = select_table((huffman_flags >> 10) & 3, &[14, 15], 3, &mut custom)?;
+        let rdy = select_table((huffman_flags >> 12) & 3, &[14, 15], 3, &mut custom)?;
+        let rsize = select_table((huffman_flags >> 14) & 1, &[1], 1, &mut custom)?;
+        let mut bit_reader = BitReader::new(reader.rest());
+        let symbol_id: HuffmanTable = decode_symbol_id_table(&mut bit_reader, symbols.len())?;
+        let tables = TextHuffmanTables {
+            fs,
+            ds,
+            dt,
+            rdw,
+            rdh,
+            rdx,
+            rdy,
+            rsize,
+            symbol_id: Some(symbol_id),
+        };
+        let mut coding = TextCoding::Huffman {
+            reader: &mut bit_reader,
+            tables: &tables,
+        };
+        decode_text_region(&params, &mut coding, &mut refinement_contexts)
+    } else {
+        let mut decoder = ArithmeticDecoder::new(reader.rest());
+        let mut integers = IntegerContexts::new(code_length);
+        let mut coding = TextCoding::Arithmetic {
+            decoder: &mut decoder,
+            integers: &mut integers,
+        };
+        decode_text_region(&params, &mut coding, &mut refinement_contexts)
+    }
+}
+
+///テキスト領域のシンボルIDのHuffman表を復号する。(T.88 7.4.3.1.7。)
+fn decode_symbol_id_table(
+    reader: &mut BitReader,
+    symbol_count: usize,
+) -> Result<HuffmanTable, String> {
+    let run_lengths: Vec<u32> = (0..35)
+        .map(|_| reader.read_bits(4))
+        .collect::<Result<Vec<u32>, String>>()?;
+    let run_table = HuffmanTable::from_code_lengths(&run_lengths);
+    let mut code_lengths: Vec<u32> = Vec::with_capacity(symbol_count);
+    while code_lengths.len() < symbol_count {
+        let code: i32 = run_table.decode_value(reader)?;
+        let (length, repeat): (u32, u32) = match code {
+            0..=31 => (code as u32, 1),
+            32 => (
+                *code_lengths
+                    .last()
+                    .ok_or_else(|| "INVALID JBIG2 SYMBOL ID TABLE.".to_string())?,
+                reader.read_bits(2)? + 3,
+            ),
+            33 => (0, reader.read_bits(3)? + 3),
+            _ => (0, reader.read_bits(7)? + 11),
+        };
+        for _ in 0..repeat {
+            code_lengths.push(length);
+        }
+    }
+    code_lengths.truncate(symbol_count);
+    reader.align();
+    Ok(HuffmanTable::from_code_lengths(&code_lengths))
+}
+
+///パターン辞書を復号する。(T.88 6.7・7.4.4。)
+fn decode_pattern_dictionary(data: &[u8]) -> Result<Vec<Bitmap>, String> {
+    let mut reader = ByteReader::new(data);
+    let flags: u8 = reader.u8()?;
+    let pattern_width: u32 = reader.u8()? as u32;
+    let pattern_height: u32 = reader.u8()? as u32;
+    let gray_max: u32 = reader.u32()?;
+    let template: u8 = (flags >> 1) & 3;
+    let count: u64 = gray_max as u64 + 1;
+    let width: u64 = count * pattern_width as u64;
+    if width > u32::MAX as u64 {
+        return Err(format!(
+            "JBIG2 PATTERN DICTIONARY IS TOO LARGE. PATTERNS: {}",
+            count
+        ));
+    }
+    let collective: Bitmap = if flags & 1 != 0 {
+        decode_mmr_region(reader.rest(), width as u32, pattern_height)?
+    } else {
+        let mut at: [(i32, i32); 4] = default_at(template);
+        at[0] = (-(pattern_width as i32), 0);
+        let params = GenericRegionParams {
+            width: width as u32,
+            height: pattern_height,
+            template,
+            tpgdon: false,
+            at,
+            skip: None,
+        };
+        let mut decoder = ArithmeticDecoder::new(reader.rest());
+        decode_generic_region(&mut decoder, &mut generic_contexts(), &params)?
+    };
+    (0..count)
+        .map(|i| {
+            collective.crop(
+                (i * pattern_width as u64) as i64,
+                0,
+                pattern_width,
+                pattern_height,
+            )
+        })
+        .collect()
+}
+
+///ハーフトーン領域を復号する。(T.88 6.6・7.4.5。)
+fn decode_halftone_region(
+    reader: &mut ByteReader,
+    info: &RegionInfo,
+    patterns: &[Bitmap],
+) -> Result<Bitmap, String> {
+    let flags: u8 = reader.u8()?;
+    let grid_width: u32 = reader.u32()?;
+    let grid_height: u32 = reader.u32()?;
+    let grid_x: i64 = reader.i32()? as i64;
+    let grid_y: i64 = reader.i32()? as i64;
+    let vector_x: i64 = reader.u16()? as i64;
+    let vector_y: i64 = reader.u16()? as i64;
+    let mmr: bool = flags & 1 != 0;
+    let template: u8 = (flags >> 1) & 3;
+    let combination_operator = CombinationOperator::new((flags >> 4) & 7);
+    let first: &Bitmap = patterns
+        .first()
+        .ok_or_else(|| "JBIG2 PATTERN DICTIONARY IS EMPTY.".to_string())?;
+    let (pattern_width, pattern_height) = (first.width as i64, first.height as i64);
+
+    let mut bitmap = Bitmap::new(info.width, info.height)?;
+    if flags & 0x80 != 0 {
+        bitmap.pixels.fill(1);
+    }
+    //グリッドの各セルのパターンを配置する位置。
+    let position = |m: i64, n: i64| -> (i64, i64) {
+        (
+            (grid_x + m * vector_y + n * vector_x) >> 8,
+            (grid_y + m * vector_x - n * vector_y) >> 8,
+        )
+    };
+    //領域の外に配置されるセルは復号を省略する。
+    let skip: Option<Bitmap> = if flags & 8 != 0 {
+        let mut skip = Bitmap::new(grid_width, grid_height)?;
+        for m in 0..grid_height {
+            for n in 0..grid_width {
+                let (x, y) = position(m as i64, n as i64);
+                if x + pattern_width <= 0
+                    || x >= info.width as i64
+                    || y + pattern_height <= 0
+                    || y >= info.height as i64
+                {
+                    skip.set(n, m, 1);
+                }
+            }
+        }
+        Some(skip)
+    } else {
+        None
+    };
+
+    //グレイコードで符号化されたビットプレーンを上位から復号する。(T.88 Annex C.5。)
+    let bits_per_pixel: u32 = symbol_code_length(patterns.len() as u64);
+    let mut gray_values: Vec<u32> = vec![0; grid_width as usize * grid_height as usize];
+    if bits_per_pixel > 1 && mmr {
+        return Err(
+            "JBIG2 MMR HALFTONE REGION WITH MULTIPLE BITPLANES IS NOT SUPPORTED.".to_string(),
+        );
+    }
+    let params = GenericRegionParams {
+        width: grid_width,
+        height: grid_height,
+        template,
+        tpgdon: false,
+        at: default_at(template),
+        skip: skip.as_ref(),
+    };
+    let mut decoder = ArithmeticDecoder::new(reader.rest());
+    let mut contexts: Vec<Context> = generic_contexts();
+    let mut previous: Option<Bitmap> = None;
+    for plane in (0..bits_per_pixel).rev() {
+        let mut current: Bitmap = if mmr {
+            decode_mmr_region(reader.rest(), grid_width, grid_height)?
+        } else {
+            decode_generic_region(&mut decoder, &mut contexts, &params)?
+        };
+        if let Some(ref previous) = previous {
+            for (pixel, upper) in current.pixels.iter_mut().zip(previous.pixels.iter()) {
+                *pixel ^= upper;
+            }
+        }
+        for (value, pixel) in gray_values.iter_mut().zip(current.pixels.iter()) {
+            *value |= (*pixel as u32) << plane;
+        }
+        previous = Some(current);
+    }
+
+    for m in 0..grid_height {
+        for n in 0..grid_width {
+            let value: usize = gray_values[(m * grid_width + n) as usize] as usize;
+            let pattern: &Bitmap = &patterns[value.min(patterns.len() - 1)];
+            let (x, y) = position(m as i64, n as i64);
+            bitmap.compose(pattern, x, y, combination_operator);
+        }
+    }
+    Ok(bitmap)
+}
+
+///セグメントヘッダー。(T.88 7.2。)
+#[derive(Debug, Clone)]
+struct SegmentHeader {
+    number: u32,
+    segment_type: u8,
+    referred_segments: Vec<u32>,
+    ///データの長さが不明で、データの最後に行数が記録されている場合はtrue。
+    has_row_count: bool,
+}
+
+///JBIG2のデータをセグメントに分割する。(シーケンシャル形式。)
+/// # Returns
+/// * 成功時:セグメントヘッダーとセグメントのデータ。
+/// * 失敗時:不正なセグメントヘッダー。
+fn parse_segments(data: &[u8]) -> Result<Vec<(SegmentHeader, &[u8])>, String> {
+    let mut segments: Vec<(SegmentHeader, &[u8])> = Vec::new();
+    let mut reader = ByteReader::new(data);
+    while reader.position < data.len() {
+        let number: u32 = reader.u32()?;
+        let flags: u8 = reader.u8()?;
+        let segment_type: u8 = flags & 0x3F;
+        let count_byte: u8 = reader.u8()?;
+        let referred_count: u32 = if count_byte >> 5 == 7 {
+            let count: u32 =
+                u32::from_be_bytes([count_byte & 0x1F, reader.u8()?, reader.u8()?, reader.u8()?]);
+            reader.bytes((count as usize + 8) / 8)?;
+            count
+        } else {
+            (count_byte >> 5) as u32
+        };
+        let mut referred_segments: Vec<u32> = Vec::new();
+        for _ in 0..referred_count {
+            referred_segments.push(if number <= 256 {
+                reader.u8()? as u32
+            } else if number <= 65536 {
+                reader.u16()? as u32
+            } else {
+                reader.u32()?
+            });
+        }
+        if flags & 0x40 != 0 {
+            reader.u32()?;
+        } else {
+            reader.u8()?;
+        }
+        let data_length: u32 = reader.u32()?;
+        let start: usize = reader.position;
+        let (length, has_row_count): (usize, bool) = if data_length == u32::MAX {
+            //データの長さが不明な汎用領域は、終端のマーカーと行数(4バイト)までをデータとする。
+            if segment_type != 38 && segment_type != 39 {
+                return Err(format!(
+                    "JBIG2 SEGMENT DATA LENGTH IS UNKNOWN. TYPE: {}",
+                    segment_type
+                ));
+            }
+            let flags: u8 = *data
+                .get(start + 17)
+                .ok_or_else(|| "JBIG2 SEGMENT DATA IS TOO SHORT.".to_string())?;
+            let (marker, header_length): ([u8; 2], usize) = if flags & 1 != 0 {
+                ([0x00, 0x00], 18)
+            } else if (flags >> 1) & 3 == 0 {
+                ([0xFF, 0xAC], 26)
+            } else {
+                ([0xFF, 0xAC], 20)
+            };
+            let offset: usize = data
+                .get(start + header_length..)
+                .and_then(|rest| rest.windows(2).position(|window| window == marker))
+                .ok_or_else(|| "JBIG2 END OF GENERIC REGION NOT FOUND.".to_string())?;
+            (header_length + offset + 2 + 4, true)
+        } else {
+            (data_length as usize, false)
+        };
+        segments.push((
+            SegmentHeader {
+                number,
+                segment_type,
+                referred_segments,
+                has_row_count,
+            },
+            reader.bytes(length)?,
+        ));
+    }
+    Ok(segments)
+}
+
+///復号したセグメントの結果。(後続のセグメントから参照される。)
+enum SegmentResult {
+    Symbols(Vec<Bitmap>),
+    Patterns(Vec<Bitmap>),
+    Table(HuffmanTable),
+    ///中間領域。
+    Region(Bitmap),
+}
+
+///ページの画像と復号したセグメントの結果。
+struct PageDecoder {
+    page: Option<Bitmap>,
+    ///ページの高さが不明(ストライプごとに広がる)場合はtrue。
+    unknown_height: bool,
+    default_pixel: u8,
+    results: HashMap<u32, SegmentResult>,
+}
+
+impl PageDecoder {
+    fn new() -> Self {
+        PageDecoder {
+            page: None,
+            unknown_height: false,
+            default_pixel: 0,
+            results: HashMap::new(),
+        }
+    }
+
+    ///参照しているセグメントのうち、指定された種類の結果を取得する。
+    fn referred<'r, T>(
+        &'r self,
+        header: &SegmentHeader,
+        select: impl Fn(&'r SegmentResult) -> Option<T>,
+    ) -> Vec<T> {
+        header
+            .referred_segments
+            .iter()
+            .filter_map(|number| self.results.get(number))
+            .filter_map(select)
+            .collect()
+    }
+
+    ///参照しているテーブルセグメントの表を取得する。
+    fn tables(&self, header: &SegmentHeader) -> Vec<&HuffmanTable> {
+        self.referred(header, |result| match result {
+            SegmentResult::Table(table) => Some(table),
+            _ => None,
+        })
+    }
+
+    ///参照しているシンボル辞書のシンボルを取得する。
+    fn symbols(&self, header: &SegmentHeader) -> Vec<&Bitmap> {
+        self.referred(header, |result| match result {
+            SegmentResult::Symbols(symbols) => Some(symbols.iter()),
+            _ => None,
+        })
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    ///領域をページに合成する。
+    fn compose(&mut self, bitmap: &Bitmap, info: &RegionInfo) -> Result<(), String> {
+        let page: &mut Bitmap = self
+            .page
+            .as_mut()
+            .ok_or_else(|| "JBIG2 PAGE INFORMATION SEGMENT NOT FOUND.".to_string())?;
+        if self.unknown_height {
+            page.extend_height(info.y.saturating_add(bitmap.height), self.default_pixel)?;
+        }
+        page.compose(
+            bitmap,
+            info.x as i64,
+            info.y as i64,
+            info.combination_operator,
+        );
+        Ok(())
+    }
+
+    ///セグメントを復号する。
+    ///(ページ・ファイルの終端とプロファイルのセグメント、必須でない拡張セグメントは無視し、それ以外の未対応の種類のセグメントはエラーとする。)
+    fn decode_segment(&mut self, header: &SegmentHeader, data: &[u8]) -> Result<(), String> {
+        let mut reader = ByteReader::new(data);
+        match header.segment_type {
+            //シンボル辞書
+            0 => {
+                let input: Vec<Bitmap> = self.symbols(header).into_iter().cloned().collect();
+                let symbols: Vec<Bitmap> =
+                    decode_symbol_dictionary(data, &input, &self.tables(header))?;
+                self.results
+                    .insert(header.number, SegmentResult::Symbols(symbols));
+            }
+            //テキスト領域
+            4 | 6 | 7 => {
+                let info = RegionInfo::parse(&mut reader)?;
+                let bitmap: Bitmap = decode_text_region_segment(
+                    &mut reader,
+                    &info,
+                    &self.symbols(header),
+                    &self.tables(header),
+                )?;
+                self.store_region(header, bitmap, &info)?;
+            }
+            //パターン辞書
+            16 => {
+                let patterns: Vec<Bitmap> = decode_pattern_dictionary(data)?;
+                self.results
+                    .insert(header.number, SegmentResult::Patterns(patterns));
+            }
+            //ハーフトーン領域
+            20 | 22 | 23 => {
+                let info = RegionInfo::parse(&mut reader)?;
+                let patterns: Vec<&Vec<Bitmap>> = self.referred(header, |result| match result {
+                    SegmentResult::Patterns(patterns) => Some(patterns),
+                    _ => None,
+                });
+                let patterns: &[Bitmap] = patterns
+                    .first()
+                    .ok_or_else(|| "JBIG2 PATTERN DICTIONARY NOT FOUND.".to_string())?;
+                let bitmap: Bitmap = decode_halftone_region(&mut reader, &info, patterns)?;
+                self.store_region(header, bitmap, &info)?;
+            }
+            //汎用領域
+            36 | 38 | 39 => {
+                let mut info = RegionInfo::parse(&mut reader)?;
+                let flags: u8 = reader.u8()?;
+                let template: u8 = (flags >> 1) & 3;
+                if flags & 0x10 != 0 {
+                    return Err("JBIG2 EXTENDED TEMPLATE IS NOT SUPPORTED.".to_string());
+                }
+                let mut at: [(i32, i32); 4] = default_at(template);
+                if flags & 1 == 0 {
+                    for pixel in at.iter_mut().take(if template == 0 { 4 } else { 1 }) {
+                        *pixel = (reader.i8()? as i32, reader.i8()? as i32);
+                    }
+                }
+                let mut rest: &[u8] = reader.rest();
+                //データの長さが不明な場合は、最後の4バイトの行数を領域の高さとする。
+                if header.has_row_count && rest.len() >= 4 {
+                    let (body, row_count) = rest.split_at(rest.len() - 4);
+                    let row_count: u32 = u32::from_be_bytes([
+                        row_count[0],
+                        row_count[1],
+                        row_count[2],
+                        row_count[3],
+                    ]);
+                    info.height = info.height.min(row_count);
+                    rest = body;
+                }
+                let bitmap: Bitmap = if flags & 1 != 0 {
+                    decode_mmr_region(rest, info.width, info.height)?
+                } else {
+                    let params = GenericRegionParams {
+                        width: info.width,
+                        height: info.height,
+                        template,
+                        tpgdon: flags & 8 != 0,
+                        at,
+                        skip: None,
+                    };
+                    let mut decoder = ArithmeticDecoder::new(rest);
+                    decode_generic_region(&mut decoder, &mut generic_contexts(), &params)?
+                };
+                self.store_region(header, bitmap, &info)?;
+            }
+            //リファインメント領域
+            40 | 42 | 43 => {
+                let info = RegionInfo::parse(&mut reader)?;
+                let flags: u8 = reader.u8()?;
+                let template: u8 = flags & 1;
+                let mut at: [(i32, i32); 2] = [(-1, -1), (-1, -1)];
+                if template == 0 {
+                    for pixel in at.iter_mut() {
+                        *pixel = (reader.i8()? as i32, reader.i8()? as i32);
+                    }
+                }
+                //中間領域を参照していない場合は、ページの同じ位置の領域を参照画像とする。
+                let reference: Bitmap = match self
+                    .referred(header, |result| match result {
+                        SegmentResult::Region(region) => Some(region),
+                        _ => None,
+                    })
+                    .first()
+                {
+                    Some(region) => (*region).clone(),
+                    None => self
+                        .page
+                        .as_ref()
+                        .ok_or_else(|| "JBIG2 PAGE INFORMATION SEGMENT NOT FOUND.".to_string())?
+                        .crop(info.x as i64, info.y as i64, info.width, info.height)?,
+                };
+                let params = RefinementParams {
+                    width: info.width,
+                    height: info.height,
+                    template,
+                    reference: &reference,
+                    dx: 0,
+                    dy: 0,
+                    tpgron: flags & 2 != 0,
+                    at,
+                };
+                let mut decoder = ArithmeticDecoder::new(reader.rest());
+                let bitmap: Bitmap =
+                    decode_refinement_region(&mut decoder, &mut refinement_contexts(), &params)?;
+                self.store_region(header, bitmap, &info)?;
+            }
+            //ページ情報
+            48 => {
+                let width: u32 = reader.u32()?;
+                let height: u32 = reader.u32()?;
+                reader.bytes(8)?;
+                let flags: u8 = reader.u8()?;
+                self.default_pixel = (flags >> 2) & 1;
+                self.unknown_height = height == u32::MAX;
+                let mut page = Bitmap::new(width, if self.unknown_height { 0 } else { height })?;
+                if self.default_pixel == 1 {
+                    page.pixels.fill(1);
+                }
+                self.page = Some(page);
+            }
+            //ストライプの終端
+            50 => {
+                let end_row: u32 = reader.u32()?;
+                if let (true, Some(page)) = (self.unknown_height, self.page.as_mut()) {
+                    page.extend_height(end_row.saturating_add(1), self.default_pixel)?;
+                }
+            }
+            //テーブル
+            53 => {
+                let table = HuffmanTable::parse(data)?;
+                self.results
+                    .insert(header.number, SegmentResult::Table(table));
+            }
+            //ページの終端・ファイルの終端・プロファイル
+            49 | 51 | 52 => {}
+            //拡張(最上位ビットが必須の拡張であることを示す。)
+            62 => {
+                let extension_type: u32 = reader.u32()?;
+                if extension_type & 0x8000_0000 != 0 {
+                    return Err(format!(
+                        "JBIG2 EXTENSION SEGMENT IS NOT SUPPORTED. EXTENSION_TYPE: {:#010x}",
+                        extension_type
+                    ));
+                }
+            }
+            segment_type => {
+                return Err(format!(
+                    "JBIG2 SEGMENT TYPE IS NOT SUPPORTED. SEGMENT_TYPE: {}",
+                    segment_type
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    ///復号した領域をページに合成する。(中間領域の場合は後続のリファインメント領域のために保持する。)
+    fn store_region(
+        &mut self,
+        header: &SegmentHeader,
+        bitmap: Bitmap,
+        info: &RegionInfo,
+    ) -> Result<(), String> {
+        if matches!(header.segment_type, 4 | 20 | 36 | 40) {
+            self.results
+                .insert(header.number, SegmentResult::Region(bitmap));
+            Ok(())
+        } else {
+            self.compose(&bitmap, info)
+        }
+    }
+}
+
+///PDFに埋め込まれたJBIG2のデータを展開する。
+/// # Arguments
+/// * `data` - 画像XObjectのデータ。(ページのセグメント。)
+/// * `globals` - JBIG2Globalsのデータ。(複数の画像で共有するシンボル辞書などのセグメント。)
+/// * `width` - 画像XObjectの幅。
+/// * `height` - 画像XObjectの高さ。
+/// # Returns
+/// * 成功時:1ピクセル1ビットの画像データ。(行ごとにバイト境界に揃え、0が黒、1が白。)
+/// * 失敗時:未対応の形式もしくは不正なデータ。
+fn decode_jbig2(
+    data: &[u8],
+    globals: Option<&[u8]>,
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>, String> {
+    let mut decoder = PageDecoder::new();
+    for (header, segment_data) in parse_segments(globals.unwrap_or_default())?
+        .into_iter()
+        .chain(parse_segments(data)?)
+    {
+        decoder.decode_segment(&header, segment_data)?;
+    }
+    let page: Bitmap = decoder
+        .page
+        .ok_or_else(|| "JBIG2 PAGE INFORMATION SEGMENT NOT FOUND.".to_string())?;
+    Ok(page.to_samples(width, height))
+}
+
+///JBIG2Globalsのデータを取得する。
+fn get_globals(
+    params: &JBIG2DecodeParams,
+    resolve: &impl Resolve,
+) -> Result<Option<Arc<[u8]>>, PdfError> {
+    match params.globals {
+        Some(ref globals) => Ok(Some(globals.data(resolve)?)),
+        None => Ok(None),
+    }
+}
+
+///JBIG2Decodeの画像XObjectのデータをJBIG2Globalsと合わせて展開する。
+/// # Arguments
+/// * `img` - 画像XObject(Width/Heightの取得に使用する)。
+/// * `data` - `raw_image_data()`で取得した画像データ。
+/// * `params` - JBIG2Decodeのパラメータ。
+/// * `resolve` - JBIG2Globalsの参照の解決に使用するリゾルバ。
+/// # Returns
+/// * 成功時:フィルタを適用していない画像と同じ形式の画像データ。(1ピクセル1ビット・0が黒。)
+/// * 失敗時:PdfError(JBIG2Globalsの取得失敗・未対応の形式もしくは不正なデータ)。
+pub(crate) fn decode_jbig2_image(
+    img: &ImageXObject,
+    data: &[u8],
+    params: &JBIG2DecodeParams,
+    resolve: &impl Resolve,
+) -> Result<Vec<u8>, PdfError> {
+    let globals: Option<Arc<[u8]>> = get_globals(params, resolve)?;
+    decode_jbig2(data, globals.as_deref(), img.width, img.height)
+        .map_err(|msg| PdfError::Other { msg })
+}
+
+///JBIG2Globalsのセグメントと画像のセグメントを連結し、単独で読み込めるJBIG2ファイルを作成する。
+///(展開できなかった画像を元の形式で保存するために使用する。JBIG2Globalsを取得できない場合は画像のセグメントのみ。)
+pub(crate) fn jbig2_file(
+    data: &[u8],
+    params: &JBIG2DecodeParams,
+    resolve: &impl Resolve,
+) -> Vec<u8> {
+    let globals: Option<Arc<[u8]>> = get_globals(params, resolve).unwrap_or_default();
+    let mut file: Vec<u8> = JBIG2_FILE_HEADER.to_vec();
+    file.extend_from_slice(globals.as_deref().unwrap_or_default());
+    file.extend_from_slice(data);
+    file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //テスト用のJBIG2のデータ。(T.88の符号化手順で作成したもの。)
+    const GENERIC_REGION: &[u8] = &[
+        0x00, 0x00, 0x00, 0x00, 0x30, 0x00, 0x01, 0x00, 0x00, 0x00, 0x13, 0x00, 0x00, 0x00, 0x10,
+        0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x08,
+        0x00, 0x00, 0x00, 0x01, 0x26, 0x00, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x10,
+        0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x03,
+        0xFF, 0xFD, 0xFF, 0x02, 0xFE, 0xFE, 0xFE, 0xDC, 0x28, 0xA3, 0xB6, 0xAF, 0x11, 0x4A, 0xBF,
+        0xFF, 0xAC, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x02, 0x32, 0x00, 0x01, 0x00, 0x00,
+        0x00, 0x04, 0x00, 0x00, 0x00, 0x07,
+    ];
+    const GENERIC_REGION_TEMPLATE2: &[u8] = &[
+        0x00, 0x00, 0x00, 0x00, 0x30, 0x00, 0x01, 0x00, 0x00, 0x00, 0x13, 0x00, 0x00, 0x00, 0x10,
+        0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x01, 0x26, 0x00, 0x01, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x10,
+        0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x04, 0xFE,
+        0xFE, 0x8E, 0x25, 0xF7, 0xA0, 0x05, 0xF7, 0x2C, 0x1E, 0xE8, 0xB7, 0xFF, 0xAC,
+    ];
+    const MMR_REGION: &[u8] = &[
+        0x00, 0x00, 0x00, 0x00, 0x30, 0x00, 0x01, 0x00, 0x00, 0x00, 0x13, 0x00, 0x00, 0x00, 0x10,
+        0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x01, 0x26, 0x00, 0x01, 0x00, 0x00, 0x00, 0x1E, 0x00, 0x00, 0x00, 0x10,
+        0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x97,
+        0x66, 0xDF, 0xFF, 0x11, 0x91, 0xC1, 0xFC, 0x60, 0x02, 0x00, 0x20,
+    ];
+    const SYMBOL_GLOBALS: &[u8] = &[
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1A, 0x04, 0x00, 0x03, 0xFF,
+        0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x03, 0x66, 0xE4, 0x74, 0x0E, 0xC4, 0xF4, 0xB2,
+        0x4E, 0x6B, 0x08, 0xCE, 0xBF, 0xFF, 0xAC,
+    ];
+    const TEXT_REGION: &[u8] = &[
+        0x00, 0x00, 0x00, 0x01, 0x30, 0x00, 0x01, 0x00, 0x00, 0x00, 0x13, 0x00, 0x00, 0x00, 0x18,
+        0x00, 0x00, 0x00, 0x0E, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x02, 0x06, 0x20, 0x00, 0x01, 0x00, 0x00, 0x00, 0x23, 0x00, 0x00, 0x00,
+        0x18, 0x00, 0x00, 0x00, 0x0E, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x10, 0x00, 0x00, 0x00, 0x06, 0xA5, 0xC2, 0xBF, 0xEB, 0x80, 0x15, 0x5E, 0x02, 0x91, 0x8F,
+        0xFF, 0xAC,
+    ];
+    const HUFFMAN_TEXT_REGION: &[u8] = &[
+        0x00, 0x00, 0x00, 0x00, 0x30, 0x00, 0x01, 0x00, 0x00, 0x00, 0x13, 0x00, 0x00, 0x00, 0x18,
+        0x00, 0x00, 0x00, 0x0E, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x1F, 0x00, 0x01, 0x00, 0x00,
+        0x00, 0x03, 0x00, 0x00, 0x00, 0x03, 0xDC, 0x3F, 0x00, 0x40, 0xE0, 0x40, 0xB8, 0xDF, 0x80,
+        0xE7, 0x00, 0x98, 0x80, 0xEF, 0x80, 0x98, 0x80, 0xE8, 0x80, 0x00, 0xC0, 0x00, 0x00, 0x00,
+        0x02, 0x06, 0x20, 0x01, 0x01, 0x00, 0x00, 0x00, 0x34, 0x00, 0x00, 0x00, 0x18, 0x00, 0x00,
+        0x00, 0x0E, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x06, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x50, 0x0E, 0xD5, 0xD0, 0xD6, 0x68, 0x04, 0x6A,
+        0xA0,
+    ];
+    const HALFTONE_REGION: &[u8] = &[
+        0x00, 0x00, 0x00, 0x00, 0x30, 0x00, 0x01, 0x00, 0x00, 0x00, 0x13, 0x00, 0x00, 0x00, 0x12,
+        0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x01, 0x10, 0x00, 0x01, 0x00, 0x00, 0x00, 0x11, 0x00, 0x04, 0x04, 0x00,
+        0x00, 0x00, 0x03, 0x74, 0x63, 0x50, 0x0F, 0x26, 0xBF, 0x10, 0x7F, 0xFF, 0xAC, 0x00, 0x00,
+        0x00, 0x02, 0x16, 0x20, 0x01, 0x01, 0x00, 0x00, 0x00, 0x2B, 0x00, 0x00, 0x00, 0x12, 0x00,
+        0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00,
+        0x00, 0x05, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04,
+        0x80, 0x00, 0x00, 0x35, 0x25, 0x7F, 0xFF, 0xAC,
+    ];
+    const REFINEMENT_REGION: &[u8] = &[
+        0x00, 0x00, 0x00, 0x00, 0x30, 0x00, 0x01, 0x00, 0x00, 0x00, 0x13, 0x00, 0x00, 0x00, 0x10,
+        0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x01, 0x24, 0x00, 0x01, 0x00, 0x00, 0x00, 0x21, 0x00, 0x00, 0x00, 0x10,
+        0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x06, 0x02,
+        0xFF, 0x8E, 0x36, 0x49, 0x0A, 0x0B, 0x9B, 0xE5, 0x73, 0xF4, 0x5F, 0x1F, 0xFF, 0xAC, 0x00,
+        0x00, 0x00, 0x02, 0x2A, 0x20, 0x01, 0x01, 0x00, 0x00, 0x00, 0x22, 0x00, 0x00, 0x00, 0x10,
+        0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x02, 0xFF,
+        0xFF, 0xFF, 0xFF, 0x51, 0x7D, 0xBC, 0xCC, 0xE1, 0x6C, 0xBD, 0x6D, 0xE2, 0x7F, 0xFF, 0xAC,
+    ];
+
+    ///文字列の各行から画像を作成する。("#"が黒。)
+    fn bitmap(rows: &[&str]) -> Bitmap {
+        let mut bitmap = Bitmap::new(rows[0].len() as u32, rows.len() as u32).unwrap();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                bitmap.set(x as u32, y as u32, (c == '#') as u8);
+            }
+        }
+        bitmap
+    }
+
+    ///JBIG2のデータを展開し、期待する画像と比較する。
+    fn assert_decoded(data: &[u8], globals: Option<&[u8]>, rows: &[&str]) {
+        let expected: Bitmap = bitmap(rows);
+        let decoded: Vec<u8> =
+            decode_jbig2(data, globals, expected.width, expected.height).unwrap();
+        assert_eq!(
+            decoded,
+            expected.to_samples(expected.width, expected.height)
+        );
+    }
+
+    const GENERIC: [&str; 8] = [
+        "................",
+        "..####....####..",
+        "..####....####..",
+        "..####....####..",
+        "................",
+        ".##############.",
+        ".##############.",
+        "................",
+    ];
+
+    const TEXT: [&str; 14] = [
+        "........................",
+        "..###..###.......###....",
+        ".#...#.#..#..#..#...#...",
+        ".#####.###..###.#####...",
+        ".#...#.#..#..#..#...#...",
+        ".#...#.###......#...#...",
+        "........................",
+        "......###...............",
+        "...#..#..#..............",
+        "..###.###...............",
+        "...#..#..#..............",
+        "......###...............",
+        "........................",
+        "........................",
+    ];
+
+    #[test]
+    fn test_decode_generic_region() {
+        assert_decoded(GENERIC_REGION, None, &GENERIC);
+        assert_decoded(MMR_REGION, None, &GENERIC);
+        //既定のピクセル値が黒のページにXORで合成する。
+        let inverted: Vec<String> = GENERIC
+            .iter()
+            .map(|row| {
+                row.chars()
+                    .map(|c| if c == '#' { '.' } else { '#' })
+                    .collect()
+            })
+            .collect();
+        let inverted: Vec<&str> = inverted.iter().map(|row| row.as_str()).collect();
+        assert_decoded(GENERIC_REGION_TEMPLATE2, None, &inverted);
+        //中間領域をリファインメントした領域。
+        let mut refined: Vec<String> = GENERIC.iter().map(|row| row.to_string()).collect();
+        refined[0].replace_range(0..1, "#");
+        refined[4].replace_range(7..8, "#");
+        refined[6].replace_range(14..15, ".");
+        let refined: Vec<&str> = refined.iter().map(|row| row.as_str()).collect();
+        assert_decoded(REFINEMENT_REGION, None, &refined);
+    }
+
+    #[test]
+    fn test_decode_text_region() {
+        assert_decoded(TEXT_REGION, Some(SYMBOL_GLOBALS), &TEXT);
+        assert_decoded(HUFFMAN_TEXT_REGION, None, &TEXT);
+        //シンボル辞書(JBIG2Globals)がない場合はエラーとする。
+        assert!(decode_jbig2(TEXT_REGION, None, 24, 14).is_err());
+    }
+
+    #[test]
+    fn test_decode_segment_type() {
+        //セグメント番号2・ページ1のセグメントを追加する。
+        let with_segment = |segment_type: u8, segment_data: &[u8]| -> Vec<u8> {
+            let mut data: Vec<u8> = GENERIC_REGION.to_vec();
+            data.extend_from_slice(&2u32.to_be_bytes());
+            data.extend_from_slice(&[segment_type, 0, 1]);
+            data.extend_from_slice(&(segment_data.len() as u32).to_be_bytes());
+            data.extend_from_slice(segment_data);
+            data
+        };
+        //必須でない拡張セグメントとページの終端は無視する。
+        assert_decoded(&with_segment(62, &[0x20, 0, 0, 0]), None, &GENERIC);
+        assert_decoded(&with_segment(49, &[]), None, &GENERIC);
+        //必須の拡張セグメントと未対応の種類のセグメントはエラーとする。
+        assert!(decode_jbig2(&with_segment(62, &[0x80, 0, 0, 0]), None, 16, 8).is_err());
+        assert!(decode_jbig2(&with_segment(5, &[]), None, 16, 8).is_err());
+    }
+
+    #[test]
+    fn test_decode_halftone_region() {
+        assert_decoded(
+            HALFTONE_REGION,
+            None,
+            &[
+                ".........#.#.####.",
+                ".....#....#.#####.",
+                ".........#.#.####.",
+                "......#...#.#####.",
+                "#####.#...........",
+                "####.#.#..#.......",
+                "#####.#...........",
+                "####.#.#...#......",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_to_samples() {
+        //画像XObjectより小さい部分は白、大きい部分は切り捨てる。
+        let bitmap = bitmap(&["#.#", ".#."]);
+        assert_eq!(
+            bitmap.to_samples(10, 3),
+            vec![0x5F, 0xFF, 0xBF, 0xFF, 0xFF, 0xFF]
+        );
+        assert_eq!(bitmap.to_samples(2, 1), vec![0x7F]);
+    }
+}
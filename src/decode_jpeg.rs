@@ -0,0 +1,251 @@
+use crate::color_management::{is_inverted_decode, samples_to_srgb, ImageColorSpace};
+
+use image::{DynamicImage, ImageBuffer, Luma, Rgb};
+use pdf::error::PdfError;
+use pdf::object::ImageXObject;
+use zune_jpeg::zune_core::bytestream::ZCursor;
+use zune_jpeg::zune_core::colorspace::ColorSpace as JpegColorSpace;
+use zune_jpeg::JpegDecoder;
+
+///JPEGを展開した色成分ごとのサンプル値。
+pub(crate) struct JpegSamples {
+    ///サンプル値(ピクセル順・成分順に並ぶ8bitの値)。
+    pub(crate) samples: Vec<u8>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    ///1ピクセルあたりの色成分数(1:グレー、3:RGB、4:CMYK)。
+    pub(crate) components: usize,
+    ///AdobeのAPP14マーカーを持つCMYKのJPEG(値が反転して格納されている)であればtrue。
+    pub(crate) adobe_inverted: bool,
+}
+
+///DCTDecodeの画像XObjectを展開し、色空間とDecode配列に従ってsRGBもしくはグレーの画像に変換する。
+/// # Arguments
+/// * `img` - 画像XObject(ColorSpace/Decodeの取得に使用する)。
+/// * `data` - `raw_image_data()`で取得したJPEGのデータ。
+/// # Returns
+/// * 成功時:展開した画像。
+/// * 失敗時:PdfError(JPEGの展開失敗もしくは未対応の色空間)。
+pub(crate) fn decode_jpeg_image(img: &ImageXObject, data: &[u8]) -> Result<DynamicImage, PdfError> {
+    let jpeg: JpegSamples = decode_jpeg_samples(data)?;
+    let color_space: ImageColorSpace = match img.color_space {
+        Some(ref color_space) => ImageColorSpace::new(color_space)?,
+        None => ImageColorSpace::from_components(jpeg.components)?,
+    };
+    if color_space.components() != jpeg.components {
+        return Err(PdfError::Other {
+            msg: format!(
+                "JPEG COMPONENTS DO NOT MATCH COLOR SPACE. JPEG: {} COLOR_SPACE: {}",
+                jpeg.components,
+                color_space.components()
+            ),
+        });
+    }
+
+    let mut samples: Vec<u8> = jpeg.samples;
+    let mut decode: Option<&[f32]> = img.decode.as_deref();
+    //AdobeのCMYKのJPEGは値が反転して格納されているため元に戻す。
+    //反転を打ち消すためのDecode配列([1 0 1 0 1 0 1 0])が指定されている場合は二重に反転させない。
+    if jpeg.adobe_inverted {
+        samples.iter_mut().for_each(|v| *v = 255 - *v);
+        if is_inverted_decode(decode) {
+            decode = None;
+        }
+    }
+
+    //デバイスグレー・RGBでDecode配列がない場合は変換せずに画像にする。
+    let image: Option<DynamicImage> = match (&color_space, decode) {
+        (ImageColorSpace::Gray, None) => {
+            ImageBuffer::<Luma<u8>, Vec<u8>>::from_raw(jpeg.width, jpeg.height, samples)
+                .map(DynamicImage::ImageLuma8)
+        }
+        (ImageColorSpace::Rgb, None) => {
+            ImageBuffer::<Rgb<u8>, Vec<u8>>::from_raw(jpeg.width, jpeg.height, samples)
+                .map(DynamicImage::ImageRgb8)
+        }
+        _ => {
+            let values: Vec<u16> = samples.iter().map(|v| *v as u16).collect();
+            return samples_to_srgb(&values, jpeg.width, jpeg.height, 8, &color_space, decode);
+        }
+    };
+    image.ok_or_else(|| PdfError::Other {
+        msg: "COULD NOT CREATE IMAGE BUFFER.".to_string(),
+    })
+}
+
+///DCTDecodeの画像XObjectをそのまま保存すると正しい色で表示されない場合はtrueを返す。
+///(CMYKのJPEG、グレー・RGB以外の色空間、Decode配列で値を反転させる画像が該当する。)
+/// # Arguments
+/// * `img` - 画像XObject。
+/// * `data` - `raw_image_data()`で取得したJPEGのデータ。
+pub(crate) fn jpeg_needs_color_conversion(img: &ImageXObject, data: &[u8]) -> bool {
+    let mut decoder = JpegDecoder::new(ZCursor::new(data));
+    //ヘッダを読めない場合は展開もできないため、変換せずにそのまま保存する。
+    if decoder.decode_headers().is_err() {
+        return false;
+    }
+    let components: u8 = decoder.info().map(|info| info.components).unwrap_or(0);
+    let device_color_space: bool = match img.color_space {
+        Some(ref color_space) => matches!(
+            ImageColorSpace::new(color_space),
+            Ok(ImageColorSpace::Gray) | Ok(ImageColorSpace::Rgb)
+        ),
+        None => true,
+    };
+    components == 4 || !device_color_space || is_inverted_decode(img.decode.as_deref())
+}
+
+///JPEGを展開し、色成分ごとのサンプル値を取得する。
+///(CMYK・YCCKのJPEGはRGBに変換せずにCMYKの値を取得する。)
+/// # Arguments
+/// * `data` - JPEGのデータ。
+/// # Returns
+/// * 成功時:サンプル値。
+/// * 失敗時:PdfError(JPEGの展開失敗もしくは未対応の色空間)。
+pub(crate) fn decode_jpeg_samples(data: &[u8]) -> Result<JpegSamples, PdfError> {
+    let to_pdf_error = |e: zune_jpeg::errors::DecodeErrors| PdfError::Other {
+        msg: format!("COULD NOT DECODE JPEG. ERR: {:?}", e),
+    };
+    let mut decoder = JpegDecoder::new(ZCursor::new(data));
+    decoder.decode_headers().map_err(to_pdf_error)?;
+    let input_color_space: JpegColorSpace =
+        decoder.input_colorspace().ok_or_else(|| PdfError::Other {
+            msg: "COULD NOT GET JPEG COLOR SPACE.".to_string(),
+        })?;
+    let (output_color_space, components): (JpegColorSpace, usize) = match input_color_space {
+        JpegColorSpace::Luma => (JpegColorSpace::Luma, 1),
+        JpegColorSpace::YCbCr | JpegColorSpace::RGB => (JpegColorSpace::RGB, 3),
+        //CMYK・YCCKは入力と同じ色空間を指定すると変換されずにそのまま出力される。
+        JpegColorSpace::CMYK => (JpegColorSpace::CMYK, 4),
+        JpegColorSpace::YCCK => (JpegColorSpace::YCCK, 4),
+        other => {
+            return Err(PdfError::Other {
+                msg: format!("UNSUPPORTED JPEG COLOR SPACE. COLOR_SPACE: {:?}", other),
+            })
+        }
+    };
+    let options = decoder
+        .options()
+        .jpeg_set_out_colorspace(output_color_space);
+    decoder.set_options(options);
+    let mut samples: Vec<u8> = decoder.decode().map_err(to_pdf_error)?;
+    let (width, height) = decoder.dimensions().unwrap_or((0, 0));
+    if samples.len() < width * height * components {
+        return Err(PdfError::Other {
+            msg: format!(
+                "JPEG DATA IS TOO SHORT. EXPECTED: {} ACTUAL: {}",
+                width * height * components,
+                samples.len()
+            ),
+        });
+    }
+    if input_color_space == JpegColorSpace::YCCK {
+        samples.chunks_mut(4).for_each(ycck_to_cmyk);
+    }
+    Ok(JpegSamples {
+        samples,
+        width: width as u32,
+        height: height as u32,
+        components,
+        adobe_inverted: components == 4 && has_adobe_marker(data),
+    })
+}
+
+///YCCKのピクセルをCMYKに変換する。(YCbCrをRGBに変換し、反転させたものをCMYとする。)
+fn ycck_to_cmyk(pixel: &mut [u8]) {
+    let y: f32 = pixel[0] as f32;
+    let cb: f32 = pixel[1] as f32 - 128.0;
+    let cr: f32 = pixel[2] as f32 - 128.0;
+    let r: f32 = y + 1.402 * cr;
+    let g: f32 = y - 0.344136 * cb - 0.714136 * cr;
+    let b: f32 = y + 1.772 * cb;
+    pixel[0] = 255 - r.round().clamp(0.0, 255.0) as u8;
+    pixel[1] = 255 - g.round().clamp(0.0, 255.0) as u8;
+    pixel[2] = 255 - b.round().clamp(0.0, 255.0) as u8;
+}
+
+///JPEGがAdobeのAPP14マーカーを持っていればtrueを返す。
+fn has_adobe_marker(data: &[u8]) -> bool {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return false;
+    }
+    let mut pos: usize = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return false;
+        }
+        let marker: u8 = data[pos + 1];
+        match marker {
+            //詰め物のバイトは読み飛ばす。
+            0xFF => {
+                pos += 1;
+                continue;
+            }
+            //長さを持たないマーカー。
+            0x01 | 0xD0..=0xD7 => {
+                pos += 2;
+                continue;
+            }
+            //SOS以降にAPP14マーカーは現れない。
+            0xDA | 0xD9 => return false,
+            _ => {}
+        }
+        let length: usize = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        if marker == 0xEE && data[pos + 4..].starts_with(b"Adobe") {
+            return true;
+        }
+        pos += 2 + length;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_adobe_marker() {
+        let adobe: Vec<u8> = [
+            &[0xFF, 0xD8][..],
+            &[0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00],
+            &[0xFF, 0xEE, 0x00, 0x0E],
+            b"Adobe",
+            &[0x00, 0x64, 0x00, 0x00, 0x00, 0x00, 0x00],
+            &[0xFF, 0xDA],
+        ]
+        .concat();
+        assert!(has_adobe_marker(&adobe));
+        let plain: [u8; 8] = [0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x02, 0xFF, 0xDA];
+        assert!(!has_adobe_marker(&plain));
+        assert!(!has_adobe_marker(b"not a jpeg"));
+    }
+
+    #[test]
+    fn test_ycck_to_cmyk() {
+        //Y=255(白)はインクなし、Y=0(黒)はCMYが最大になる。Kはそのまま残る。
+        let mut white: [u8; 4] = [255, 128, 128, 10];
+        ycck_to_cmyk(&mut white);
+        assert_eq!(white, [0, 0, 0, 10]);
+        let mut black: [u8; 4] = [0, 128, 128, 20];
+        ycck_to_cmyk(&mut black);
+        assert_eq!(black, [255, 255, 255, 20]);
+    }
+
+    #[test]
+    fn test_decode_jpeg_samples_rgb() {
+        let image = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(8, 8, Rgb([200, 100, 50])));
+        let mut encoded: Vec<u8> = Vec::new();
+        image
+            .write_to(
+                &mut std::io::Cursor::new(&mut encoded),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+        let jpeg = decode_jpeg_samples(&encoded).unwrap();
+        assert_eq!((jpeg.width, jpeg.height, jpeg.components), (8, 8, 3));
+        assert!(!jpeg.adobe_inverted);
+        assert!(jpeg.samples[0].abs_diff(200) <= 3);
+        assert!(jpeg.samples[1].abs_diff(100) <= 3);
+        assert!(jpeg.samples[2].abs_diff(50) <= 3);
+    }
+}
@@ -0,0 +1,386 @@
+use crate::color_management::{samples_to_srgb, ImageColorSpace};
+use crate::decode_ccitt::decode_ccitt_image;
+use crate::decode_jbig2::decode_jbig2_image;
+use crate::decode_jpeg::decode_jpeg_image;
+
+use image::{DynamicImage, ImageBuffer, Luma, Rgb};
+use pdf::enc::StreamFilter;
+use pdf::error::PdfError;
+use pdf::object::{ColorSpace, ImageXObject, Resolve};
+use std::sync::Arc;
+
+///画像XObjectのデータを取得する。(JBIG2Decode・CCITTFaxDecodeの画像は展開し、フィルタを適用していない画像として返す。)
+/// # Arguments
+/// * `img` - 画像XObject。
+/// * `resolve` - 参照の解決に使用するリゾルバ。
+/// # Returns
+/// * 成功時:画像データと未適用のフィルタ。(`raw_image_data()`と同じ。)
+/// * 失敗時:PdfError(画像データの取得もしくはJBIG2・CCITTの展開失敗)。
+pub(crate) fn image_data<'a>(
+    img: &'a ImageXObject,
+    resolve: &impl Resolve,
+) -> Result<(Arc<[u8]>, Option<&'a StreamFilter>), PdfError> {
+    let (data, filter) = img.raw_image_data(resolve)?;
+    match filter {
+        Some(StreamFilter::JBIG2Decode(params)) => Ok((
+            decode_jbig2_image(img, &data, params, resolve)?.into(),
+            None,
+        )),
+        Some(StreamFilter::CCITTFaxDecode(params)) => {
+            Ok((decode_ccitt_image(img, &data, params)?.into(), None))
+        }
+        _ => Ok((data, filter)),
+    }
+}
+
+///画像XObjectのデータを展開して画像に変換する。
+/// # Arguments
+/// * `img` - 画像XObject。
+/// * `data` - `raw_image_data()`で取得した画像データ。
+/// * `filter` - `raw_image_data()`で取得した未適用のフィルタ。
+/// # Returns
+/// * 成功時:展開した画像。(JPXDecode・展開に失敗したJBIG2Decodeの画像は展開できないためNone。)
+/// * 失敗時:PdfError(未対応のフィルタもしくは画像データの展開失敗)。
+pub(crate) fn decode_image(
+    img: &ImageXObject,
+    data: &[u8],
+    filter: Option<&StreamFilter>,
+) -> Result<Option<DynamicImage>, PdfError> {
+    match filter {
+        Some(StreamFilter::DCTDecode(_)) => decode_jpeg_image(img, data).map(Some),
+        Some(StreamFilter::JBIG2Decode(_)) | Some(StreamFilter::JPXDecode) => Ok(None),
+        Some(StreamFilter::CCITTFaxDecode(params)) => {
+            decode_raw_image(img, &decode_ccitt_image(img, data, params)?, None).map(Some)
+        }
+        None | Some(StreamFilter::FlateDecode(_)) => decode_raw_image(img, data, filter).map(Some),
+        Some(f) => Err(PdfError::Other {
+            msg: format!("UNSUPPORTED FILTER. FILTER: {:?}", f),
+        }),
+    }
+}
+
+///画像XObjectのサンプルデータを展開して画像に変換する。
+/// # Arguments
+/// * `img` - 画像XObject(Width/Height/BitsPerComponent/ColorSpaceの取得に使用する)。
+/// * `data` - `raw_image_data()`で取得した画像データ。
+/// * `filter` - `raw_image_data()`で取得した未適用のフィルタ(FlateDecodeもしくはNone)。
+/// # Returns
+/// * 成功時:展開した画像(色空間とDecode配列に従ってsRGBもしくはグレーに変換したもの)。
+/// * 失敗時:PdfError。
+pub(crate) fn decode_raw_image(
+    img: &ImageXObject,
+    data: &[u8],
+    filter: Option<&StreamFilter>,
+) -> Result<DynamicImage, PdfError> {
+    let samples: RawSamples = decode_raw_samples(img, data, filter)?;
+    if img.image_mask {
+        return values_to_image(samples.values, img.width, img.height, 1, 1)
+            .map_err(|msg| PdfError::Other { msg });
+    }
+    let color_space: ImageColorSpace = get_color_space(img.color_space.as_ref())?;
+    match color_space {
+        //デバイス色空間でDecode配列がない場合は16bitの精度を保ったまま画像にする。
+        ImageColorSpace::Gray | ImageColorSpace::Rgb | ImageColorSpace::Cmyk
+            if img.decode.is_none() =>
+        {
+            values_to_image(
+                samples.values,
+                img.width,
+                img.height,
+                samples.components,
+                samples.bits,
+            )
+            .map_err(|msg| PdfError::Other { msg })
+        }
+        _ => samples_to_srgb(
+            &samples.values,
+            img.width,
+            img.height,
+            samples.bits,
+            &color_space,
+            img.decode.as_deref(),
+        ),
+    }
+}
+
+///1成分ずつに分解した画像のサンプル値。
+pub(crate) struct RawSamples {
+    ///サンプル値(ピクセル順・成分順に並ぶ)。
+    pub(crate) values: Vec<u16>,
+    ///1ピクセルあたりの色成分数。
+    pub(crate) components: u32,
+    ///1成分あたりのビット数。
+    pub(crate) bits: u32,
+}
+
+///画像XObjectのサンプルデータを展開し、1成分ずつの値に分解する。
+/// # Arguments
+/// * `img` - 画像XObject(Width/Height/BitsPerComponent/ColorSpaceの取得に使用する)。
+/// * `data` - `raw_image_data()`で取得した画像データ。
+/// * `filter` - `raw_image_data()`で取得した未適用のフィルタ(FlateDecodeもしくはNone)。
+/// # Returns
+/// * 成功時:サンプル値。
+/// * 失敗時:PdfError。
+pub(crate) fn decode_raw_samples(
+    img: &ImageXObject,
+    data: &[u8],
+    filter: Option<&StreamFilter>,
+) -> Result<RawSamples, PdfError> {
+    //FlateDecodeはraw_image_data()で展開されないため、ここで展開する(Predictorも適用される)。
+    let samples: Vec<u8> = match filter {
+        None => data.to_vec(),
+        Some(f @ StreamFilter::FlateDecode(_)) => pdf::enc::decode(data, f)?,
+        Some(f) => {
+            return Err(PdfError::Other {
+                msg: format!("UNSUPPORTED FILTER FOR RAW IMAGE. FILTER: {:?}", f),
+            })
+        }
+    };
+
+    let (components, bits) = if img.image_mask {
+        (1, 1)
+    } else {
+        let components = get_color_space(img.color_space.as_ref())?.components() as u32;
+        let bits = match img.bits_per_component {
+            Some(bits) => bits as u32,
+            None => 8,
+        };
+        (components, bits)
+    };
+
+    let values: Vec<u16> =
+        checked_unpack_samples(&samples, img.width, img.height, components, bits)
+            .map_err(|msg| PdfError::Other { msg })?;
+    Ok(RawSamples {
+        values,
+        components,
+        bits,
+    })
+}
+
+///画像の色空間から変換用の色空間を取得する。
+/// # Arguments
+/// * `color_space` - 画像の色空間。
+/// # Returns
+/// * 成功時:変換用の色空間。
+/// * 失敗時:PdfError(未対応の色空間もしくは色空間の指定なし)。
+fn get_color_space(color_space: Option<&ColorSpace>) -> Result<ImageColorSpace<'_>, PdfError> {
+    match color_space {
+        Some(color_space) => ImageColorSpace::new(color_space),
+        None => Err(PdfError::Other {
+            msg: "COLOR SPACE IS NOT SPECIFIED.".to_string(),
+        }),
+    }
+}
+
+///展開済みのサンプル列から画像を作成する。
+/// # Arguments
+/// * `samples` - 展開済みのサンプル列(各行はバイト境界に揃えられている)。
+/// * `width` - 画像の幅。
+/// * `height` - 画像の高さ。
+/// * `components` - 1ピクセルあたりの色成分数(1、3、4のいずれか)。
+/// * `bits` - 1成分あたりのビット数(1、2、4、8、16のいずれか)。
+/// # Returns
+/// * 成功時:グレースケールもしくはRGBの画像(16bitの場合は16bitのまま)。
+/// * 失敗時:エラーメッセージ。
+#[cfg(test)]
+fn samples_to_image(
+    samples: &[u8],
+    width: u32,
+    height: u32,
+    components: u32,
+    bits: u32,
+) -> Result<DynamicImage, String> {
+    let values: Vec<u16> = checked_unpack_samples(samples, width, height, components, bits)?;
+    values_to_image(values, width, height, components, bits)
+}
+
+///サンプル列の形式と長さを確認し、1成分ずつの値に分解する。
+/// # Returns
+/// * 成功時:サンプル値。
+/// * 失敗時:エラーメッセージ(未対応のビット数・色成分数もしくはデータ不足)。
+fn checked_unpack_samples(
+    samples: &[u8],
+    width: u32,
+    height: u32,
+    components: u32,
+    bits: u32,
+) -> Result<Vec<u16>, String> {
+    if !matches!(bits, 1 | 2 | 4 | 8 | 16) {
+        return Err(format!("UNSUPPORTED BITS PER COMPONENT. BITS: {}", bits));
+    }
+    //DeviceN色空間の色成分数は最大32。
+    if !(1..=32).contains(&components) {
+        return Err(format!(
+            "UNSUPPORTED COLOR COMPONENTS. COMPONENTS: {}",
+            components
+        ));
+    }
+    let row_bytes: usize = (width as usize * components as usize * bits as usize).div_ceil(8);
+    let needed: usize = row_bytes * height as usize;
+    if samples.len() < needed {
+        return Err(format!(
+            "IMAGE DATA IS TOO SHORT. EXPECTED: {} ACTUAL: {}",
+            needed,
+            samples.len()
+        ));
+    }
+    Ok(unpack_samples(
+        samples, width, height, components, bits, row_bytes,
+    ))
+}
+
+///サンプル値から画像を作成する。
+/// # Returns
+/// * 成功時:グレースケールもしくはRGBの画像(16bitの場合は16bitのまま)。
+/// * 失敗時:エラーメッセージ。
+fn values_to_image(
+    values: Vec<u16>,
+    width: u32,
+    height: u32,
+    components: u32,
+    bits: u32,
+) -> Result<DynamicImage, String> {
+    if !matches!(components, 1 | 3 | 4) {
+        return Err(format!(
+            "UNSUPPORTED COLOR COMPONENTS. COMPONENTS: {}",
+            components
+        ));
+    }
+    let max_value: u32 = (1u32 << bits) - 1;
+
+    if bits == 16 {
+        return match components {
+            1 => ImageBuffer::<Luma<u16>, Vec<u16>>::from_raw(width, height, values)
+                .map(DynamicImage::ImageLuma16)
+                .ok_or_else(|| "COULD NOT CREATE IMAGE BUFFER.".to_string()),
+            3 => ImageBuffer::<Rgb<u16>, Vec<u16>>::from_raw(width, height, values)
+                .map(DynamicImage::ImageRgb16)
+                .ok_or_else(|| "COULD NOT CREATE IMAGE BUFFER.".to_string()),
+            _ => {
+                let rgb: Vec<u8> = cmyk_to_rgb(&values, max_value);
+                ImageBuffer::<Rgb<u8>, Vec<u8>>::from_raw(width, height, rgb)
+                    .map(DynamicImage::ImageRgb8)
+                    .ok_or_else(|| "COULD NOT CREATE IMAGE BUFFER.".to_string())
+            }
+        };
+    }
+
+    //8bit未満のサンプルは0-255の範囲に拡大する。
+    let scaled: Vec<u8> = values
+        .iter()
+        .map(|v| (*v as u32 * 255 / max_value) as u8)
+        .collect();
+    match components {
+        1 => ImageBuffer::<Luma<u8>, Vec<u8>>::from_raw(width, height, scaled)
+            .map(DynamicImage::ImageLuma8)
+            .ok_or_else(|| "COULD NOT CREATE IMAGE BUFFER.".to_string()),
+        3 => ImageBuffer::<Rgb<u8>, Vec<u8>>::from_raw(width, height, scaled)
+            .map(DynamicImage::ImageRgb8)
+            .ok_or_else(|| "COULD NOT CREATE IMAGE BUFFER.".to_string()),
+        _ => {
+            let rgb: Vec<u8> = cmyk_to_rgb(&values, max_value);
+            ImageBuffer::<Rgb<u8>, Vec<u8>>::from_raw(width, height, rgb)
+                .map(DynamicImage::ImageRgb8)
+                .ok_or_else(|| "COULD NOT CREATE IMAGE BUFFER.".to_string())
+        }
+    }
+}
+
+///サンプル列を1成分ずつの値に分解する。(各行の末尾の余りビットは読み飛ばす。)
+fn unpack_samples(
+    samples: &[u8],
+    width: u32,
+    height: u32,
+    components: u32,
+    bits: u32,
+    row_bytes: usize,
+) -> Vec<u16> {
+    let per_row: usize = width as usize * components as usize;
+    let mut values: Vec<u16> = Vec::with_capacity(per_row * height as usize);
+    for row in samples.chunks(row_bytes).take(height as usize) {
+        match bits {
+            8 => values.extend(row[..per_row].iter().map(|b| *b as u16)),
+            16 => values.extend(
+                row[..per_row * 2]
+                    .chunks(2)
+                    .map(|b| u16::from_be_bytes([b[0], b[1]])),
+            ),
+            _ => {
+                let mask: u8 = ((1u16 << bits) - 1) as u8;
+                for i in 0..per_row {
+                    let bit_pos: usize = i * bits as usize;
+                    let shift: usize = 8 - bits as usize - (bit_pos % 8);
+                    values.push(((row[bit_pos / 8] >> shift) & mask) as u16);
+                }
+            }
+        }
+    }
+    values
+}
+
+///CMYKの値を単純な計算でRGB(8bit)に変換する。
+fn cmyk_to_rgb(values: &[u16], max_value: u32) -> Vec<u8> {
+    values
+        .chunks(4)
+        .flat_map(|cmyk| {
+            let max_value: u64 = max_value as u64;
+            let k: u64 = max_value - cmyk[3] as u64;
+            let convert = |c: u16| -> u8 {
+                ((max_value - c as u64) * k * 255 / (max_value * max_value)) as u8
+            };
+            [convert(cmyk[0]), convert(cmyk[1]), convert(cmyk[2])]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_to_image_gray_1bit() {
+        //1行目:10000000 2行目:01000000 (幅2、高さ2、各行はバイト境界に揃える)
+        let samples: [u8; 2] = [0b1000_0000, 0b0100_0000];
+        let image = samples_to_image(&samples, 2, 2, 1, 1).unwrap().to_luma8();
+        assert_eq!(image.into_raw(), vec![255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_samples_to_image_rgb_8bit() {
+        let samples: [u8; 6] = [1, 2, 3, 4, 5, 6];
+        let image = samples_to_image(&samples, 2, 1, 3, 8).unwrap();
+        assert!(matches!(image, DynamicImage::ImageRgb8(_)));
+        assert_eq!(image.to_rgb8().into_raw(), samples.to_vec());
+    }
+
+    #[test]
+    fn test_samples_to_image_gray_16bit() {
+        let samples: [u8; 4] = [0x12, 0x34, 0xff, 0xff];
+        let image = samples_to_image(&samples, 2, 1, 1, 16).unwrap();
+        match image {
+            DynamicImage::ImageLuma16(buf) => assert_eq!(buf.into_raw(), vec![0x1234, 0xffff]),
+            _ => panic!("16bit gray image expected."),
+        }
+    }
+
+    #[test]
+    fn test_samples_to_image_cmyk() {
+        //C=255,M=0,Y=0,K=0はシアン、K=255は黒になる。
+        let samples: [u8; 8] = [255, 0, 0, 0, 0, 0, 0, 255];
+        let image = samples_to_image(&samples, 2, 1, 4, 8).unwrap().to_rgb8();
+        assert_eq!(image.into_raw(), vec![0, 255, 255, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_samples_to_image_too_short() {
+        let samples: [u8; 5] = [0; 5];
+        assert!(samples_to_image(&samples, 2, 1, 3, 8).is_err());
+    }
+
+    #[test]
+    fn test_samples_to_image_invalid_bits() {
+        let samples: [u8; 8] = [0; 8];
+        assert!(samples_to_image(&samples, 2, 1, 1, 3).is_err());
+    }
+}
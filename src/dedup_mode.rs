@@ -0,0 +1,143 @@
+use pdf::object::PlainRef;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+
+///同じPDFファイル内の重複した画像を判定する方法。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DedupMode {
+    ///画像データのBLAKE3ハッシュ値で判定する。
+    #[default]
+    Blake3,
+    ///画像データのSHA-256ハッシュ値で判定する。
+    Sha256,
+    ///画像XObjectの参照で判定する。(インライン画像はBLAKE3ハッシュ値で判定する。)
+    ObjectRef,
+    ///重複を判定せずに全ての画像を保存する。
+    Off,
+}
+
+///重複の判定に使用するキー。
+///(画像データ全体の代わりにハッシュ値もしくは参照のみを保持し、PDFファイルの大きさに関わらずメモリの使用量を抑える。)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum DedupKey {
+    Digest([u8; 32]),
+    Object(PlainRef),
+}
+
+impl DedupMode {
+    ///画像データのハッシュ値を取得する。(DedupMode::Sha256の場合はSHA-256、それ以外はBLAKE3。)
+    /// # Arguments
+    /// * `data` - `raw_image_data()`で取得した画像データ。
+    /// * `blake3_hash` - 画像データのBLAKE3ハッシュ値。(マニフェスト用に計算済みのものを使用する。)
+    /// # Returns
+    /// * 重複を判定する場合:ハッシュ値。
+    /// * 重複を判定しない場合(DedupMode::Off):None。
+    pub(crate) fn digest(&self, data: &[u8], blake3_hash: &blake3::Hash) -> Option<[u8; 32]> {
+        match self {
+            DedupMode::Sha256 => Some(Sha256::digest(data).into()),
+            DedupMode::Off => None,
+            _ => Some(*blake3_hash.as_bytes()),
+        }
+    }
+
+    ///ハッシュ値のアルゴリズム名を取得する。(永続化した重複判定ストアで異なるアルゴリズムの値を区別するため。)
+    pub(crate) fn digest_name(&self) -> &'static str {
+        match self {
+            DedupMode::Sha256 => "sha256",
+            _ => "blake3",
+        }
+    }
+
+    ///画像の重複の判定に使用するキーを作成する。
+    /// # Arguments
+    /// * `data` - `raw_image_data()`で取得した画像データ。
+    /// * `blake3_hash` - 画像データのBLAKE3ハッシュ値。(マニフェスト用に計算済みのものを使用する。)
+    /// * `object_ref` - 画像XObjectの参照。(インライン画像の場合はNone。)
+    /// # Returns
+    /// * 重複を判定する場合:キー。
+    /// * 重複を判定しない場合(DedupMode::Off):None。
+    pub(crate) fn key(
+        &self,
+        data: &[u8],
+        blake3_hash: &blake3::Hash,
+        object_ref: Option<PlainRef>,
+    ) -> Option<DedupKey> {
+        match (self, object_ref) {
+            (DedupMode::ObjectRef, Some(r)) => Some(DedupKey::Object(r)),
+            _ => self.digest(data, blake3_hash).map(DedupKey::Digest),
+        }
+    }
+}
+
+impl FromStr for DedupMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "blake3" => Ok(DedupMode::Blake3),
+            "sha256" | "sha-256" => Ok(DedupMode::Sha256),
+            "objref" => Ok(DedupMode::ObjectRef),
+            "off" | "none" => Ok(DedupMode::Off),
+            _ => Err(format!(
+                "UNSUPPORTED DEDUP MODE: {} (blake3|sha256|objref|off)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DedupMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name: &str = match self {
+            DedupMode::Blake3 => "blake3",
+            DedupMode::Sha256 => "sha256",
+            DedupMode::ObjectRef => "objref",
+            DedupMode::Off => "off",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_mode_from_str() {
+        assert_eq!("blake3".parse::<DedupMode>(), Ok(DedupMode::Blake3));
+        assert_eq!("SHA-256".parse::<DedupMode>(), Ok(DedupMode::Sha256));
+        assert_eq!("objref".parse::<DedupMode>(), Ok(DedupMode::ObjectRef));
+        assert_eq!("off".parse::<DedupMode>(), Ok(DedupMode::Off));
+        assert!("md5".parse::<DedupMode>().is_err());
+        assert_eq!(DedupMode::ObjectRef.to_string(), "objref");
+    }
+
+    #[test]
+    fn test_dedup_key() {
+        let data: &[u8] = b"image data";
+        let other: &[u8] = b"other image data";
+        let hash = blake3::hash(data);
+        let other_hash = blake3::hash(other);
+        let r = PlainRef { id: 5, gen: 0 };
+
+        assert_eq!(
+            DedupMode::Blake3.key(data, &hash, Some(r)),
+            DedupMode::Blake3.key(data, &hash, None)
+        );
+        assert_ne!(
+            DedupMode::Sha256.key(data, &hash, None),
+            DedupMode::Sha256.key(other, &other_hash, None)
+        );
+        //参照で判定する場合は、画像データが異なっても同じ参照であれば重複とする。
+        assert_eq!(
+            DedupMode::ObjectRef.key(data, &hash, Some(r)),
+            DedupMode::ObjectRef.key(other, &other_hash, Some(r))
+        );
+        assert_eq!(
+            DedupMode::ObjectRef.key(data, &hash, None),
+            DedupMode::Blake3.key(data, &hash, None)
+        );
+        assert_eq!(DedupMode::Off.key(data, &hash, Some(r)), None);
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+///保存した画像ファイルの接尾辞とパス。(マスク適用前の画像も保存した場合は複数になる。)
+pub(crate) type StoredFiles = Vec<(String, PathBuf)>;
+
+///PDFファイル間で共有する重複判定ストア。
+///画像データのハッシュ値ごとに、最初に保存した画像ファイル(接尾辞とパス)を記録する。
+///ファイルに永続化する場合は1行に「アルゴリズム名:ハッシュ値(16進数)<TAB>接尾辞<TAB>パス」を記録する。
+///(並列に処理している別のPDFファイルの同じ画像は、記録される前であれば両方保存されることがある。)
+pub(crate) struct DedupStore {
+    ///ハッシュ値のアルゴリズム名。(異なるアルゴリズムで記録された行は無視する。)
+    digest_name: &'static str,
+    ///保存済みの画像(ハッシュ値と、接尾辞・ファイルのパス)。
+    entries: RwLock<HashMap<[u8; 32], StoredFiles>>,
+    ///追記用のストアファイル。(永続化しない場合はNone。)
+    writer: Option<Mutex<File>>,
+}
+
+impl DedupStore {
+    ///永続化しない空のストアを作成する。
+    /// # Arguments
+    /// * `digest_name` - ハッシュ値のアルゴリズム名。
+    pub(crate) fn new(digest_name: &'static str) -> Self {
+        DedupStore {
+            digest_name,
+            entries: RwLock::new(HashMap::new()),
+            writer: None,
+        }
+    }
+
+    ///前回までの実行で作成されたストアファイルを読み込む。(存在しない場合は作成する。)
+    ///(削除されたファイルの行と書き込み途中の行は無視し、有効な行のみでストアファイルを作成し直す。)
+    /// # Arguments
+    /// * `store_path` - ストアファイルのパス。
+    /// * `digest_name` - ハッシュ値のアルゴリズム名。
+    pub(crate) fn open(store_path: &Path, digest_name: &'static str) -> std::io::Result<Self> {
+        let mut entries: HashMap<[u8; 32], StoredFiles> = HashMap::new();
+        //他のアルゴリズムの行は削除せずに残す。
+        let mut other_lines: Vec<String> = Vec::new();
+        if store_path.is_file() {
+            let reader = BufReader::new(File::open(store_path)?);
+            for line in reader.split(b'\n') {
+                let line: Vec<u8> = line?;
+                let line: &str = match std::str::from_utf8(&line) {
+                    Ok(line) => line,
+                    Err(_) => continue,
+                };
+                let mut fields = line.splitn(3, '\t');
+                let (key, suffix, path) = match (fields.next(), fields.next(), fields.next()) {
+                    (Some(key), Some(suffix), Some(path)) => (key, suffix, PathBuf::from(path)),
+                    _ => continue,
+                };
+                let (name, hex) = match key.split_once(':') {
+                    Some(key) => key,
+                    None => continue,
+                };
+                if !path.is_file() {
+                    continue;
+                }
+                if name != digest_name {
+                    other_lines.push(line.to_string());
+                    continue;
+                }
+                if let Some(digest) = decode_hex(hex) {
+                    entries
+                        .entry(digest)
+                        .or_default()
+                        .push((suffix.to_string(), path));
+                }
+            }
+        }
+        let mut writer: File = File::create(store_path)?;
+        for line in other_lines.iter() {
+            writeln!(writer, "{}", line)?;
+        }
+        for (digest, files) in entries.iter() {
+            for (suffix, path) in files.iter() {
+                writeln!(
+                    writer,
+                    "{}:{}\t{}\t{}",
+                    digest_name,
+                    encode_hex(digest),
+                    suffix,
+                    path.display()
+                )?;
+            }
+        }
+        writer.flush()?;
+        let writer: File = OpenOptions::new().append(true).open(store_path)?;
+        Ok(DedupStore {
+            digest_name,
+            entries: RwLock::new(entries),
+            writer: Some(Mutex::new(writer)),
+        })
+    }
+
+    ///最初に保存した画像ファイルを取得する。(いずれかのファイルが削除されていればNone。)
+    /// # Arguments
+    /// * `digest` - 画像データのハッシュ値。
+    pub(crate) fn get(&self, digest: &[u8; 32]) -> Option<StoredFiles> {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        let files = entries.get(digest)?;
+        if files.iter().all(|(_, path)| path.is_file()) {
+            Some(files.clone())
+        } else {
+            None
+        }
+    }
+
+    ///保存した画像ファイルを記録する。(既に記録済みのファイルが残っている場合は記録しない。)
+    /// # Arguments
+    /// * `digest` - 画像データのハッシュ値。
+    /// * `files` - 保存したファイルの接尾辞とパス。(永続化する場合はフルパスに変換して記録する。)
+    /// # Returns
+    /// * 記録した場合:true。
+    pub(crate) fn insert(
+        &self,
+        digest: [u8; 32],
+        files: &[(String, PathBuf)],
+    ) -> std::io::Result<bool> {
+        let files: StoredFiles = files
+            .iter()
+            .map(|(suffix, path)| {
+                let path: PathBuf = std::path::absolute(path).unwrap_or_else(|_| path.clone());
+                (suffix.clone(), path)
+            })
+            .collect();
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        if let Some(recorded) = entries.get(&digest) {
+            if recorded.iter().all(|(_, path)| path.is_file()) {
+                return Ok(false);
+            }
+        }
+        if let Some(ref writer) = self.writer {
+            let mut lines: String = String::new();
+            for (suffix, path) in files.iter() {
+                lines.push_str(&format!(
+                    "{}:{}\t{}\t{}\n",
+                    self.digest_name,
+                    encode_hex(&digest),
+                    suffix,
+                    path.display()
+                ));
+            }
+            let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+            writer.write_all(lines.as_bytes())?;
+            writer.flush()?;
+        }
+        entries.insert(digest, files);
+        Ok(true)
+    }
+}
+
+///ハッシュ値を16進数の文字列に変換する。
+fn encode_hex(digest: &[u8; 32]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+///16進数の文字列をハッシュ値に変換する。(64文字の16進数でなければNone。)
+fn decode_hex(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut digest: [u8; 32] = [0; 32];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_dedup_store() {
+        let dir_path: PathBuf = std::env::temp_dir().join(format!(
+            "pdf_to_image_rs_dedup_store_{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir_path).unwrap();
+        let store_path: PathBuf = dir_path.join("dedup_store.tsv");
+        let image_path: PathBuf = dir_path.join("a.png");
+        let removed_path: PathBuf = dir_path.join("b.png");
+        fs::write(&image_path, b"a").unwrap();
+        fs::write(&removed_path, b"b").unwrap();
+
+        let store = DedupStore::open(&store_path, "blake3").unwrap();
+        let digest: [u8; 32] = *blake3::hash(b"a").as_bytes();
+        let removed_digest: [u8; 32] = *blake3::hash(b"b").as_bytes();
+        assert!(store.get(&digest).is_none());
+        assert!(store
+            .insert(digest, &[(String::new(), image_path.clone())])
+            .unwrap());
+        //最初に保存したファイルが残っていれば記録し直さない。
+        assert!(!store
+            .insert(digest, &[(String::new(), dir_path.join("c.png"))])
+            .unwrap());
+        assert!(store
+            .insert(removed_digest, &[(String::new(), removed_path.clone())])
+            .unwrap());
+        assert_eq!(store.get(&digest).unwrap()[0].1, image_path);
+        drop(store);
+        //削除されたファイルと書き込み途中の行は読み込まない。
+        fs::remove_file(&removed_path).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&store_path).unwrap();
+        file.write_all(b"blake3:00").unwrap();
+        drop(file);
+
+        let store = DedupStore::open(&store_path, "blake3").unwrap();
+        let found = store.get(&digest);
+        let removed = store.get(&removed_digest);
+        //異なるアルゴリズムのストアとしては読み込まない。
+        let sha256_store = DedupStore::open(&store_path, "sha256").unwrap();
+        let sha256_found = sha256_store.get(&digest);
+        drop(sha256_store);
+        let reopened = DedupStore::open(&store_path, "blake3").unwrap();
+        let found_again = reopened.get(&digest);
+        fs::remove_dir_all(&dir_path).unwrap();
+        assert_eq!(found.unwrap()[0].1, image_path);
+        assert!(removed.is_none());
+        assert!(sha256_found.is_none());
+        assert!(found_again.is_some());
+        assert_eq!(decode_hex(&encode_hex(&digest)), Some(digest));
+        assert_eq!(DedupStore::new("blake3").get(&digest), None);
+    }
+}
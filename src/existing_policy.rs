@@ -0,0 +1,66 @@
+use std::fmt;
+use std::str::FromStr;
+
+///画像の保存先ディレクトリが既に存在する場合の動作。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExistingPolicy {
+    ///PDFファイルを処理せずに無視する。
+    #[default]
+    Skip,
+    ///保存先ディレクトリを削除してから画像を取得し直す。
+    Overwrite,
+    ///前回の実行で保存されなかった画像のみを取得する。(保存先ディレクトリの再開用マニフェストを使用する。)
+    Resume,
+    ///エラーとして扱う。
+    Fail,
+}
+
+impl FromStr for ExistingPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "skip" => Ok(ExistingPolicy::Skip),
+            "overwrite" => Ok(ExistingPolicy::Overwrite),
+            "resume" => Ok(ExistingPolicy::Resume),
+            "fail" => Ok(ExistingPolicy::Fail),
+            _ => Err(format!(
+                "UNSUPPORTED EXISTING POLICY: {} (skip|overwrite|resume|fail)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ExistingPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name: &str = match self {
+            ExistingPolicy::Skip => "skip",
+            ExistingPolicy::Overwrite => "overwrite",
+            ExistingPolicy::Resume => "resume",
+            ExistingPolicy::Fail => "fail",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_existing_policy_from_str() {
+        assert_eq!("skip".parse::<ExistingPolicy>(), Ok(ExistingPolicy::Skip));
+        assert_eq!(
+            "Overwrite".parse::<ExistingPolicy>(),
+            Ok(ExistingPolicy::Overwrite)
+        );
+        assert_eq!(
+            "resume".parse::<ExistingPolicy>(),
+            Ok(ExistingPolicy::Resume)
+        );
+        assert_eq!("FAIL".parse::<ExistingPolicy>(), Ok(ExistingPolicy::Fail));
+        assert!("keep".parse::<ExistingPolicy>().is_err());
+        assert_eq!(ExistingPolicy::Resume.to_string(), "resume");
+    }
+}
@@ -0,0 +1,323 @@
+use crate::dedup_mode::DedupMode;
+use pdf::error::PdfError;
+use std::fmt;
+use std::path::PathBuf;
+
+///画像取得処理で発生するエラー。
+#[derive(Debug)]
+pub enum ExtractError {
+    ///PDFファイルの検索に失敗した。
+    Seek { path: PathBuf, reason: String },
+    ///PDFファイルのフルパスの取得に失敗した。
+    PathResolution { path: PathBuf, reason: String },
+    ///画像の保存先ディレクトリの作成に失敗した。
+    DirectoryCreation {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    ///画像の保存先ディレクトリが既に存在する。(ExistingPolicy::Fail指定時。)
+    DestinationExists { path: PathBuf },
+    ///画像の保存先ディレクトリの削除に失敗した。(ExistingPolicy::Overwrite指定時。)
+    DirectoryRemoval {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    ///再開用マニフェストの読み込み・書き込み、もしくは抽出結果のマニフェストの書き込みに失敗した。
+    Manifest {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    ///PDFファイル間で共有する重複判定ストアの読み込みもしくは書き込みに失敗した。
+    DedupStore {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    ///PDFファイルのデータ(標準入力など)の読み込みに失敗した。
+    Input { source: std::io::Error },
+    ///PDFファイルのオープンに失敗した。
+    Open { path: PathBuf, source: PdfError },
+    ///暗号化されたPDFファイルのパスワードが誤っているか、指定されていない。
+    Password { path: PathBuf, source: PdfError },
+    ///パスワードを記載したファイル(--password-file・--password-map)の読み込みに失敗した。
+    PasswordFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    ///ページもしくはページ内の画像の取得に失敗した。
+    Page { page: u64, source: PdfError },
+    ///未対応の形式(フィルタ)の画像だった。
+    UnsupportedFilter {
+        page: u64,
+        object_name: String,
+        filter: String,
+    },
+    ///画像データの展開もしくは変換に失敗した。
+    Decode {
+        page: u64,
+        object_name: String,
+        source: PdfError,
+    },
+    ///ページの描画に失敗した。
+    Render { page: u64, source: PdfError },
+    ///画像ファイルの作成もしくは書き込みに失敗した。
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    ///アーカイブの作成もしくは書き込みの終了(終端のレコードの書き込みなど)に失敗した。
+    Archive {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    ///ワーカースレッドがパニックした。
+    WorkerPanicked { count: usize },
+    ///ファイル名のテンプレートが重複の判定方法ではファイル名の重複を避けられない。
+    ///(`{page}`と`{index}`の両方を含まないテンプレートを、DedupMode::ObjectRef・DedupMode::Offと組み合わせた。)
+    FileNameTemplate {
+        template: String,
+        dedup_mode: DedupMode,
+    },
+}
+
+impl ExtractError {
+    ///PDFファイルのオープンに失敗した場合のエラーを作成する。
+    ///(パスワードが誤っているか指定されていない場合はExtractError::Password、それ以外はExtractError::Open。)
+    pub(crate) fn open_failed(path: PathBuf, source: PdfError) -> Self {
+        if is_password_error(&source) {
+            ExtractError::Password { path, source }
+        } else {
+            ExtractError::Open { path, source }
+        }
+    }
+
+    ///エラーに対応する終了コードを取得する。
+    /// # Returns
+    /// * 10:PDFファイルの検索失敗。
+    /// * 20:PDFファイルのフルパス取得失敗。
+    /// * 21:ディレクトリ作成失敗・ディレクトリ削除失敗。
+    /// * 22:PDFファイルオープン失敗・PDFファイルのデータの読み込み失敗。
+    /// * 23:保存先ディレクトリが既に存在する。
+    /// * 24:再開用マニフェストの読み込み・作成失敗、抽出結果のマニフェストの作成失敗。
+    /// * 25:重複判定ストアの読み込み・書き込み失敗。
+    /// * 26:アーカイブの書き込み失敗。
+    /// * 27:暗号化されたPDFファイルのパスワードの誤り・未指定。
+    /// * 28:パスワードを記載したファイルの読み込み失敗。
+    /// * 29:ファイル名のテンプレートと重複の判定方法の組み合わせではファイル名が重複する。
+    /// * 1:上記以外(ページ取得失敗、ページ内画像取得失敗など)。
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ExtractError::Seek { .. } => 10,
+            ExtractError::PathResolution { .. } => 20,
+            ExtractError::DirectoryCreation { .. } => 21,
+            ExtractError::DirectoryRemoval { .. } => 21,
+            ExtractError::Input { .. } => 22,
+            ExtractError::Open { .. } => 22,
+            ExtractError::DestinationExists { .. } => 23,
+            ExtractError::Manifest { .. } => 24,
+            ExtractError::DedupStore { .. } => 25,
+            ExtractError::Archive { .. } => 26,
+            ExtractError::Password { .. } => 27,
+            ExtractError::PasswordFile { .. } => 28,
+            ExtractError::FileNameTemplate { .. } => 29,
+            _ => 1,
+        }
+    }
+}
+
+///パスワードの誤りもしくは未指定によるエラーであればtrueを返す。(コンテキスト付きのエラーは元のエラーで判定する。)
+fn is_password_error(e: &PdfError) -> bool {
+    match e {
+        PdfError::InvalidPassword => true,
+        PdfError::Try { source, .. } => is_password_error(source),
+        _ => false,
+    }
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractError::Seek { path, reason } => write!(
+                f,
+                "ERROR OCCURED WHILE SEEKING PDF FILES. DIRECTORY: {} ERR: {}",
+                path.display(),
+                reason
+            ),
+            ExtractError::PathResolution { path, reason } => write!(
+                f,
+                "COULD NOT GET PDF FULL PATH. FILE: {} ERR: {}",
+                path.display(),
+                reason
+            ),
+            ExtractError::DirectoryCreation { path, source } => write!(
+                f,
+                "COULD NOT CREATE DIRECTORY. DIRECTORY: {} ERR: {}",
+                path.display(),
+                source
+            ),
+            ExtractError::DestinationExists { path } => {
+                write!(f, "DIRECTORY ALREADY EXISTS. DIRECTORY: {}", path.display())
+            }
+            ExtractError::DirectoryRemoval { path, source } => write!(
+                f,
+                "COULD NOT REMOVE DIRECTORY. DIRECTORY: {} ERR: {}",
+                path.display(),
+                source
+            ),
+            ExtractError::Manifest { path, source } => write!(
+                f,
+                "COULD NOT READ OR WRITE MANIFEST. DIRECTORY: {} ERR: {}",
+                path.display(),
+                source
+            ),
+            ExtractError::DedupStore { path, source } => write!(
+                f,
+                "COULD NOT READ OR WRITE DEDUP STORE. FILE: {} ERR: {}",
+                path.display(),
+                source
+            ),
+            ExtractError::Input { source } => {
+                write!(f, "COULD NOT READ PDF DATA. ERR: {}", source)
+            }
+            ExtractError::Open { path, source } => write!(
+                f,
+                "COULD NOT OPEN PDF FILE. FILE: {} ERR: {}",
+                path.display(),
+                source
+            ),
+            ExtractError::Page { page, source } => {
+                write!(f, "PAGE PROCESS ERROR. PAGE: {} ERR: {}", page, source)
+            }
+            ExtractError::UnsupportedFilter {
+                page,
+                object_name,
+                filter,
+            } => write!(
+                f,
+                "UNSUPPORTED IMAGE FORMAT. PAGE: {} OBJECT_NAME: {} FILTER: {}",
+                page, object_name, filter
+            ),
+            ExtractError::Decode {
+                page,
+                object_name,
+                source,
+            } => write!(
+                f,
+                "COULD NOT DECODE IMAGE. PAGE: {} OBJECT_NAME: {} ERR: {}",
+                page, object_name, source
+            ),
+            ExtractError::Render { page, source } => {
+                write!(f, "COULD NOT RENDER PAGE. PAGE: {} ERR: {}", page, source)
+            }
+            ExtractError::Write { path, source } => write!(
+                f,
+                "COULD NOT WRITE IMAGE FILE. FILE: {} ERR: {}",
+                path.display(),
+                source
+            ),
+            ExtractError::Archive { path, source } => write!(
+                f,
+                "COULD NOT WRITE ARCHIVE. ARCHIVE: {} ERR: {}",
+                path.display(),
+                source
+            ),
+            ExtractError::Password { path, source } => write!(
+                f,
+                "WRONG OR MISSING PASSWORD. FILE: {} ERR: {}",
+                path.display(),
+                source
+            ),
+            ExtractError::PasswordFile { path, source } => write!(
+                f,
+                "COULD NOT READ PASSWORD FILE. FILE: {} ERR: {}",
+                path.display(),
+                source
+            ),
+            ExtractError::WorkerPanicked { count } => {
+                write!(f, "WORKER THREAD PANICKED. COUNT: {}", count)
+            }
+            ExtractError::FileNameTemplate {
+                template,
+                dedup_mode,
+            } => write!(
+                f,
+                "FILE NAME TEMPLATE MAY PRODUCE DUPLICATE NAMES WITH DEDUP MODE {}. USE BOTH {{page}} AND {{index}} OR DEDUP MODE blake3|sha256: {}",
+                dedup_mode, template
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ExtractError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExtractError::DirectoryCreation { source, .. } => Some(source),
+            ExtractError::DirectoryRemoval { source, .. } => Some(source),
+            ExtractError::Manifest { source, .. } => Some(source),
+            ExtractError::DedupStore { source, .. } => Some(source),
+            ExtractError::Input { source } => Some(source),
+            ExtractError::Open { source, .. } => Some(source),
+            ExtractError::Password { source, .. } => Some(source),
+            ExtractError::PasswordFile { source, .. } => Some(source),
+            ExtractError::Page { source, .. } => Some(source),
+            ExtractError::Decode { source, .. } => Some(source),
+            ExtractError::Render { source, .. } => Some(source),
+            ExtractError::Write { source, .. } => Some(source),
+            ExtractError::Archive { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code() {
+        let seek = ExtractError::Seek {
+            path: PathBuf::from("a"),
+            reason: String::new(),
+        };
+        assert_eq!(seek.exit_code(), 10);
+        let path = ExtractError::PathResolution {
+            path: PathBuf::from("a.pdf"),
+            reason: String::new(),
+        };
+        assert_eq!(path.exit_code(), 20);
+        let dir = ExtractError::DirectoryCreation {
+            path: PathBuf::from("a"),
+            source: std::io::Error::other("test"),
+        };
+        assert_eq!(dir.exit_code(), 21);
+        let exists = ExtractError::DestinationExists {
+            path: PathBuf::from("a"),
+        };
+        assert_eq!(exists.exit_code(), 23);
+        let archive = ExtractError::Archive {
+            path: PathBuf::from("-"),
+            source: std::io::Error::other("test"),
+        };
+        assert_eq!(archive.exit_code(), 26);
+        let password = ExtractError::open_failed(PathBuf::from("a.pdf"), PdfError::InvalidPassword);
+        assert!(matches!(password, ExtractError::Password { .. }));
+        assert_eq!(password.exit_code(), 27);
+        let password_file = ExtractError::PasswordFile {
+            path: PathBuf::from("passwords.csv"),
+            source: std::io::Error::other("test"),
+        };
+        assert_eq!(password_file.exit_code(), 28);
+        let template = ExtractError::FileNameTemplate {
+            template: "{hash}.{ext}".to_string(),
+            dedup_mode: DedupMode::Off,
+        };
+        assert_eq!(template.exit_code(), 29);
+        let open = ExtractError::open_failed(PathBuf::from("a.pdf"), PdfError::EOF);
+        assert_eq!(open.exit_code(), 22);
+        let unsupported = ExtractError::UnsupportedFilter {
+            page: 1,
+            object_name: "Im0".to_string(),
+            filter: "CCITTFaxDecode".to_string(),
+        };
+        assert_eq!(unsupported.exit_code(), 1);
+    }
+}
@@ -0,0 +1,374 @@
+use crate::collect_page_images::PageImage;
+use crate::near_duplicate::cluster_id;
+use crate::output_sink::OutputSink;
+
+use pdf::enc::StreamFilter;
+use pdf::object::{ColorSpace, ImageXObject};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+///保存先ディレクトリに作成するJSON Lines形式のマニフェストのファイル名。
+pub const MANIFEST_JSONL_FILE_NAME: &str = "manifest.jsonl";
+///保存先ディレクトリに作成するCSV形式のマニフェストのファイル名。
+pub const MANIFEST_CSV_FILE_NAME: &str = "manifest.csv";
+
+///画像の処理結果。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageStatus {
+    ///画像ファイルを保存した。
+    Written,
+    ///同じPDFファイル内で重複していたため保存しなかった。
+    Duplicate,
+    ///他のPDFファイルで保存済みだったため、最初に保存したファイルへのハードリンクを作成した。
+    Linked,
+    ///他のPDFファイルで保存済みだったため保存せず、最初に保存したファイルのパスを記録した。
+    Referenced,
+    ///同じPDFファイル内の近似した画像と判定されたため保存しなかった。(NearDuplicateAction::Skip指定時。)
+    NearDuplicate,
+    ///前回の実行で保存済みだったため保存しなかった。
+    Resumed,
+    ///大きさ・バイト数・縦横比の条件(ImageFilter)を満たさなかったため保存しなかった。
+    Filtered,
+    ///未対応の形式(フィルタ)だったため保存しなかった。
+    Unsupported,
+    ///変換もしくは書き込みに失敗した。
+    Failed,
+}
+
+impl ImageStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ImageStatus::Written => "written",
+            ImageStatus::Duplicate => "duplicate",
+            ImageStatus::Linked => "linked",
+            ImageStatus::Referenced => "referenced",
+            ImageStatus::NearDuplicate => "near_duplicate",
+            ImageStatus::Resumed => "resumed",
+            ImageStatus::Filtered => "filtered",
+            ImageStatus::Unsupported => "unsupported",
+            ImageStatus::Failed => "failed",
+        }
+    }
+}
+
+///マニフェストに記録する1つの画像の情報。
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestEntry {
+    ///PDFファイルのパス。
+    pub pdf_path: PathBuf,
+    ///画像が配置されているページ番号。
+    pub page: u64,
+    ///ページ内の画像の番号。
+    pub index: u64,
+    ///埋め込みオブジェクト名。(Form XObject内の画像は"_"で連結した名前、インライン画像は"Inline"と番号。)
+    pub object_name: String,
+    ///画像XObjectの参照("12 0 R"の形式)。(インライン画像の場合はNone。)
+    pub object_ref: Option<String>,
+    ///未適用のフィルタ。(展開済みの場合はNone。)
+    pub filter: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub color_space: Option<String>,
+    pub bits_per_component: Option<i32>,
+    ///画像データのバイト数。(PDFに格納されたストリームのうち、ASCIIHex・ASCII85・LZW・RunLengthのフィルタのみ展開したバイト数。)
+    pub byte_size: usize,
+    ///画像データのBLAKE3ハッシュ値(16進数)。
+    pub hash: String,
+    ///保存した画像ファイルのパス。(重複などで保存しなかった場合は空。Referencedの場合は最初に保存したファイルのパス。)
+    pub output_paths: Vec<PathBuf>,
+    pub status: ImageStatus,
+    ///他の画像と重複していればtrue。(Duplicate・Linked・Referenced。)
+    pub duplicate: bool,
+    ///知覚ハッシュ(16進数)。(知覚ハッシュを指定しない場合、もしくは展開できない画像の場合はNone。)
+    pub perceptual_hash: Option<String>,
+    ///近似した画像をまとめたクラスタの識別子("ページ番号_ページ内の番号"の形式で、クラスタの最初の画像を表す)。
+    ///(近似した画像がない場合はNone。)
+    pub cluster: Option<String>,
+    ///同じPDFファイル内の先に処理された画像と近似していればtrue。
+    pub near_duplicate: bool,
+}
+
+impl ManifestEntry {
+    ///画像の情報からマニフェストの項目を作成する。(処理結果は保存前の状態(Failed)とする。)
+    /// # Arguments
+    /// * `page` - ページ番号。
+    /// * `index` - ページ内の画像の番号。
+    /// * `object_name` - 埋め込みオブジェクト名。
+    /// * `image` - ページから収集した画像。
+    /// * `img` - 画像XObject。
+    /// * `data` - `raw_image_data()`で取得した画像データ。
+    /// * `hash` - 画像データのBLAKE3ハッシュ値。
+    /// * `filter` - `raw_image_data()`で取得した未適用のフィルタ。
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        page: u64,
+        index: u64,
+        object_name: &str,
+        image: &PageImage,
+        img: &ImageXObject,
+        data: &[u8],
+        hash: &blake3::Hash,
+        filter: Option<&StreamFilter>,
+    ) -> Self {
+        ManifestEntry {
+            pdf_path: PathBuf::new(),
+            page,
+            index,
+            object_name: object_name.to_string(),
+            object_ref: image.object_ref().map(|r| format!("{} {} R", r.id, r.gen)),
+            filter: filter.map(filter_name),
+            width: img.width,
+            height: img.height,
+            color_space: img.color_space.as_ref().map(color_space_name),
+            bits_per_component: img.bits_per_component,
+            byte_size: data.len(),
+            hash: hash.to_hex().to_string(),
+            output_paths: Vec::new(),
+            status: ImageStatus::Failed,
+            duplicate: false,
+            perceptual_hash: None,
+            cluster: None,
+            near_duplicate: false,
+        }
+    }
+
+    ///処理結果を設定した項目を作成する。
+    pub(crate) fn with_status(&self, status: ImageStatus, output_paths: Vec<PathBuf>) -> Self {
+        ManifestEntry {
+            output_paths,
+            status,
+            duplicate: matches!(
+                status,
+                ImageStatus::Duplicate | ImageStatus::Linked | ImageStatus::Referenced
+            ),
+            ..self.clone()
+        }
+    }
+
+    ///CSVの1行分の値を作成する。(複数の保存先のパスは";"で連結する。)
+    fn csv_record(&self) -> [String; 18] {
+        let output_paths: Vec<String> = self
+            .output_paths
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        [
+            self.pdf_path.display().to_string(),
+            self.page.to_string(),
+            self.index.to_string(),
+            self.object_name.clone(),
+            self.object_ref.clone().unwrap_or_default(),
+            self.filter.clone().unwrap_or_default(),
+            self.width.to_string(),
+            self.height.to_string(),
+            self.color_space.clone().unwrap_or_default(),
+            self.bits_per_component
+                .map(|bpc| bpc.to_string())
+                .unwrap_or_default(),
+            self.byte_size.to_string(),
+            self.hash.clone(),
+            output_paths.join(";"),
+            self.status.as_str().to_string(),
+            self.duplicate.to_string(),
+            self.perceptual_hash.clone().unwrap_or_default(),
+            self.cluster.clone().unwrap_or_default(),
+            self.near_duplicate.to_string(),
+        ]
+    }
+}
+
+///CSVのヘッダ。
+const CSV_HEADER: [&str; 18] = [
+    "pdf_path",
+    "page",
+    "index",
+    "object_name",
+    "object_ref",
+    "filter",
+    "width",
+    "height",
+    "color_space",
+    "bits_per_component",
+    "byte_size",
+    "hash",
+    "output_paths",
+    "status",
+    "duplicate",
+    "perceptual_hash",
+    "cluster",
+    "near_duplicate",
+];
+
+///マニフェストを保存先ディレクトリに書き込む。(ページ番号・ページ内の画像の番号の順に並べ替える。)
+/// # Arguments
+/// * `dest_dir_path` - 画像の保存先ディレクトリ。
+/// * `entries` - マニフェストの項目。
+/// * `csv` - JSON Lines形式に加えてCSV形式のマニフェストも作成する場合はtrue。
+/// * `sink` - マニフェストの書き込み先。(アーカイブに書き込む場合は画像ファイルと同じアーカイブに格納する。)
+/// # Returns
+/// * 成功時:作成したマニフェストのパス。
+/// * 失敗時:std::io::Error。
+pub(crate) fn write_manifest(
+    dest_dir_path: &Path,
+    entries: &mut [ManifestEntry],
+    csv: bool,
+    sink: &OutputSink,
+) -> std::io::Result<Vec<PathBuf>> {
+    entries.sort_by_key(|entry| (entry.page, entry.index));
+    let mut manifest_paths: Vec<PathBuf> = Vec::new();
+
+    let jsonl_path: PathBuf = dest_dir_path.join(MANIFEST_JSONL_FILE_NAME);
+    let mut jsonl: Vec<u8> = Vec::new();
+    for entry in entries.iter() {
+        serde_json::to_writer(&mut jsonl, entry)?;
+        jsonl.push(b'\n');
+    }
+    sink.write_file(&jsonl_path, u64::MAX, &jsonl)?;
+    manifest_paths.push(jsonl_path);
+
+    if csv {
+        let csv_path: PathBuf = dest_dir_path.join(MANIFEST_CSV_FILE_NAME);
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(CSV_HEADER)?;
+        for entry in entries.iter() {
+            writer.write_record(entry.csv_record())?;
+        }
+        let csv_data: Vec<u8> = writer.into_inner().map_err(|e| e.into_error())?;
+        sink.write_file(&csv_path, u64::MAX, &csv_data)?;
+        manifest_paths.push(csv_path);
+    }
+    Ok(manifest_paths)
+}
+
+///近似した画像があったクラスタの最初の画像にもクラスタの識別子を設定する。
+///(最初の画像の処理時には、後で近似した画像が見つかるかどうかが分からないため。)
+pub(crate) fn assign_clusters(entries: &mut [ManifestEntry]) {
+    let clusters: HashSet<String> = entries
+        .iter()
+        .filter_map(|entry| entry.cluster.clone())
+        .collect();
+    for entry in entries.iter_mut() {
+        let id: String = cluster_id(entry.page, entry.index);
+        if entry.cluster.is_none() && clusters.contains(&id) {
+            entry.cluster = Some(id);
+        }
+    }
+}
+
+///フィルタの名前を取得する。(パラメータは含まない。)
+fn filter_name(filter: &StreamFilter) -> String {
+    let name: String = format!("{:?}", filter);
+    match name.split_once('(') {
+        Some((name, _)) => name.to_string(),
+        None => name,
+    }
+}
+
+///色空間の名前を取得する。(Indexed・Separation・DeviceNはベースもしくは代替の色空間の名前を含む。)
+fn color_space_name(color_space: &ColorSpace) -> String {
+    match color_space {
+        ColorSpace::DeviceGray => "DeviceGray".to_string(),
+        ColorSpace::DeviceRGB => "DeviceRGB".to_string(),
+        ColorSpace::DeviceCMYK => "DeviceCMYK".to_string(),
+        ColorSpace::DeviceN { alt, .. } => format!("DeviceN/{}", color_space_name(alt)),
+        ColorSpace::CalGray(_) => "CalGray".to_string(),
+        ColorSpace::CalRGB(_) => "CalRGB".to_string(),
+        ColorSpace::CalCMYK(_) => "CalCMYK".to_string(),
+        ColorSpace::Indexed(base, _, _) => format!("Indexed/{}", color_space_name(base)),
+        ColorSpace::Separation(_, alt, _) => format!("Separation/{}", color_space_name(alt)),
+        ColorSpace::Icc(_) => "ICCBased".to_string(),
+        ColorSpace::Pattern => "Pattern".to_string(),
+        ColorSpace::Named(name) => name.as_str().to_string(),
+        ColorSpace::Other(_) => "Other".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(page: u64, index: u64, status: ImageStatus) -> ManifestEntry {
+        ManifestEntry {
+            pdf_path: PathBuf::from("a.pdf"),
+            page,
+            index,
+            object_name: "Im1".to_string(),
+            object_ref: Some("5 0 R".to_string()),
+            filter: Some("DCTDecode".to_string()),
+            width: 2,
+            height: 3,
+            color_space: Some("DeviceRGB".to_string()),
+            bits_per_component: Some(8),
+            byte_size: 10,
+            hash: "00".to_string(),
+            output_paths: Vec::new(),
+            status: ImageStatus::Failed,
+            duplicate: false,
+            perceptual_hash: None,
+            cluster: None,
+            near_duplicate: false,
+        }
+        .with_status(
+            status,
+            vec![PathBuf::from("a/x.jpg"), PathBuf::from("a/y.png")],
+        )
+    }
+
+    #[test]
+    fn test_write_manifest() {
+        let dest_dir_path: PathBuf = std::env::temp_dir().join(format!(
+            "pdf_to_image_rs_extraction_manifest_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dest_dir_path).unwrap();
+        let mut entries = vec![
+            entry(2, 1, ImageStatus::Duplicate),
+            entry(1, 1, ImageStatus::Written),
+        ];
+        let paths =
+            write_manifest(&dest_dir_path, &mut entries, true, &OutputSink::Directory).unwrap();
+        let jsonl = std::fs::read_to_string(dest_dir_path.join(MANIFEST_JSONL_FILE_NAME)).unwrap();
+        let csv = std::fs::read_to_string(dest_dir_path.join(MANIFEST_CSV_FILE_NAME)).unwrap();
+        std::fs::remove_dir_all(&dest_dir_path).unwrap();
+
+        assert_eq!(paths.len(), 2);
+        let lines: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["page"], 1);
+        assert_eq!(lines[0]["status"], "written");
+        assert_eq!(lines[0]["duplicate"], false);
+        assert_eq!(lines[1]["status"], "duplicate");
+        assert_eq!(lines[1]["duplicate"], true);
+        assert_eq!(lines[1]["object_ref"], "5 0 R");
+        let csv_lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(csv_lines.len(), 3);
+        assert!(csv_lines[0].starts_with("pdf_path,page,index"));
+        assert!(csv_lines[1].contains("a/x.jpg;a/y.png,written,false"));
+    }
+
+    #[test]
+    fn test_assign_clusters() {
+        let mut near = entry(2, 1, ImageStatus::NearDuplicate);
+        near.cluster = Some(cluster_id(1, 1));
+        near.near_duplicate = true;
+        let mut entries = vec![
+            entry(1, 1, ImageStatus::Written),
+            entry(1, 2, ImageStatus::Written),
+            near,
+        ];
+        assign_clusters(&mut entries);
+        assert_eq!(entries[0].cluster.as_deref(), Some("000001_000001"));
+        assert_eq!(entries[1].cluster, None);
+        assert_eq!(entries[2].cluster.as_deref(), Some("000001_000001"));
+        assert!(!entries[0].near_duplicate);
+        assert_eq!(
+            serde_json::to_value(&entries[2]).unwrap()["status"],
+            "near_duplicate"
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use crate::extract_error::ExtractError;
+use crate::extraction_manifest::ManifestEntry;
+use std::path::PathBuf;
+
+///画像取得処理の結果。
+///ページ単位・PDFファイル単位の結果をmerge()で集約して使用する。
+#[derive(Debug, Default)]
+pub struct ExtractionReport {
+    ///処理したPDFファイルの数。
+    pub pdf_files: u64,
+    ///保存先ディレクトリが既に存在していたため無視したPDFファイルの数。
+    pub skipped_files: u64,
+    ///処理したページの数。
+    pub pages: u64,
+    ///描画して保存したページの数。(--render-pages指定時のみ。)
+    pub rendered_pages: u64,
+    ///描画できない内容(シェーディング・パターン・Type3フォントなど)を描画せずに、
+    ///もしくは埋め込まれていないフォントを別の書体で代替して保存したページの数。
+    pub incomplete_pages: u64,
+    ///保存した画像の数。
+    pub images_written: u64,
+    ///同じPDFファイル内で重複していたため保存しなかった画像の数。
+    pub duplicate_images: u64,
+    ///同じPDFファイル内の近似した画像と判定された画像の数。(NearDuplicateAction::Skipの場合は保存しなかった画像の数。)
+    pub near_duplicates: u64,
+    ///他のPDFファイルで保存済みだったため、最初に保存したファイルを参照もしくはリンクした画像の数。
+    pub global_duplicates: u64,
+    ///前回の実行で保存済みだったため保存しなかった画像・ページの数。(ExistingPolicy::Resume指定時のみ。)
+    pub resumed_files: u64,
+    ///大きさ・バイト数・縦横比の条件を満たさなかったため保存しなかった画像の数。
+    pub filtered_images: u64,
+    ///展開できなかったため、元の形式のまま(JBIG2ファイル・TIFFファイルとして)保存した画像の数。
+    pub undecoded_images: u64,
+    ///保存した画像ファイルのパス。(アーカイブに保存した場合はアーカイブ内のパス。)
+    pub output_files: Vec<PathBuf>,
+    ///マニフェストに記録した画像の情報。(ページを描画した場合は記録しない。)
+    pub manifest_entries: Vec<ManifestEntry>,
+    ///処理中に発生したエラー(処理を中断しなかったものを含む)。
+    pub errors: Vec<ExtractError>,
+}
+
+impl ExtractionReport {
+    ///別の結果をこの結果に集約する。
+    pub fn merge(&mut self, other: ExtractionReport) {
+        self.pdf_files += other.pdf_files;
+        self.skipped_files += other.skipped_files;
+        self.pages += other.pages;
+        self.rendered_pages += other.rendered_pages;
+        self.incomplete_pages += other.incomplete_pages;
+        self.images_written += other.images_written;
+        self.duplicate_images += other.duplicate_images;
+        self.near_duplicates += other.near_duplicates;
+        self.global_duplicates += other.global_duplicates;
+        self.resumed_files += other.resumed_files;
+        self.filtered_images += other.filtered_images;
+        self.undecoded_images += other.undecoded_images;
+        self.output_files.extend(other.output_files);
+        self.manifest_entries.extend(other.manifest_entries);
+        self.errors.extend(other.errors);
+    }
+
+    ///エラーが1件も発生していなければtrueを返す。
+    pub fn is_success(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let mut report = ExtractionReport {
+            pages: 1,
+            images_written: 2,
+            output_files: vec![PathBuf::from("a.png"), PathBuf::from("b.png")],
+            ..Default::default()
+        };
+        let other = ExtractionReport {
+            pages: 1,
+            images_written: 1,
+            duplicate_images: 1,
+            filtered_images: 2,
+            undecoded_images: 1,
+            incomplete_pages: 1,
+            output_files: vec![PathBuf::from("c.jpg")],
+            errors: vec![ExtractError::WorkerPanicked { count: 1 }],
+            ..Default::default()
+        };
+        assert!(report.is_success());
+        report.merge(other);
+        assert_eq!(report.pages, 2);
+        assert_eq!(report.images_written, 3);
+        assert_eq!(report.duplicate_images, 1);
+        assert_eq!(report.filtered_images, 2);
+        assert_eq!(report.undecoded_images, 1);
+        assert_eq!(report.incomplete_pages, 1);
+        assert_eq!(report.output_files.len(), 3);
+        assert!(!report.is_success());
+    }
+}
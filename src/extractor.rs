@@ -0,0 +1,676 @@
+use crate::archive_format::ArchiveFormat;
+use crate::dedup_mode::DedupMode;
+use crate::dedup_store::DedupStore;
+use crate::existing_policy::ExistingPolicy;
+use crate::extract_error::ExtractError;
+use crate::extraction_report::ExtractionReport;
+use crate::fax_mode::FaxMode;
+use crate::file_name_template::FileNameTemplate;
+use crate::find_pdf_files::find_pdf_files;
+use crate::get_image_from_pdf::{get_images, get_images_from_reader};
+use crate::global_dedup::GlobalDedup;
+use crate::image_filter::ImageFilter;
+use crate::near_duplicate::{NearDuplicateAction, DEFAULT_NEAR_DUPLICATE_THRESHOLD};
+use crate::output_format::OutputFormat;
+use crate::output_sink::OutputSink;
+use crate::page_selection::PageSelection;
+use crate::password_map::PasswordMap;
+use crate::path_pattern::PathPattern;
+use crate::perceptual_hash::PerceptualHash;
+use crate::set_workers_limit::get_main_workers_limit;
+
+use log::{error, info};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use threadpool::ThreadPool;
+
+///ページを描画する場合の既定の解像度(DPI)。
+pub const DEFAULT_RENDER_DPI: f32 = 150.0;
+
+///PDFファイルから画像を取得する処理の設定と実行を行う。
+///
+/// # Examples
+/// ```no_run
+/// use pdf_to_image_rs::Extractor;
+/// use std::path::Path;
+///
+/// let report = Extractor::new()
+///     .main_workers(2)
+///     .extract_dir(Path::new("test_pdf/correct_pdf"))
+///     .unwrap();
+/// assert!(report.is_success());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Extractor {
+    pub(crate) main_workers: Option<usize>,
+    pub(crate) page_workers: Option<usize>,
+    pub(crate) output_format: OutputFormat,
+    pub(crate) fax_mode: FaxMode,
+    pub(crate) apply_masks: bool,
+    pub(crate) keep_unmasked: bool,
+    pub(crate) render_pages: bool,
+    pub(crate) dpi: Option<f32>,
+    pub(crate) output_dir: Option<PathBuf>,
+    pub(crate) on_existing: ExistingPolicy,
+    pub(crate) file_name_template: FileNameTemplate,
+    pub(crate) manifest_csv: bool,
+    pub(crate) dedup_mode: DedupMode,
+    pub(crate) global_dedup: GlobalDedup,
+    pub(crate) dedup_store: Option<PathBuf>,
+    pub(crate) perceptual_hash: Option<PerceptualHash>,
+    pub(crate) near_duplicate_threshold: Option<u32>,
+    pub(crate) near_duplicate_action: NearDuplicateAction,
+    pub(crate) recursive: bool,
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) follow_symlinks: bool,
+    pub(crate) include_patterns: Vec<PathPattern>,
+    pub(crate) exclude_patterns: Vec<PathPattern>,
+    pub(crate) archive: Option<ArchiveFormat>,
+    pub(crate) page_selection: Option<PageSelection>,
+    pub(crate) image_filter: ImageFilter,
+    pub(crate) passwords: PasswordMap,
+}
+
+impl Extractor {
+    ///既定の設定でExtractorを作成する。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///PDFファイルを並列に処理するスレッド数を指定する。(未指定時はCPUコア数から決定する。)
+    pub fn main_workers(mut self, workers: usize) -> Self {
+        self.main_workers = Some(workers.max(1));
+        self
+    }
+
+    ///1つのPDFファイルのページを並列に処理するスレッド数を指定する。(未指定時はCPUコア数と使用率から決定する。)
+    pub fn page_workers(mut self, workers: usize) -> Self {
+        self.page_workers = Some(workers.max(1));
+        self
+    }
+
+    ///保存する画像の形式を指定する。(未指定時はOutputFormat::Original。)
+    pub fn output_format(mut self, output_format: OutputFormat) -> Self {
+        self.output_format = output_format;
+        self
+    }
+
+    ///CCITTFaxDecode(Group 3/4)の画像を保存する方法を指定する。(未指定時はFaxMode::Tiff。)
+    ///TIFFに格納する場合はK・Columns・Rows・BlackIs1・EndOfLine・EncodedByteAlignを対応するTIFFのタグに変換する。
+    pub fn fax_mode(mut self, fax_mode: FaxMode) -> Self {
+        self.fax_mode = fax_mode;
+        self
+    }
+
+    ///CCITTFaxDecodeの画像を展開せずにTIFFに格納するかを取得する。
+    pub(crate) fn keep_fax_tiff(&self) -> bool {
+        self.fax_mode == FaxMode::Tiff
+            && matches!(
+                self.output_format,
+                OutputFormat::Original | OutputFormat::Tiff
+            )
+    }
+
+    ///SMask・Maskを持つ画像にマスクを適用し、アルファチャンネル付きの画像として保存するかを指定する。(未指定時はfalse。)
+    pub fn apply_masks(mut self, apply_masks: bool) -> Self {
+        self.apply_masks = apply_masks;
+        self
+    }
+
+    ///マスクを適用した画像に加え、マスクを適用していない元の画像も保存するかを指定する。(未指定時はfalse。)
+    ///元の画像のファイル名には"_unmasked"が付く。
+    pub fn keep_unmasked(mut self, keep_unmasked: bool) -> Self {
+        self.keep_unmasked = keep_unmasked;
+        self
+    }
+
+    ///埋め込まれた画像を取得する代わりに、各ページを描画した画像を保存するかを指定する。(未指定時はfalse。)
+    ///保存する形式はoutput_format()に従う。(OutputFormat::Originalの場合はPNG。)
+    ///シェーディング・パターン・Type3フォント・埋め込まれていないType0フォントなどを描画しなかったページ、
+    ///標準14フォント以外の埋め込まれていないフォントを別の書体で代替したページの数は
+    ///ExtractionReport::incomplete_pagesに記録する。
+    pub fn render_pages(mut self, render_pages: bool) -> Self {
+        self.render_pages = render_pages;
+        self
+    }
+
+    ///ページを描画する解像度(DPI)を指定する。(未指定時はDEFAULT_RENDER_DPI。)
+    pub fn dpi(mut self, dpi: f32) -> Self {
+        self.dpi = Some(dpi.max(1.0));
+        self
+    }
+
+    ///ページを描画する解像度(DPI)を取得する。
+    pub(crate) fn render_dpi(&self) -> f32 {
+        self.dpi.unwrap_or(DEFAULT_RENDER_DPI)
+    }
+
+    ///画像の出力先ディレクトリを指定する。(未指定時はPDFファイルと同じディレクトリ。)
+    ///extract_dir()では入力ディレクトリからの相対パスを保持したディレクトリ構成で保存する。
+    pub fn output_dir(mut self, output_dir: &Path) -> Self {
+        self.output_dir = Some(output_dir.to_path_buf());
+        self
+    }
+
+    ///画像の保存先ディレクトリが既に存在する場合の動作を指定する。(未指定時はExistingPolicy::Skip。)
+    pub fn on_existing(mut self, on_existing: ExistingPolicy) -> Self {
+        self.on_existing = on_existing;
+        self
+    }
+
+    ///保存する画像のファイル名のテンプレートを指定する。(未指定時はDEFAULT_FILE_NAME_TEMPLATE。)
+    ///描画したページのファイル名は"page_{ページ番号(6桁)}.{拡張子}"で固定。
+    pub fn file_name_template(mut self, file_name_template: FileNameTemplate) -> Self {
+        self.file_name_template = file_name_template;
+        self
+    }
+
+    ///JSON Lines形式のマニフェスト(manifest.jsonl)に加えて、CSV形式のマニフェスト(manifest.csv)も作成するかを指定する。(未指定時はfalse。)
+    pub fn manifest_csv(mut self, manifest_csv: bool) -> Self {
+        self.manifest_csv = manifest_csv;
+        self
+    }
+
+    ///同じPDFファイル内の重複した画像を判定する方法を指定する。(未指定時はDedupMode::Blake3。)
+    ///DedupMode::ObjectRef・DedupMode::Offの場合、file_name_template()には`{page}`と`{index}`の両方を含む必要がある。
+    ///画像データ全体ではなくハッシュ値もしくは参照のみを保持するため、画像の数に比例したメモリしか使用しない。
+    pub fn dedup_mode(mut self, dedup_mode: DedupMode) -> Self {
+        self.dedup_mode = dedup_mode;
+        self
+    }
+
+    ///他のPDFファイルで保存済みの画像と重複した場合の動作を指定する。(未指定時はGlobalDedup::Off。)
+    ///重複はdedup_mode()のハッシュ値で判定する。(DedupMode::Offの場合は判定しない。DedupMode::ObjectRefの場合はBLAKE3。)
+    pub fn global_dedup(mut self, global_dedup: GlobalDedup) -> Self {
+        self.global_dedup = global_dedup;
+        self
+    }
+
+    ///PDFファイル間の重複判定ストアを保存するファイルを指定する。(未指定時は永続化せず、実行ごとに作成する。)
+    ///指定したファイルは実行をまたいで使用し、以前の実行で保存した画像も重複として扱う。
+    pub fn dedup_store(mut self, dedup_store: &Path) -> Self {
+        self.dedup_store = Some(dedup_store.to_path_buf());
+        self
+    }
+
+    ///同じPDFファイル内の近似した画像(再圧縮された同じ画像など)を判定する知覚ハッシュを指定する。(未指定時は判定しない。)
+    ///JPXDecode・展開に失敗したJBIG2Decodeなど展開できない画像は判定しない。
+    pub fn perceptual_hash(mut self, perceptual_hash: PerceptualHash) -> Self {
+        self.perceptual_hash = Some(perceptual_hash);
+        self
+    }
+
+    ///近似した画像と判定する知覚ハッシュのハミング距離の最大値を指定する。(未指定時はDEFAULT_NEAR_DUPLICATE_THRESHOLD。)
+    pub fn near_duplicate_threshold(mut self, threshold: u32) -> Self {
+        self.near_duplicate_threshold = Some(threshold.min(64));
+        self
+    }
+
+    ///近似した画像と判定する知覚ハッシュのハミング距離の最大値を取得する。
+    pub(crate) fn near_duplicate_max_distance(&self) -> u32 {
+        self.near_duplicate_threshold
+            .unwrap_or(DEFAULT_NEAR_DUPLICATE_THRESHOLD)
+    }
+
+    ///近似した画像が見つかった場合の動作を指定する。(未指定時はNearDuplicateAction::Flag。)
+    pub fn near_duplicate_action(mut self, near_duplicate_action: NearDuplicateAction) -> Self {
+        self.near_duplicate_action = near_duplicate_action;
+        self
+    }
+
+    ///extract_dir()でサブディレクトリ内のPDFファイルも再帰的に検索するかを指定する。(未指定時はfalse。)
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    ///再帰的に検索する場合の深さの最大値を指定する。(未指定時は制限しない。1の場合は指定したディレクトリの直下のみ。)
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth.max(1));
+        self
+    }
+
+    ///PDFファイルの検索時にシンボリックリンクをたどるかを指定する。(未指定時はfalse。)
+    ///(たどらない場合、シンボリックリンクのファイル・ディレクトリは無視する。)
+    pub fn follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    ///検索対象とするPDFファイルのパターンを追加する。(未指定時は全てのPDFファイル。複数指定時はいずれかに一致するもの。)
+    pub fn include_pattern(mut self, pattern: PathPattern) -> Self {
+        self.include_patterns.push(pattern);
+        self
+    }
+
+    ///検索対象から除外するPDFファイル・ディレクトリのパターンを追加する。(ディレクトリが一致した場合はその下を検索しない。)
+    pub fn exclude_pattern(mut self, pattern: PathPattern) -> Self {
+        self.exclude_patterns.push(pattern);
+        self
+    }
+
+    ///画像とマニフェストをPDFファイルごとに1つのアーカイブにまとめて保存する形式を指定する。(未指定時はディレクトリに保存する。)
+    ///アーカイブは保存先ディレクトリのパスに拡張子を付けたファイルに保存する。(例:"a.pdf"の場合は"a.zip"。)
+    ///アーカイブは途中から再開できないため、ExistingPolicy::Resumeの場合も作成し直す。PDFファイル間の重複(global_dedup())は判定しない。
+    pub fn archive(mut self, archive_format: ArchiveFormat) -> Self {
+        self.archive = Some(archive_format);
+        self
+    }
+
+    ///処理するページを指定する。(未指定時は全てのページ。)
+    pub fn pages(mut self, page_selection: PageSelection) -> Self {
+        self.page_selection = Some(page_selection);
+        self
+    }
+
+    ///保存する画像の大きさ・バイト数・縦横比の条件を指定する。(未指定時は全ての画像を保存する。ページを描画する場合は判定しない。)
+    pub fn image_filter(mut self, image_filter: ImageFilter) -> Self {
+        self.image_filter = image_filter;
+        self
+    }
+
+    ///暗号化されたPDFファイルを開くパスワードを指定する。(password_map()にないPDFファイルに使用する。未指定時は空のパスワード。)
+    pub fn password(mut self, password: &str) -> Self {
+        self.passwords.set_default_password(password);
+        self
+    }
+
+    ///暗号化されたPDFファイルを開くPDFファイルごとのパスワードを追加する。(未指定時はpassword()のパスワードのみ使用する。)
+    pub fn password_map(mut self, password_map: PasswordMap) -> Self {
+        self.passwords.extend(password_map);
+        self
+    }
+
+    ///ファイル名のテンプレートと重複の判定方法の組み合わせで、ファイル名が重複しないことを検証する。
+    ///(`{page}`と`{index}`の両方を含まないテンプレートは、同じ画像データを重複として保存しない場合のみファイル名が一意になる。
+    ///DedupMode::ObjectRef・DedupMode::Offでは別の画像XObjectの同じ画像データが同じファイル名になり、先に保存した画像が上書きされる。)
+    /// # Returns
+    /// * 失敗時:ExtractError::FileNameTemplate。
+    fn check_file_name_template(&self) -> Result<(), ExtractError> {
+        let content_dedup: bool = matches!(self.dedup_mode, DedupMode::Blake3 | DedupMode::Sha256);
+        if self.file_name_template.requires_content_dedup() && !content_dedup {
+            let e = ExtractError::FileNameTemplate {
+                template: self.file_name_template.to_string(),
+                dedup_mode: self.dedup_mode,
+            };
+            error!("{}", e);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    ///PDFファイル間で共有する重複判定ストアを作成もしくは読み込む。
+    /// # Returns
+    /// * 成功時:重複判定ストア。(PDFファイル間の重複を判定しない場合はNone。)
+    /// * 失敗時:ExtractError::DedupStore(ストアファイルの読み込みもしくは作成の失敗)。
+    fn open_dedup_store(&self) -> Result<Option<Arc<DedupStore>>, ExtractError> {
+        //アーカイブ内のファイルは参照・リンクできないため、アーカイブに保存する場合も判定しない。
+        if self.global_dedup == GlobalDedup::Off
+            || self.dedup_mode == DedupMode::Off
+            || self.archive.is_some()
+        {
+            return Ok(None);
+        }
+        let digest_name: &'static str = self.dedup_mode.digest_name();
+        match self.dedup_store {
+            Some(ref store_path) => match DedupStore::open(store_path, digest_name) {
+                Ok(store) => Ok(Some(Arc::new(store))),
+                Err(e) => {
+                    error!(
+                        "COULD NOT OPEN DEDUP STORE. FILE: {} ERR: {}",
+                        store_path.display(),
+                        e
+                    );
+                    Err(ExtractError::DedupStore {
+                        path: store_path.to_path_buf(),
+                        source: e,
+                    })
+                }
+            },
+            None => Ok(Some(Arc::new(DedupStore::new(digest_name)))),
+        }
+    }
+
+    ///PDFファイルから画像を取得する。
+    /// # Arguments
+    /// * `pdf_file_path` - PDFファイルのパス。
+    /// # Returns
+    /// * 成功時:処理結果(ページ単位・画像単位のエラーはerrorsに格納される)。
+    /// * 失敗時:PDFファイル単位のエラー(パス取得失敗・ディレクトリ作成失敗・オープン失敗・重複判定ストアの読み込み失敗)、
+    ///   ファイル名のテンプレートの不正。
+    pub fn extract_file(&self, pdf_file_path: &Path) -> Result<ExtractionReport, ExtractError> {
+        self.check_file_name_template()?;
+        let dedup_store: Option<Arc<DedupStore>> = self.open_dedup_store()?;
+        get_images(pdf_file_path, None, self, dedup_store)
+    }
+
+    ///PDFファイルのデータ(標準入力など)から画像を取得し、画像ファイルとマニフェストをアーカイブとして書き込む。
+    ///(保存先ディレクトリ・一時ファイルは作成しない。output_dir()・on_existing()・global_dedup()の設定は使用しない。)
+    /// # Arguments
+    /// * `reader` - PDFファイルのデータの読み込み元。
+    /// * `pdf_name` - PDFファイルの名前(ログ・マニフェスト・ファイル名のテンプレートの{pdf_stem}に使用する)。
+    /// * `archive_format` - アーカイブの形式。
+    /// * `writer` - アーカイブの書き込み先(標準出力など)。(シークできなくてもよい。)
+    /// # Returns
+    /// * 成功時:処理結果(ページ単位・画像単位のエラーはerrorsに格納される)。
+    /// * 失敗時:PDFファイルのデータの読み込み失敗・オープン失敗、アーカイブの書き込みの終了の失敗、ファイル名のテンプレートの不正。
+    pub fn extract_reader<R, W>(
+        &self,
+        mut reader: R,
+        pdf_name: &str,
+        archive_format: ArchiveFormat,
+        writer: W,
+    ) -> Result<ExtractionReport, ExtractError>
+    where
+        R: Read,
+        W: Write + Send + 'static,
+    {
+        self.check_file_name_template()?;
+        let sink: Arc<OutputSink> = Arc::new(OutputSink::archive(
+            archive_format,
+            Path::new(""),
+            Box::new(writer),
+        ));
+        let report = get_images_from_reader(&mut reader, pdf_name, Arc::clone(&sink), self)?;
+        if let Err(e) = sink.finish() {
+            error!("COULD NOT WRITE ARCHIVE. FILE: {} ERR: {}", pdf_name, e);
+            return Err(ExtractError::Archive {
+                path: PathBuf::from("-"),
+                source: e,
+            });
+        }
+        Ok(report)
+    }
+
+    ///ディレクトリ内の全てのPDFファイルから画像を取得する。
+    ///(PDFファイルの検索方法はrecursive()・max_depth()・follow_symlinks()・include_pattern()・exclude_pattern()に従う。)
+    /// # Arguments
+    /// * `directory_path` - PDFファイルが格納されているディレクトリのパス。
+    /// # Returns
+    /// * 成功時:全PDFファイルの処理結果(PDFファイル単位のエラーもerrorsに格納される)。
+    /// * 失敗時:PDFファイルの検索失敗、入力ディレクトリのフルパス取得失敗、出力先ディレクトリの作成失敗、重複判定ストアの読み込み失敗、
+    ///   ファイル名のテンプレートの不正。
+    pub fn extract_dir(&self, directory_path: &Path) -> Result<ExtractionReport, ExtractError> {
+        self.check_file_name_template()?;
+        //出力先ディレクトリでの相対パスの基準とするため、入力ディレクトリをフルパスに変換する。
+        let input_root: Arc<PathBuf> = match directory_path.canonicalize() {
+            Ok(path) => Arc::new(path),
+            Err(e) => {
+                error!(
+                    "COULD NOT GET DIRECTORY FULL PATH. DIRECTORY: {} ERR: {}",
+                    directory_path.display(),
+                    e
+                );
+                return Err(ExtractError::Seek {
+                    path: directory_path.to_path_buf(),
+                    reason: e.to_string(),
+                });
+            }
+        };
+        self.create_output_dir()?;
+        let _pdf_files: Vec<PathBuf> = match find_pdf_files(&input_root, self) {
+            Ok(files) => files,
+            Err(e) => {
+                error!("ERROR OCCURED WHILE SEEKING PDF FILES. ERR: {}", e);
+                return Err(ExtractError::Seek {
+                    path: directory_path.to_path_buf(),
+                    reason: e.to_string(),
+                });
+            }
+        };
+        self.extract_in_pool(_pdf_files, Some(input_root))
+    }
+
+    ///複数のPDFファイルから画像を取得する。
+    ///出力先ディレクトリ指定時は、全てのPDFファイルに共通する親ディレクトリからの相対パスを保持したディレクトリ構成で保存する。
+    /// # Arguments
+    /// * `pdf_file_paths` - PDFファイルのパス。(同じファイルが複数含まれる場合は1回のみ処理する。)
+    /// # Returns
+    /// * 成功時:全PDFファイルの処理結果(PDFファイル単位のエラーもerrorsに格納される)。
+    /// * 失敗時:出力先ディレクトリの作成失敗、重複判定ストアの読み込み失敗、ファイル名のテンプレートの不正。
+    pub fn extract_files(
+        &self,
+        pdf_file_paths: &[PathBuf],
+    ) -> Result<ExtractionReport, ExtractError> {
+        self.check_file_name_template()?;
+        self.create_output_dir()?;
+        //フルパスが同じファイルは1回のみ処理する。(同じ保存先ディレクトリに並列に書き込まないため。)
+        let mut full_paths: HashSet<PathBuf> = HashSet::new();
+        let mut pdf_files: Vec<PathBuf> = Vec::new();
+        for path in pdf_file_paths {
+            let full_path: PathBuf = full_path(path);
+            if full_paths.insert(full_path) {
+                pdf_files.push(path.clone());
+            }
+        }
+        let input_root: Option<Arc<PathBuf>> = common_ancestor(&full_paths).map(Arc::new);
+        self.extract_in_pool(pdf_files, input_root)
+    }
+
+    ///出力先ディレクトリを作成する。(未指定時は何もしない。)
+    fn create_output_dir(&self) -> Result<(), ExtractError> {
+        if let Some(ref output_dir) = self.output_dir {
+            if let Err(e) = std::fs::create_dir_all(output_dir) {
+                error!(
+                    "COULD NOT CREATE DIRECTORY. DIRECTORY: {} ERR: {}",
+                    output_dir.display(),
+                    e
+                );
+                return Err(ExtractError::DirectoryCreation {
+                    path: output_dir.to_path_buf(),
+                    source: e,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    ///PDFファイルをスレッドプールで並列に処理する。
+    /// # Arguments
+    /// * `pdf_files` - PDFファイルのパス。
+    /// * `input_root` - 出力先ディレクトリ指定時に、相対パスを保持する基準となるディレクトリ(フルパス)。
+    /// # Returns
+    /// * 成功時:全PDFファイルの処理結果(PDFファイル単位のエラーもerrorsに格納される)。
+    /// * 失敗時:重複判定ストアの読み込み失敗。
+    fn extract_in_pool(
+        &self,
+        pdf_files: Vec<PathBuf>,
+        input_root: Option<Arc<PathBuf>>,
+    ) -> Result<ExtractionReport, ExtractError> {
+        //PDFファイル間の重複を判定する場合は、全てのスレッドで1つのストアを共有する。
+        let dedup_store: Option<Arc<DedupStore>> = self.open_dedup_store()?;
+        let _pool = ThreadPool::new(self.main_workers.unwrap_or_else(get_main_workers_limit));
+        let extractor: Arc<Extractor> = Arc::new(self.clone());
+        //各スレッドの処理結果を受け取るチャネル。
+        let (result_sender, result_receiver) =
+            mpsc::channel::<Result<ExtractionReport, ExtractError>>();
+        for file in pdf_files {
+            let result_sender_ref = result_sender.clone();
+            let extractor_ref = Arc::clone(&extractor);
+            let input_root_ref = input_root.clone();
+            let dedup_store_ref = dedup_store.clone();
+            _pool.execute(move || {
+                let file_path = file.as_path();
+                let result = get_images(
+                    file_path,
+                    input_root_ref.as_deref().map(|root| root.as_path()),
+                    &extractor_ref,
+                    dedup_store_ref,
+                );
+                match &result {
+                    Ok(report) if report.is_success() => {
+                        info!("PDF FILE PROCESS COMPLETE. FILE : {:?}", file_path);
+                    }
+                    Ok(report) => {
+                        error!(
+                            "PDF FILE PROCESS ERROR. FILE : {:?} ERRORS : {}",
+                            file_path,
+                            report.errors.len()
+                        );
+                    }
+                    Err(e) => {
+                        error!("PDF FILE PROCESS ERROR. FILE : {:?} ERR : {}", file_path, e);
+                    }
+                }
+                //受信側は全てのタスクの終了後に読み出すため、送信の失敗は無視する。
+                let _ = result_sender_ref.send(result);
+            });
+        }
+        // 全てのタスクが終了するのを待つ
+        _pool.join();
+        drop(result_sender);
+
+        let mut report = ExtractionReport::default();
+        for result in result_receiver.iter() {
+            match result {
+                Ok(file_report) => report.merge(file_report),
+                Err(e) => {
+                    report.pdf_files += 1;
+                    report.errors.push(e);
+                }
+            }
+        }
+        //パニックしたスレッドは結果を送信しないため、パニックの有無も確認する。
+        if _pool.panic_count() > 0 {
+            report.errors.push(ExtractError::WorkerPanicked {
+                count: _pool.panic_count(),
+            });
+        }
+        Ok(report)
+    }
+}
+
+///PDFファイルのフルパスを取得する。
+///(存在しないファイルなどでフルパスを取得できない場合も、共通の親ディレクトリを求められるようにカレントディレクトリを基準とした絶対パスにする。)
+fn full_path(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+///全てのパスに共通する親ディレクトリを取得する。
+/// # Returns
+/// * 共通する親ディレクトリがある場合:そのパス。
+/// * パスが空の場合、もしくは共通する親ディレクトリがない場合(異なるドライブなど):None。
+fn common_ancestor<'a>(paths: impl IntoIterator<Item = &'a PathBuf>) -> Option<PathBuf> {
+    let mut ancestor: Option<PathBuf> = None;
+    for path in paths {
+        let parent: &Path = path.parent()?;
+        ancestor = match ancestor {
+            None => Some(parent.to_path_buf()),
+            Some(ancestor) => Some(
+                ancestor
+                    .ancestors()
+                    .find(|candidate| parent.starts_with(candidate))?
+                    .to_path_buf(),
+            ),
+        };
+    }
+    ancestor.filter(|ancestor| !ancestor.as_os_str().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_common_ancestor() {
+        let paths = [
+            PathBuf::from("/data/in/2023/a.pdf"),
+            PathBuf::from("/data/in/2024/q1/b.pdf"),
+        ];
+        assert_eq!(common_ancestor(&paths), Some(PathBuf::from("/data/in")));
+        assert_eq!(
+            common_ancestor(&paths[..1]),
+            Some(PathBuf::from("/data/in/2023"))
+        );
+        assert_eq!(common_ancestor(&[]), None);
+    }
+
+    #[test]
+    ///フルパスを取得できないファイルが含まれていても、共通の親ディレクトリを取得できることを確認する。
+    fn test_full_path() {
+        let paths = [
+            full_path(Path::new("test_pdf/correct_pdf/aaa.pdf")),
+            full_path(Path::new("test_pdf/not_found_pdf/aaa.pdf")),
+        ];
+        assert!(paths.iter().all(|path| path.is_absolute()));
+        assert_eq!(
+            paths[1],
+            std::env::current_dir()
+                .unwrap()
+                .join("test_pdf/not_found_pdf/aaa.pdf")
+        );
+        assert!(common_ancestor(&paths).is_some());
+    }
+
+    #[test_log::test]
+    ///PDFファイルのデータから取得した画像が、ディレクトリを作成せずにアーカイブに書き込まれることを確認する。
+    ///(PDFファイルのパスを使用しないため、他のテストがPDFファイルの隣に作成するディレクトリとは重ならない。)
+    fn test_extract_reader() {
+        let archive_path: PathBuf = std::env::temp_dir().join(format!(
+            "pdf_to_image_rs_extract_reader_{}.tar",
+            std::process::id()
+        ));
+        let pdf_data: Vec<u8> = std::fs::read("test_pdf/correct_pdf/aaa.pdf").unwrap();
+        let result = Extractor::new().extract_reader(
+            pdf_data.as_slice(),
+            "aaa.pdf",
+            ArchiveFormat::Tar,
+            std::fs::File::create(&archive_path).unwrap(),
+        );
+        let archive_data: Vec<u8> = std::fs::read(&archive_path).unwrap();
+        std::fs::remove_file(&archive_path).unwrap();
+        let report = result.expect("PDF FILE PROCESS ERROR.");
+        assert!(report.is_success());
+        assert!(report.images_written > 0);
+        //PDFファイルの名前から保存先ディレクトリを作成しない。
+        assert!(!Path::new("aaa").exists());
+        let names: Vec<String> = tar::Archive::new(archive_data.as_slice())
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+            .collect();
+        assert_eq!(names.len() as u64, report.images_written + 1);
+        assert!(names.iter().any(|name| name.ends_with(".jpg")));
+        assert_eq!(names.last().unwrap(), "manifest.jsonl");
+
+        //PDFファイルとして開けないデータはエラーになる。
+        let broken: Vec<u8> = std::fs::read("test_pdf/broken_pdf/broken.pdf").unwrap();
+        let result = Extractor::new().extract_reader(
+            broken.as_slice(),
+            "broken.pdf",
+            ArchiveFormat::Zip,
+            std::io::sink(),
+        );
+        assert_eq!(result.unwrap_err().exit_code(), 22);
+    }
+
+    #[test]
+    ///`{page}`と`{index}`を含まないテンプレートは、画像データのハッシュ値で重複を判定する場合のみ使用できることを確認する。
+    fn test_check_file_name_template() {
+        let hash_only: FileNameTemplate = "{hash}.{ext}".parse().unwrap();
+        for dedup_mode in [DedupMode::Blake3, DedupMode::Sha256] {
+            let extractor = Extractor::new()
+                .file_name_template(hash_only.clone())
+                .dedup_mode(dedup_mode);
+            assert!(extractor.check_file_name_template().is_ok());
+        }
+        for dedup_mode in [DedupMode::ObjectRef, DedupMode::Off] {
+            let extractor = Extractor::new()
+                .file_name_template(hash_only.clone())
+                .dedup_mode(dedup_mode);
+            let e = extractor.check_file_name_template().unwrap_err();
+            assert!(matches!(e, ExtractError::FileNameTemplate { .. }));
+            //ファイルを開く前に失敗する。
+            let e = extractor
+                .extract_file(Path::new("test_pdf/not_found_pdf/aaa.pdf"))
+                .unwrap_err();
+            assert_eq!(e.exit_code(), 29);
+        }
+        let extractor = Extractor::new().dedup_mode(DedupMode::Off);
+        assert!(extractor.check_file_name_template().is_ok());
+    }
+}
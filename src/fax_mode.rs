@@ -0,0 +1,48 @@
+use std::fmt;
+use std::str::FromStr;
+
+///CCITTFaxDecode(Group 3/4)の画像を保存する方法。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FaxMode {
+    ///展開せず、CCITTのデータをそのままTIFFに格納する。(出力形式がOriginal・TIFF以外の場合は展開して変換する。)
+    #[default]
+    Tiff,
+    ///展開してPNGもしくは指定された形式で保存する。(展開できない場合はTIFFに格納する。)
+    Decode,
+}
+
+impl FromStr for FaxMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "tiff" | "tif" => Ok(FaxMode::Tiff),
+            "decode" => Ok(FaxMode::Decode),
+            _ => Err(format!("UNSUPPORTED FAX MODE: {} (tiff|decode)", s)),
+        }
+    }
+}
+
+impl fmt::Display for FaxMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name: &str = match self {
+            FaxMode::Tiff => "tiff",
+            FaxMode::Decode => "decode",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fax_mode_from_str() {
+        assert_eq!("tiff".parse::<FaxMode>(), Ok(FaxMode::Tiff));
+        assert_eq!("TIF".parse::<FaxMode>(), Ok(FaxMode::Tiff));
+        assert_eq!("Decode".parse::<FaxMode>(), Ok(FaxMode::Decode));
+        assert!("png".parse::<FaxMode>().is_err());
+        assert_eq!(FaxMode::Decode.to_string(), "decode");
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt;
+use std::str::FromStr;
+
+///既定のファイル名のテンプレート。(同じPDFファイルからは実行ごとに同じファイル名が作成される。)
+pub const DEFAULT_FILE_NAME_TEMPLATE: &str = "image_{page}_{index}_{xobject}.{ext}";
+
+///保存する画像のファイル名のテンプレート。
+///以下のプレースホルダを使用できる。
+/// * `{pdf_stem}` - PDFファイル名から拡張子を取り除いたもの。
+/// * `{page}` - ページ番号(6桁)。
+/// * `{index}` - ページ内の画像の番号(6桁)。
+/// * `{xobject}` - 埋め込みオブジェクト名(数字を6桁に変換したもの)。
+/// * `{objnum}` - 画像XObjectのオブジェクト番号。(インライン画像は0。)
+/// * `{width}`・`{height}` - 画像の幅・高さ。
+/// * `{hash}` - 画像データのBLAKE3ハッシュ値(16進数の先頭16文字)。
+/// * `{ext}` - 拡張子。(テンプレートの末尾に".{ext}"としてのみ指定できる。省略時は末尾に追加する。)
+///
+///同じPDFファイル内でファイル名が重複しないよう、`{hash}`もしくは`{page}`と`{index}`の両方を含む必要がある。
+///`{page}`と`{index}`の両方を含まない場合、同じ画像データは重複として保存しない必要があるため、
+///画像データのハッシュ値で重複を判定する(DedupMode::Blake3・DedupMode::Sha256)場合のみ使用できる。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileNameTemplate {
+    template: String,
+    ///".{ext}"を除いたテンプレートを分割したもの。
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placeholder {
+    PdfStem,
+    Page,
+    Index,
+    XObject,
+    ObjNum,
+    Width,
+    Height,
+    Hash,
+}
+
+///ファイル名のプレースホルダに埋め込む値。
+pub(crate) struct FileNameValues<'a> {
+    pub(crate) pdf_stem: &'a str,
+    pub(crate) page: u64,
+    pub(crate) index: u64,
+    pub(crate) xobject: &'a str,
+    pub(crate) objnum: u64,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    ///画像データのハッシュ値。
+    pub(crate) hash: &'a str,
+}
+
+impl FileNameTemplate {
+    ///`{page}`と`{index}`の両方を含まず、ファイル名の重複を`{hash}`のみで避けるテンプレートであればtrueを返す。
+    ///(同じ画像データの画像XObjectが複数ある場合、重複として保存しなければ同じファイル名になる。)
+    pub(crate) fn requires_content_dedup(&self) -> bool {
+        let contains =
+            |placeholder: Placeholder| self.segments.contains(&Segment::Placeholder(placeholder));
+        !(contains(Placeholder::Page) && contains(Placeholder::Index))
+    }
+
+    ///テンプレートに値を埋め込んでファイル名を作成する。
+    /// # Arguments
+    /// * `values` - プレースホルダに埋め込む値。
+    /// * `suffix` - 拡張子の直前に付ける接尾辞("_unmasked"など)。
+    /// * `ext` - 拡張子。
+    pub(crate) fn render(&self, values: &FileNameValues, suffix: &str, ext: &str) -> String {
+        let mut file_name: String = String::new();
+        for segment in self.segments.iter() {
+            match segment {
+                Segment::Literal(literal) => file_name.push_str(literal),
+                Segment::Placeholder(placeholder) => {
+                    let value: String = match placeholder {
+                        Placeholder::PdfStem => sanitize(values.pdf_stem),
+                        Placeholder::Page => format!("{:06}", values.page),
+                        Placeholder::Index => format!("{:06}", values.index),
+                        Placeholder::XObject => sanitize(values.xobject),
+                        Placeholder::ObjNum => values.objnum.to_string(),
+                        Placeholder::Width => values.width.to_string(),
+                        Placeholder::Height => values.height.to_string(),
+                        Placeholder::Hash => values.hash.to_string(),
+                    };
+                    file_name.push_str(&value);
+                }
+            }
+        }
+        format!("{}{}.{}", file_name, suffix, ext)
+    }
+}
+
+///ファイル名に使用できない文字(パス区切り文字・制御文字)を"_"に置き換える。
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c == '/' || c == '\\' || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+impl Default for FileNameTemplate {
+    fn default() -> Self {
+        DEFAULT_FILE_NAME_TEMPLATE.parse().unwrap()
+    }
+}
+
+impl FromStr for FileNameTemplate {
+    type Err = String;
+
+    ///テンプレートを解析し、ファイル名が重複しないかを検証する。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let body: &str = s.strip_suffix(".{ext}").unwrap_or(s);
+        if body.is_empty() {
+            return Err("FILE NAME TEMPLATE IS EMPTY.".to_string());
+        }
+        if body.contains('/') || body.contains('\\') {
+            return Err(format!(
+                "FILE NAME TEMPLATE MUST NOT CONTAIN PATH SEPARATORS: {}",
+                s
+            ));
+        }
+
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut rest: &str = body;
+        while let Some(start) = rest.find('{') {
+            if rest[..start].contains('}') {
+                return Err(format!("UNOPENED PLACEHOLDER IN FILE NAME TEMPLATE: {}", s));
+            }
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_string()));
+            }
+            let end: usize = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => return Err(format!("UNCLOSED PLACEHOLDER IN FILE NAME TEMPLATE: {}", s)),
+            };
+            let placeholder: Placeholder = match &rest[start + 1..end] {
+                "pdf_stem" => Placeholder::PdfStem,
+                "page" => Placeholder::Page,
+                "index" => Placeholder::Index,
+                "xobject" => Placeholder::XObject,
+                "objnum" => Placeholder::ObjNum,
+                "width" => Placeholder::Width,
+                "height" => Placeholder::Height,
+                "hash" => Placeholder::Hash,
+                "ext" => {
+                    return Err(format!(
+                        "{{ext}} MUST BE AT THE END OF FILE NAME TEMPLATE AS \".{{ext}}\": {}",
+                        s
+                    ))
+                }
+                name => {
+                    return Err(format!(
+                        "UNKNOWN PLACEHOLDER IN FILE NAME TEMPLATE: {{{}}} (pdf_stem|page|index|xobject|objnum|width|height|hash|ext)",
+                        name
+                    ))
+                }
+            };
+            segments.push(Segment::Placeholder(placeholder));
+            rest = &rest[end + 1..];
+        }
+        if rest.contains('}') {
+            return Err(format!("UNOPENED PLACEHOLDER IN FILE NAME TEMPLATE: {}", s));
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+
+        //同じPDFファイル内の異なる画像に同じファイル名が作成されないことを検証する。
+        let contains =
+            |placeholder: Placeholder| segments.contains(&Segment::Placeholder(placeholder));
+        let unique: bool = contains(Placeholder::Hash)
+            || (contains(Placeholder::Page) && contains(Placeholder::Index));
+        if !unique {
+            return Err(format!(
+                "FILE NAME TEMPLATE MAY PRODUCE DUPLICATE NAMES. USE {{hash}} OR BOTH {{page}} AND {{index}}: {}",
+                s
+            ));
+        }
+        Ok(FileNameTemplate {
+            template: s.to_string(),
+            segments,
+        })
+    }
+}
+
+impl fmt::Display for FileNameTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.template)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> FileNameValues<'static> {
+        FileNameValues {
+            pdf_stem: "sample",
+            page: 3,
+            index: 2,
+            xobject: "Fm000001_Im000005",
+            objnum: 12,
+            width: 640,
+            height: 480,
+            hash: "0123456789abcdef",
+        }
+    }
+
+    #[test]
+    fn test_file_name_template_render() {
+        let template = FileNameTemplate::default();
+        assert_eq!(
+            template.render(&values(), "", "png"),
+            "image_000003_000002_Fm000001_Im000005.png"
+        );
+        let template: FileNameTemplate = "{pdf_stem}-{objnum}-{width}x{height}-{hash}"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            template.render(&values(), "_unmasked", "jpg"),
+            "sample-12-640x480-0123456789abcdef_unmasked.jpg"
+        );
+        let values = FileNameValues {
+            xobject: "a/b",
+            ..values()
+        };
+        let template: FileNameTemplate = "{page}_{index}_{xobject}.{ext}".parse().unwrap();
+        assert_eq!(template.render(&values, "", "png"), "000003_000002_a_b.png");
+    }
+
+    #[test]
+    fn test_file_name_template_validation() {
+        assert!("{page}_{index}".parse::<FileNameTemplate>().is_ok());
+        assert!("{hash}.{ext}".parse::<FileNameTemplate>().is_ok());
+        //ページ内の番号がないため、同じページの画像のファイル名が重複する。
+        assert!("{pdf_stem}_{page}".parse::<FileNameTemplate>().is_err());
+        assert!("{xobject}.{ext}".parse::<FileNameTemplate>().is_err());
+        assert!("{page}_{index}_{size}".parse::<FileNameTemplate>().is_err());
+        assert!("{page}_{index".parse::<FileNameTemplate>().is_err());
+        assert!("{page}_{index}}".parse::<FileNameTemplate>().is_err());
+        assert!("a}{page}_{index}".parse::<FileNameTemplate>().is_err());
+        assert!("{ext}_{page}_{index}".parse::<FileNameTemplate>().is_err());
+        assert!("dir/{page}_{index}".parse::<FileNameTemplate>().is_err());
+        assert!(".{ext}".parse::<FileNameTemplate>().is_err());
+    }
+
+    #[test]
+    fn test_file_name_template_requires_content_dedup() {
+        assert!(!FileNameTemplate::default().requires_content_dedup());
+        assert!(!"{hash}_{page}_{index}"
+            .parse::<FileNameTemplate>()
+            .unwrap()
+            .requires_content_dedup());
+        assert!("{hash}.{ext}"
+            .parse::<FileNameTemplate>()
+            .unwrap()
+            .requires_content_dedup());
+        assert!("{page}_{hash}"
+            .parse::<FileNameTemplate>()
+            .unwrap()
+            .requires_content_dedup());
+    }
+}
@@ -0,0 +1,193 @@
+use crate::extractor::Extractor;
+
+use log::{info, log_enabled, warn, Level};
+use std::path::{Path, PathBuf};
+use walkdir::{DirEntry, WalkDir};
+
+///ディレクトリ内のPDFファイル(拡張子が大文字・小文字を問わず"pdf"のファイル)を検索する。
+///再帰的に検索するか、検索する深さ、シンボリックリンクをたどるか、対象とするパターン・除外するパターンはextractorの設定に従う。
+/// # Arguments
+/// * `directory_path` - PDFファイルを検索するディレクトリのパス。
+/// * `extractor` - 画像取得処理の設定。
+/// # Returns
+/// * 成功時:見つかったPDFファイルのパス(ディレクトリ・ファイル名の順に並べ替えたもの)。
+///   (サブディレクトリの読み込み失敗・シンボリックリンクの循環は警告を出力して無視する。)
+/// * 失敗時:std::io::Error(指定したディレクトリの読み込み失敗)。
+pub(crate) fn find_pdf_files(
+    directory_path: &Path,
+    extractor: &Extractor,
+) -> std::io::Result<Vec<PathBuf>> {
+    let max_depth: usize = if extractor.recursive {
+        extractor.max_depth.unwrap_or(usize::MAX)
+    } else {
+        1
+    };
+    let walker = WalkDir::new(directory_path)
+        .follow_links(extractor.follow_symlinks)
+        .max_depth(max_depth)
+        .sort_by_file_name()
+        .into_iter()
+        //除外するパターンに一致したディレクトリ以下は検索しない。
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !entry.file_type().is_dir()
+                || !is_excluded(directory_path, entry, extractor)
+        });
+
+    let mut pdf_files: Vec<PathBuf> = Vec::new();
+    for entry in walker {
+        let entry: DirEntry = match entry {
+            Ok(entry) => entry,
+            Err(e) if e.depth() == 0 => return Err(e.into()),
+            Err(e) => {
+                warn!("COULD NOT READ DIRECTORY ENTRY. IT IGNORED. ERR: {}", e);
+                continue;
+            }
+        };
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let is_pdf: bool = entry
+            .path()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"));
+        if !is_pdf || is_excluded(directory_path, &entry, extractor) {
+            continue;
+        }
+        let relative_path: &Path = entry
+            .path()
+            .strip_prefix(directory_path)
+            .unwrap_or(entry.path());
+        if !extractor.include_patterns.is_empty()
+            && !extractor
+                .include_patterns
+                .iter()
+                .any(|pattern| pattern.is_match(relative_path))
+        {
+            if log_enabled!(Level::Debug) {
+                info!(
+                    "PDF FILE DOES NOT MATCH INCLUDE PATTERNS. FILE: {}",
+                    entry.path().display()
+                );
+            }
+            continue;
+        }
+        pdf_files.push(entry.into_path());
+    }
+    Ok(pdf_files)
+}
+
+///ファイルもしくはディレクトリが除外するパターンに一致すればtrueを返す。
+fn is_excluded(directory_path: &Path, entry: &DirEntry, extractor: &Extractor) -> bool {
+    let relative_path: &Path = entry
+        .path()
+        .strip_prefix(directory_path)
+        .unwrap_or(entry.path());
+    let excluded: bool = extractor
+        .exclude_patterns
+        .iter()
+        .any(|pattern| pattern.is_match(relative_path));
+    if excluded && log_enabled!(Level::Debug) {
+        info!(
+            "PATH MATCHES EXCLUDE PATTERNS. IT IGNORED. PATH: {}",
+            entry.path().display()
+        );
+    }
+    excluded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn file_names(directory_path: &Path, extractor: &Extractor) -> Vec<String> {
+        find_pdf_files(directory_path, extractor)
+            .unwrap()
+            .iter()
+            .map(|path| {
+                path.strip_prefix(directory_path)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_find_pdf_files() {
+        let root: PathBuf = std::env::temp_dir().join(format!(
+            "pdf_to_image_rs_find_pdf_files_{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(root.join("sub/deep")).unwrap();
+        fs::create_dir_all(root.join("old")).unwrap();
+        for file in [
+            "a.pdf",
+            "B.PDF",
+            "notes.txt",
+            "sub/c.pdf",
+            "sub/deep/d.pdf",
+            "old/e.pdf",
+        ] {
+            fs::write(root.join(file), b"").unwrap();
+        }
+
+        let flat = file_names(&root, &Extractor::new());
+        let recursive = file_names(&root, &Extractor::new().recursive(true));
+        let limited = file_names(&root, &Extractor::new().recursive(true).max_depth(2));
+        let excluded = file_names(
+            &root,
+            &Extractor::new()
+                .recursive(true)
+                .exclude_pattern("old".parse().unwrap())
+                .exclude_pattern("B.*".parse().unwrap()),
+        );
+        let included = file_names(
+            &root,
+            &Extractor::new()
+                .recursive(true)
+                .include_pattern("sub/**".parse().unwrap()),
+        );
+        let missing = find_pdf_files(&root.join("missing"), &Extractor::new());
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(flat, vec!["B.PDF", "a.pdf"]);
+        assert_eq!(
+            recursive,
+            vec!["B.PDF", "a.pdf", "old/e.pdf", "sub/c.pdf", "sub/deep/d.pdf"]
+        );
+        assert_eq!(limited, vec!["B.PDF", "a.pdf", "old/e.pdf", "sub/c.pdf"]);
+        assert_eq!(excluded, vec!["a.pdf", "sub/c.pdf", "sub/deep/d.pdf"]);
+        assert_eq!(included, vec!["sub/c.pdf", "sub/deep/d.pdf"]);
+        assert!(missing.is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_pdf_files_symlink() {
+        let root: PathBuf = std::env::temp_dir().join(format!(
+            "pdf_to_image_rs_find_pdf_files_symlink_{}",
+            std::process::id()
+        ));
+        let target: PathBuf = root.join("target");
+        let input: PathBuf = root.join("input");
+        fs::create_dir_all(&target).unwrap();
+        fs::create_dir_all(&input).unwrap();
+        fs::write(target.join("linked.pdf"), b"").unwrap();
+        std::os::unix::fs::symlink(&target, input.join("link")).unwrap();
+        //シンボリックリンクの循環は無視する。
+        std::os::unix::fs::symlink(&input, input.join("loop")).unwrap();
+
+        let not_followed = file_names(&input, &Extractor::new().recursive(true));
+        let followed = file_names(
+            &input,
+            &Extractor::new().recursive(true).follow_symlinks(true),
+        );
+        fs::remove_dir_all(&root).unwrap();
+
+        assert!(not_followed.is_empty());
+        assert_eq!(followed, vec!["link/linked.pdf"]);
+    }
+}
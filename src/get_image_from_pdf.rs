@@ -1,4 +1,23 @@
-use crate::get_thread_id::get_thread_id_number;
+use crate::apply_image_mask::has_image_mask;
+use crate::archive_format::ArchiveFormat;
+use crate::collect_page_images::{collect_page_images, PageImage};
+use crate::decode_ccitt::decode_ccitt_image;
+use crate::decode_jbig2::{decode_jbig2_image, jbig2_file};
+use crate::decode_raw_image::decode_image;
+use crate::dedup_mode::DedupKey;
+use crate::dedup_store::DedupStore;
+use crate::existing_policy::ExistingPolicy;
+use crate::extract_error::ExtractError;
+use crate::extraction_manifest::{assign_clusters, write_manifest, ImageStatus, ManifestEntry};
+use crate::extraction_report::ExtractionReport;
+use crate::extractor::Extractor;
+use crate::file_name_template::FileNameValues;
+use crate::global_dedup::GlobalDedup;
+use crate::near_duplicate::{cluster_id, NearDuplicateAction, NearDuplicateIndex};
+use crate::output_format::{convert_image, convert_masked_image, OutputFormat};
+use crate::output_sink::OutputSink;
+use crate::render_page::render_page;
+use crate::resume_manifest::{image_key, page_key, ResumeManifest};
 use crate::set_workers_limit::get_sub_workers_limit;
 
 use file_method::check_path::is_valid_file;
@@ -9,35 +28,47 @@ use pdf::any::AnySync;
 use pdf::backend::Backend;
 use pdf::enc::StreamFilter;
 use pdf::file::Cache;
+use pdf::file::CachedFile;
 use pdf::file::File as PdfFile;
 use pdf::file::Log;
-use pdf::primitive::Name;
 use pdf::{error::PdfError, file::FileOptions, object::*};
 use regex::{Captures, Regex};
-use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fs::File;
-use std::io::Write;
+use std::io::{BufWriter, Read};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, RwLock};
-use std::thread;
+use std::sync::{mpsc, Arc, RwLock};
 use threadpool::ThreadPool;
 
 ///PDFファイルから画像を取得する。
 /// # Arguments
 /// * `pdf_file_path` - PDFファイルのパス。
+/// * `input_root` - 出力先ディレクトリ指定時に、相対パスを保持する基準となる入力ディレクトリ(フルパス)。
+///   (Noneの場合は出力先ディレクトリの直下に保存する。)
+/// * `extractor` - 画像取得処理の設定。
+/// * `dedup_store` - PDFファイル間で共有する重複判定ストア。(PDFファイル間の重複を判定しない場合はNone。)
 /// # Returns
-/// * 成功時:0
-/// * 失敗時:以下のエラーコード。
-/// * 1:ページ取得失敗もしくはページ内画像取得失敗。
-/// * 20:PDFファイルのフルパス取得失敗。
-/// * 21:ディレクトリ作成失敗。
-/// * 22:PDFファイルオープン失敗。
+/// * 成功時:処理結果。(ページ取得失敗・ページ内画像取得失敗などはerrorsに格納される。)
+/// * 失敗時:以下のエラー。
+/// * ExtractError::PathResolution:PDFファイルのフルパス取得失敗。
+/// * ExtractError::DestinationExists:保存先ディレクトリ(アーカイブにまとめて保存する場合はアーカイブ)が既に存在する。(ExistingPolicy::Fail指定時。)
+/// * ExtractError::DirectoryCreation:ディレクトリ作成失敗。
+/// * ExtractError::DirectoryRemoval:ディレクトリ削除失敗。(ExistingPolicy::Overwrite指定時。)
+/// * ExtractError::Open:PDFファイルオープン失敗。
+/// * ExtractError::Manifest:再開用マニフェストの読み込みもしくは作成の失敗。
+/// * ExtractError::Archive:アーカイブの作成・書き込みの失敗。(アーカイブにまとめて保存する場合。)
 ///
-pub fn get_images(pdf_file_path: &Path) -> u32 {
-    let mut return_value: u32 = 0;
+pub(crate) fn get_images(
+    pdf_file_path: &Path,
+    input_root: Option<&Path>,
+    extractor: &Extractor,
+    dedup_store: Option<Arc<DedupStore>>,
+) -> Result<ExtractionReport, ExtractError> {
+    let mut report = ExtractionReport {
+        pdf_files: 1,
+        ..Default::default()
+    };
     let start_time: i64 = Utc::now().timestamp_micros();
-    let my_thread_id: std::thread::ThreadId = thread::current().id();
 
     //受け取ったファイルのパスをフルパスに変換する。
     let pdf_path = match is_valid_file(pdf_file_path) {
@@ -48,55 +79,419 @@ pub fn get_images(pdf_file_path: &Path) -> u32 {
                 pdf_file_path.display(),
                 e
             );
-            return 20;
+            return Err(ExtractError::PathResolution {
+                path: pdf_file_path.to_path_buf(),
+                reason: e.to_string(),
+            });
+        }
+    };
+
+    //画像の保存先ディレクトリ。(出力先ディレクトリ未指定時はpdf_pathから拡張子を取り除いたもの。)
+    //シンボリックリンクをたどって見つけたPDFファイルはフルパスが入力ディレクトリの外になるため、検索時のパスを使用する。
+    let source_path: &Path = match input_root {
+        Some(root) if pdf_file_path.starts_with(root) => pdf_file_path,
+        _ => &pdf_path,
+    };
+    let dest_dir_path: Arc<PathBuf> = Arc::new(get_dest_dir_path(
+        source_path,
+        input_root,
+        extractor.output_dir.as_deref(),
+    ));
+    //アーカイブにまとめて保存する場合は、保存先ディレクトリのパスに拡張子を付けたファイルに保存する。
+    let archive_path: Option<PathBuf> = extractor
+        .archive
+        .map(|archive_format| get_archive_path(&dest_dir_path, archive_format));
+    let dest_path: &Path = archive_path.as_deref().unwrap_or(&dest_dir_path);
+    //保存先(dest_dir_pathの示すディレクトリもしくはアーカイブ)が既に存在している場合は指定された動作に従う。
+    let dest_dir_exists: bool = match archive_path {
+        Some(ref archive_path) => archive_path.is_file(),
+        None => dest_dir_path.is_dir(),
+    };
+    if dest_dir_exists {
+        match extractor.on_existing {
+            ExistingPolicy::Skip => {
+                info!(
+                    "DIRECTORY ALREADY EXISTS. IGNORE THIS FILE. DIRECTORY: {} FILE : {}",
+                    dest_path.display(),
+                    pdf_path.display()
+                );
+                report.skipped_files += 1;
+                return Ok(report);
+            }
+            ExistingPolicy::Fail => {
+                error!(
+                    "DIRECTORY ALREADY EXISTS. DIRECTORY: {} FILE : {}",
+                    dest_path.display(),
+                    pdf_path.display()
+                );
+                return Err(ExtractError::DestinationExists {
+                    path: dest_path.to_path_buf(),
+                });
+            }
+            ExistingPolicy::Overwrite | ExistingPolicy::Resume => {
+                info!(
+                    "DIRECTORY ALREADY EXISTS. POLICY: {} DIRECTORY: {} FILE : {}",
+                    extractor.on_existing,
+                    dest_path.display(),
+                    pdf_path.display()
+                );
+            }
         }
     };
 
-    //pdf_pathから拡張子を取り除く。
-    let dest_dir_path: Arc<PathBuf> = Arc::new(pdf_path.with_extension(""));
-    //dest_dir_pathの示すディレクトリが存在していない場合はディレクトリを作成する。
-    if !dest_dir_path.is_dir() {
-        match std::fs::create_dir_all(dest_dir_path.as_ref()) {
-            Ok(_) => {}
+    //PDFファイルを開く
+    //(開けないPDFファイルのために空のディレクトリが残り、再実行時に無視されることのないよう、ディレクトリ作成より先に開く。)
+    //(暗号化されたPDFファイルはパスワードを指定して開く。パスワードがない場合は空のパスワードで開く。)
+    let password: &str = extractor.passwords.get(&pdf_path).unwrap_or_default();
+    let file: Arc<CachedFile<Vec<u8>>> = Arc::new(
+        match FileOptions::cached()
+            .password(password.as_bytes())
+            .open(&pdf_path)
+        {
+            Ok(file) => file,
+            Err(e) => {
+                error!(
+                    "COULD NOT OPEN PDF FILE. FILE: {} ERR: {}",
+                    pdf_path.display(),
+                    e
+                );
+                return Err(ExtractError::open_failed(pdf_path, e));
+            }
+        },
+    );
+
+    if let (Some(archive_format), Some(archive_path)) = (extractor.archive, archive_path) {
+        return get_images_to_archive(
+            file,
+            &pdf_path,
+            archive_format,
+            &archive_path,
+            dest_dir_exists,
+            extractor,
+            report,
+            start_time,
+        );
+    }
+
+    //前回の実行の再開用マニフェストを読み込む。上書きする場合は前回の実行結果を削除する。
+    //(PDFファイルを開けない場合に前回の実行結果が失われないよう、PDFファイルを開いた後に行う。)
+    let mut resume_manifest: Option<ResumeManifest> = None;
+    if dest_dir_exists && extractor.on_existing == ExistingPolicy::Resume {
+        match ResumeManifest::open(&dest_dir_path) {
+            Ok(Some(manifest)) => {
+                match manifest.remove_unrecorded_files(&dest_dir_path) {
+                    Ok(removed) => info!(
+                        "RESUME EXTRACTION. DIRECTORY: {} COMPLETED_FILES: {} REMOVED_INCOMPLETE_FILES: {}",
+                        dest_dir_path.display(),
+                        manifest.len(),
+                        removed
+                    ),
+                    Err(e) => warn!(
+                        "COULD NOT REMOVE INCOMPLETE FILES. DIRECTORY: {} ERR: {}",
+                        dest_dir_path.display(),
+                        e
+                    ),
+                }
+                resume_manifest = Some(manifest);
+            }
+            Ok(None) => {
+                //再開用マニフェストがない(再開に対応する前に作成された)ディレクトリは完了済みとして無視する。
+                info!(
+                    "RESUME MANIFEST NOT FOUND. IGNORE THIS FILE. DIRECTORY: {} FILE : {}",
+                    dest_dir_path.display(),
+                    pdf_path.display()
+                );
+                report.skipped_files += 1;
+                return Ok(report);
+            }
             Err(e) => {
                 error!(
-                    "COULD NOT CREATE DIRECTORY. DIRECTORY: {} ERR: {}",
+                    "COULD NOT READ RESUME MANIFEST. DIRECTORY: {} ERR: {}",
                     dest_dir_path.display(),
                     e
                 );
-                return 21;
+                return Err(ExtractError::Manifest {
+                    path: dest_dir_path.to_path_buf(),
+                    source: e,
+                });
             }
         }
-    } else {
-        info!(
-            "DIRECTORY ALREADY EXISTS. IGNORE THIS FILE. DIRECTORY: {} FILE : {}",
+    } else if dest_dir_exists && extractor.on_existing == ExistingPolicy::Overwrite {
+        if let Err(e) = std::fs::remove_dir_all(dest_dir_path.as_ref()) {
+            error!(
+                "COULD NOT REMOVE DIRECTORY. DIRECTORY: {} ERR: {}",
+                dest_dir_path.display(),
+                e
+            );
+            return Err(ExtractError::DirectoryRemoval {
+                path: dest_dir_path.to_path_buf(),
+                source: e,
+            });
+        }
+    }
+
+    //dest_dir_pathの示すディレクトリを作成する。
+    if let Err(e) = std::fs::create_dir_all(dest_dir_path.as_ref()) {
+        error!(
+            "COULD NOT CREATE DIRECTORY. DIRECTORY: {} ERR: {}",
             dest_dir_path.display(),
-            pdf_path.display()
+            e
         );
-        return 0;
+        return Err(ExtractError::DirectoryCreation {
+            path: dest_dir_path.to_path_buf(),
+            source: e,
+        });
+    }
+
+    //中断された場合に再開できるよう、保存が完了した画像を再開用マニフェストに記録する。
+    let resume_manifest: Arc<ResumeManifest> = match resume_manifest {
+        Some(manifest) => Arc::new(manifest),
+        None => match ResumeManifest::create(&dest_dir_path) {
+            Ok(manifest) => Arc::new(manifest),
+            Err(e) => {
+                error!(
+                    "COULD NOT CREATE RESUME MANIFEST. DIRECTORY: {} ERR: {}",
+                    dest_dir_path.display(),
+                    e
+                );
+                return Err(ExtractError::Manifest {
+                    path: dest_dir_path.to_path_buf(),
+                    source: e,
+                });
+            }
+        },
     };
 
-    //PDFファイルを開く
-    let file = Arc::new(match FileOptions::cached().open(&pdf_path) {
-        Ok(file) => file,
+    Ok(extract_pages(
+        file,
+        &pdf_path,
+        dest_dir_path,
+        resume_manifest,
+        Arc::new(OutputSink::Directory),
+        dedup_store,
+        extractor,
+        report,
+        start_time,
+    ))
+}
+
+///PDFファイルの画像とマニフェストを1つのアーカイブに保存する。
+///(書き込み途中のアーカイブが完了したものとして扱われないよう、".part"を付けたファイルに書き込んでから名前を変更する。)
+///(アーカイブは途中から再開できないため、ExistingPolicy::Resumeの場合も作成し直す。)
+/// # Arguments
+/// * `file` - PDFファイル。
+/// * `pdf_path` - PDFファイルのフルパス。
+/// * `archive_format` - アーカイブの形式。
+/// * `archive_path` - アーカイブのパス。
+/// * `archive_exists` - アーカイブが既に存在している場合はtrue。
+/// * `extractor` - 画像取得処理の設定。
+/// * `report` - PDFファイルの処理結果。
+/// * `start_time` - 処理を開始した時刻(マイクロ秒)。
+/// # Returns
+/// * 成功時:処理結果。(画像ファイル・マニフェストに記録するパスはアーカイブ内のパス。)
+/// * 失敗時:以下のエラー。
+/// * ExtractError::DirectoryCreation:アーカイブを保存するディレクトリの作成失敗。
+/// * ExtractError::Archive:アーカイブの作成・書き込み・名前の変更の失敗。
+#[allow(clippy::too_many_arguments)]
+fn get_images_to_archive(
+    file: Arc<CachedFile<Vec<u8>>>,
+    pdf_path: &Path,
+    archive_format: ArchiveFormat,
+    archive_path: &Path,
+    archive_exists: bool,
+    extractor: &Extractor,
+    report: ExtractionReport,
+    start_time: i64,
+) -> Result<ExtractionReport, ExtractError> {
+    if archive_exists && extractor.on_existing == ExistingPolicy::Resume {
+        info!(
+            "ARCHIVE CANNOT BE RESUMED. IT RECREATED. ARCHIVE: {} FILE : {}",
+            archive_path.display(),
+            pdf_path.display()
+        );
+    }
+    if let Some(parent) = archive_path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        if let Err(e) = std::fs::create_dir_all(parent) {
+            error!(
+                "COULD NOT CREATE DIRECTORY. DIRECTORY: {} ERR: {}",
+                parent.display(),
+                e
+            );
+            return Err(ExtractError::DirectoryCreation {
+                path: parent.to_path_buf(),
+                source: e,
+            });
+        }
+    }
+    let mut part_path = archive_path.as_os_str().to_owned();
+    part_path.push(".part");
+    let part_path: PathBuf = PathBuf::from(part_path);
+    let part_file: File = match File::create(&part_path) {
+        Ok(part_file) => part_file,
         Err(e) => {
             error!(
-                "COULD NOT OPEN PDF FILE. FILE: {} ERR: {}",
-                pdf_path.display(),
+                "COULD NOT CREATE ARCHIVE. ARCHIVE: {} ERR: {}",
+                part_path.display(),
                 e
             );
-            return 22;
+            return Err(ExtractError::Archive {
+                path: part_path,
+                source: e,
+            });
         }
-    });
+    };
+    //アーカイブ内のパスは保存先ディレクトリからの相対パスとするため、保存先ディレクトリは空のパスとする。
+    let sink: Arc<OutputSink> = Arc::new(OutputSink::archive(
+        archive_format,
+        Path::new(""),
+        Box::new(BufWriter::new(part_file)),
+    ));
+    let report: ExtractionReport = extract_pages(
+        file,
+        pdf_path,
+        Arc::new(PathBuf::new()),
+        Arc::new(ResumeManifest::new()),
+        Arc::clone(&sink),
+        None,
+        extractor,
+        report,
+        start_time,
+    );
+    if let Err(e) = sink
+        .finish()
+        .and_then(|_| std::fs::rename(&part_path, archive_path))
+    {
+        error!(
+            "COULD NOT WRITE ARCHIVE. ARCHIVE: {} ERR: {}",
+            archive_path.display(),
+            e
+        );
+        let _ = std::fs::remove_file(&part_path);
+        return Err(ExtractError::Archive {
+            path: archive_path.to_path_buf(),
+            source: e,
+        });
+    }
+    if log_enabled!(Level::Debug) {
+        info!("ARCHIVE WRITTEN. ARCHIVE: {}", archive_path.display());
+    }
+    Ok(report)
+}
 
+///メモリ上のPDFファイル(標準入力から読み込んだものなど)から画像を取得し、アーカイブに書き込む。
+///(保存先ディレクトリ・再開用マニフェストは作成しない。PDFファイル間の重複は判定しない。)
+/// # Arguments
+/// * `reader` - PDFファイルのデータの読み込み元。
+/// * `pdf_name` - PDFファイルの名前(ログ・マニフェスト・ファイル名のテンプレートの{pdf_stem}に使用する)。
+/// * `sink` - 画像ファイルとマニフェストの書き込み先のアーカイブ。(書き込みの終了は呼び出し元で行う。)
+/// * `extractor` - 画像取得処理の設定。
+/// # Returns
+/// * 成功時:処理結果。(ページ取得失敗・ページ内画像取得失敗などはerrorsに格納される。)
+/// * 失敗時:以下のエラー。
+/// * ExtractError::Input:PDFファイルのデータの読み込み失敗。
+/// * ExtractError::Open:PDFファイルオープン失敗。
+pub(crate) fn get_images_from_reader(
+    reader: &mut dyn Read,
+    pdf_name: &str,
+    sink: Arc<OutputSink>,
+    extractor: &Extractor,
+) -> Result<ExtractionReport, ExtractError> {
+    let report = ExtractionReport {
+        pdf_files: 1,
+        ..Default::default()
+    };
+    let start_time: i64 = Utc::now().timestamp_micros();
+    let pdf_path: PathBuf = PathBuf::from(pdf_name);
+
+    let mut data: Vec<u8> = Vec::new();
+    if let Err(e) = reader.read_to_end(&mut data) {
+        error!("COULD NOT READ PDF DATA. FILE: {} ERR: {}", pdf_name, e);
+        return Err(ExtractError::Input { source: e });
+    }
+    let password: &str = extractor.passwords.get(&pdf_path).unwrap_or_default();
+    let file: Arc<CachedFile<Vec<u8>>> = Arc::new(
+        match FileOptions::cached()
+            .password(password.as_bytes())
+            .load(data)
+        {
+            Ok(file) => file,
+            Err(e) => {
+                error!("COULD NOT OPEN PDF FILE. FILE: {} ERR: {}", pdf_name, e);
+                return Err(ExtractError::open_failed(pdf_path, e));
+            }
+        },
+    );
+    //アーカイブ内のパスは保存先ディレクトリからの相対パスとするため、保存先ディレクトリは空のパスとする。
+    Ok(extract_pages(
+        file,
+        &pdf_path,
+        Arc::new(PathBuf::new()),
+        Arc::new(ResumeManifest::new()),
+        sink,
+        None,
+        extractor,
+        report,
+        start_time,
+    ))
+}
+
+///開いたPDFファイルの全てのページをスレッドプールで並列に処理し、マニフェストを書き込む。
+/// # Arguments
+/// * `file` - PDFファイル。
+/// * `pdf_path` - PDFファイルのパス(ログ・マニフェストに使用する)。
+/// * `dest_dir_path` - 画像ファイルの保存先ディレクトリ。
+/// * `resume_manifest` - 再開用マニフェスト。
+/// * `sink` - 画像ファイルとマニフェストの書き込み先。
+/// * `dedup_store` - PDFファイル間で共有する重複判定ストア。(PDFファイル間の重複を判定しない場合はNone。)
+/// * `extractor` - 画像取得処理の設定。
+/// * `report` - PDFファイルの処理結果。(各ページの処理結果を集約する。)
+/// * `start_time` - 処理を開始した時刻(マイクロ秒)。
+/// # Returns
+/// * PDFファイルの処理結果。(ページ取得失敗・ページ内画像取得失敗などはerrorsに格納される。)
+#[allow(clippy::too_many_arguments)]
+fn extract_pages(
+    file: Arc<CachedFile<Vec<u8>>>,
+    pdf_path: &Path,
+    dest_dir_path: Arc<PathBuf>,
+    resume_manifest: Arc<ResumeManifest>,
+    sink: Arc<OutputSink>,
+    dedup_store: Option<Arc<DedupStore>>,
+    extractor: &Extractor,
+    mut report: ExtractionReport,
+    start_time: i64,
+) -> ExtractionReport {
     //let pool = ThreadPool::new(1);
-    let pool = ThreadPool::new(get_sub_workers_limit(50.0));
-    let image_hash_list: Arc<RwLock<HashSet<Arc<[u8]>>>> = Arc::new(RwLock::new(HashSet::new()));
-    let mut page_counter: u64 = 0;
+    let pool = ThreadPool::new(
+        extractor
+            .page_workers
+            .unwrap_or_else(|| get_sub_workers_limit(50.0)),
+    );
+    let image_hash_list: Arc<RwLock<HashSet<DedupKey>>> = Arc::new(RwLock::new(HashSet::new()));
+    let near_duplicate_index: Arc<NearDuplicateIndex> = Arc::new(NearDuplicateIndex::default());
+    let extractor_arc: Arc<Extractor> = Arc::new(extractor.clone());
+    //処理するページ番号。(ページを指定しない場合は全てのページ。)
+    let page_count: u64 = file.num_pages() as u64;
+    let page_numbers: Vec<u64> = match &extractor.page_selection {
+        Some(page_selection) => page_selection.pages(page_count),
+        None => (1..=page_count).collect(),
+    };
+    if page_numbers.is_empty() && page_count > 0 {
+        warn!(
+            "NO PAGES MATCHED. FILE: {} PAGES: {}",
+            pdf_path.display(),
+            page_count
+        );
+    }
+    //各スレッドの処理結果を受け取るチャネル。
+    //(クロージャにコピーされた変数を書き換えても呼び出し元には反映されないため、チャネルで集約する。)
+    let (result_sender, result_receiver) =
+        mpsc::channel::<Result<ExtractionReport, ExtractError>>();
 
-    for page in file.pages() {
-        page_counter += 1;
-        let page: PageRc = match page {
+    for page_counter in page_numbers {
+        let page: PageRc = match file.get_page((page_counter - 1) as u32) {
             Ok(page) => page,
             Err(e) => {
                 warn!(
@@ -105,12 +500,10 @@ pub fn get_images(pdf_file_path: &Path) -> u32 {
                     page_counter,
                     e
                 );
-                if return_value == 0 {
-                    return_value = 1;
-                    if log::log_enabled!(log::Level::Debug) {
-                        info!("RETURN VALUE : {}", return_value);
-                    }
-                }
+                report.errors.push(ExtractError::Page {
+                    page: page_counter,
+                    source: e,
+                });
                 continue;
             }
         };
@@ -118,46 +511,125 @@ pub fn get_images(pdf_file_path: &Path) -> u32 {
         // 以下のように参照を作成してクロージャに渡す。
         let file_ref = Arc::clone(&file);
         let image_hash_list_ref = Arc::clone(&image_hash_list);
+        let near_duplicate_index_ref = Arc::clone(&near_duplicate_index);
         let dest_dir_path_ref = Arc::clone(&dest_dir_path);
         let pdf_parh_string: String = pdf_path.display().to_string();
+        let pdf_stem: String = pdf_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let result_sender_ref = result_sender.clone();
+        let extractor_ref = Arc::clone(&extractor_arc);
+        let resume_manifest_ref = Arc::clone(&resume_manifest);
+        let dedup_store_ref = dedup_store.clone();
+        let sink_ref = Arc::clone(&sink);
 
         //get_images_from_page()を使ってスレッドを生成して画像を取得する。
+        //(ページを描画する場合はrender_page_to_file()でページを描画して保存する。)
         // スレッドプールにタスクを追加。
         pool.execute(move || {
-            match get_images_from_page(
-                &page,
-                file_ref,
-                image_hash_list_ref,
-                dest_dir_path_ref,
-                &my_thread_id,
-                start_time,
-                page_counter,
-            ) {
-                Ok(result) => {
+            let page_result = if extractor_ref.render_pages {
+                render_page_to_file(
+                    &page,
+                    file_ref,
+                    dest_dir_path_ref,
+                    resume_manifest_ref,
+                    sink_ref,
+                    page_counter,
+                    extractor_ref,
+                )
+            } else {
+                get_images_from_page(
+                    &page,
+                    file_ref,
+                    image_hash_list_ref,
+                    near_duplicate_index_ref,
+                    dedup_store_ref,
+                    dest_dir_path_ref,
+                    resume_manifest_ref,
+                    sink_ref,
+                    &pdf_stem,
+                    page_counter,
+                    extractor_ref,
+                )
+            };
+            match &page_result {
+                Ok(page_report) if page_report.is_success() => {
                     if log_enabled!(Level::Debug) {
                         info!(
-                            "PAGE PROCESS COMPLETE. PAGE: {} FILE : {} RESULT : {}",
-                            page_counter, pdf_parh_string, result
+                            "PAGE PROCESS COMPLETE. PAGE: {} FILE : {} IMAGES : {}",
+                            page_counter, pdf_parh_string, page_report.images_written
                         )
                     }
                 }
+                Ok(page_report) => {
+                    //未対応の画像や書き込みに失敗した画像があった場合もエラーとして扱う。
+                    error!(
+                        "PAGE PROCESS COMPLETE WITH ERRORS. PAGE: {} FILE : {} FAILED_IMAGES : {}",
+                        page_counter,
+                        pdf_parh_string,
+                        page_report.errors.len()
+                    );
+                }
                 Err(e) => {
                     error!(
                         "PAGE PROCESS ERROR. PAGE: {} FILE : {} ERR : {}",
                         page_counter, pdf_parh_string, e
                     );
-                    if return_value == 0 {
-                        return_value = 1;
-                        if log::log_enabled!(log::Level::Debug) {
-                            info!("RETURN VALUE : {}", return_value);
-                        }
-                    }
                 }
-            }
+            };
+            //受信側は全てのタスクの終了後に読み出すため、送信の失敗は無視する。
+            let _ = result_sender_ref.send(page_result);
         });
     }
     // 全てのタスクが終了するのを待つ。
     pool.join();
+    drop(result_sender);
+    for page_result in result_receiver.iter() {
+        match page_result {
+            Ok(page_report) => report.merge(page_report),
+            Err(e) => report.errors.push(e),
+        }
+    }
+    //パニックしたスレッドは結果を送信しないため、パニックの有無も確認する。
+    if pool.panic_count() > 0 {
+        report.errors.push(ExtractError::WorkerPanicked {
+            count: pool.panic_count(),
+        });
+    }
+    //抽出した画像の一覧をマニフェストとして保存する。(ページを描画した場合は作成しない。)
+    if !extractor.render_pages {
+        for entry in report.manifest_entries.iter_mut() {
+            entry.pdf_path = pdf_path.to_path_buf();
+        }
+        assign_clusters(&mut report.manifest_entries);
+        match write_manifest(
+            &dest_dir_path,
+            &mut report.manifest_entries,
+            extractor.manifest_csv,
+            &sink,
+        ) {
+            Ok(manifest_paths) => {
+                if log_enabled!(Level::Debug) {
+                    info!("MANIFEST WRITTEN. FILES: {:?}", manifest_paths);
+                }
+            }
+            Err(e) => {
+                error!(
+                    "COULD NOT WRITE MANIFEST. DIRECTORY: {} ERR: {}",
+                    dest_dir_path.display(),
+                    e
+                );
+                report.errors.push(ExtractError::Manifest {
+                    path: dest_dir_path.to_path_buf(),
+                    source: e,
+                });
+            }
+        }
+    }
+    if !report.is_success() && log::log_enabled!(log::Level::Debug) {
+        info!("ERRORS : {}", report.errors.len());
+    }
     let end_time: i64 = Utc::now().timestamp_micros();
     let elapsed_time: i64 = end_time - start_time;
     info!(
@@ -165,42 +637,88 @@ pub fn get_images(pdf_file_path: &Path) -> u32 {
         pdf_path.display(),
         elapsed_time
     );
-    return_value
+    report
+}
+
+///画像の保存先ディレクトリのパスを取得する。
+/// # Arguments
+/// * `pdf_path` - PDFファイルのフルパス。
+/// * `input_root` - 相対パスを保持する基準となる入力ディレクトリのフルパス。
+/// * `output_dir` - 出力先ディレクトリ。
+/// # Returns
+/// * 出力先ディレクトリ未指定時:PDFファイルのパスから拡張子を取り除いたもの。
+/// * 出力先ディレクトリ指定時:出力先ディレクトリの下に、入力ディレクトリからの相対パスから拡張子を取り除いたもの。
+///   (入力ディレクトリ未指定時もしくは入力ディレクトリ外のPDFファイルの場合は出力先ディレクトリ直下にファイル名から拡張子を取り除いたもの。)
+pub(crate) fn get_dest_dir_path(
+    pdf_path: &Path,
+    input_root: Option<&Path>,
+    output_dir: Option<&Path>,
+) -> PathBuf {
+    let output_dir: &Path = match output_dir {
+        Some(output_dir) => output_dir,
+        None => return pdf_path.with_extension(""),
+    };
+    let relative_path: &Path = input_root
+        .and_then(|root| pdf_path.strip_prefix(root).ok())
+        .unwrap_or_else(|| Path::new(pdf_path.file_name().unwrap_or(pdf_path.as_os_str())));
+    output_dir.join(relative_path.with_extension(""))
+}
+
+///アーカイブのパスを取得する。(保存先ディレクトリのパスにアーカイブの拡張子を付けたもの。)
+/// # Arguments
+/// * `dest_dir_path` - 画像の保存先ディレクトリ。
+/// * `archive_format` - アーカイブの形式。
+fn get_archive_path(dest_dir_path: &Path, archive_format: ArchiveFormat) -> PathBuf {
+    //PDFファイル名に"."が含まれる場合も拡張子が置き換わらないよう、末尾に追加する。
+    let mut archive_path = dest_dir_path.as_os_str().to_owned();
+    archive_path.push(".");
+    archive_path.push(archive_format.extension());
+    PathBuf::from(archive_path)
 }
 
 ///PDFファイルのページから画像を取得する。
 /// # Arguments
 /// * `page` - PDFファイルのページ。
 /// * `file` - PDFファイル。
-/// * `images_kvs` - 画像データのハッシュセット。(スレッド感で共有するためArc<RwLock<HashSet<Arc<[u8]>>>>)
+/// * `images_kvs` - 保存済みの画像の重複判定キーのハッシュセット。(スレッド間で共有するためArc<RwLock<HashSet<DedupKey>>>)
+/// * `near_duplicate_index` - 近似した画像のクラスタ。(知覚ハッシュを指定した場合のみ使用する。)
+/// * `dedup_store` - PDFファイル間で共有する重複判定ストア。(PDFファイル間の重複を判定しない場合はNone。)
 /// * `dest_dir_path` - 画像ファイルの保存先ディレクトリ。
-/// * `parent_thread_id` - 親スレッドのID(保存する画像のファイル名に使用するため)。
-/// * `unixtime_val` - 現在時刻のUNIXTIME(保存する画像のファイル名に使用するため)。
+/// * `resume_manifest` - 再開用マニフェスト(保存済みの画像の確認と保存した画像の記録に使用する)。
+/// * `sink` - 画像ファイルの書き込み先。
+/// * `pdf_stem` - PDFファイル名から拡張子を取り除いたもの(保存する画像のファイル名に使用するため)。
 /// * `page_count` - PDFのページ番号(保存する画像のファイル名に使用するため)。
+/// * `extractor` - 画像取得処理の設定。
 /// # Returns
-/// * 全ページの処理成功時:0
-/// * 上記以外:0より大きい数字。
-/// * (0以外の値には、少なくとも一部の画像取得に失敗した以外の意味はない。失敗の詳細はログに出力される。)
+/// * 成功時:ページの処理結果。(保存できなかった画像のエラーはerrorsに格納される。)
+/// * 失敗時:ExtractError::Page(ページのリソース取得失敗など)。
+#[allow(clippy::too_many_arguments)]
 fn get_images_from_page<T, K, Y, L>(
     page: &PageRc,
     file: Arc<PdfFile<T, K, Y, L>>,
-    images_kvs: Arc<RwLock<HashSet<Arc<[u8]>>>>,
+    images_kvs: Arc<RwLock<HashSet<DedupKey>>>,
+    near_duplicate_index: Arc<NearDuplicateIndex>,
+    dedup_store: Option<Arc<DedupStore>>,
     dest_dir_path: Arc<PathBuf>,
-    parent_thread_id: &std::thread::ThreadId,
-    unixtime_val: i64,
+    resume_manifest: Arc<ResumeManifest>,
+    sink: Arc<OutputSink>,
+    pdf_stem: &str,
     page_count: u64,
-) -> Result<u32, PdfError>
+    extractor: Arc<Extractor>,
+) -> Result<ExtractionReport, ExtractError>
 where
     T: Backend,
     K: Cache<std::result::Result<AnySync, Arc<PdfError>>>,
     Y: Cache<std::result::Result<Arc<[u8]>, Arc<PdfError>>>,
     L: Log,
 {
-    let mut return_value: u32 = 0;
+    let mut report = ExtractionReport {
+        pages: 1,
+        ..Default::default()
+    };
     let re = Regex::new(r"\d+").unwrap();
-    let my_thread_id: std::thread::ThreadId = thread::current().id();
 
-    let mut images: HashMap<Name, RcRef<XObject>> = HashMap::new();
+    let mut images: Vec<(String, PageImage)> = Vec::new();
     let resources: &MaybeRef<Resources> = {
         match page.resources() {
             Ok(resources) => resources,
@@ -211,25 +729,30 @@ where
                     page_count,
                     e
                 );
-                return Err(e);
+                return Err(ExtractError::Page {
+                    page: page_count,
+                    source: e,
+                });
             }
         }
     };
     let resolver = file.resolver();
 
-    for (name, &r) in resources.xobjects.iter() {
-        let object = resolver.get(r).unwrap();
-        if matches!(*object, pdf::object::XObject::Image(_)) {
-            if log_enabled!(Level::Debug) {
-                log::info!(
-                    "XObject_Name: {} DEST_PATH : {} PAGE: {}",
-                    name,
-                    dest_dir_path.display(),
-                    page_count
-                );
-            }
-            images.insert(name.clone(), object.clone());
-        }
+    //ページ直下のXObjectに加え、Form XObject内に配置された画像とインライン画像も収集する。
+    let mut failures: Vec<(String, PdfError)> = Vec::new();
+    collect_page_images(page, resources, &resolver, &mut images, &mut failures);
+    for (name, e) in failures {
+        warn!(
+            "COULD NOT GET XOBJECT OR CONTENTS. IT IGNORED. NAME: {} DEST_PATH : {} PAGE: {} ERR: {}",
+            name,
+            dest_dir_path.display(),
+            page_count,
+            e
+        );
+        report.errors.push(ExtractError::Page {
+            page: page_count,
+            source: e,
+        });
     }
     if log_enabled!(Level::Debug) {
         log::info!(
@@ -239,27 +762,103 @@ where
         );
     }
 
-    let mut image_count: i64 = 0;
+    let mut image_count: u64 = 0;
 
     for o in images.iter() {
         image_count += 1;
 
-        let img = match **o.1 {
-            XObject::Image(ref im) => im,
-            _ => {
+        let img = match o.1.image() {
+            Some(im) => im,
+            None => {
                 continue;
             }
         };
-        let (data, filter) = img.raw_image_data(&resolver)?;
-        let ext = match filter {
-            Some(StreamFilter::DCTDecode(_)) => "jpg",
-            Some(StreamFilter::JBIG2Decode(_)) => "jbig2",
-            Some(StreamFilter::JPXDecode) => "jp2k",
-            _ => {
-                if log_enabled!(Level::Warn) {
-                    let hex_dump: Vec<String> =
-                        data.iter().take(8).map(|b| format!("{:02x}", b)).collect();
-                    warn!(
+        let (data, filter) = img
+            .raw_image_data(&resolver)
+            .map_err(|e| ExtractError::Page {
+                page: page_count,
+                source: e,
+            })?;
+        let hash: blake3::Hash = blake3::hash(&data);
+        let mut entry = ManifestEntry::new(
+            page_count,
+            image_count,
+            &o.0,
+            &o.1,
+            img,
+            &data,
+            &hash,
+            filter,
+        );
+        //条件を満たさない画像(スペーサー・小さな記号など)は保存しない。(重複の判定にも使用しない。)
+        if !extractor
+            .image_filter
+            .matches(img.width, img.height, data.len() as u64)
+        {
+            if log_enabled!(Level::Debug) {
+                info!(
+                    "IMAGE FILTERED. OBJECT_NAME: {} DEST_PATH : {} PAGE: {} IMAGE_COUNT : {} SIZE : {}x{} BYTES : {}",
+                    o.0,
+                    dest_dir_path.display(),
+                    page_count,
+                    image_count,
+                    img.width,
+                    img.height,
+                    data.len()
+                );
+            }
+            report.filtered_images += 1;
+            report
+                .manifest_entries
+                .push(entry.with_status(ImageStatus::Filtered, Vec::new()));
+            continue;
+        }
+        //重複の判定には画像データではなくハッシュ値もしくは参照を使用する。(DedupMode::Offの場合はNone。)
+        let dedup_key: Option<DedupKey> = extractor.dedup_mode.key(&data, &hash, o.1.object_ref());
+        //前回の実行で保存済みの画像は保存しない。(以降の重複を判定するため、重複判定キーはHashSetに追加する。)
+        if resume_manifest.contains(&image_key(page_count, &o.0, "")) {
+            if log_enabled!(Level::Debug) {
+                info!(
+                    "IMAGE FILE ALREADY SAVED IN PREVIOUS RUN. OBJECT_NAME: {} DEST_PATH : {} PAGE: {} IMAGE_COUNT : {}",
+                    o.0,
+                    dest_dir_path.display(),
+                    page_count,
+                    image_count
+                );
+            }
+            if let Some(key) = dedup_key {
+                images_kvs.write().unwrap().insert(key);
+            }
+            report.resumed_files += 1;
+            let output_paths: Vec<PathBuf> = ["", "_unmasked"]
+                .iter()
+                .filter_map(|suffix| {
+                    resume_manifest.file_name(&image_key(page_count, &o.0, suffix))
+                })
+                .map(|file_name| dest_dir_path.join(file_name))
+                .collect();
+            report
+                .manifest_entries
+                .push(entry.with_status(ImageStatus::Resumed, output_paths));
+            continue;
+        }
+
+        //DCTDecode・JPXDecodeの画像はそのまま、もしくは指定された形式に変換して保存する。
+        //CCITTFaxDecodeの画像はTIFFに格納する、もしくは展開してPNGか指定された形式で保存する。
+        //JBIG2Decode・FlateDecode・LZWDecode・RunLengthDecode・フィルタなしの画像は展開してPNGもしくは指定された形式で保存する。
+        //(LZWDecode・RunLengthDecodeはraw_image_data()で展開済みのためNoneになる。)
+        if !matches!(
+            filter,
+            None | Some(StreamFilter::DCTDecode(_))
+                | Some(StreamFilter::CCITTFaxDecode(_))
+                | Some(StreamFilter::JBIG2Decode(_))
+                | Some(StreamFilter::JPXDecode)
+                | Some(StreamFilter::FlateDecode(_))
+        ) {
+            if log_enabled!(Level::Warn) {
+                let hex_dump: Vec<String> =
+                    data.iter().take(8).map(|b| format!("{:02x}", b)).collect();
+                warn!(
                     "UNSUPPORTED IMAGE FORMAT. TOP_8 : {} OBJECT_NAME: {} DEST_PATH : {} PAGE: {} IMAGE_COUNT : {}",
                     hex_dump.join("_"),
                     o.0,
@@ -267,16 +866,25 @@ where
                     page_count,
                     image_count
                 );
-                }
-                return_value += 1;
-                continue;
             }
-        };
+            report.errors.push(ExtractError::UnsupportedFilter {
+                page: page_count,
+                object_name: o.0.to_string(),
+                filter: match filter {
+                    Some(f) => format!("{:?}", f),
+                    None => "None".to_string(),
+                },
+            });
+            report
+                .manifest_entries
+                .push(entry.with_status(ImageStatus::Unsupported, Vec::new()));
+            continue;
+        }
 
         //PDFファイル内の同じ画像はスキップする。
         {
             let read_set = images_kvs.read().unwrap();
-            if read_set.contains(&data) {
+            if dedup_key.is_some_and(|key| read_set.contains(&key)) {
                 if log_enabled!(Level::Debug) {
                     info!(
                         "IMAGE FILE ALREADY EXISTS. OBJECT_NAME: {} DEST_PATH : {} PAGE: {} IMAGE_COUNT : {}",
@@ -286,13 +894,189 @@ where
                         image_count
                     );
                 }
+                report.duplicate_images += 1;
+                report
+                    .manifest_entries
+                    .push(entry.with_status(ImageStatus::Duplicate, Vec::new()));
                 continue;
             }
         }
+
+        //埋め込みオブジェクト名の数字を6桁に変換する。
+        let converted_embbeded_object_name: std::borrow::Cow<str> =
+            re.replace_all(&o.0, |caps: &Captures| {
+                let num: u32 = (caps[0]).parse().unwrap();
+                format!("{:06}", num)
+            });
+
+        let file_name_values = FileNameValues {
+            pdf_stem,
+            page: page_count,
+            index: image_count,
+            xobject: &converted_embbeded_object_name,
+            objnum: o.1.object_ref().map(|r| r.id).unwrap_or(0),
+            width: img.width,
+            height: img.height,
+            hash: &entry.hash[..16],
+        };
+
+        //他のPDFファイルで保存済みの画像は保存せず、最初に保存したファイルを参照もしくはリンクする。
+        let global_digest: Option<[u8; 32]> = match dedup_store {
+            Some(_) => extractor.dedup_mode.digest(&data, &hash),
+            None => None,
+        };
+        if let (Some(store), Some(digest)) = (dedup_store.as_deref(), global_digest) {
+            if let Some(first_files) = store.get(&digest) {
+                let (status, output_paths) = link_first_files(
+                    &first_files,
+                    &file_name_values,
+                    &dest_dir_path,
+                    &resume_manifest,
+                    page_count,
+                    &o.0,
+                    &extractor,
+                    &mut report,
+                );
+                if log_enabled!(Level::Debug) {
+                    info!(
+                        "IMAGE FILE ALREADY EXISTS IN OTHER PDF FILE. OBJECT_NAME: {} DEST_PATH : {} PAGE: {} IMAGE_COUNT : {} FIRST_PATH : {}",
+                        o.0,
+                        dest_dir_path.display(),
+                        page_count,
+                        image_count,
+                        first_files[0].1.display()
+                    );
+                }
+                if let Some(key) = dedup_key {
+                    images_kvs.write().unwrap().insert(key);
+                }
+                report.global_duplicates += 1;
+                report
+                    .manifest_entries
+                    .push(entry.with_status(status, output_paths));
+                continue;
+            }
+        }
+
+        //JBIG2Decodeの画像はJBIG2Globalsと合わせて展開し、フィルタを適用していない1bitの画像として扱う。
+        //(展開できない場合はJBIG2Globalsを連結した単独のJBIG2ファイルとして保存する。)
+        let (data, filter): (Arc<[u8]>, Option<&StreamFilter>) = match filter {
+            Some(StreamFilter::JBIG2Decode(params)) => {
+                match decode_jbig2_image(img, &data, params, &resolver) {
+                    Ok(decoded) => (decoded.into(), None),
+                    Err(e) => {
+                        warn!(
+                            "COULD NOT DECODE JBIG2 IMAGE. IT SAVED AS JBIG2 FILE. OBJECT_NAME: {} DEST_PATH : {} PAGE: {} IMAGE_COUNT : {} ERR: {}",
+                            o.0,
+                            dest_dir_path.display(),
+                            page_count,
+                            image_count,
+                            e
+                        );
+                        report.undecoded_images += 1;
+                        (jbig2_file(&data, params, &resolver).into(), filter)
+                    }
+                }
+            }
+            //CCITTFaxDecodeの画像はTIFFに格納しない場合に展開する。(展開できない場合はTIFFに格納する。)
+            Some(StreamFilter::CCITTFaxDecode(params)) if !extractor.keep_fax_tiff() => {
+                match decode_ccitt_image(img, &data, params) {
+                    Ok(decoded) => (decoded.into(), None),
+                    Err(e) => {
+                        warn!(
+                            "COULD NOT DECODE CCITT FAX IMAGE. IT SAVED AS TIFF FILE. OBJECT_NAME: {} DEST_PATH : {} PAGE: {} IMAGE_COUNT : {} ERR: {}",
+                            o.0,
+                            dest_dir_path.display(),
+                            page_count,
+                            image_count,
+                            e
+                        );
+                        report.undecoded_images += 1;
+                        (data, filter)
+                    }
+                }
+            }
+            _ => (data, filter),
+        };
+
+        //知覚ハッシュを計算する。(近似した画像の判定は保存前に書き込みロックを取得して行う。)
+        //(展開できない画像と展開に失敗した画像は判定しない。展開の失敗は以降の変換でエラーとして扱う。)
+        let mut near_duplicate_hash: Option<u64> = None;
+        if let Some(perceptual_hash) = extractor.perceptual_hash {
+            if let Ok(Some(decoded)) = decode_image(img, &data, filter) {
+                let hash: u64 = perceptual_hash.compute(&decoded);
+                entry.perceptual_hash = Some(format!("{:016x}", hash));
+                near_duplicate_hash = Some(hash);
+            }
+        }
+
+        //保存する画像(データ・拡張子・ファイル名の接尾辞)。
+        let mut outputs: Vec<(Arc<[u8]>, &str, &str)> = Vec::new();
+
+        //マスクを持つ画像はマスクを適用し、アルファチャンネル付きの画像に変換する。
+        if extractor.apply_masks && has_image_mask(img) {
+            match convert_masked_image(img, &data, filter, &resolver, extractor.output_format) {
+                Ok(Some((masked_data, masked_ext))) => outputs.push((masked_data, masked_ext, "")),
+                Ok(None) => {
+                    warn!(
+                        "COULD NOT APPLY MASK. IT SAVED WITHOUT MASK. OBJECT_NAME: {} DEST_PATH : {} PAGE: {} IMAGE_COUNT : {}",
+                        o.0,
+                        dest_dir_path.display(),
+                        page_count,
+                        image_count
+                    );
+                }
+                Err(e) => {
+                    warn!(
+                        "COULD NOT APPLY MASK. IT SAVED WITHOUT MASK. OBJECT_NAME: {} DEST_PATH : {} PAGE: {} IMAGE_COUNT : {} ERR: {}",
+                        o.0,
+                        dest_dir_path.display(),
+                        page_count,
+                        image_count,
+                        e
+                    );
+                    report.errors.push(ExtractError::Decode {
+                        page: page_count,
+                        object_name: o.0.to_string(),
+                        source: e,
+                    });
+                }
+            }
+        }
+
+        //マスクを適用しなかった場合、もしくは元の画像も保存する場合は画像データを保存する形式に変換する。
+        if outputs.is_empty() || extractor.keep_unmasked {
+            let suffix: &str = if outputs.is_empty() { "" } else { "_unmasked" };
+            match convert_image(img, &data, filter, extractor.output_format) {
+                Ok((output_data, ext)) => outputs.push((output_data, ext, suffix)),
+                Err(e) => {
+                    warn!(
+                        "COULD NOT CONVERT IMAGE. OBJECT_NAME: {} DEST_PATH : {} PAGE: {} IMAGE_COUNT : {} ERR: {}",
+                        o.0,
+                        dest_dir_path.display(),
+                        page_count,
+                        image_count,
+                        e
+                    );
+                    report.errors.push(ExtractError::Decode {
+                        page: page_count,
+                        object_name: o.0.to_string(),
+                        source: e,
+                    });
+                }
+            }
+        }
+        if outputs.is_empty() {
+            report
+                .manifest_entries
+                .push(entry.with_status(ImageStatus::Failed, Vec::new()));
+            continue;
+        }
+
         {
             //まだ処理されていない画像であればHashSetの書き込みロックを取得して再確認する。
             let mut write_set = images_kvs.write().unwrap();
-            if write_set.contains(&data) {
+            if dedup_key.is_some_and(|key| write_set.contains(&key)) {
                 if log_enabled!(Level::Debug) {
                     info!(
                         "IMAGE FILE ALREADY EXISTS. OBJECT_NAME: {} DEST_PATH : {} PAGE: {} IMAGE_COUNT : {}",
@@ -302,62 +1086,133 @@ where
                         image_count
                     );
                 }
+                report.duplicate_images += 1;
+                report
+                    .manifest_entries
+                    .push(entry.with_status(ImageStatus::Duplicate, Vec::new()));
                 continue;
             }
 
-            //埋め込みオブジェクト名の数字を6桁に変換する。
-            let converted_embbeded_object_name: std::borrow::Cow<str> =
-                re.replace_all(o.0, |caps: &Captures| {
-                    let num: u32 = (caps[0]).parse().unwrap();
-                    format!("{:06}", num)
-                });
+            //知覚ハッシュが近い画像(異なる品質で再圧縮された同じ画像など)を判定する。
+            //(保存した画像のみをクラスタとして登録するため、書き込みロックを取得したまま判定・登録する。)
+            if let Some(hash) = near_duplicate_hash {
+                entry.cluster =
+                    near_duplicate_index.find(hash, extractor.near_duplicate_max_distance());
+            }
+            if let Some(ref cluster) = entry.cluster {
+                entry.near_duplicate = true;
+                report.near_duplicates += 1;
+                if log_enabled!(Level::Debug) {
+                    info!(
+                        "NEAR DUPLICATE IMAGE FOUND. OBJECT_NAME: {} DEST_PATH : {} PAGE: {} IMAGE_COUNT : {} CLUSTER : {}",
+                        o.0,
+                        dest_dir_path.display(),
+                        page_count,
+                        image_count,
+                        cluster
+                    );
+                }
+                if extractor.near_duplicate_action == NearDuplicateAction::Skip {
+                    report
+                        .manifest_entries
+                        .push(entry.with_status(ImageStatus::NearDuplicate, Vec::new()));
+                    continue;
+                }
+            }
 
-            let save_path_str = format!(
-                "{}/image_{}_{}_{:06}_{:06}_{:06}_{:06}.{}",
-                dest_dir_path.display(),
-                unixtime_val,
-                converted_embbeded_object_name,
-                image_count,
-                page_count,
-                get_thread_id_number(parent_thread_id),
-                get_thread_id_number(&my_thread_id),
-                ext
-            );
+            let mut all_written: bool = true;
+            //再開用マニフェストに記録する画像のキーとファイルのパス。
+            let mut written_files: Vec<(String, PathBuf)> = Vec::new();
+            //重複判定ストアに記録するファイルの接尾辞とパス。
+            let mut stored_files: Vec<(String, PathBuf)> = Vec::new();
+            for (output_data, ext, suffix) in outputs {
+                let save_path: PathBuf = dest_dir_path.join(extractor.file_name_template.render(
+                    &file_name_values,
+                    suffix,
+                    ext,
+                ));
 
-            //画像ファイルを作成する。
-            let mut output = match File::create(&save_path_str) {
-                Ok(file) => file,
-                Err(e) => {
+                //画像ファイルの書き込みを行う。
+                if let Err(e) = sink.write_file(&save_path, page_count, &output_data) {
                     warn!(
-                    "COULD NOT CREATE IMAGE FILE. OBJECT_NAME: {} DEST_PATH : {} PAGE: {} IMAGE_COUNT : {} ERR: {}",
-                    o.0,save_path_str, page_count, image_count, e
-                );
-                    return_value += 1;
+                        "COULD NOT WRITE IMAGE FILE. OBJECT_NAME: {} DEST_PATH : {} PAGE: {} IMAGE_COUNT : {} ERR: {}",
+                        o.0,
+                        save_path.display(),
+                        page_count,
+                        image_count,
+                        e
+                    );
+                    report.errors.push(ExtractError::Write {
+                        path: save_path,
+                        source: e,
+                    });
+                    all_written = false;
                     continue;
                 }
-            };
-
-            //画像ファイルの書き込みを行う。
-            match output.write_all(&data) {
-                Ok(_) => {
-                    if log_enabled!(Level::Debug) {
-                        info!(
+                if log_enabled!(Level::Debug) {
+                    info!(
                         "IMAGE FILE WRITTEN. OBJECT_NAME: {} DEST_PATH : {} PAGE: {} IMAGE_COUNT : {}",
-                        o.0,save_path_str, page_count, image_count
+                        o.0,
+                        save_path.display(),
+                        page_count,
+                        image_count
                     );
-                    }
                 }
-                Err(e) => {
-                    warn!(
-                    "COULD NOT WRITE IMAGE FILE. OBJECT_NAME: {} DEST_PATH : {} PAGE: {} IMAGE_COUNT : {} ERR: {}",
-                    o.0,save_path_str, page_count, image_count, e
+                report.images_written += 1;
+                report.output_files.push(save_path.clone());
+                written_files.push((image_key(page_count, &o.0, suffix), save_path.clone()));
+                stored_files.push((suffix.to_string(), save_path));
+            }
+            let output_paths: Vec<PathBuf> =
+                written_files.iter().map(|(_, path)| path.clone()).collect();
+            if !all_written {
+                report
+                    .manifest_entries
+                    .push(entry.with_status(ImageStatus::Failed, output_paths));
+                continue;
+            }
+            //保存した画像で近似した画像がなければ新しいクラスタとして登録する。
+            if let (Some(hash), None) = (near_duplicate_hash, &entry.cluster) {
+                near_duplicate_index.insert(hash, cluster_id(page_count, image_count));
+            }
+            report
+                .manifest_entries
+                .push(entry.with_status(ImageStatus::Written, output_paths));
+            if let Err(e) = resume_manifest.record(&written_files) {
+                warn!(
+                    "COULD NOT WRITE RESUME MANIFEST. OBJECT_NAME: {} DEST_PATH : {} PAGE: {} IMAGE_COUNT : {} ERR: {}",
+                    o.0,
+                    dest_dir_path.display(),
+                    page_count,
+                    image_count,
+                    e
                 );
-                    return_value += 1;
-                    continue;
+                report.errors.push(ExtractError::Manifest {
+                    path: dest_dir_path.to_path_buf(),
+                    source: e,
+                });
+            }
+            //他のPDFファイルの重複を判定するため、重複判定ストアに記録する。
+            if let (Some(store), Some(digest)) = (dedup_store.as_deref(), global_digest) {
+                if let Err(e) = store.insert(digest, &stored_files) {
+                    warn!(
+                        "COULD NOT WRITE DEDUP STORE. OBJECT_NAME: {} DEST_PATH : {} PAGE: {} IMAGE_COUNT : {} ERR: {}",
+                        o.0,
+                        dest_dir_path.display(),
+                        page_count,
+                        image_count,
+                        e
+                    );
+                    report.errors.push(ExtractError::DedupStore {
+                        path: extractor.dedup_store.clone().unwrap_or_default(),
+                        source: e,
+                    });
                 }
-            };
-            //ファイルの書き込みに成功したらHashSetに画像データのハッシュを追加する。
-            write_set.insert(data.clone());
+            }
+            //ファイルの書き込みに成功したらHashSetに重複判定キーを追加する。
+            if let Some(key) = dedup_key {
+                write_set.insert(key);
+            }
             if log_enabled!(Level::Debug) {
                 info!(
                     "NEW HASH INSERTED. OBJECT_NAME: {} DEST_PATH: {} PAGE: {} IMAGE_COUNT : {} HASHSET_LENGTH: {}",
@@ -370,12 +1225,214 @@ where
             }
         }
     }
-    Ok(return_value)
+    Ok(report)
+}
+
+///他のPDFファイルで最初に保存した画像ファイルへのハードリンクを作成する。
+///(GlobalDedup::Referenceの場合、もしくはハードリンクを作成できなかった場合は最初に保存したファイルを参照する。)
+/// # Arguments
+/// * `first_files` - 最初に保存した画像ファイルの接尾辞とパス。
+/// * `file_name_values` - ファイル名のプレースホルダに埋め込む値。
+/// * `dest_dir_path` - 画像ファイルの保存先ディレクトリ。
+/// * `resume_manifest` - 再開用マニフェスト(作成したハードリンクの記録に使用する)。
+/// * `page_count` - PDFのページ番号。
+/// * `object_name` - 埋め込みオブジェクト名。
+/// * `extractor` - 画像取得処理の設定。(重複した場合の動作とファイル名のテンプレートを使用する。)
+/// * `report` - ページの処理結果。(作成したハードリンクのパスを追加する。)
+/// # Returns
+/// * 画像の処理結果と、マニフェストに記録するファイルのパス。
+#[allow(clippy::too_many_arguments)]
+fn link_first_files(
+    first_files: &[(String, PathBuf)],
+    file_name_values: &FileNameValues,
+    dest_dir_path: &Path,
+    resume_manifest: &ResumeManifest,
+    page_count: u64,
+    object_name: &str,
+    extractor: &Extractor,
+    report: &mut ExtractionReport,
+) -> (ImageStatus, Vec<PathBuf>) {
+    let first_paths: Vec<PathBuf> = first_files.iter().map(|(_, path)| path.clone()).collect();
+    if extractor.global_dedup != GlobalDedup::Link {
+        return (ImageStatus::Referenced, first_paths);
+    }
+    //再開用マニフェストに記録する画像のキーとハードリンクのパス。
+    let mut linked_files: Vec<(String, PathBuf)> = Vec::new();
+    for (suffix, first_path) in first_files {
+        let ext: &str = first_path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("");
+        let link_path: PathBuf = dest_dir_path.join(extractor.file_name_template.render(
+            file_name_values,
+            suffix,
+            ext,
+        ));
+        if let Err(e) = std::fs::hard_link(first_path, &link_path) {
+            warn!(
+                "COULD NOT CREATE HARD LINK. IT REFERENCED. FIRST_PATH : {} LINK_PATH : {} ERR: {}",
+                first_path.display(),
+                link_path.display(),
+                e
+            );
+            //作成済みのハードリンクは削除して、最初に保存したファイルを参照する。
+            for (_, linked_path) in linked_files.iter() {
+                let _ = std::fs::remove_file(linked_path);
+            }
+            return (ImageStatus::Referenced, first_paths);
+        }
+        linked_files.push((image_key(page_count, object_name, suffix), link_path));
+    }
+    if let Err(e) = resume_manifest.record(&linked_files) {
+        warn!(
+            "COULD NOT WRITE RESUME MANIFEST. OBJECT_NAME: {} DEST_PATH : {} PAGE: {} ERR: {}",
+            object_name,
+            dest_dir_path.display(),
+            page_count,
+            e
+        );
+        report.errors.push(ExtractError::Manifest {
+            path: dest_dir_path.to_path_buf(),
+            source: e,
+        });
+    }
+    let link_paths: Vec<PathBuf> = linked_files.into_iter().map(|(_, path)| path).collect();
+    report.output_files.extend(link_paths.iter().cloned());
+    (ImageStatus::Linked, link_paths)
+}
+
+///PDFファイルのページを描画して画像ファイルに保存する。
+/// # Arguments
+/// * `page` - PDFファイルのページ。
+/// * `file` - PDFファイル。
+/// * `dest_dir_path` - 画像ファイルの保存先ディレクトリ。
+/// * `resume_manifest` - 再開用マニフェスト(保存済みのページの確認と保存したページの記録に使用する)。
+/// * `sink` - 画像ファイルの書き込み先。
+/// * `page_count` - PDFのページ番号(保存する画像のファイル名に使用するため)。
+/// * `extractor` - 画像取得処理の設定。
+/// # Returns
+/// * 成功時:ページの処理結果。(書き込みに失敗した場合のエラーはerrorsに格納される。)
+/// * 失敗時:ExtractError::Render(ページの描画失敗・画像の変換失敗)。
+fn render_page_to_file<T, K, Y, L>(
+    page: &PageRc,
+    file: Arc<PdfFile<T, K, Y, L>>,
+    dest_dir_path: Arc<PathBuf>,
+    resume_manifest: Arc<ResumeManifest>,
+    sink: Arc<OutputSink>,
+    page_count: u64,
+    extractor: Arc<Extractor>,
+) -> Result<ExtractionReport, ExtractError>
+where
+    T: Backend,
+    K: Cache<std::result::Result<AnySync, Arc<PdfError>>>,
+    Y: Cache<std::result::Result<Arc<[u8]>, Arc<PdfError>>>,
+    L: Log,
+{
+    let mut report = ExtractionReport {
+        pages: 1,
+        ..Default::default()
+    };
+    //前回の実行で保存済みのページは描画しない。
+    if resume_manifest.contains(&page_key(page_count)) {
+        if log_enabled!(Level::Debug) {
+            info!(
+                "PAGE IMAGE FILE ALREADY SAVED IN PREVIOUS RUN. DEST_PATH : {} PAGE: {}",
+                dest_dir_path.display(),
+                page_count
+            );
+        }
+        report.resumed_files += 1;
+        return Ok(report);
+    }
+    let resolver = file.resolver();
+    //Originalの場合はPNGで保存する。
+    let output_format: OutputFormat = match extractor.output_format {
+        OutputFormat::Original => OutputFormat::Png,
+        output_format => output_format,
+    };
+    let (output_data, unsupported): (Vec<u8>, Vec<&'static str>) =
+        render_page(page, &resolver, extractor.render_dpi())
+            .and_then(|(rendered, unsupported)| {
+                output_format
+                    .encode(&image::DynamicImage::ImageRgb8(rendered))
+                    .map(|output_data| (output_data, unsupported))
+            })
+            .map_err(|e| {
+                error!(
+                    "COULD NOT RENDER PAGE. DEST_PATH : {} PAGE: {} ERR: {}",
+                    dest_dir_path.display(),
+                    page_count,
+                    e
+                );
+                ExtractError::Render {
+                    page: page_count,
+                    source: e,
+                }
+            })?;
+
+    //描画できない内容は欠けたまま保存し、ページ数を記録する。
+    if !unsupported.is_empty() {
+        warn!(
+            "PAGE RENDERED WITHOUT UNSUPPORTED CONTENT. DEST_PATH : {} PAGE: {} SKIPPED: {}",
+            dest_dir_path.display(),
+            page_count,
+            unsupported.join(", ")
+        );
+        report.incomplete_pages += 1;
+    }
+
+    let save_path: PathBuf = dest_dir_path.join(format!(
+        "page_{:06}.{}",
+        page_count,
+        output_format.extension().unwrap_or("png")
+    ));
+    match sink.write_file(&save_path, page_count, &output_data) {
+        Ok(_) => {
+            if log_enabled!(Level::Debug) {
+                info!(
+                    "PAGE IMAGE FILE WRITTEN. DEST_PATH : {} PAGE: {}",
+                    save_path.display(),
+                    page_count
+                );
+            }
+            report.rendered_pages += 1;
+            if let Err(e) = resume_manifest.record(&[(page_key(page_count), save_path.clone())]) {
+                warn!(
+                    "COULD NOT WRITE RESUME MANIFEST. DEST_PATH : {} PAGE: {} ERR: {}",
+                    dest_dir_path.display(),
+                    page_count,
+                    e
+                );
+                report.errors.push(ExtractError::Manifest {
+                    path: dest_dir_path.to_path_buf(),
+                    source: e,
+                });
+            }
+            report.output_files.push(save_path);
+        }
+        Err(e) => {
+            warn!(
+                "COULD NOT WRITE PAGE IMAGE FILE. DEST_PATH : {} PAGE: {} ERR: {}",
+                save_path.display(),
+                page_count,
+                e
+            );
+            report.errors.push(ExtractError::Write {
+                path: save_path,
+                source: e,
+            });
+        }
+    }
+    Ok(report)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::decode_ccitt::{fax_tiff_rows, tiff_tags};
+    use crate::fax_mode::FaxMode;
+    use crate::output_format::OutputFormat;
+    use crate::password_map::PasswordMap;
 
     use std::fs;
     use std::path::Path;
@@ -393,6 +1450,17 @@ mod tests {
         false
     }
 
+    ///テストごとの一時ディレクトリのパスを取得する。(前回のテストで残ったディレクトリは削除する。)
+    ///(同じPDFファイルを使用する他のテストと保存先が重ならないようにする。)
+    fn temp_dir(name: &str) -> PathBuf {
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("pdf_to_image_rs_{}_{}", name, std::process::id()));
+        if path.exists() {
+            fs::remove_dir_all(&path).expect("COULD NOT REMOVE DIRECTORY.");
+        }
+        path
+    }
+
     #[test_log::test]
     ///正常なPDFファイルから画像を取得するテスト
     /// 1.正常なPDFファイルを指定して画像を取得する。
@@ -406,8 +1474,10 @@ mod tests {
         let pdf_extension: &str = "pdf";
         let file_string: String = format!("{}/{}.{}", dir_str, file_name_str, pdf_extension);
         let pdf_file_path = Path::new(file_string.as_str());
-        let result = get_images(pdf_file_path);
-        assert_eq!(result, 0);
+        let result = get_images(pdf_file_path, None, &Extractor::new(), None);
+        let report = result.expect("PDF FILE PROCESS ERROR.");
+        assert!(report.is_success());
+        assert!(report.images_written > 0);
         let extension = "jpg";
         let dest_dir_string: String = format!("{}/{}", dir_str, file_name_str);
         let exists = check_files_with_extension(dest_dir_string.as_str(), extension);
@@ -427,24 +1497,556 @@ mod tests {
         }
     }
 
+    #[test_log::test]
+    ///Form XObject内に配置された画像を取得するテスト
+    /// (test_pdf/form_pdf/form.pdfはForm XObject同士が循環参照しており、その中に画像が1つ配置されている。)
+    fn test_get_images_form_xobject() {
+        let pdf_file_path = Path::new("test_pdf/form_pdf/form.pdf");
+        let result = get_images(pdf_file_path, None, &Extractor::new(), None);
+        let report = result.expect("PDF FILE PROCESS ERROR.");
+        let dest_dir_string: String = "test_pdf/form_pdf/form".to_string();
+        let exists = check_files_with_extension(dest_dir_string.as_str(), "png");
+        let manifest: String = fs::read_to_string(format!("{}/manifest.jsonl", dest_dir_string))
+            .expect("COULD NOT READ MANIFEST.");
+        fs::remove_dir_all(&dest_dir_string).expect("COULD NOT REMOVE DIRECTORY.");
+        assert!(report.is_success());
+        assert_eq!(report.images_written, 1);
+        assert!(exists);
+        //マニフェストに保存した画像が記録されていることを確認する。
+        assert_eq!(manifest.lines().count(), 1);
+        assert_eq!(report.manifest_entries.len(), 1);
+        let entry = &report.manifest_entries[0];
+        assert_eq!(entry.status, ImageStatus::Written);
+        assert_eq!(entry.page, 1);
+        assert_eq!(entry.output_paths, report.output_files);
+    }
+
+    #[test_log::test]
+    ///インライン画像を取得するテスト
+    /// (test_pdf/inline_pdf/inline.pdfはページのコンテンツストリームに2x2のグレースケールのインライン画像を含む。)
+    fn test_get_images_inline_image() {
+        let pdf_file_path = Path::new("test_pdf/inline_pdf/inline.pdf");
+        let result = get_images(pdf_file_path, None, &Extractor::new(), None);
+        let report = result.expect("PDF FILE PROCESS ERROR.");
+        let dest_dir_string: String = "test_pdf/inline_pdf/inline".to_string();
+        let exists = check_files_with_extension(dest_dir_string.as_str(), "png");
+        fs::remove_dir_all(&dest_dir_string).expect("COULD NOT REMOVE DIRECTORY.");
+        assert!(report.is_success());
+        assert_eq!(report.images_written, 1);
+        assert!(exists);
+    }
+
+    #[test]
+    fn test_get_dest_dir_path() {
+        let pdf_path = Path::new("/data/in/a/b/c.pdf");
+        assert_eq!(
+            get_dest_dir_path(pdf_path, Some(Path::new("/data/in")), None),
+            PathBuf::from("/data/in/a/b/c")
+        );
+        assert_eq!(
+            get_dest_dir_path(
+                pdf_path,
+                Some(Path::new("/data/in")),
+                Some(Path::new("/out"))
+            ),
+            PathBuf::from("/out/a/b/c")
+        );
+        assert_eq!(
+            get_dest_dir_path(pdf_path, None, Some(Path::new("/out"))),
+            PathBuf::from("/out/c")
+        );
+        assert_eq!(
+            get_dest_dir_path(pdf_path, Some(Path::new("/other")), Some(Path::new("/out"))),
+            PathBuf::from("/out/c")
+        );
+    }
+
+    #[test]
+    fn test_get_archive_path() {
+        assert_eq!(
+            get_archive_path(Path::new("/out/a/b.c"), ArchiveFormat::Cbz),
+            PathBuf::from("/out/a/b.c.cbz")
+        );
+        assert_eq!(
+            get_archive_path(Path::new("doc"), ArchiveFormat::Zip),
+            PathBuf::from("doc.zip")
+        );
+    }
+
+    #[test_log::test]
+    ///アーカイブにまとめて保存する場合に、保存先ディレクトリを作成せずにマニフェストを含むアーカイブが作成されることを確認する。
+    fn test_get_images_archive() {
+        let output_dir: &str = "test_pdf/archive_pdf_out";
+        let extractor = Extractor::new()
+            .output_dir(Path::new(output_dir))
+            .archive(ArchiveFormat::Cbz);
+        let result = get_images(
+            Path::new("test_pdf/form_pdf/form.pdf"),
+            None,
+            &extractor,
+            None,
+        );
+        let archive_path = Path::new("test_pdf/archive_pdf_out/form.cbz");
+        let archive_data: Option<Vec<u8>> = fs::read(archive_path).ok();
+        let dir_exists = Path::new("test_pdf/archive_pdf_out/form").exists();
+        let part_exists = Path::new("test_pdf/archive_pdf_out/form.cbz.part").exists();
+        //既に存在するアーカイブは既定の動作(ExistingPolicy::Skip)では作成し直さない。
+        let skipped = get_images(
+            Path::new("test_pdf/form_pdf/form.pdf"),
+            None,
+            &extractor,
+            None,
+        );
+        let _ = fs::remove_dir_all(output_dir);
+        let report = result.expect("PDF FILE PROCESS ERROR.");
+        assert!(report.is_success());
+        assert_eq!(report.images_written, 1);
+        assert!(!dir_exists);
+        assert!(!part_exists);
+        assert_eq!(skipped.expect("PDF FILE PROCESS ERROR.").skipped_files, 1);
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(
+            archive_data.expect("ARCHIVE NOT FOUND."),
+        ))
+        .unwrap();
+        let names: Vec<String> = (0..archive.len())
+            .map(|i| archive.by_index(i).unwrap().name().to_string())
+            .collect();
+        assert_eq!(names.len(), 2);
+        assert!(names[0].ends_with(".png"));
+        assert_eq!(names[1], "manifest.jsonl");
+        assert_eq!(
+            report.manifest_entries[0].output_paths,
+            vec![PathBuf::from(&names[0])]
+        );
+    }
+
+    #[test_log::test]
+    ///出力先ディレクトリを指定した場合に、元のPDFファイルの隣ではなく出力先ディレクトリに保存されることを確認する。
+    ///(入力ディレクトリからの相対パスを保持して保存されることも確認する。)
+    fn test_get_images_output_dir() {
+        let pdf_file_path = Path::new("test_pdf/form_pdf/form.pdf");
+        let output_dir: PathBuf = temp_dir("output_dir");
+        let extractor = Extractor::new().output_dir(&output_dir);
+        let input_root = Path::new("test_pdf").canonicalize().unwrap();
+        let result = get_images(pdf_file_path, Some(&input_root), &extractor, None);
+        let report = result.expect("PDF FILE PROCESS ERROR.");
+        let dest_dir_path: PathBuf = output_dir.join("form_pdf/form");
+        let exists = check_files_with_extension(dest_dir_path.to_str().unwrap(), "png");
+        fs::remove_dir_all(&output_dir).expect("COULD NOT REMOVE DIRECTORY.");
+        assert!(report.is_success());
+        assert!(exists);
+        assert!(!report.output_files.is_empty());
+        assert!(report
+            .output_files
+            .iter()
+            .all(|path| path.starts_with(&dest_dir_path)));
+    }
+
+    #[test_log::test]
+    ///同じ画像を含む別のPDFファイルの画像が、最初に保存したファイルへのハードリンクになることを確認する。
+    ///(同じ内容のPDFファイルとして、test_pdf/form_pdf/form.pdfを一時ディレクトリにコピーして使用する。)
+    fn test_get_images_global_dedup() {
+        let input_dir: PathBuf = temp_dir("global_dedup_input");
+        fs::create_dir_all(&input_dir).unwrap();
+        for name in ["a.pdf", "b.pdf"] {
+            fs::copy("test_pdf/form_pdf/form.pdf", input_dir.join(name)).unwrap();
+        }
+        let output_dir: PathBuf = temp_dir("global_dedup_output");
+        let extractor = Extractor::new()
+            .output_dir(&output_dir)
+            .global_dedup(GlobalDedup::Link);
+        let input_root = input_dir.canonicalize().unwrap();
+        let dedup_store = Arc::new(DedupStore::new("blake3"));
+        let first = get_images(
+            &input_root.join("a.pdf"),
+            Some(&input_root),
+            &extractor,
+            Some(Arc::clone(&dedup_store)),
+        )
+        .expect("PDF FILE PROCESS ERROR.");
+        //同じ画像を含む別のPDFファイルは、最初に保存したファイルへのハードリンクを作成する。
+        let second = get_images(
+            &input_root.join("b.pdf"),
+            Some(&input_root),
+            &extractor,
+            Some(dedup_store),
+        )
+        .expect("PDF FILE PROCESS ERROR.");
+        let linked_exists = second.output_files.iter().all(|path| path.is_file());
+        fs::remove_dir_all(&output_dir).expect("COULD NOT REMOVE DIRECTORY.");
+        fs::remove_dir_all(&input_dir).expect("COULD NOT REMOVE DIRECTORY.");
+        assert!(first.is_success());
+        assert!(second.is_success());
+        assert!(first.images_written > 0);
+        assert_eq!(second.images_written, 0);
+        assert_eq!(second.global_duplicates, first.images_written);
+        assert_eq!(second.output_files.len(), first.output_files.len());
+        assert!(linked_exists);
+        assert!(second
+            .manifest_entries
+            .iter()
+            .all(|entry| matches!(entry.status, ImageStatus::Linked | ImageStatus::Duplicate)));
+    }
+
+    #[test_log::test]
+    ///暗号化されたPDFファイルから画像を取得するテスト
+    /// (test_pdf/encrypted_pdf/encrypted.pdfはRC4(128ビット)で暗号化され、ユーザーパスワードは"secret"。)
+    /// 1.--password・--password-mapのパスワードで開いて画像を取得できることを確認する。
+    /// 2.パスワードが誤っている場合・指定されていない場合はExtractError::Password(終了コード27)になることを確認する。
+    fn test_get_images_encrypted() {
+        let pdf_file_path = Path::new("test_pdf/encrypted_pdf/encrypted.pdf");
+        let output_dir: PathBuf = temp_dir("encrypted");
+        let extractor = || Extractor::new().output_dir(&output_dir);
+        //開けないPDFファイルの保存先ディレクトリは作成されないため、先に確認する。
+        let wrong_password = get_images(pdf_file_path, None, &extractor().password("wrong"), None);
+        let missing_password = get_images(pdf_file_path, None, &extractor(), None);
+        let with_password = get_images(pdf_file_path, None, &extractor().password("secret"), None)
+            .expect("PDF FILE PROCESS ERROR.");
+        let password_map = PasswordMap::from_csv(&b"encrypted.pdf,secret\n"[..]).unwrap();
+        let with_password_map = get_images(
+            pdf_file_path,
+            None,
+            &extractor()
+                .password("wrong")
+                .password_map(password_map)
+                .on_existing(ExistingPolicy::Overwrite),
+            None,
+        )
+        .expect("PDF FILE PROCESS ERROR.");
+        fs::remove_dir_all(&output_dir).expect("COULD NOT REMOVE DIRECTORY.");
+        assert!(with_password.is_success());
+        assert_eq!(with_password.images_written, 1);
+        assert!(with_password_map.is_success());
+        assert_eq!(with_password_map.images_written, 1);
+        for result in [wrong_password, missing_password] {
+            let e = result.unwrap_err();
+            assert!(matches!(e, ExtractError::Password { .. }));
+            assert_eq!(e.exit_code(), 27);
+        }
+    }
+
+    #[test_log::test]
+    ///保存先ディレクトリが既に存在する場合の動作を確認するテスト
+    /// 1.画像を取得した後、一部の画像ファイルが保存されなかった状態(再開用マニフェストの記録なし)にする。
+    /// 2.resumeを指定して、保存されなかった画像のみが取得されることを確認する。
+    /// 3.failを指定してエラーになること、overwriteを指定して全ての画像が取得し直されることを確認する。
+    fn test_get_images_on_existing() {
+        let pdf_file_path = Path::new("test_pdf/form_pdf/form.pdf");
+        let output_dir: PathBuf = temp_dir("on_existing");
+        let dest_dir_path: PathBuf = output_dir.join("form");
+        let extractor = || Extractor::new().output_dir(&output_dir);
+        let first =
+            get_images(pdf_file_path, None, &extractor(), None).expect("PDF FILE PROCESS ERROR.");
+        let skipped =
+            get_images(pdf_file_path, None, &extractor(), None).expect("PDF FILE PROCESS ERROR.");
+        let resumed_all = get_images(
+            pdf_file_path,
+            None,
+            &extractor().on_existing(ExistingPolicy::Resume),
+            None,
+        )
+        .expect("PDF FILE PROCESS ERROR.");
+        //中断された状態を再現するため、再開用マニフェストを空にする。
+        fs::write(
+            dest_dir_path.join(crate::resume_manifest::RESUME_MANIFEST_FILE_NAME),
+            b"",
+        )
+        .unwrap();
+        let resumed = get_images(
+            pdf_file_path,
+            None,
+            &extractor().on_existing(ExistingPolicy::Resume),
+            None,
+        )
+        .expect("PDF FILE PROCESS ERROR.");
+        let failed = get_images(
+            pdf_file_path,
+            None,
+            &extractor().on_existing(ExistingPolicy::Fail),
+            None,
+        );
+        let overwritten = get_images(
+            pdf_file_path,
+            None,
+            &extractor().on_existing(ExistingPolicy::Overwrite),
+            None,
+        )
+        .expect("PDF FILE PROCESS ERROR.");
+        let file_count: usize = fs::read_dir(&dest_dir_path)
+            .unwrap()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .unwrap()
+                    .path()
+                    .extension()
+                    .is_some_and(|ext| ext == "png")
+            })
+            .count();
+        fs::remove_dir_all(&output_dir).expect("COULD NOT REMOVE DIRECTORY.");
+        assert_eq!(first.images_written, 1);
+        assert_eq!(skipped.skipped_files, 1);
+        assert_eq!(resumed_all.images_written, 0);
+        assert_eq!(resumed_all.resumed_files, 1);
+        assert_eq!(resumed.images_written, 1);
+        assert_eq!(resumed.resumed_files, 0);
+        assert!(matches!(
+            failed,
+            Err(ExtractError::DestinationExists { .. })
+        ));
+        assert_eq!(overwritten.images_written, 1);
+        assert_eq!(file_count, 1);
+        //ファイル名は実行ごとに変わらない。
+        assert_eq!(first.output_files, overwritten.output_files);
+    }
+
+    #[test_log::test]
+    ///ページを描画して保存するテスト
+    /// (test_pdf/vector_pdf/vector.pdfは200x100ポイントのページの左半分を赤、右半分をForm XObjectで青に塗りつぶしている。)
+    fn test_get_images_render_pages() {
+        let pdf_file_path = Path::new("test_pdf/vector_pdf/vector.pdf");
+        let extractor = Extractor::new().render_pages(true).dpi(72.0);
+        let result = get_images(pdf_file_path, None, &extractor, None);
+        let report = result.expect("PDF FILE PROCESS ERROR.");
+        let rendered: Vec<image::RgbImage> = report
+            .output_files
+            .iter()
+            .map(|path| image::open(path).expect("COULD NOT OPEN IMAGE.").to_rgb8())
+            .collect();
+        fs::remove_dir_all("test_pdf/vector_pdf/vector").expect("COULD NOT REMOVE DIRECTORY.");
+        assert!(report.is_success());
+        assert_eq!(report.rendered_pages, 1);
+        assert_eq!(report.incomplete_pages, 0);
+        assert_eq!(report.images_written, 0);
+        assert_eq!(rendered.len(), 1);
+        assert_eq!(rendered[0].dimensions(), (200, 100));
+        assert_eq!(rendered[0].get_pixel(50, 50).0, [255, 0, 0]);
+        assert_eq!(rendered[0].get_pixel(150, 50).0, [0, 0, 255]);
+    }
+
+    #[test_log::test]
+    ///埋め込まれたTrueTypeフォントのテキストと画像が描画され、シェーディングを含むページが記録されることを確認する。
+    ///(render.ttfは"A"が1emの正方形、"B"が下半分の長方形のグリフを持つ。)
+    fn test_get_images_render_text_and_image() {
+        let pdf_file_path = Path::new("test_pdf/render_pdf/render.pdf");
+        let extractor = Extractor::new().render_pages(true).dpi(72.0);
+        let result = get_images(pdf_file_path, None, &extractor, None);
+        let report = result.expect("PDF FILE PROCESS ERROR.");
+        let rendered: Vec<image::RgbImage> = report
+            .output_files
+            .iter()
+            .map(|path| image::open(path).expect("COULD NOT OPEN IMAGE.").to_rgb8())
+            .collect();
+        fs::remove_dir_all("test_pdf/render_pdf/render").expect("COULD NOT REMOVE DIRECTORY.");
+        assert!(report.is_success());
+        assert_eq!(report.rendered_pages, 1);
+        assert_eq!(report.incomplete_pages, 1);
+        assert_eq!(rendered.len(), 1);
+        let page: &image::RgbImage = &rendered[0];
+        assert_eq!(page.dimensions(), (200, 100));
+        //"ABA"を20ポイントで(10, 60)から描画する。
+        assert_eq!(page.get_pixel(20, 30).0, [0, 0, 255]);
+        assert_eq!(page.get_pixel(40, 35).0, [0, 0, 255]);
+        assert_eq!(page.get_pixel(60, 30).0, [0, 0, 255]);
+        assert_eq!(page.get_pixel(40, 25).0, [255, 255, 255]);
+        assert_eq!(page.get_pixel(80, 30).0, [255, 255, 255]);
+        //2x2の画像(赤・緑/青・黄)を(120, 20)から40ポイント四方に描画する。
+        assert_eq!(page.get_pixel(130, 50).0, [255, 0, 0]);
+        assert_eq!(page.get_pixel(150, 50).0, [0, 255, 0]);
+        assert_eq!(page.get_pixel(130, 70).0, [0, 0, 255]);
+        assert_eq!(page.get_pixel(150, 70).0, [255, 255, 0]);
+    }
+
+    #[test_log::test]
+    ///SMaskを持つ画像にマスクを適用し、アルファチャンネル付きのPNGと元の画像が保存されることを確認する。
+    fn test_get_images_apply_masks() {
+        let pdf_file_path = Path::new("test_pdf/smask_pdf/smask.pdf");
+        let extractor = Extractor::new().apply_masks(true).keep_unmasked(true);
+        let result = get_images(pdf_file_path, None, &extractor, None);
+        let report = result.expect("PDF FILE PROCESS ERROR.");
+        let mut masked: Vec<image::RgbaImage> = Vec::new();
+        let mut unmasked_count: usize = 0;
+        for path in report.output_files.iter() {
+            let file_name: &str = path.file_name().and_then(|s| s.to_str()).unwrap();
+            if file_name.ends_with("_unmasked.png") {
+                unmasked_count += 1;
+            } else {
+                masked.push(image::open(path).expect("COULD NOT OPEN IMAGE.").to_rgba8());
+            }
+        }
+        fs::remove_dir_all("test_pdf/smask_pdf/smask").expect("COULD NOT REMOVE DIRECTORY.");
+        assert!(report.is_success());
+        assert_eq!(report.images_written, 2);
+        assert_eq!(unmasked_count, 1);
+        assert_eq!(masked.len(), 1);
+        let alpha: Vec<u8> = masked[0].pixels().map(|p| p[3]).collect();
+        assert_eq!(alpha, vec![0, 255, 128, 255]);
+        assert_eq!(masked[0].get_pixel(1, 0).0, [0, 255, 0, 255]);
+    }
+
+    #[test_log::test]
+    ///CMYKのJPEG(Adobeの反転あり・なし)、Indexed、Separationの画像がsRGBに変換されることを確認する。
+    fn test_get_images_color_spaces() {
+        let pdf_file_path = Path::new("test_pdf/color_pdf/color.pdf");
+        let extractor = Extractor::new().output_format(OutputFormat::Png);
+        let result = get_images(pdf_file_path, None, &extractor, None);
+        let report = result.expect("PDF FILE PROCESS ERROR.");
+        let mut output_files: Vec<PathBuf> = report.output_files.clone();
+        output_files.sort();
+        let pixels: Vec<[u8; 3]> = output_files
+            .iter()
+            .map(|path| image::open(path).expect("COULD NOT OPEN IMAGE.").to_rgb8())
+            .map(|image| image.get_pixel(0, 0).0)
+            .collect();
+        fs::remove_dir_all("test_pdf/color_pdf/color").expect("COULD NOT REMOVE DIRECTORY.");
+        assert!(report.is_success());
+        //Im1:シアン、Im2:マゼンタ、Im3:青(パレットの2色目)、Im4:赤
+        let expected: [[u8; 3]; 4] = [[0, 255, 255], [255, 0, 255], [0, 0, 255], [255, 0, 0]];
+        assert_eq!(pixels.len(), expected.len());
+        for (pixel, expected) in pixels.iter().zip(expected.iter()) {
+            for (c, e) in pixel.iter().zip(expected.iter()) {
+                assert!(c.abs_diff(*e) <= 3, "{:?} != {:?}", pixel, expected);
+            }
+        }
+    }
+
+    #[test_log::test]
+    ///JBIG2Decodeの画像がJBIG2Globalsと合わせて展開され、展開できない画像はJBIG2ファイルとして保存されることを確認する。
+    fn test_get_images_jbig2() {
+        let pdf_file_path = Path::new("test_pdf/jbig2_pdf/jbig2.pdf");
+        let extractor = Extractor::new().output_format(OutputFormat::Png);
+        let result = get_images(pdf_file_path, None, &extractor, None);
+        let report = result.expect("PDF FILE PROCESS ERROR.");
+        let mut output_files: Vec<PathBuf> = report.output_files.clone();
+        output_files.sort();
+        let images: Vec<Option<image::GrayImage>> = output_files
+            .iter()
+            .map(|path| match path.extension().and_then(|ext| ext.to_str()) {
+                Some("png") => Some(image::open(path).expect("COULD NOT OPEN IMAGE.").to_luma8()),
+                _ => None,
+            })
+            .collect();
+        let jbig2_file: Vec<u8> = fs::read(&output_files[3]).expect("COULD NOT READ FILE.");
+        fs::remove_dir_all("test_pdf/jbig2_pdf/jbig2").expect("COULD NOT REMOVE DIRECTORY.");
+        assert!(report.is_success());
+        assert_eq!(images.len(), 4);
+        let to_rows = |image: &image::GrayImage| -> Vec<String> {
+            image
+                .rows()
+                .map(|row| row.map(|p| if p.0[0] < 128 { '#' } else { '.' }).collect())
+                .collect()
+        };
+        //Im1:汎用領域(算術符号)、Im3:汎用領域(MMR)のImageMask
+        let generic: [&str; 8] = [
+            "................",
+            "..####....####..",
+            "..####....####..",
+            "..####....####..",
+            "................",
+            ".##############.",
+            ".##############.",
+            "................",
+        ];
+        assert_eq!(to_rows(images[0].as_ref().unwrap()), generic);
+        assert_eq!(to_rows(images[2].as_ref().unwrap()), generic);
+        //Im2:JBIG2Globalsのシンボル辞書を参照するテキスト領域
+        let text: Vec<String> = to_rows(images[1].as_ref().unwrap());
+        assert_eq!((text.len(), text[0].len()), (14, 24));
+        assert_eq!(text[1], "..###..###.......###....");
+        assert_eq!(text[9], "..###.###...............");
+        //Im4:未対応の汎用領域はファイルヘッダーを付けたJBIG2ファイルとして保存される。
+        assert!(images[3].is_none());
+        //展開できずにJBIG2ファイルとして保存した画像は結果に記録される。
+        assert_eq!(report.undecoded_images, 1);
+        assert!(jbig2_file.starts_with(&[0x97, 0x4A, 0x42, 0x32, 0x0D, 0x0A, 0x1A, 0x0A]));
+    }
+
+    #[test_log::test]
+    ///CCITTFaxDecodeの画像が既定ではTIFFに格納され、FaxMode::Decodeでは展開してPNGで保存されることを確認する。
+    fn test_get_images_ccitt() {
+        let pdf_file_path = Path::new("test_pdf/ccitt_pdf/ccitt.pdf");
+        let expected: [&str; 6] = [
+            "....................",
+            "..####......####....",
+            "..#####....######...",
+            "#...................",
+            "...................#",
+            "####################",
+        ];
+        let to_rows = |image: &image::GrayImage| -> Vec<String> {
+            image
+                .rows()
+                .map(|row| row.map(|p| if p.0[0] < 128 { '#' } else { '.' }).collect())
+                .collect()
+        };
+        for fax_mode in [FaxMode::Tiff, FaxMode::Decode] {
+            let extractor = Extractor::new().fax_mode(fax_mode);
+            let result = get_images(pdf_file_path, None, &extractor, None);
+            let report = result.expect("PDF FILE PROCESS ERROR.");
+            let mut output_files: Vec<PathBuf> = report.output_files.clone();
+            output_files.sort();
+            let files: Vec<Vec<u8>> = output_files
+                .iter()
+                .map(|path| fs::read(path).expect("COULD NOT READ FILE."))
+                .collect();
+            fs::remove_dir_all("test_pdf/ccitt_pdf/ccitt").expect("COULD NOT REMOVE DIRECTORY.");
+            assert!(report.is_success());
+            assert_eq!(files.len(), 4);
+            for (index, (path, file)) in output_files.iter().zip(files.iter()).enumerate() {
+                let ext = path.extension().and_then(|ext| ext.to_str());
+                match fax_mode {
+                    FaxMode::Tiff => {
+                        assert_eq!(ext, Some("tiff"));
+                        assert!(file.starts_with(b"II*\0"));
+                        //格納した符号化データをTIFFのタグから復元したパラメータで展開する。
+                        assert_eq!(fax_tiff_rows(file), expected, "{}", path.display());
+                    }
+                    FaxMode::Decode => assert_eq!(ext, Some("png")),
+                }
+                //Im2(T.4・EOLあり)のTIFFはimageクレートで読み込めないため、タグを確認する。
+                if fax_mode == FaxMode::Tiff && index == 1 {
+                    let tags = tiff_tags(file);
+                    assert_eq!(tags[&256], 20);
+                    assert_eq!(tags[&257], 6);
+                    //Compressionは3(T.4)、T4OptionsはEOLのバイト境界への整列(4)。
+                    assert_eq!(tags[&259], 3);
+                    assert_eq!(tags[&292], 4);
+                    assert_eq!(tags[&279] as usize, file.len() - tags[&273] as usize);
+                    continue;
+                }
+                let image = image::load_from_memory(file).expect("COULD NOT OPEN IMAGE.");
+                assert_eq!(to_rows(&image.to_luma8()), expected, "{}", path.display());
+            }
+        }
+    }
+
+    #[test_log::test]
+    ///壊れたPDFファイルを指定した場合はPDFファイルオープン失敗となり、出力先ディレクトリも作成されないことを確認する。
+    fn test_get_images_broken_pdf() {
+        let pdf_file_path = Path::new("test_pdf/broken_pdf/broken.pdf");
+        let result = get_images(pdf_file_path, None, &Extractor::new(), None);
+        assert!(matches!(result, Err(ExtractError::Open { .. })));
+        assert!(!Path::new("test_pdf/broken_pdf/broken").exists());
+    }
+
     #[test_log::test]
     fn test_get_images_invalid_pdf() {
         let pdf_file_path = Path::new("path/to/invalid.pdf");
-        let result = get_images(pdf_file_path);
-        assert_ne!(result, 0);
+        let result = get_images(pdf_file_path, None, &Extractor::new(), None);
+        assert!(result.is_err());
     }
 
     #[test_log::test]
     fn test_get_images_existing_directory() {
         let pdf_file_path = Path::new("test_pdf/correct_pdf");
-        let result = get_images(pdf_file_path);
-        assert_eq!(result, 20);
+        let result = get_images(pdf_file_path, None, &Extractor::new(), None);
+        assert!(matches!(result, Err(ExtractError::PathResolution { .. })));
     }
 
     #[test_log::test]
     fn test_get_images_non_existing_directory() {
         let pdf_file_path = Path::new("path/to/non_existing_directory");
-        let result = get_images(pdf_file_path);
-        assert_ne!(result, 0);
+        let result = get_images(pdf_file_path, None, &Extractor::new(), None);
+        assert!(result.is_err());
     }
 }
@@ -0,0 +1,58 @@
+use std::fmt;
+use std::str::FromStr;
+
+///他のPDFファイルで保存済みの画像と重複した場合の動作。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GlobalDedup {
+    ///PDFファイル間の重複は判定せず、PDFファイルごとに保存する。
+    #[default]
+    Off,
+    ///画像ファイルを保存せず、マニフェストに最初に保存したファイルのパスを記録する。
+    Reference,
+    ///最初に保存したファイルへのハードリンクを作成する。(作成できない場合はReferenceと同じ動作になる。)
+    Link,
+}
+
+impl FromStr for GlobalDedup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" => Ok(GlobalDedup::Off),
+            "reference" => Ok(GlobalDedup::Reference),
+            "link" => Ok(GlobalDedup::Link),
+            _ => Err(format!(
+                "UNSUPPORTED GLOBAL DEDUP MODE: {} (off|reference|link)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for GlobalDedup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name: &str = match self {
+            GlobalDedup::Off => "off",
+            GlobalDedup::Reference => "reference",
+            GlobalDedup::Link => "link",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_global_dedup_from_str() {
+        assert_eq!("off".parse::<GlobalDedup>(), Ok(GlobalDedup::Off));
+        assert_eq!(
+            "Reference".parse::<GlobalDedup>(),
+            Ok(GlobalDedup::Reference)
+        );
+        assert_eq!("LINK".parse::<GlobalDedup>(), Ok(GlobalDedup::Link));
+        assert!("copy".parse::<GlobalDedup>().is_err());
+        assert_eq!(GlobalDedup::Link.to_string(), "link");
+    }
+}
@@ -0,0 +1,158 @@
+///保存する画像の大きさ・バイト数・縦横比の条件。(いずれかの条件を満たさない画像は保存しない。)
+///(スペーサーの1x1の画像や小さな記号などを除外するために使用する。未指定の条件は判定しない。)
+///
+/// # Examples
+/// ```
+/// use pdf_to_image_rs::ImageFilter;
+///
+/// let filter = ImageFilter::new().min_width(16).min_height(16).max_aspect_ratio(4.0);
+/// assert!(filter.matches(640, 480, 10_000));
+/// assert!(!filter.matches(1, 1, 10));
+/// assert!(!filter.matches(800, 100, 10_000));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ImageFilter {
+    min_width: Option<u32>,
+    max_width: Option<u32>,
+    min_height: Option<u32>,
+    max_height: Option<u32>,
+    min_area: Option<u64>,
+    max_area: Option<u64>,
+    min_bytes: Option<u64>,
+    max_bytes: Option<u64>,
+    min_aspect_ratio: Option<f32>,
+    max_aspect_ratio: Option<f32>,
+}
+
+impl ImageFilter {
+    ///条件を指定しないImageFilterを作成する。(全ての画像が条件を満たす。)
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    ///幅の最小値(ピクセル)を指定する。
+    pub fn min_width(mut self, width: u32) -> Self {
+        self.min_width = Some(width);
+        self
+    }
+
+    ///幅の最大値(ピクセル)を指定する。
+    pub fn max_width(mut self, width: u32) -> Self {
+        self.max_width = Some(width);
+        self
+    }
+
+    ///高さの最小値(ピクセル)を指定する。
+    pub fn min_height(mut self, height: u32) -> Self {
+        self.min_height = Some(height);
+        self
+    }
+
+    ///高さの最大値(ピクセル)を指定する。
+    pub fn max_height(mut self, height: u32) -> Self {
+        self.max_height = Some(height);
+        self
+    }
+
+    ///画素数(幅×高さ)の最小値を指定する。
+    pub fn min_area(mut self, area: u64) -> Self {
+        self.min_area = Some(area);
+        self
+    }
+
+    ///画素数(幅×高さ)の最大値を指定する。
+    pub fn max_area(mut self, area: u64) -> Self {
+        self.max_area = Some(area);
+        self
+    }
+
+    ///画像データのバイト数の最小値を指定する。
+    ///(PDFに格納されたストリームのバイト数。ASCIIHex・ASCII85・LZW・RunLengthのフィルタのみ展開した後で数え、
+    ///Flate・DCT・JPX・JBIG2・CCITTFaxのフィルタは展開しない。)
+    pub fn min_bytes(mut self, bytes: u64) -> Self {
+        self.min_bytes = Some(bytes);
+        self
+    }
+
+    ///画像データのバイト数の最大値を指定する。(バイト数の数え方は`min_bytes`と同じ。)
+    pub fn max_bytes(mut self, bytes: u64) -> Self {
+        self.max_bytes = Some(bytes);
+        self
+    }
+
+    ///縦横比(幅÷高さ)の最小値を指定する。
+    pub fn min_aspect_ratio(mut self, ratio: f32) -> Self {
+        self.min_aspect_ratio = Some(ratio);
+        self
+    }
+
+    ///縦横比(幅÷高さ)の最大値を指定する。
+    pub fn max_aspect_ratio(mut self, ratio: f32) -> Self {
+        self.max_aspect_ratio = Some(ratio);
+        self
+    }
+
+    ///条件が1つも指定されていなければtrueを返す。
+    pub fn is_empty(&self) -> bool {
+        *self == ImageFilter::default()
+    }
+
+    ///画像が全ての条件を満たしていればtrueを返す。
+    /// # Arguments
+    /// * `width` - 画像の幅(ピクセル)。
+    /// * `height` - 画像の高さ(ピクセル)。
+    /// * `byte_size` - 画像データのバイト数。(ASCIIHex・ASCII85・LZW・RunLengthのフィルタのみ展開したストリームのバイト数。)
+    pub fn matches(&self, width: u32, height: u32, byte_size: u64) -> bool {
+        let area: u64 = width as u64 * height as u64;
+        //高さが0の画像は縦横比を無限大として扱う。
+        let aspect_ratio: f32 = if height == 0 {
+            f32::INFINITY
+        } else {
+            width as f32 / height as f32
+        };
+        self.min_width.is_none_or(|min| width >= min)
+            && self.max_width.is_none_or(|max| width <= max)
+            && self.min_height.is_none_or(|min| height >= min)
+            && self.max_height.is_none_or(|max| height <= max)
+            && self.min_area.is_none_or(|min| area >= min)
+            && self.max_area.is_none_or(|max| area <= max)
+            && self.min_bytes.is_none_or(|min| byte_size >= min)
+            && self.max_bytes.is_none_or(|max| byte_size <= max)
+            && self.min_aspect_ratio.is_none_or(|min| aspect_ratio >= min)
+            && self.max_aspect_ratio.is_none_or(|max| aspect_ratio <= max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_filter_matches() {
+        assert!(ImageFilter::new().is_empty());
+        assert!(ImageFilter::new().matches(1, 1, 1));
+
+        let filter = ImageFilter::new()
+            .min_width(10)
+            .max_height(100)
+            .min_area(200)
+            .max_bytes(1000);
+        assert!(!filter.is_empty());
+        assert!(filter.matches(20, 100, 1000));
+        assert!(!filter.matches(9, 100, 1000));
+        assert!(!filter.matches(20, 101, 1000));
+        assert!(!filter.matches(10, 10, 1000));
+        assert!(!filter.matches(20, 100, 1001));
+
+        let filter = ImageFilter::new()
+            .min_aspect_ratio(0.5)
+            .max_aspect_ratio(2.0)
+            .min_bytes(1);
+        assert!(filter.matches(100, 200, 1));
+        assert!(filter.matches(200, 100, 1));
+        assert!(!filter.matches(100, 201, 1));
+        assert!(!filter.matches(201, 100, 1));
+        assert!(!filter.matches(100, 0, 1));
+        assert!(!filter.matches(100, 100, 0));
+    }
+}
@@ -0,0 +1,284 @@
+///JBIG2の算術符号(MQ符号)の確率推定テーブル。(Qe値・MPS時の次の状態・LPS時の次の状態・MPSを反転するか。)
+const QE_TABLE: [(u32, u8, u8, bool); 47] = [
+    (0x5601, 1, 1, true),
+    (0x3401, 2, 6, false),
+    (0x1801, 3, 9, false),
+    (0x0AC1, 4, 12, false),
+    (0x0521, 5, 29, false),
+    (0x0221, 38, 33, false),
+    (0x5601, 7, 6, true),
+    (0x5401, 8, 14, false),
+    (0x4801, 9, 14, false),
+    (0x3801, 10, 14, false),
+    (0x3001, 11, 17, false),
+    (0x2401, 12, 18, false),
+    (0x1C01, 13, 20, false),
+    (0x1601, 29, 21, false),
+    (0x5601, 15, 14, true),
+    (0x5401, 16, 14, false),
+    (0x5101, 17, 15, false),
+    (0x4801, 18, 16, false),
+    (0x3801, 19, 17, false),
+    (0x3401, 20, 18, false),
+    (0x3001, 21, 19, false),
+    (0x2801, 22, 19, false),
+    (0x2401, 23, 20, false),
+    (0x2201, 24, 21, false),
+    (0x1C01, 25, 22, false),
+    (0x1801, 26, 23, false),
+    (0x1601, 27, 24, false),
+    (0x1401, 28, 25, false),
+    (0x1201, 29, 26, false),
+    (0x1101, 30, 27, false),
+    (0x0AC1, 31, 28, false),
+    (0x09C1, 32, 29, false),
+    (0x08A1, 33, 30, false),
+    (0x0521, 34, 31, false),
+    (0x0441, 35, 32, false),
+    (0x02A1, 36, 33, false),
+    (0x0221, 37, 34, false),
+    (0x0141, 38, 35, false),
+    (0x0111, 39, 36, false),
+    (0x0085, 40, 37, false),
+    (0x0049, 41, 38, false),
+    (0x0025, 42, 39, false),
+    (0x0015, 43, 40, false),
+    (0x0009, 44, 41, false),
+    (0x0005, 45, 42, false),
+    (0x0001, 45, 43, false),
+    (0x5601, 46, 46, false),
+];
+
+///算術符号のコンテキストの状態。(確率推定テーブルの番号とMPSの値。)
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Context {
+    index: u8,
+    mps: u8,
+}
+
+///JBIG2の算術符号(MQ符号)の復号器。(T.88 Annex E.3。)
+pub(crate) struct ArithmeticDecoder<'a> {
+    data: &'a [u8],
+    position: usize,
+    c: u32,
+    a: u32,
+    ct: u32,
+}
+
+impl<'a> ArithmeticDecoder<'a> {
+    ///復号器を作成する。(INITDEC。)
+    /// # Arguments
+    /// * `data` - 算術符号化されたデータ。(データの終端以降は0xFFとして扱う。)
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        let mut decoder = ArithmeticDecoder {
+            data,
+            position: 0,
+            c: (data.first().copied().unwrap_or(0xFF) as u32) << 16,
+            a: 0,
+            ct: 0,
+        };
+        decoder.byte_in();
+        decoder.c <<= 7;
+        decoder.ct -= 7;
+        decoder.a = 0x8000;
+        decoder
+    }
+
+    ///指定された位置のバイトを取得する。(データの終端以降は0xFF。)
+    fn byte_at(&self, position: usize) -> u32 {
+        self.data.get(position).copied().unwrap_or(0xFF) as u32
+    }
+
+    ///次のバイトを読み込む。(BYTEIN。0xFFの後に0x8Fより大きいバイトが続く場合はマーカーとして扱い、1を補う。)
+    fn byte_in(&mut self) {
+        if self.byte_at(self.position) == 0xFF {
+            if self.byte_at(self.position + 1) > 0x8F {
+                self.c += 0xFF00;
+                self.ct = 8;
+            } else {
+                self.position += 1;
+                self.c += self.byte_at(self.position) << 9;
+                self.ct = 7;
+            }
+        } else {
+            self.position += 1;
+            self.c += self.byte_at(self.position) << 8;
+            self.ct = 8;
+        }
+    }
+
+    ///1ビットを復号する。(DECODE。)
+    /// # Arguments
+    /// * `context` - 使用するコンテキスト。(復号後に状態を更新する。)
+    pub(crate) fn decode(&mut self, context: &mut Context) -> u8 {
+        let (qe, nmps, nlps, switch) = QE_TABLE[context.index as usize];
+        self.a -= qe;
+        let d: u8;
+        if (self.c >> 16) < qe {
+            //LPS_EXCHANGE
+            if self.a < qe {
+                d = context.mps;
+                context.index = nmps;
+            } else {
+                d = 1 - context.mps;
+                if switch {
+                    context.mps = d;
+                }
+                context.index = nlps;
+            }
+            self.a = qe;
+        } else {
+            self.c -= qe << 16;
+            if self.a & 0x8000 != 0 {
+                return context.mps;
+            }
+            //MPS_EXCHANGE
+            if self.a < qe {
+                d = 1 - context.mps;
+                if switch {
+                    context.mps = d;
+                }
+                context.index = nlps;
+            } else {
+                d = context.mps;
+                context.index = nmps;
+            }
+        }
+        //RENORMD
+        loop {
+            if self.ct == 0 {
+                self.byte_in();
+            }
+            self.a <<= 1;
+            self.c <<= 1;
+            self.ct -= 1;
+            if self.a & 0x8000 != 0 {
+                break;
+            }
+        }
+        d
+    }
+}
+
+///算術符号化された整数の種類。(T.88 Annex A.2。各種類は個別のコンテキストを持つ。)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum IntegerType {
+    Iadh,
+    Iadw,
+    Iaex,
+    Iaai,
+    Iadt,
+    Iafs,
+    Iads,
+    Iait,
+    Iari,
+    Iardw,
+    Iardh,
+    Iardx,
+    Iardy,
+}
+
+///整数の種類の数。
+const INTEGER_TYPES: usize = 13;
+
+///算術符号化された整数の復号に使用するコンテキスト。(シンボル辞書とテキスト領域で共有する。)
+pub(crate) struct IntegerContexts {
+    contexts: Vec<[Context; 512]>,
+    ///シンボルIDのコンテキスト。(IAID。シンボルIDのビット数に応じて作成する。)
+    id_contexts: Vec<Context>,
+    id_code_length: u32,
+}
+
+impl IntegerContexts {
+    ///全てのコンテキストを初期状態で作成する。
+    /// # Arguments
+    /// * `id_code_length` - シンボルIDのビット数。(SBSYMCODELEN。)
+    pub(crate) fn new(id_code_length: u32) -> Self {
+        IntegerContexts {
+            contexts: vec![[Context::default(); 512]; INTEGER_TYPES],
+            id_contexts: vec![Context::default(); 1 << id_code_length.min(24)],
+            id_code_length,
+        }
+    }
+
+    ///整数を復号する。(IAxの復号手順。)
+    /// # Returns
+    /// * 復号した値。(OOBの場合はNone。)
+    pub(crate) fn decode(
+        &mut self,
+        decoder: &mut ArithmeticDecoder,
+        integer_type: IntegerType,
+    ) -> Option<i32> {
+        let contexts: &mut [Context; 512] = &mut self.contexts[integer_type as usize];
+        let mut prev: usize = 1;
+        let mut read_bits = |decoder: &mut ArithmeticDecoder, length: u32| -> u32 {
+            let mut value: u32 = 0;
+            for _ in 0..length {
+                let bit: u8 = decoder.decode(&mut contexts[prev]);
+                prev = if prev < 256 {
+                    (prev << 1) | bit as usize
+                } else {
+                    (((prev << 1) | bit as usize) & 511) | 256
+                };
+                value = (value << 1) | bit as u32;
+            }
+            value
+        };
+        let sign: u32 = read_bits(decoder, 1);
+        let value: i64 = if read_bits(decoder, 1) == 0 {
+            read_bits(decoder, 2) as i64
+        } else if read_bits(decoder, 1) == 0 {
+            read_bits(decoder, 4) as i64 + 4
+        } else if read_bits(decoder, 1) == 0 {
+            read_bits(decoder, 6) as i64 + 20
+        } else if read_bits(decoder, 1) == 0 {
+            read_bits(decoder, 8) as i64 + 84
+        } else if read_bits(decoder, 1) == 0 {
+            read_bits(decoder, 12) as i64 + 340
+        } else {
+            read_bits(decoder, 32) as i64 + 4436
+        };
+        match (sign, value) {
+            (0, value) => Some(value.min(i32::MAX as i64) as i32),
+            (_, 0) => None,
+            (_, value) => Some((-value).max(i32::MIN as i64) as i32),
+        }
+    }
+
+    ///シンボルIDを復号する。(IAIDの復号手順。)
+    pub(crate) fn decode_id(&mut self, decoder: &mut ArithmeticDecoder) -> u32 {
+        let mask: usize = self.id_contexts.len() - 1;
+        let mut prev: usize = 1;
+        for _ in 0..self.id_code_length {
+            let bit: u8 = decoder.decode(&mut self.id_contexts[prev & mask]);
+            prev = (prev << 1) | bit as usize;
+        }
+        (prev & ((1usize << self.id_code_length) - 1)) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arithmetic_decoder() {
+        //T.88 Annex H.2の算術符号の復号器のテストシーケンス。
+        let encoded: [u8; 30] = [
+            0x84, 0xC7, 0x3B, 0xFC, 0xE1, 0xA1, 0x43, 0x04, 0x02, 0x20, 0x00, 0x00, 0x41, 0x0D,
+            0xBB, 0x86, 0xF4, 0x31, 0x7F, 0xFF, 0x88, 0xFF, 0x37, 0x47, 0x1A, 0xDB, 0x6A, 0xDF,
+            0xFF, 0xAC,
+        ];
+        let expected: [u8; 32] = [
+            0x00, 0x02, 0x00, 0x51, 0x00, 0x00, 0x00, 0xC0, 0x03, 0x52, 0x87, 0x2A, 0xAA, 0xAA,
+            0xAA, 0xAA, 0x82, 0xC0, 0x20, 0x00, 0xFC, 0xD7, 0x9E, 0xF6, 0xBF, 0x7F, 0xED, 0x90,
+            0x4F, 0x46, 0xA3, 0xBF,
+        ];
+        let mut decoder = ArithmeticDecoder::new(&encoded);
+        let mut context = Context::default();
+        let decoded: Vec<u8> = (0..32)
+            .map(|_| (0..8).fold(0u8, |byte, _| (byte << 1) | decoder.decode(&mut context)))
+            .collect();
+        assert_eq!(decoded, expected);
+    }
+}
@@ -0,0 +1,527 @@
+///ビット単位でデータを読み込む。(上位ビットから読み込む。データの終端以降は0として扱う。)
+pub(crate) struct BitReader<'a> {
+    data: &'a [u8],
+    ///次に読み込むビットの位置。
+    bit_position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        BitReader {
+            data,
+            bit_position: 0,
+        }
+    }
+
+    ///1ビットを読み込む。
+    pub(crate) fn read_bit(&mut self) -> Result<u32, String> {
+        let byte: u8 = match self.data.get(self.bit_position / 8) {
+            Some(byte) => *byte,
+            None => return Err("JBIG2 HUFFMAN DATA IS TOO SHORT.".to_string()),
+        };
+        let bit: u32 = ((byte >> (7 - self.bit_position % 8)) & 1) as u32;
+        self.bit_position += 1;
+        Ok(bit)
+    }
+
+    ///指定されたビット数を読み込む。(32ビットまで。)
+    pub(crate) fn read_bits(&mut self, length: u32) -> Result<u32, String> {
+        let mut value: u64 = 0;
+        for _ in 0..length {
+            value = (value << 1) | self.read_bit()? as u64;
+        }
+        Ok(value as u32)
+    }
+
+    ///次のバイト境界まで読み飛ばす。
+    pub(crate) fn align(&mut self) {
+        self.bit_position = self.bit_position.div_ceil(8) * 8;
+    }
+
+    ///次に読み込むバイトの位置を取得する。(バイト境界にない場合は次のバイト境界。)
+    pub(crate) fn byte_position(&self) -> usize {
+        self.bit_position.div_ceil(8)
+    }
+
+    ///次のバイト境界以降のデータを取得する。
+    pub(crate) fn remaining(&self) -> &'a [u8] {
+        self.data.get(self.byte_position()..).unwrap_or_default()
+    }
+
+    ///指定されたバイト数を読み飛ばす。(バイト境界から読み飛ばす。)
+    pub(crate) fn skip_bytes(&mut self, length: usize) {
+        self.bit_position = (self.byte_position() + length) * 8;
+    }
+}
+
+///Huffman表の1行の種類。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineKind {
+    ///RANGELOWからの範囲。
+    Normal,
+    ///RANGELOWより小さい値。(下限の範囲外の値。)
+    Lower,
+    ///RANGELOW以上の値。(上限の範囲外の値。)
+    Upper,
+    ///OOB(範囲外)。
+    OutOfBand,
+}
+
+///Huffman表の1行。(T.88 Annex B。)
+#[derive(Debug, Clone, Copy)]
+struct TableLine {
+    prefix_length: u32,
+    range_length: u32,
+    range_low: i32,
+    kind: LineKind,
+    ///符号。(prefix_lengthが0の行は使用しない。)
+    code: u32,
+}
+
+///JBIG2のHuffman表。(標準の表もしくはテーブルセグメントで定義された表。)
+#[derive(Debug, Clone)]
+pub(crate) struct HuffmanTable {
+    lines: Vec<TableLine>,
+}
+
+///標準のHuffman表(B.1〜B.15)の行。(PREFLEN・RANGELEN・RANGELOW・種類。)
+type StandardLine = (u32, u32, i32, LineKind);
+
+const TABLE_B1: &[StandardLine] = &[
+    (1, 4, 0, LineKind::Normal),
+    (2, 8, 16, LineKind::Normal),
+    (3, 16, 272, LineKind::Normal),
+    (3, 32, 65808, LineKind::Upper),
+];
+const TABLE_B2: &[StandardLine] = &[
+    (1, 0, 0, LineKind::Normal),
+    (2, 0, 1, LineKind::Normal),
+    (3, 0, 2, LineKind::Normal),
+    (4, 3, 3, LineKind::Normal),
+    (5, 6, 11, LineKind::Normal),
+    (6, 32, 75, LineKind::Upper),
+    (6, 0, 0, LineKind::OutOfBand),
+];
+const TABLE_B3: &[StandardLine] = &[
+    (8, 8, -256, LineKind::Normal),
+    (1, 0, 0, LineKind::Normal),
+    (2, 0, 1, LineKind::Normal),
+    (3, 0, 2, LineKind::Normal),
+    (4, 3, 3, LineKind::Normal),
+    (5, 6, 11, LineKind::Normal),
+    (8, 32, -257, LineKind::Lower),
+    (7, 32, 75, LineKind::Upper),
+    (6, 0, 0, LineKind::OutOfBand),
+];
+const TABLE_B4: &[StandardLine] = &[
+    (1, 0, 1, LineKind::Normal),
+    (2, 0, 2, LineKind::Normal),
+    (3, 0, 3, LineKind::Normal),
+    (4, 3, 4, LineKind::Normal),
+    (5, 6, 12, LineKind::Normal),
+    (5, 32, 76, LineKind::Upper),
+];
+const TABLE_B5: &[StandardLine] = &[
+    (7, 8, -255, LineKind::Normal),
+    (1, 0, 1, LineKind::Normal),
+    (2, 0, 2, LineKind::Normal),
+    (3, 0, 3, LineKind::Normal),
+    (4, 3, 4, LineKind::Normal),
+    (5, 6, 12, LineKind::Normal),
+    (7, 32, -256, LineKind::Lower),
+    (6, 32, 76, LineKind::Upper),
+];
+const TABLE_B6: &[StandardLine] = &[
+    (5, 10, -2048, LineKind::Normal),
+    (4, 9, -1024, LineKind::Normal),
+    (4, 8, -512, LineKind::Normal),
+    (4, 7, -256, LineKind::Normal),
+    (5, 6, -128, LineKind::Normal),
+    (5, 5, -64, LineKind::Normal),
+    (4, 5, -32, LineKind::Normal),
+    (2, 7, 0, LineKind::Normal),
+    (3, 7, 128, LineKind::Normal),
+    (3, 8, 256, LineKind::Normal),
+    (4, 9, 512, LineKind::Normal),
+    (4, 10, 1024, LineKind::Normal),
+    (6, 32, -2049, LineKind::Lower),
+    (6, 32, 2048, LineKind::Upper),
+];
+const TABLE_B7: &[StandardLine] = &[
+    (4, 9, -1024, LineKind::Normal),
+    (3, 8, -512, LineKind::Normal),
+    (4, 7, -256, LineKind::Normal),
+    (5, 6, -128, LineKind::Normal),
+    (5, 5, -64, LineKind::Normal),
+    (4, 5, -32, LineKind::Normal),
+    (4, 5, 0, LineKind::Normal),
+    (5, 5, 32, LineKind::Normal),
+    (5, 6, 64, LineKind::Normal),
+    (4, 7, 128, LineKind::Normal),
+    (3, 8, 256, LineKind::Normal),
+    (3, 9, 512, LineKind::Normal),
+    (3, 10, 1024, LineKind::Normal),
+    (5, 32, -1025, LineKind::Lower),
+    (5, 32, 2048, LineKind::Upper),
+];
+const TABLE_B8: &[StandardLine] = &[
+    (8, 3, -15, LineKind::Normal),
+    (9, 1, -7, LineKind::Normal),
+    (8, 1, -5, LineKind::Normal),
+    (9, 0, -3, LineKind::Normal),
+    (7, 0, -2, LineKind::Normal),
+    (4, 0, -1, LineKind::Normal),
+    (2, 1, 0, LineKind::Normal),
+    (5, 0, 2, LineKind::Normal),
+    (6, 0, 3, LineKind::Normal),
+    (3, 4, 4, LineKind::Normal),
+    (6, 1, 20, LineKind::Normal),
+    (4, 4, 22, LineKind::Normal),
+    (4, 5, 38, LineKind::Normal),
+    (5, 6, 70, LineKind::Normal),
+    (5, 7, 134, LineKind::Normal),
+    (6, 7, 262, LineKind::Normal),
+    (7, 8, 390, LineKind::Normal),
+    (6, 10, 646, LineKind::Normal),
+    (9, 32, -16, LineKind::Lower),
+    (9, 32, 1670, LineKind::Upper),
+    (2, 0, 0, LineKind::OutOfBand),
+];
+const TABLE_B9: &[StandardLine] = &[
+    (8, 4, -31, LineKind::Normal),
+    (9, 2, -15, LineKind::Normal),
+    (8, 2, -11, LineKind::Normal),
+    (9, 1, -7, LineKind::Normal),
+    (7, 1, -5, LineKind::Normal),
+    (4, 1, -3, LineKind::Normal),
+    (3, 1, -1, LineKind::Normal),
+    (3, 1, 1, LineKind::Normal),
+    (5, 1, 3, LineKind::Normal),
+    (6, 1, 5, LineKind::Normal),
+    (3, 5, 7, LineKind::Normal),
+    (6, 2, 39, LineKind::Normal),
+    (4, 5, 43, LineKind::Normal),
+    (4, 6, 75, LineKind::Normal),
+    (5, 7, 139, LineKind::Normal),
+    (5, 8, 267, LineKind::Normal),
+    (6, 8, 523, LineKind::Normal),
+    (7, 9, 779, LineKind::Normal),
+    (6, 11, 1291, LineKind::Normal),
+    (9, 32, -32, LineKind::Lower),
+    (9, 32, 3339, LineKind::Upper),
+    (2, 0, 0, LineKind::OutOfBand),
+];
+const TABLE_B10: &[StandardLine] = &[
+    (7, 4, -21, LineKind::Normal),
+    (8, 0, -5, LineKind::Normal),
+    (7, 0, -4, LineKind::Normal),
+    (5, 0, -3, LineKind::Normal),
+    (2, 2, -2, LineKind::Normal),
+    (5, 0, 2, LineKind::Normal),
+    (6, 0, 3, LineKind::Normal),
+    (7, 0, 4, LineKind::Normal),
+    (8, 0, 5, LineKind::Normal),
+    (2, 6, 6, LineKind::Normal),
+    (5, 5, 70, LineKind::Normal),
+    (6, 5, 102, LineKind::Normal),
+    (6, 6, 134, LineKind::Normal),
+    (6, 7, 198, LineKind::Normal),
+    (6, 8, 326, LineKind::Normal),
+    (6, 9, 582, LineKind::Normal),
+    (6, 10, 1094, LineKind::Normal),
+    (7, 11, 2118, LineKind::Normal),
+    (8, 32, -22, LineKind::Lower),
+    (8, 32, 4166, LineKind::Upper),
+    (2, 0, 0, LineKind::OutOfBand),
+];
+const TABLE_B11: &[StandardLine] = &[
+    (1, 0, 1, LineKind::Normal),
+    (2, 1, 2, LineKind::Normal),
+    (4, 0, 4, LineKind::Normal),
+    (4, 1, 5, LineKind::Normal),
+    (5, 1, 7, LineKind::Normal),
+    (5, 2, 9, LineKind::Normal),
+    (6, 2, 13, LineKind::Normal),
+    (7, 2, 17, LineKind::Normal),
+    (7, 3, 21, LineKind::Normal),
+    (7, 4, 29, LineKind::Normal),
+    (7, 5, 45, LineKind::Normal),
+    (7, 6, 77, LineKind::Normal),
+    (7, 32, 141, LineKind::Upper),
+];
+const TABLE_B12: &[StandardLine] = &[
+    (1, 0, 1, LineKind::Normal),
+    (2, 0, 2, LineKind::Normal),
+    (3, 1, 3, LineKind::Normal),
+    (5, 0, 5, LineKind::Normal),
+    (5, 1, 6, LineKind::Normal),
+    (6, 1, 8, LineKind::Normal),
+    (7, 0, 10, LineKind::Normal),
+    (7, 1, 11, LineKind::Normal),
+    (7, 2, 13, LineKind::Normal),
+    (7, 3, 17, LineKind::Normal),
+    (7, 4, 25, LineKind::Normal),
+    (8, 5, 41, LineKind::Normal),
+    (8, 32, 73, LineKind::Upper),
+];
+const TABLE_B13: &[StandardLine] = &[
+    (1, 0, 1, LineKind::Normal),
+    (3, 0, 2, LineKind::Normal),
+    (4, 0, 3, LineKind::Normal),
+    (5, 0, 4, LineKind::Normal),
+    (4, 1, 5, LineKind::Normal),
+    (3, 3, 7, LineKind::Normal),
+    (6, 1, 15, LineKind::Normal),
+    (6, 2, 17, LineKind::Normal),
+    (6, 3, 21, LineKind::Normal),
+    (6, 4, 29, LineKind::Normal),
+    (6, 5, 45, LineKind::Normal),
+    (7, 6, 77, LineKind::Normal),
+    (7, 32, 141, LineKind::Upper),
+];
+const TABLE_B14: &[StandardLine] = &[
+    (3, 0, -2, LineKind::Normal),
+    (3, 0, -1, LineKind::Normal),
+    (1, 0, 0, LineKind::Normal),
+    (3, 0, 1, LineKind::Normal),
+    (3, 0, 2, LineKind::Normal),
+];
+const TABLE_B15: &[StandardLine] = &[
+    (7, 4, -24, LineKind::Normal),
+    (6, 2, -8, LineKind::Normal),
+    (5, 1, -4, LineKind::Normal),
+    (4, 0, -2, LineKind::Normal),
+    (3, 0, -1, LineKind::Normal),
+    (1, 0, 0, LineKind::Normal),
+    (3, 0, 1, LineKind::Normal),
+    (4, 0, 2, LineKind::Normal),
+    (5, 1, 3, LineKind::Normal),
+    (6, 2, 5, LineKind::Normal),
+    (7, 4, 9, LineKind::Normal),
+    (7, 32, -25, LineKind::Lower),
+    (7, 32, 25, LineKind::Upper),
+];
+
+impl HuffmanTable {
+    ///行から表を作成し、符号を割り当てる。(T.88 B.3の手順。)
+    fn from_lines(lines: Vec<(u32, u32, i32, LineKind)>) -> Self {
+        let max_length: u32 = lines.iter().map(|line| line.0).max().unwrap_or(0);
+        let mut length_counts: Vec<u32> = vec![0; max_length as usize + 1];
+        for line in lines.iter() {
+            length_counts[line.0 as usize] += 1;
+        }
+        length_counts[0] = 0;
+        let mut codes: Vec<u32> = vec![0; lines.len()];
+        let mut first_code: u32 = 0;
+        for length in 1..=max_length {
+            first_code = (first_code + length_counts[length as usize - 1]) << 1;
+            let mut code: u32 = first_code;
+            for (i, line) in lines.iter().enumerate() {
+                if line.0 == length {
+                    codes[i] = code;
+                    code += 1;
+                }
+            }
+        }
+        HuffmanTable {
+            lines: lines
+                .into_iter()
+                .zip(codes)
+                .map(
+                    |((prefix_length, range_length, range_low, kind), code)| TableLine {
+                        prefix_length,
+                        range_length,
+                        range_low,
+                        kind,
+                        code,
+                    },
+                )
+                .collect(),
+        }
+    }
+
+    ///標準のHuffman表を取得する。
+    /// # Arguments
+    /// * `number` - 表の番号。(1〜15。Table B.1〜B.15。)
+    pub(crate) fn standard(number: u32) -> Self {
+        let lines: &[StandardLine] = match number {
+            1 => TABLE_B1,
+            2 => TABLE_B2,
+            3 => TABLE_B3,
+            4 => TABLE_B4,
+            5 => TABLE_B5,
+            6 => TABLE_B6,
+            7 => TABLE_B7,
+            8 => TABLE_B8,
+            9 => TABLE_B9,
+            10 => TABLE_B10,
+            11 => TABLE_B11,
+            12 => TABLE_B12,
+            13 => TABLE_B13,
+            14 => TABLE_B14,
+            _ => TABLE_B15,
+        };
+        HuffmanTable::from_lines(lines.to_vec())
+    }
+
+    ///テーブルセグメントのデータから表を作成する。(T.88 7.4.13。)
+    pub(crate) fn parse(data: &[u8]) -> Result<Self, String> {
+        let flags: u8 = *data
+            .first()
+            .ok_or_else(|| "JBIG2 TABLE SEGMENT IS TOO SHORT.".to_string())?;
+        if data.len() < 9 {
+            return Err("JBIG2 TABLE SEGMENT IS TOO SHORT.".to_string());
+        }
+        let has_oob: bool = flags & 1 != 0;
+        let prefix_bits: u32 = ((flags >> 1) & 7) as u32 + 1;
+        let range_bits: u32 = ((flags >> 4) & 7) as u32 + 1;
+        let low: i32 = i32::from_be_bytes([data[1], data[2], data[3], data[4]]);
+        let high: i32 = i32::from_be_bytes([data[5], data[6], data[7], data[8]]);
+        let mut reader = BitReader::new(&data[9..]);
+        let mut lines: Vec<StandardLine> = Vec::new();
+        let mut current: i64 = low as i64;
+        while current < high as i64 {
+            let prefix_length: u32 = reader.read_bits(prefix_bits)?;
+            let range_length: u32 = reader.read_bits(range_bits)?;
+            lines.push((
+                prefix_length,
+                range_length,
+                current as i32,
+                LineKind::Normal,
+            ));
+            current += 1i64 << range_length.min(32);
+        }
+        lines.push((
+            reader.read_bits(prefix_bits)?,
+            32,
+            low.wrapping_sub(1),
+            LineKind::Lower,
+        ));
+        lines.push((reader.read_bits(prefix_bits)?, 32, high, LineKind::Upper));
+        if has_oob {
+            lines.push((reader.read_bits(prefix_bits)?, 0, 0, LineKind::OutOfBand));
+        }
+        Ok(HuffmanTable::from_lines(lines))
+    }
+
+    ///符号の長さから表を作成する。(テキスト領域のシンボルIDの表など。値は行の番号。)
+    pub(crate) fn from_code_lengths(code_lengths: &[u32]) -> Self {
+        HuffmanTable::from_lines(
+            code_lengths
+                .iter()
+                .enumerate()
+                .map(|(i, length)| (*length, 0, i as i32, LineKind::Normal))
+                .collect(),
+        )
+    }
+
+    ///値を1つ復号する。
+    /// # Returns
+    /// * 成功時:復号した値。(OOBの場合はNone。)
+    /// * 失敗時:表にない符号もしくはデータの不足。
+    pub(crate) fn decode(&self, reader: &mut BitReader) -> Result<Option<i32>, String> {
+        let mut code: u32 = 0;
+        let mut length: u32 = 0;
+        while length < 32 {
+            code = (code << 1) | reader.read_bit()?;
+            length += 1;
+            let line: Option<&TableLine> = self
+                .lines
+                .iter()
+                .find(|line| line.prefix_length == length && line.code == code);
+            if let Some(line) = line {
+                let value: i64 = match line.kind {
+                    LineKind::OutOfBand => return Ok(None),
+                    LineKind::Lower => {
+                        line.range_low as i64 - reader.read_bits(line.range_length)? as i64
+                    }
+                    LineKind::Normal | LineKind::Upper => {
+                        line.range_low as i64 + reader.read_bits(line.range_length)? as i64
+                    }
+                };
+                return Ok(Some(value.clamp(i32::MIN as i64, i32::MAX as i64) as i32));
+            }
+        }
+        Err("INVALID JBIG2 HUFFMAN CODE.".to_string())
+    }
+
+    ///値を1つ復号する。(OOBを許容しない場合。)
+    pub(crate) fn decode_value(&self, reader: &mut BitReader) -> Result<i32, String> {
+        self.decode(reader)?
+            .ok_or_else(|| "UNEXPECTED JBIG2 HUFFMAN OOB.".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///表の(符号の長さ, 符号)の一覧を取得する。
+    fn codes(table: &HuffmanTable) -> Vec<(u32, u32)> {
+        table
+            .lines
+            .iter()
+            .map(|line| (line.prefix_length, line.code))
+            .collect()
+    }
+
+    #[test]
+    fn test_standard_table_codes() {
+        assert_eq!(
+            codes(&HuffmanTable::standard(2)),
+            vec![(1, 0), (2, 2), (3, 6), (4, 14), (5, 30), (6, 62), (6, 63)]
+        );
+        assert_eq!(
+            codes(&HuffmanTable::standard(8))[..7],
+            [
+                (8, 0xFC),
+                (9, 0x1FC),
+                (8, 0xFD),
+                (9, 0x1FD),
+                (7, 0x7C),
+                (4, 0x0A),
+                (2, 0x00)
+            ]
+        );
+        assert_eq!(codes(&HuffmanTable::standard(8))[20], (2, 0x01));
+        assert_eq!(
+            codes(&HuffmanTable::standard(15))[11..],
+            [(7, 0x7E), (7, 0x7F)]
+        );
+    }
+
+    #[test]
+    fn test_decode_standard_table() {
+        //B.1:"10"+8ビット(16+5)、"0"+4ビット(3)、"111"+32ビット(65808+1)。
+        let data: [u8; 7] = [0b1000_0001, 0b0100_0111, 0b1100_0000, 0, 0, 0, 0b0100_0000];
+        let mut reader = BitReader::new(&data);
+        let table = HuffmanTable::standard(1);
+        assert_eq!(table.decode(&mut reader), Ok(Some(21)));
+        assert_eq!(table.decode(&mut reader), Ok(Some(3)));
+        assert_eq!(table.decode(&mut reader), Ok(Some(65809)));
+        //B.2のOOB("111111")とB.3の下限の範囲外("11111111"+32ビット)。
+        let data: [u8; 6] = [0b1111_1111, 0b1111_1100, 0, 0, 0, 0b0000_1000];
+        let mut reader = BitReader::new(&data);
+        assert_eq!(HuffmanTable::standard(2).decode(&mut reader), Ok(None));
+        assert_eq!(
+            HuffmanTable::standard(3).decode(&mut reader),
+            Ok(Some(-259))
+        );
+    }
+
+    #[test]
+    fn test_parse_table_segment() {
+        //HTOOB=1・HTPS=2・HTRS=2、0〜3(PREFLEN=1・RANGELEN=2)と4〜7(PREFLEN=2・RANGELEN=2)の表。
+        //(下限・上限の範囲外は3ビット、OOBは3ビット。)
+        let mut data: Vec<u8> = vec![0b0001_0011];
+        data.extend_from_slice(&0i32.to_be_bytes());
+        data.extend_from_slice(&8i32.to_be_bytes());
+        //01 10 | 10 10 | 11 | 11 | 11
+        data.extend_from_slice(&[0b0110_1010, 0b1111_1100]);
+        let table = HuffmanTable::parse(&data).unwrap();
+        assert_eq!(
+            codes(&table),
+            vec![(1, 0), (2, 2), (3, 6), (3, 7), (3, 6 + 2)]
+        );
+    }
+}
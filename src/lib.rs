@@ -0,0 +1,62 @@
+//! PDFファイルに埋め込まれた画像を取り出すライブラリ。
+//!
+//! [`Extractor`]で設定を行い、[`Extractor::extract_file`]もしくは[`Extractor::extract_dir`]で画像を取得する。
+//! 処理結果は[`ExtractionReport`]、PDFファイル単位の失敗は[`ExtractError`]で返される。
+extern crate file_method;
+
+pub mod archive_format;
+pub mod dedup_mode;
+pub mod existing_policy;
+pub mod extract_error;
+pub mod extraction_manifest;
+pub mod extraction_report;
+pub mod extractor;
+pub mod fax_mode;
+pub mod file_name_template;
+pub mod global_dedup;
+pub mod image_filter;
+pub mod near_duplicate;
+pub mod output_format;
+pub mod page_selection;
+pub mod password_map;
+pub mod path_pattern;
+pub mod perceptual_hash;
+
+mod apply_image_mask;
+mod collect_page_images;
+mod color_management;
+mod decode_ccitt;
+mod decode_jbig2;
+mod decode_jpeg;
+mod decode_raw_image;
+mod dedup_store;
+mod find_pdf_files;
+mod font_encoding;
+mod get_image_from_pdf;
+mod jbig2_arithmetic;
+mod jbig2_huffman;
+mod output_sink;
+mod parse_type1_font;
+mod render_font;
+mod render_page;
+mod resume_manifest;
+mod set_workers_limit;
+mod standard_font;
+
+pub use archive_format::ArchiveFormat;
+pub use dedup_mode::DedupMode;
+pub use existing_policy::ExistingPolicy;
+pub use extract_error::ExtractError;
+pub use extraction_manifest::{ImageStatus, ManifestEntry};
+pub use extraction_report::ExtractionReport;
+pub use extractor::Extractor;
+pub use fax_mode::FaxMode;
+pub use file_name_template::FileNameTemplate;
+pub use global_dedup::GlobalDedup;
+pub use image_filter::ImageFilter;
+pub use near_duplicate::NearDuplicateAction;
+pub use output_format::OutputFormat;
+pub use page_selection::PageSelection;
+pub use password_map::PasswordMap;
+pub use path_pattern::PathPattern;
+pub use perceptual_hash::PerceptualHash;
@@ -1,19 +1,20 @@
-extern crate file_method;
-
-use file_method::seek_file::seek_file_by_extension;
-
-mod get_image_from_pdf;
-mod get_thread_id;
-mod set_workers_limit;
-
 use chrono::{self, Utc};
 use clap::Parser;
-use get_image_from_pdf::get_images;
 use log::{error, info};
-use set_workers_limit::get_main_workers_limit;
+use pdf_to_image_rs::extractor::DEFAULT_RENDER_DPI;
+use pdf_to_image_rs::file_name_template::DEFAULT_FILE_NAME_TEMPLATE;
+use pdf_to_image_rs::{
+    ArchiveFormat, DedupMode, ExistingPolicy, ExtractError, Extractor, FaxMode, FileNameTemplate,
+    GlobalDedup, ImageFilter, NearDuplicateAction, OutputFormat, PageSelection, PasswordMap,
+    PathPattern, PerceptualHash,
+};
 use std::env;
-use std::path::Path;
-use threadpool::ThreadPool;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+///標準入力から読み込んだPDFファイルの名前。(ログ・一覧・ファイル名の{pdf_stem}に使用する。)
+const STDIN_PDF_NAME: &str = "stdin.pdf";
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -22,54 +23,539 @@ struct Args {
     #[arg(
         short = 's',
         long = "pdfdir",
+        required_unless_present_any = ["pdf_files", "file_list", "stream"],
+        conflicts_with_all = ["pdf_files", "file_list", "stream"],
         help = "pdfファイルが格納されているディレクトリのパスを指定します。"
     )]
-    pdfdir: String,
+    pdfdir: Option<String>,
+
+    #[arg(
+        value_name = "PDF",
+        help = "処理するpdfファイルのパスを指定します。(複数指定可) \"-\"を指定した場合は標準入力から1行に1つずつパスを読み込みます。"
+    )]
+    pdf_files: Vec<String>,
+
+    #[arg(
+        long = "file-list",
+        value_name = "FILE",
+        help = "処理するpdfファイルのパスを1行に1つずつ記載したファイルを指定します。(\"-\"の場合は標準入力から読み込みます。空行は無視します。)"
+    )]
+    file_list: Option<String>,
+
+    #[arg(
+        long = "stream",
+        value_name = "FORMAT",
+        conflicts_with_all = ["pdf_files", "file_list", "outdir", "on_existing", "global_dedup", "dedup_store"],
+        help = "標準入力からpdfファイルのデータを読み込み、画像と一覧(manifest.jsonl)を指定した形式(tar|zip|cbz)のアーカイブとして標準出力に書き込みます。ディレクトリ・一時ファイルは作成しません。(ログは標準エラー出力に出力します。)"
+    )]
+    stream: Option<ArchiveFormat>,
+
+    #[arg(
+        short = 'o',
+        long = "outdir",
+        help = "画像の出力先ディレクトリを指定します。pdfdirからの相対パスを保持したディレクトリ構成で保存します。(未指定時はpdfファイルと同じディレクトリに保存します。)"
+    )]
+    outdir: Option<String>,
+
+    #[arg(
+        short = 'r',
+        long = "recursive",
+        help = "pdfdirのサブディレクトリ内のpdfファイルも再帰的に検索します。"
+    )]
+    recursive: bool,
+
+    #[arg(
+        long = "max-depth",
+        requires = "recursive",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        help = "--recursive指定時に検索する深さの最大値を指定します。(1の場合はpdfdirの直下のみ)"
+    )]
+    max_depth: Option<usize>,
+
+    #[arg(
+        long = "follow-symlinks",
+        help = "pdfファイルの検索時にシンボリックリンクをたどります。(未指定時はシンボリックリンクを無視します。)"
+    )]
+    follow_symlinks: bool,
+
+    #[arg(
+        long = "include",
+        value_name = "GLOB",
+        help = "検索対象とするpdfファイルのglobパターンを指定します。(複数指定可) \"/\"を含むパターンはpdfdirからの相対パスと、含まないパターンはファイル名と比較します。"
+    )]
+    include: Vec<PathPattern>,
+
+    #[arg(
+        long = "exclude",
+        value_name = "GLOB",
+        help = "検索対象から除外するpdfファイル・ディレクトリのglobパターンを指定します。(複数指定可) ディレクトリが一致した場合はその下を検索しません。"
+    )]
+    exclude: Vec<PathPattern>,
+
+    #[arg(
+        long = "archive",
+        value_name = "FORMAT",
+        conflicts_with_all = ["stream", "global_dedup", "dedup_store"],
+        help = "画像と一覧(manifest.jsonl)をpdfファイルごとに1つのアーカイブ(zip|cbz|tar)にまとめて保存します。cbzの場合は画像をページ順に格納します。(保存先ディレクトリの代わりに、拡張子を付けたファイルに保存します。)"
+    )]
+    archive: Option<ArchiveFormat>,
+
+    #[arg(
+        long = "on-existing",
+        default_value = "skip",
+        help = "画像の保存先ディレクトリが既に存在する場合の動作を指定します。(skip|overwrite|resume|fail) resumeの場合は前回の実行で保存されなかった画像のみを取得します。"
+    )]
+    on_existing: ExistingPolicy,
+
+    #[arg(
+        long = "name-template",
+        default_value = DEFAULT_FILE_NAME_TEMPLATE,
+        help = "保存する画像のファイル名のテンプレートを指定します。({pdf_stem}|{page}|{index}|{xobject}|{objnum}|{width}|{height}|{hash}|{ext}) ファイル名が重複しないよう、{hash}もしくは{page}と{index}の両方を含める必要があります。{page}と{index}の両方を含まない場合は--dedupにblake3もしくはsha256を指定する必要があります。"
+    )]
+    name_template: FileNameTemplate,
+
+    #[arg(
+        long = "manifest-csv",
+        help = "画像の一覧(manifest.jsonl)に加えて、CSV形式の一覧(manifest.csv)も保存先ディレクトリに作成します。"
+    )]
+    manifest_csv: bool,
+
+    #[arg(
+        long = "dedup",
+        default_value = "blake3",
+        help = "同じpdfファイル内の重複した画像を判定する方法を指定します。(blake3|sha256|objref|off) objrefの場合は同じオブジェクトを参照する画像を重複とし、offの場合は全ての画像を保存します。"
+    )]
+    dedup: DedupMode,
+
+    #[arg(
+        long = "global-dedup",
+        default_value = "off",
+        help = "他のpdfファイルで保存済みの画像と重複した場合の動作を指定します。(off|reference|link) referenceの場合は保存せずに一覧(manifest.jsonl)に最初に保存したファイルのパスを記録し、linkの場合は最初に保存したファイルへのハードリンクを作成します。"
+    )]
+    global_dedup: GlobalDedup,
+
+    #[arg(
+        long = "dedup-store",
+        help = "--global-dedup指定時に、重複判定に使用する保存済みの画像の一覧を記録するファイルを指定します。実行をまたいで重複を判定します。"
+    )]
+    dedup_store: Option<String>,
+
+    #[arg(
+        long = "perceptual-hash",
+        help = "同じpdfファイル内の近似した画像(異なる品質で再圧縮された同じ画像など)を知覚ハッシュで判定します。(ahash|dhash|phash) 判定結果は一覧(manifest.jsonl)のclusterに記録します。"
+    )]
+    perceptual_hash: Option<PerceptualHash>,
+
+    #[arg(
+        long = "near-duplicate-threshold",
+        requires = "perceptual_hash",
+        value_parser = clap::value_parser!(u32).range(0..=64),
+        help = "--perceptual-hash指定時に、近似した画像と判定する知覚ハッシュのハミング距離の最大値を指定します。(0-64 既定値は5)"
+    )]
+    near_duplicate_threshold: Option<u32>,
+
+    #[arg(
+        long = "near-duplicate-action",
+        requires = "perceptual_hash",
+        help = "--perceptual-hash指定時に、近似した画像が見つかった場合の動作を指定します。(flag|skip) flagの場合は保存して一覧に記録し、skipの場合は保存しません。(既定値はflag)"
+    )]
+    near_duplicate_action: Option<NearDuplicateAction>,
 
     #[arg(short = 'd', long = "debug", help = "デバッグモードを有効にします。")]
     debug: bool,
+
+    #[arg(
+        short = 'f',
+        long = "format",
+        default_value = "original",
+        help = "保存する画像の形式を指定します。(png|jpg|webp|tiff|original) originalの場合はPDFに埋め込まれている形式のまま保存します。"
+    )]
+    format: OutputFormat,
+
+    #[arg(
+        long = "fax",
+        default_value = "tiff",
+        help = "CCITTFaxDecode(Group 3/4)の画像を保存する方法を指定します。(tiff|decode) tiffの場合は展開せずにTIFFに格納し、decodeの場合は展開してPNG(--format指定時はその形式)で保存します。(--formatがoriginal|tiff以外の場合は常に展開します。)"
+    )]
+    fax: FaxMode,
+
+    #[arg(
+        long = "apply-masks",
+        help = "SMask・Maskを持つ画像にマスクを適用し、アルファチャンネル付きの画像(PNG)として保存します。"
+    )]
+    apply_masks: bool,
+
+    #[arg(
+        long = "keep-unmasked",
+        requires = "apply_masks",
+        help = "--apply-masks指定時に、マスクを適用していない元の画像も保存します。"
+    )]
+    keep_unmasked: bool,
+
+    #[arg(
+        long = "render-pages",
+        conflicts_with = "apply_masks",
+        help = "埋め込まれた画像を取り出す代わりに、各ページを描画した画像を保存します。(--formatがoriginalの場合はPNGで保存します。)"
+    )]
+    render_pages: bool,
+
+    #[arg(
+        long = "dpi",
+        default_value_t = DEFAULT_RENDER_DPI,
+        value_parser = parse_dpi,
+        requires = "render_pages",
+        help = "--render-pages指定時にページを描画する解像度(DPI)を指定します。"
+    )]
+    dpi: f32,
+
+    #[arg(
+        long = "pages",
+        value_name = "PAGES",
+        help = "処理するページをカンマ区切りで指定します。(例:1-5,10,20-) odd・evenで奇数・偶数ページ、last:Nで最後のNページを指定できます。(未指定時は全てのページ)"
+    )]
+    pages: Option<PageSelection>,
+
+    #[arg(
+        long = "min-width",
+        value_name = "PIXELS",
+        help = "保存する画像の幅の最小値を指定します。"
+    )]
+    min_width: Option<u32>,
+
+    #[arg(
+        long = "max-width",
+        value_name = "PIXELS",
+        help = "保存する画像の幅の最大値を指定します。"
+    )]
+    max_width: Option<u32>,
+
+    #[arg(
+        long = "min-height",
+        value_name = "PIXELS",
+        help = "保存する画像の高さの最小値を指定します。"
+    )]
+    min_height: Option<u32>,
+
+    #[arg(
+        long = "max-height",
+        value_name = "PIXELS",
+        help = "保存する画像の高さの最大値を指定します。"
+    )]
+    max_height: Option<u32>,
+
+    #[arg(
+        long = "min-area",
+        value_name = "PIXELS",
+        help = "保存する画像の画素数(幅×高さ)の最小値を指定します。"
+    )]
+    min_area: Option<u64>,
+
+    #[arg(
+        long = "max-area",
+        value_name = "PIXELS",
+        help = "保存する画像の画素数(幅×高さ)の最大値を指定します。"
+    )]
+    max_area: Option<u64>,
+
+    #[arg(
+        long = "min-bytes",
+        value_name = "SIZE",
+        value_parser = parse_byte_size,
+        help = "保存する画像データのバイト数の最小値を指定します。(PDFに格納されたストリームのバイト数で、ASCIIHex・ASCII85・LZW・RunLengthのみ展開し、Flate・DCT・JPX・JBIG2・CCITTFaxは展開しない。K・M・Gの接尾辞で1024単位を指定可能)"
+    )]
+    min_bytes: Option<u64>,
+
+    #[arg(
+        long = "max-bytes",
+        value_name = "SIZE",
+        value_parser = parse_byte_size,
+        help = "保存する画像データのバイト数の最大値を指定します。(バイト数の数え方は--min-bytesと同じ。K・M・Gの接尾辞で1024単位を指定可能)"
+    )]
+    max_bytes: Option<u64>,
+
+    #[arg(
+        long = "min-aspect",
+        value_name = "RATIO",
+        value_parser = parse_aspect_ratio,
+        help = "保存する画像の縦横比(幅÷高さ)の最小値を指定します。(例:0.5、1:2)"
+    )]
+    min_aspect: Option<f32>,
+
+    #[arg(
+        long = "max-aspect",
+        value_name = "RATIO",
+        value_parser = parse_aspect_ratio,
+        help = "保存する画像の縦横比(幅÷高さ)の最大値を指定します。(例:2、16:9)"
+    )]
+    max_aspect: Option<f32>,
+
+    #[arg(
+        long = "password",
+        value_name = "PASSWORD",
+        conflicts_with = "password_file",
+        help = "暗号化されたpdfファイルを開くパスワードを指定します。(--password-mapに記載されていないpdfファイルに使用します。)"
+    )]
+    password: Option<String>,
+
+    #[arg(
+        long = "password-file",
+        value_name = "FILE",
+        help = "暗号化されたpdfファイルを開くパスワードを1行目に記載したファイルを指定します。(--passwordの代わりに使用します。)"
+    )]
+    password_file: Option<String>,
+
+    #[arg(
+        long = "password-map",
+        value_name = "CSV",
+        help = "pdfファイルごとのパスワードを\"ファイル名,パスワード\"の形式で記載したCSVファイル(ヘッダー行なし)を指定します。(ファイル名はパスもしくはファイル名で判定します。)"
+    )]
+    password_map: Option<String>,
+}
+
+///--dpiの値を解析する。(1以上2400以下の数値のみ受け付ける。)
+fn parse_dpi(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(dpi) if (1.0..=2400.0).contains(&dpi) => Ok(dpi),
+        _ => Err(format!("INVALID DPI. VALUE: {} (1-2400)", value)),
+    }
 }
 
-fn start(directory_path: &Path) -> u32 {
-    let mut return_value: u32 = 0;
+///--min-bytes・--max-bytesの値を解析する。(K・M・Gの接尾辞は1024単位として扱う。)
+fn parse_byte_size(value: &str) -> Result<u64, String> {
+    let upper: String = value.trim().to_ascii_uppercase();
+    let number: &str = upper.strip_suffix('B').unwrap_or(&upper);
+    let (number, unit): (&str, u64) = if let Some(number) = number.strip_suffix('K') {
+        (number, 1 << 10)
+    } else if let Some(number) = number.strip_suffix('M') {
+        (number, 1 << 20)
+    } else if let Some(number) = number.strip_suffix('G') {
+        (number, 1 << 30)
+    } else {
+        (number, 1)
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(unit))
+        .ok_or_else(|| format!("INVALID BYTE SIZE. VALUE: {} (e.g. 512, 10K, 2M)", value))
+}
+
+///--min-aspect・--max-aspectの値を解析する。("幅:高さ"の形式もしくは0より大きい数値のみ受け付ける。)
+fn parse_aspect_ratio(value: &str) -> Result<f32, String> {
+    let ratio: Option<f32> = match value.split_once(':') {
+        Some((width, height)) => {
+            match (width.trim().parse::<f32>(), height.trim().parse::<f32>()) {
+                (Ok(width), Ok(height)) if height > 0.0 => Some(width / height),
+                _ => None,
+            }
+        }
+        None => value.trim().parse::<f32>().ok(),
+    };
+    match ratio {
+        Some(ratio) if ratio.is_finite() && ratio > 0.0 => Ok(ratio),
+        _ => Err(format!(
+            "INVALID ASPECT RATIO. VALUE: {} (e.g. 1.5, 16:9)",
+            value
+        )),
+    }
+}
 
-    let _pdf_files: Vec<std::path::PathBuf> = match seek_file_by_extension(directory_path, "pdf") {
-        Ok(files) => files,
+///ディレクトリ内の全てのPDFファイルから画像を取得する。
+/// # Arguments
+/// * `directory_path` - PDFファイルが格納されているディレクトリのパス。
+/// * `extractor` - 画像取得処理の設定。
+/// # Returns
+/// * 成功時:0
+/// * 失敗時:以下のエラーコード。
+/// * 1:少なくとも1つのPDFファイルの処理に失敗。
+/// * 10:PDFファイルの検索失敗。
+/// * 21:出力先ディレクトリの作成失敗。
+/// * 25:重複判定ストアの読み込み失敗。
+/// * 27:処理に失敗した全てのPDFファイルが、暗号化されたPDFファイルのパスワードの誤り・未指定。
+fn start(directory_path: &Path, extractor: &Extractor) -> u32 {
+    summarize(extractor.extract_dir(directory_path))
+}
+
+///指定された全てのPDFファイルから画像を取得する。
+/// # Arguments
+/// * `pdf_file_paths` - PDFファイルのパス。
+/// * `extractor` - 画像取得処理の設定。
+/// # Returns
+/// * 成功時:0
+/// * 失敗時:以下のエラーコード。
+/// * 1:少なくとも1つのPDFファイルの処理に失敗。
+/// * 21:出力先ディレクトリの作成失敗。
+/// * 25:重複判定ストアの読み込み失敗。
+/// * 27:処理に失敗した全てのPDFファイルが、暗号化されたPDFファイルのパスワードの誤り・未指定。
+fn start_files(pdf_file_paths: &[PathBuf], extractor: &Extractor) -> u32 {
+    summarize(extractor.extract_files(pdf_file_paths))
+}
+
+///標準入力から読み込んだPDFファイルから画像を取得し、アーカイブとして標準出力に書き込む。
+/// # Arguments
+/// * `archive_format` - アーカイブの形式。
+/// * `extractor` - 画像取得処理の設定。
+/// # Returns
+/// * 成功時:0
+/// * 失敗時:以下のエラーコード。
+/// * 1:ページ取得失敗・ページ内画像取得失敗など。
+/// * 22:標準入力の読み込み失敗・PDFファイルオープン失敗。
+/// * 26:アーカイブの書き込み失敗。
+/// * 27:暗号化されたPDFファイルのパスワードの誤り・未指定。
+fn start_stream(archive_format: ArchiveFormat, extractor: &Extractor) -> u32 {
+    summarize(extractor.extract_reader(
+        std::io::stdin().lock(),
+        STDIN_PDF_NAME,
+        archive_format,
+        BufWriter::new(std::io::stdout()),
+    ))
+}
+
+///処理結果の集計をログに出力し、終了コードを取得する。
+fn summarize(
+    result: Result<pdf_to_image_rs::ExtractionReport, pdf_to_image_rs::ExtractError>,
+) -> u32 {
+    let report = match result {
+        Ok(report) => report,
         Err(e) => {
-            error!("ERROR OCCURED WHILE SEEKING PDF FILES. ERR: {}", e);
-            return 10;
+            error!("{}", e);
+            return e.exit_code() as u32;
         }
     };
-    let _pool = ThreadPool::new(get_main_workers_limit());
-    for file in _pdf_files {
-        _pool.execute(move || {
-            let file_path = file.as_path();
-            let file_path_clone = Path::new(file_path);
-            let result: u32 = get_images(file_path_clone);
-            match result {
-                0 => {
-                    info!("PDF FILE PROCESS COMPLETE. FILE : {:?}", file_path);
-                }
-                _ => {
-                    error!(
-                        "PDF FILE PROCESS ERROR. FILE : {:?} RESULT : {}",
-                        file_path, result
-                    );
-                    if return_value == 0 {
-                        return_value = 1;
-                        if log::log_enabled!(log::Level::Debug) {
-                            info!("RETURN VALUE : {}", return_value);
-                        }
-                    }
-                }
-            }
-        });
+    info!(
+        "EXTRACTION SUMMARY. PDF_FILES : {} SKIPPED_FILES : {} PAGES : {} RENDERED_PAGES : {} INCOMPLETE_PAGES : {} IMAGES : {} DUPLICATES : {} NEAR_DUPLICATES : {} GLOBAL_DUPLICATES : {} RESUMED : {} FILTERED : {} UNDECODED : {} ERRORS : {}",
+        report.pdf_files,
+        report.skipped_files,
+        report.pages,
+        report.rendered_pages,
+        report.incomplete_pages,
+        report.images_written,
+        report.duplicate_images,
+        report.near_duplicates,
+        report.global_duplicates,
+        report.resumed_files,
+        report.filtered_images,
+        report.undecoded_images,
+        report.errors.len()
+    );
+    if report.is_success() {
+        return 0;
+    }
+    //全てのエラーがパスワードの誤り・未指定の場合は、パスワードのエラーとして区別できるよう27を返す。
+    let return_value: u32 = if report
+        .errors
+        .iter()
+        .all(|e| matches!(e, ExtractError::Password { .. }))
+    {
+        27
+    } else {
+        1
+    };
+    if log::log_enabled!(log::Level::Debug) {
+        info!("RETURN VALUE : {}", return_value);
     }
-    // 全てのタスクが終了するのを待つ
-    _pool.join();
     return_value
 }
 
+///コマンドライン引数から保存する画像の条件を作成する。(指定されなかった条件は判定しない。)
+fn image_filter_from_args(args: &Args) -> ImageFilter {
+    let mut image_filter = ImageFilter::new();
+    if let Some(width) = args.min_width {
+        image_filter = image_filter.min_width(width);
+    }
+    if let Some(width) = args.max_width {
+        image_filter = image_filter.max_width(width);
+    }
+    if let Some(height) = args.min_height {
+        image_filter = image_filter.min_height(height);
+    }
+    if let Some(height) = args.max_height {
+        image_filter = image_filter.max_height(height);
+    }
+    if let Some(area) = args.min_area {
+        image_filter = image_filter.min_area(area);
+    }
+    if let Some(area) = args.max_area {
+        image_filter = image_filter.max_area(area);
+    }
+    if let Some(bytes) = args.min_bytes {
+        image_filter = image_filter.min_bytes(bytes);
+    }
+    if let Some(bytes) = args.max_bytes {
+        image_filter = image_filter.max_bytes(bytes);
+    }
+    if let Some(ratio) = args.min_aspect {
+        image_filter = image_filter.min_aspect_ratio(ratio);
+    }
+    if let Some(ratio) = args.max_aspect {
+        image_filter = image_filter.max_aspect_ratio(ratio);
+    }
+    image_filter
+}
+
+///パスワードファイルの1行目からパスワードを読み込む。(行末の改行は取り除く。)
+/// # Arguments
+/// * `password_file` - パスワードを記載したファイル。
+/// # Returns
+/// * 成功時:パスワード。
+/// * 失敗時:std::io::Error(ファイルの読み込み失敗)。
+fn read_password_file(password_file: &Path) -> std::io::Result<String> {
+    let content: String = std::fs::read_to_string(password_file)?;
+    Ok(content.lines().next().unwrap_or_default().to_string())
+}
+
+///コマンドライン引数・ファイル一覧・標準入力から処理するPDFファイルのパスを取得する。
+/// # Arguments
+/// * `pdf_files` - コマンドライン引数で指定されたパス。("-"の場合は標準入力から読み込む。)
+/// * `file_list` - PDFファイルのパスを記載したファイル。("-"の場合は標準入力から読み込む。)
+/// # Returns
+/// * 成功時:PDFファイルのパス。(指定された順。)
+/// * 失敗時:std::io::Error(ファイル一覧もしくは標準入力の読み込み失敗)。
+fn collect_pdf_file_paths(
+    pdf_files: &[String],
+    file_list: Option<&str>,
+) -> std::io::Result<Vec<PathBuf>> {
+    let mut pdf_file_paths: Vec<PathBuf> = Vec::new();
+    //標準入力は1回のみ読み込む。
+    let mut stdin_read: bool = false;
+    let mut read_stdin = |pdf_file_paths: &mut Vec<PathBuf>| -> std::io::Result<()> {
+        if !stdin_read {
+            pdf_file_paths.extend(read_path_list(std::io::stdin().lock())?);
+            stdin_read = true;
+        }
+        Ok(())
+    };
+    for pdf_file in pdf_files {
+        if pdf_file == "-" {
+            read_stdin(&mut pdf_file_paths)?;
+        } else {
+            pdf_file_paths.push(PathBuf::from(pdf_file));
+        }
+    }
+    match file_list {
+        Some("-") => read_stdin(&mut pdf_file_paths)?,
+        Some(file_list) => {
+            pdf_file_paths.extend(read_path_list(BufReader::new(File::open(file_list)?))?)
+        }
+        None => {}
+    }
+    Ok(pdf_file_paths)
+}
+
+///1行に1つずつ記載されたパスを読み込む。(前後の空白と空行は無視する。)
+fn read_path_list(reader: impl BufRead) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for line in reader.lines() {
+        let line: String = line?;
+        let line: &str = line.trim();
+        if !line.is_empty() {
+            paths.push(PathBuf::from(line));
+        }
+    }
+    Ok(paths)
+}
+
 fn main() {
     let args = Args::parse();
     if args.debug {
@@ -80,9 +566,92 @@ fn main() {
     env_logger::init();
     info!("START PDF TO IMAGE CONVERTER.");
     let start_time: i64 = Utc::now().timestamp_micros();
-    let pdf_dir_str: String = args.pdfdir;
-    let path = Path::new(pdf_dir_str.as_str());
-    let return_value = start(path);
+    let image_filter: ImageFilter = image_filter_from_args(&args);
+    let mut extractor = Extractor::new()
+        .output_format(args.format)
+        .fax_mode(args.fax)
+        .apply_masks(args.apply_masks)
+        .keep_unmasked(args.keep_unmasked)
+        .render_pages(args.render_pages)
+        .dpi(args.dpi)
+        .on_existing(args.on_existing)
+        .file_name_template(args.name_template)
+        .manifest_csv(args.manifest_csv)
+        .dedup_mode(args.dedup)
+        .global_dedup(args.global_dedup)
+        .recursive(args.recursive)
+        .follow_symlinks(args.follow_symlinks)
+        .image_filter(image_filter);
+    if let Some(ref outdir) = args.outdir {
+        extractor = extractor.output_dir(Path::new(outdir.as_str()));
+    }
+    if let Some(archive_format) = args.archive {
+        extractor = extractor.archive(archive_format);
+    }
+    if let Some(page_selection) = args.pages {
+        extractor = extractor.pages(page_selection);
+    }
+    if let Some(ref password) = args.password {
+        extractor = extractor.password(password);
+    }
+    if let Some(ref password_file) = args.password_file {
+        match read_password_file(Path::new(password_file.as_str())) {
+            Ok(password) => extractor = extractor.password(&password),
+            Err(e) => {
+                let e = ExtractError::PasswordFile {
+                    path: PathBuf::from(password_file),
+                    source: e,
+                };
+                error!("{}", e);
+                std::process::exit(e.exit_code());
+            }
+        }
+    }
+    if let Some(ref password_map) = args.password_map {
+        match PasswordMap::load(Path::new(password_map.as_str())) {
+            Ok(password_map) => extractor = extractor.password_map(password_map),
+            Err(e) => {
+                let e = ExtractError::PasswordFile {
+                    path: PathBuf::from(password_map),
+                    source: e,
+                };
+                error!("{}", e);
+                std::process::exit(e.exit_code());
+            }
+        }
+    }
+    if let Some(max_depth) = args.max_depth {
+        extractor = extractor.max_depth(max_depth);
+    }
+    for pattern in args.include {
+        extractor = extractor.include_pattern(pattern);
+    }
+    for pattern in args.exclude {
+        extractor = extractor.exclude_pattern(pattern);
+    }
+    if let Some(ref dedup_store) = args.dedup_store {
+        extractor = extractor.dedup_store(Path::new(dedup_store.as_str()));
+    }
+    if let Some(perceptual_hash) = args.perceptual_hash {
+        extractor = extractor.perceptual_hash(perceptual_hash);
+    }
+    if let Some(threshold) = args.near_duplicate_threshold {
+        extractor = extractor.near_duplicate_threshold(threshold);
+    }
+    if let Some(near_duplicate_action) = args.near_duplicate_action {
+        extractor = extractor.near_duplicate_action(near_duplicate_action);
+    }
+    let return_value = match (args.pdfdir, args.stream) {
+        (Some(ref pdf_dir_str), _) => start(Path::new(pdf_dir_str.as_str()), &extractor),
+        (None, Some(archive_format)) => start_stream(archive_format, &extractor),
+        (None, None) => match collect_pdf_file_paths(&args.pdf_files, args.file_list.as_deref()) {
+            Ok(pdf_file_paths) => start_files(&pdf_file_paths, &extractor),
+            Err(e) => {
+                error!("COULD NOT READ PDF FILE LIST. ERR: {}", e);
+                10
+            }
+        },
+    };
     let end_time: i64 = Utc::now().timestamp_micros();
     let elapsed_time: i64 = end_time - start_time;
     info!(
@@ -91,3 +660,163 @@ fn main() {
     );
     std::process::exit(return_value as i32);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_log::test]
+    ///壊れたPDFファイルを含むディレクトリを指定した場合は0以外が返ることを確認する。
+    fn test_start_broken_pdf() {
+        let directory_path = Path::new("test_pdf/broken_pdf");
+        let result = start(directory_path, &Extractor::new());
+        assert_eq!(result, 1);
+    }
+
+    #[test_log::test]
+    ///開けるPDFファイルでも、ページ内の画像の取得に失敗した場合はエラーが集約され、0以外が返ることを確認する。
+    ///(test_pdf/broken_image_pdf/broken_image.pdfは1ページ目の画像は正常で、2ページ目の画像のFlateDecodeのデータが壊れている。)
+    fn test_summarize_broken_image() {
+        let output_dir = Path::new("test_pdf/broken_image_out");
+        let pdf_file_paths = vec![PathBuf::from("test_pdf/broken_image_pdf/broken_image.pdf")];
+        let result = Extractor::new()
+            .output_dir(output_dir)
+            .extract_files(&pdf_file_paths);
+        let _ = std::fs::remove_dir_all(output_dir);
+        let report = result.expect("PDF FILE PROCESS ERROR.");
+        assert_eq!(report.pdf_files, 1);
+        assert_eq!(report.images_written, 1);
+        assert!(!report.errors.is_empty());
+        assert_eq!(summarize(Ok(report)), 1);
+    }
+
+    #[test_log::test]
+    ///出力先ディレクトリを指定した場合は、入力ディレクトリからの相対パスを保持して保存されることを確認する。
+    fn test_start_output_dir() {
+        let directory_path = Path::new("test_pdf/correct_pdf");
+        let output_dir = Path::new("test_pdf/correct_pdf_out");
+        let result = start(directory_path, &Extractor::new().output_dir(output_dir));
+        let exists = output_dir.join("aaa").is_dir();
+        std::fs::remove_dir_all(output_dir).expect("COULD NOT REMOVE DIRECTORY.");
+        assert_eq!(result, 0);
+        assert!(exists);
+    }
+
+    #[test_log::test]
+    ///PDFファイルを直接指定した場合は、指定したファイルのみ処理されることを確認する。
+    fn test_start_files() {
+        let output_dir = Path::new("test_pdf/file_list_out");
+        let pdf_file_paths = vec![
+            PathBuf::from("test_pdf/correct_pdf/aaa.pdf"),
+            PathBuf::from("test_pdf/correct_pdf/./aaa.pdf"),
+        ];
+        let result = start_files(&pdf_file_paths, &Extractor::new().output_dir(output_dir));
+        let exists = output_dir.join("aaa").is_dir();
+        std::fs::remove_dir_all(output_dir).expect("COULD NOT REMOVE DIRECTORY.");
+        assert_eq!(result, 0);
+        assert!(exists);
+        assert_eq!(
+            start_files(&[PathBuf::from("test_pdf/missing.pdf")], &Extractor::new()),
+            1
+        );
+    }
+
+    #[test_log::test]
+    ///暗号化されたPDFファイルのパスワードが誤っている場合・指定されていない場合は27が返ることを確認する。
+    ///(パスワードが正しい場合は0が返る。)
+    fn test_start_files_encrypted() {
+        let output_dir = Path::new("test_pdf/encrypted_out");
+        let pdf_file_paths = vec![PathBuf::from("test_pdf/encrypted_pdf/encrypted.pdf")];
+        let extractor = || Extractor::new().output_dir(output_dir);
+        let missing = start_files(&pdf_file_paths, &extractor());
+        let wrong = start_files(&pdf_file_paths, &extractor().password("wrong"));
+        let mut password_map = PasswordMap::new();
+        password_map.insert("encrypted.pdf", "secret");
+        let correct = start_files(&pdf_file_paths, &extractor().password_map(password_map));
+        let _ = std::fs::remove_dir_all(output_dir);
+        assert_eq!(missing, 27);
+        assert_eq!(wrong, 27);
+        assert_eq!(correct, 0);
+        //パスワード以外のエラーを含む場合は1が返る。
+        let pdf_file_paths = vec![
+            PathBuf::from("test_pdf/encrypted_pdf/encrypted.pdf"),
+            PathBuf::from("test_pdf/missing.pdf"),
+        ];
+        assert_eq!(start_files(&pdf_file_paths, &Extractor::new()), 1);
+    }
+
+    #[test]
+    fn test_read_path_list() {
+        let list: &[u8] = b"a.pdf\n\n  dir/b c.pdf \r\n";
+        let paths = read_path_list(list).unwrap();
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("a.pdf"), PathBuf::from("dir/b c.pdf")]
+        );
+        let list_path = std::env::temp_dir().join(format!(
+            "pdf_to_image_rs_file_list_{}.txt",
+            std::process::id()
+        ));
+        std::fs::write(&list_path, "c.pdf\n").unwrap();
+        let paths = collect_pdf_file_paths(
+            &["a.pdf".to_string(), "b.pdf".to_string()],
+            list_path.to_str(),
+        );
+        std::fs::remove_file(&list_path).unwrap();
+        assert_eq!(
+            paths.unwrap(),
+            vec![
+                PathBuf::from("a.pdf"),
+                PathBuf::from("b.pdf"),
+                PathBuf::from("c.pdf")
+            ]
+        );
+        assert!(collect_pdf_file_paths(&[], Some("path/to/missing.txt")).is_err());
+    }
+
+    #[test]
+    fn test_read_password_file() {
+        let password_path = std::env::temp_dir().join(format!(
+            "pdf_to_image_rs_password_{}.txt",
+            std::process::id()
+        ));
+        std::fs::write(&password_path, "p@ss word\r\nsecond line\n").unwrap();
+        let password = read_password_file(&password_path);
+        std::fs::remove_file(&password_path).unwrap();
+        assert_eq!(password.unwrap(), "p@ss word");
+        assert!(read_password_file(Path::new("path/to/missing.txt")).is_err());
+    }
+
+    #[test]
+    fn test_parse_image_filter_values() {
+        assert_eq!(parse_byte_size("512"), Ok(512));
+        assert_eq!(parse_byte_size("10k"), Ok(10 * 1024));
+        assert_eq!(parse_byte_size("2MB"), Ok(2 * 1024 * 1024));
+        assert!(parse_byte_size("-1").is_err());
+        assert!(parse_byte_size("K").is_err());
+        assert_eq!(parse_aspect_ratio("1.5"), Ok(1.5));
+        assert_eq!(parse_aspect_ratio("1:2"), Ok(0.5));
+        assert!(parse_aspect_ratio("16:0").is_err());
+        assert!(parse_aspect_ratio("0").is_err());
+
+        let args = Args::parse_from([
+            "pdf_to_image_rs",
+            "a.pdf",
+            "--min-width",
+            "16",
+            "--max-aspect",
+            "4:1",
+        ]);
+        let image_filter = image_filter_from_args(&args);
+        assert!(image_filter.matches(16, 16, 1));
+        assert!(!image_filter.matches(15, 16, 1));
+        assert!(!image_filter.matches(100, 20, 1));
+    }
+
+    #[test_log::test]
+    fn test_start_non_existing_directory() {
+        let directory_path = Path::new("path/to/non_existing_directory");
+        let result = start(directory_path, &Extractor::new());
+        assert_eq!(result, 10);
+    }
+}
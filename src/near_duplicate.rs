@@ -0,0 +1,109 @@
+use crate::perceptual_hash::hamming_distance;
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+///近似した画像と判定する知覚ハッシュのハミング距離の既定値。
+pub const DEFAULT_NEAR_DUPLICATE_THRESHOLD: u32 = 5;
+
+///近似した画像(再圧縮された同じ画像など)が見つかった場合の動作。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NearDuplicateAction {
+    ///画像を保存し、マニフェストに近似した画像であることを記録する。
+    #[default]
+    Flag,
+    ///画像を保存せず、マニフェストに近似した画像であることを記録する。
+    Skip,
+}
+
+impl FromStr for NearDuplicateAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "flag" => Ok(NearDuplicateAction::Flag),
+            "skip" => Ok(NearDuplicateAction::Skip),
+            _ => Err(format!(
+                "UNSUPPORTED NEAR DUPLICATE ACTION: {} (flag|skip)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for NearDuplicateAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name: &str = match self {
+            NearDuplicateAction::Flag => "flag",
+            NearDuplicateAction::Skip => "skip",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+///近似した画像をまとめたクラスタの識別子を作成する。(クラスタの最初の画像のページ番号とページ内の番号。)
+pub(crate) fn cluster_id(page: u64, index: u64) -> String {
+    format!("{:06}_{:06}", page, index)
+}
+
+///同じPDFファイル内の画像の知覚ハッシュと、その画像が属するクラスタ。
+///(クラスタの最初の画像の知覚ハッシュのみを保持し、以降の画像はそれとの距離で判定する。)
+#[derive(Debug, Default)]
+pub(crate) struct NearDuplicateIndex {
+    clusters: Mutex<Vec<(u64, String)>>,
+}
+
+impl NearDuplicateIndex {
+    ///近似した画像のクラスタを検索する。
+    /// # Arguments
+    /// * `hash` - 画像の知覚ハッシュ。
+    /// * `threshold` - 近似した画像と判定するハミング距離の最大値。
+    /// # Returns
+    /// * 近似した画像がある場合:最も距離が近いクラスタの識別子。
+    /// * 近似した画像がない場合:None。
+    pub(crate) fn find(&self, hash: u64, threshold: u32) -> Option<String> {
+        let clusters = self.clusters.lock().unwrap_or_else(|e| e.into_inner());
+        clusters
+            .iter()
+            .map(|(cluster_hash, id)| (hamming_distance(hash, *cluster_hash), id))
+            .filter(|(distance, _)| *distance <= threshold)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, id)| id.clone())
+    }
+
+    ///新しいクラスタを登録する。(保存した画像のみを登録する。)
+    /// # Arguments
+    /// * `hash` - クラスタの最初の画像の知覚ハッシュ。
+    /// * `cluster_id` - クラスタの識別子。
+    pub(crate) fn insert(&self, hash: u64, cluster_id: String) {
+        let mut clusters = self.clusters.lock().unwrap_or_else(|e| e.into_inner());
+        clusters.push((hash, cluster_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_near_duplicate_index() {
+        let index = NearDuplicateIndex::default();
+        assert_eq!(index.find(0, 2), None);
+        index.insert(0, cluster_id(1, 1));
+        assert_eq!(index.find(u64::MAX, 2), None);
+        index.insert(u64::MAX, cluster_id(1, 2));
+        assert_eq!(index.find(0b11, 2), Some("000001_000001".to_string()));
+        assert_eq!(index.find(0b111, 2), None);
+        assert_eq!(
+            index.find(u64::MAX - 1, 2),
+            Some("000001_000002".to_string())
+        );
+        assert_eq!(
+            "Skip".parse::<NearDuplicateAction>(),
+            Ok(NearDuplicateAction::Skip)
+        );
+        assert!("drop".parse::<NearDuplicateAction>().is_err());
+        assert_eq!(NearDuplicateAction::Flag.to_string(), "flag");
+    }
+}
@@ -0,0 +1,236 @@
+use crate::apply_image_mask::apply_image_mask;
+use crate::decode_ccitt::ccitt_tiff;
+use crate::decode_jpeg::jpeg_needs_color_conversion;
+use crate::decode_raw_image::decode_image;
+
+use image::{DynamicImage, ImageFormat};
+use log::warn;
+use pdf::enc::StreamFilter;
+use pdf::error::PdfError;
+use pdf::object::{ImageXObject, Resolve};
+use std::fmt;
+use std::io::Cursor;
+use std::str::FromStr;
+use std::sync::Arc;
+
+///保存する画像の形式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    ///PDFに埋め込まれている形式のまま保存する。(展開が必要な画像はPNGで保存する。)
+    #[default]
+    Original,
+    Png,
+    Jpg,
+    Webp,
+    Tiff,
+}
+
+impl OutputFormat {
+    ///保存するファイルの拡張子を取得する。(Originalの場合はNone。)
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            OutputFormat::Original => None,
+            OutputFormat::Png => Some("png"),
+            OutputFormat::Jpg => Some("jpg"),
+            OutputFormat::Webp => Some("webp"),
+            OutputFormat::Tiff => Some("tiff"),
+        }
+    }
+
+    ///画像をこの形式でエンコードする。
+    /// # Arguments
+    /// * `image` - エンコードする画像。
+    /// # Returns
+    /// * 成功時:エンコードしたバイト列。
+    /// * 失敗時:PdfError。
+    pub(crate) fn encode(&self, image: &DynamicImage) -> Result<Vec<u8>, PdfError> {
+        let (format, image): (ImageFormat, DynamicImage) = match self {
+            OutputFormat::Original | OutputFormat::Png => (ImageFormat::Png, image.clone()),
+            //JPEGはアルファチャンネルと16bitに対応していないため、8bitのRGBもしくはグレーに変換する。
+            OutputFormat::Jpg => (ImageFormat::Jpeg, to_8bit_opaque(image)),
+            //WebPのエンコーダは8bitのみに対応している。
+            OutputFormat::Webp => (ImageFormat::WebP, to_8bit(image)),
+            OutputFormat::Tiff => (ImageFormat::Tiff, image.clone()),
+        };
+        let mut encoded: Vec<u8> = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut encoded), format)
+            .map_err(|e| PdfError::Other {
+                msg: format!("COULD NOT ENCODE IMAGE. FORMAT: {:?} ERR: {}", format, e),
+            })?;
+        Ok(encoded)
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "original" => Ok(OutputFormat::Original),
+            "png" => Ok(OutputFormat::Png),
+            "jpg" | "jpeg" => Ok(OutputFormat::Jpg),
+            "webp" => Ok(OutputFormat::Webp),
+            "tiff" | "tif" => Ok(OutputFormat::Tiff),
+            _ => Err(format!(
+                "UNSUPPORTED OUTPUT FORMAT: {} (png|jpg|webp|tiff|original)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.extension() {
+            Some(ext) => write!(f, "{}", ext),
+            None => write!(f, "original"),
+        }
+    }
+}
+
+///保存するデータと拡張子。
+pub(crate) type ConvertedImage = (Arc<[u8]>, &'static str);
+
+///画像XObjectのデータを保存する形式に変換する。
+/// # Arguments
+/// * `img` - 画像XObject。
+/// * `data` - `raw_image_data()`で取得した画像データ。
+/// * `filter` - `raw_image_data()`で取得した未適用のフィルタ。
+/// * `output_format` - 保存する画像の形式。
+/// # Returns
+/// * 成功時:保存するデータと拡張子。(展開した画像は色空間に従ってsRGBもしくはグレーに変換される。)
+///   (JPXDecode・展開に失敗したJBIG2Decodeの画像は展開できないため、変換せずに元の形式で返す。)
+///   (CCITTFaxDecodeの画像は展開せずにTIFFに格納して返す。展開する場合は事前に展開しておく。)
+/// * 失敗時:PdfError(画像データの展開もしくはエンコードの失敗)。
+pub(crate) fn convert_image(
+    img: &ImageXObject,
+    data: &Arc<[u8]>,
+    filter: Option<&StreamFilter>,
+    output_format: OutputFormat,
+) -> Result<ConvertedImage, PdfError> {
+    //Originalの場合に展開した画像を保存する形式。
+    let (source_ext, source_format): (&'static str, OutputFormat) = match filter {
+        Some(StreamFilter::DCTDecode(_)) => {
+            //JPEGのまま保存する場合は再圧縮しない。(CMYKなど、そのままでは正しい色で表示されないJPEGを除く。)
+            if matches!(output_format, OutputFormat::Original | OutputFormat::Jpg)
+                && !jpeg_needs_color_conversion(img, data)
+            {
+                return Ok((Arc::clone(data), "jpg"));
+            }
+            ("jpg", OutputFormat::Jpg)
+        }
+        Some(StreamFilter::CCITTFaxDecode(params)) => {
+            return Ok((ccitt_tiff(img, data, params)?.into(), "tiff"));
+        }
+        Some(StreamFilter::JBIG2Decode(_)) => ("jbig2", OutputFormat::Png),
+        Some(StreamFilter::JPXDecode) => ("jp2k", OutputFormat::Png),
+        _ => ("png", OutputFormat::Png),
+    };
+    let decoded: Option<DynamicImage> = decode_image(img, data, filter)?;
+
+    match (decoded, output_format.extension()) {
+        (Some(image), Some(ext)) => Ok((output_format.encode(&image)?.into(), ext)),
+        (Some(image), None) => Ok((source_format.encode(&image)?.into(), source_ext)),
+        (None, Some(ext)) => {
+            warn!(
+                "COULD NOT CONVERT IMAGE. IT SAVED IN ORIGINAL FORMAT. ORIGINAL: {} REQUESTED: {}",
+                source_ext, ext
+            );
+            Ok((Arc::clone(data), source_ext))
+        }
+        (None, None) => Ok((Arc::clone(data), source_ext)),
+    }
+}
+
+///画像XObjectにSMaskもしくはMaskを適用し、アルファチャンネル付きの画像として保存する形式に変換する。
+/// # Arguments
+/// * `img` - 画像XObject。
+/// * `data` - `raw_image_data()`で取得した画像データ。
+/// * `filter` - `raw_image_data()`で取得した未適用のフィルタ。
+/// * `resolve` - マスク画像の参照の解決に使用するリゾルバ。
+/// * `output_format` - 保存する画像の形式。
+///   (アルファチャンネルに対応していないJPEGとOriginalの場合はPNGで保存する。)
+/// # Returns
+/// * 成功時:保存するデータと拡張子。(画像もしくはマスクが展開できない形式の場合はNone。)
+/// * 失敗時:PdfError(画像データ・マスクの展開もしくはエンコードの失敗)。
+pub(crate) fn convert_masked_image(
+    img: &ImageXObject,
+    data: &Arc<[u8]>,
+    filter: Option<&StreamFilter>,
+    resolve: &impl Resolve,
+    output_format: OutputFormat,
+) -> Result<Option<ConvertedImage>, PdfError> {
+    let base: DynamicImage = match decode_image(img, data, filter)? {
+        Some(base) => base,
+        None => return Ok(None),
+    };
+    let masked: DynamicImage = match apply_image_mask(img, &base, data, filter, resolve)? {
+        Some(masked) => DynamicImage::ImageRgba8(masked),
+        None => return Ok(None),
+    };
+    let output_format: OutputFormat = match output_format {
+        OutputFormat::Webp | OutputFormat::Tiff => output_format,
+        _ => OutputFormat::Png,
+    };
+    let ext: &'static str = output_format.extension().unwrap_or("png");
+    Ok(Some((output_format.encode(&masked)?.into(), ext)))
+}
+
+///画像を8bitの画像に変換する。
+fn to_8bit(image: &DynamicImage) -> DynamicImage {
+    match image {
+        DynamicImage::ImageLuma8(_)
+        | DynamicImage::ImageLumaA8(_)
+        | DynamicImage::ImageRgb8(_)
+        | DynamicImage::ImageRgba8(_) => image.clone(),
+        _ if image.color().has_alpha() => DynamicImage::ImageRgba8(image.to_rgba8()),
+        _ if image.color().has_color() => DynamicImage::ImageRgb8(image.to_rgb8()),
+        _ => DynamicImage::ImageLuma8(image.to_luma8()),
+    }
+}
+
+///画像をアルファチャンネルのない8bitの画像に変換する。
+fn to_8bit_opaque(image: &DynamicImage) -> DynamicImage {
+    match image {
+        DynamicImage::ImageLuma8(_) | DynamicImage::ImageRgb8(_) => image.clone(),
+        _ if image.color().has_color() => DynamicImage::ImageRgb8(image.to_rgb8()),
+        _ => DynamicImage::ImageLuma8(image.to_luma8()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+
+    #[test]
+    fn test_output_format_from_str() {
+        assert_eq!("png".parse::<OutputFormat>(), Ok(OutputFormat::Png));
+        assert_eq!("JPEG".parse::<OutputFormat>(), Ok(OutputFormat::Jpg));
+        assert_eq!("webp".parse::<OutputFormat>(), Ok(OutputFormat::Webp));
+        assert_eq!("tif".parse::<OutputFormat>(), Ok(OutputFormat::Tiff));
+        assert_eq!(
+            "original".parse::<OutputFormat>(),
+            Ok(OutputFormat::Original)
+        );
+        assert!("bmp".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn test_output_format_encode() {
+        let image = DynamicImage::ImageRgb16(ImageBuffer::from_pixel(4, 4, Rgb([0, 32768, 65535])));
+        let formats = [
+            (OutputFormat::Png, ImageFormat::Png),
+            (OutputFormat::Jpg, ImageFormat::Jpeg),
+            (OutputFormat::Webp, ImageFormat::WebP),
+            (OutputFormat::Tiff, ImageFormat::Tiff),
+        ];
+        for (output_format, image_format) in formats {
+            let encoded = output_format.encode(&image).unwrap();
+            assert_eq!(image::guess_format(&encoded).unwrap(), image_format);
+            let decoded = image::load_from_memory(&encoded).unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (4, 4));
+        }
+    }
+}
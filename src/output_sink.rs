@@ -0,0 +1,235 @@
+use crate::archive_format::ArchiveFormat;
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use zip::write::{SimpleFileOptions, StreamWriter};
+use zip::{CompressionMethod, ZipWriter};
+
+///アーカイブに格納するファイルの更新日時(1980-01-01T00:00:00Z)。
+///(書き込んだ日時ではなく固定の日時とする。zip形式で表せる最も古い日時。)
+///(ファイルを格納する順序はArchiveFormat::Cbz以外はページの処理が終了した順のため、同じPDFファイルから同じアーカイブが作成されるとは限らない。)
+const ARCHIVE_ENTRY_MTIME: u64 = 315_532_800;
+
+///ページ順に格納するため書き込みの終了まで保持するファイル。(ページ番号とアーカイブ内のパスの順に並べる。)
+type PendingFiles = BTreeMap<(u64, String), Vec<u8>>;
+
+///画像ファイル・マニフェストの書き込み先。
+pub(crate) enum OutputSink {
+    ///保存先ディレクトリにファイルとして書き込む。
+    Directory,
+    ///アーカイブに書き込む。
+    Archive {
+        ///アーカイブ内のパスの基準とするディレクトリ。(このディレクトリからの相対パスで格納する。)
+        base_dir: PathBuf,
+        ///書き込み先のアーカイブ。(複数のスレッドから書き込むためMutexで保護する。書き込みを終了した後はNone。)
+        writer: Mutex<Option<ArchiveWriter>>,
+        ///書き込みの終了まで保持するファイル。(ArchiveFormat::Cbz以外は保持せずに書き込むためNone。)
+        pending: Option<Mutex<PendingFiles>>,
+    },
+}
+
+///書き込み途中のアーカイブ。
+///(書き込み先はシークできなくてもよいよう、zip形式の場合もストリームとして書き込む。)
+pub(crate) enum ArchiveWriter {
+    Tar(tar::Builder<Box<dyn Write + Send>>),
+    Zip(Box<ZipWriter<StreamWriter<Box<dyn Write + Send>>>>),
+}
+
+impl ArchiveWriter {
+    ///ファイルをアーカイブに追加する。
+    /// # Arguments
+    /// * `entry_name` - アーカイブ内のパス("/"区切り)。
+    /// * `data` - ファイルの内容。
+    fn append(&mut self, entry_name: &str, data: &[u8]) -> std::io::Result<()> {
+        match self {
+            ArchiveWriter::Tar(builder) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(ARCHIVE_ENTRY_MTIME);
+                builder.append_data(&mut header, entry_name, data)
+            }
+            ArchiveWriter::Zip(zip) => {
+                //zip::DateTime::default()はARCHIVE_ENTRY_MTIMEと同じ1980-01-01 00:00:00。
+                let options = SimpleFileOptions::default()
+                    .compression_method(CompressionMethod::Stored)
+                    .unix_permissions(0o644)
+                    .large_file(data.len() as u64 >= u32::MAX as u64)
+                    .last_modified_time(zip::DateTime::default());
+                zip.start_file(entry_name, options)
+                    .map_err(std::io::Error::other)?;
+                zip.write_all(data)
+            }
+        }
+    }
+}
+
+impl OutputSink {
+    ///アーカイブに書き込む出力先を作成する。
+    /// # Arguments
+    /// * `archive_format` - アーカイブの形式。
+    /// * `base_dir` - アーカイブ内のパスの基準とするディレクトリ。
+    /// * `writer` - アーカイブの書き込み先(標準出力・ファイルなど)。
+    pub(crate) fn archive(
+        archive_format: ArchiveFormat,
+        base_dir: &Path,
+        writer: Box<dyn Write + Send>,
+    ) -> Self {
+        let (writer, pending) = match archive_format {
+            ArchiveFormat::Tar => (ArchiveWriter::Tar(tar::Builder::new(writer)), None),
+            ArchiveFormat::Zip => (
+                ArchiveWriter::Zip(Box::new(ZipWriter::new_stream(writer))),
+                None,
+            ),
+            //ページは並列に処理するため、書き込みの終了時にページ順に並べ替えて格納する。
+            ArchiveFormat::Cbz => (
+                ArchiveWriter::Zip(Box::new(ZipWriter::new_stream(writer))),
+                Some(Mutex::new(PendingFiles::new())),
+            ),
+        };
+        OutputSink::Archive {
+            base_dir: base_dir.to_path_buf(),
+            writer: Mutex::new(Some(writer)),
+            pending,
+        }
+    }
+
+    ///ファイルを書き込む。
+    /// # Arguments
+    /// * `path` - ファイルのパス。(アーカイブの場合は基準とするディレクトリからの相対パスで格納する。)
+    /// * `page` - ファイルのページ番号。(ArchiveFormat::Cbzの格納順に使用する。ページに属さないファイルはu64::MAX。)
+    /// * `data` - ファイルの内容。
+    pub(crate) fn write_file(&self, path: &Path, page: u64, data: &[u8]) -> std::io::Result<()> {
+        let (base_dir, writer, pending) = match self {
+            OutputSink::Directory => return std::fs::write(path, data),
+            OutputSink::Archive {
+                base_dir,
+                writer,
+                pending,
+            } => (base_dir, writer, pending),
+        };
+        let entry_name: String = path
+            .strip_prefix(base_dir)
+            .unwrap_or(path)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<String>>()
+            .join("/");
+        //パニックしたスレッドがロックを保持していた場合も書き込みは続ける。
+        let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+        let writer: &mut ArchiveWriter = match writer.as_mut() {
+            Some(writer) => writer,
+            None => return Err(std::io::Error::other("ARCHIVE IS ALREADY FINISHED.")),
+        };
+        match pending {
+            Some(pending) => {
+                pending
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert((page, entry_name), data.to_vec());
+                Ok(())
+            }
+            None => writer.append(&entry_name, data),
+        }
+    }
+
+    ///アーカイブの書き込みを終了する。(保持していたファイルと終端のレコードを書き込み、書き込み先をフラッシュする。)
+    ///(ディレクトリに書き込む場合は何もしない。)
+    pub(crate) fn finish(&self) -> std::io::Result<()> {
+        let (writer, pending) = match self {
+            OutputSink::Directory => return Ok(()),
+            OutputSink::Archive {
+                writer, pending, ..
+            } => (writer, pending),
+        };
+        let mut writer = match writer.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            Some(writer) => writer,
+            None => return Ok(()),
+        };
+        if let Some(pending) = pending {
+            let pending: PendingFiles =
+                std::mem::take(&mut *pending.lock().unwrap_or_else(|e| e.into_inner()));
+            for ((_, entry_name), data) in pending {
+                writer.append(&entry_name, &data)?;
+            }
+        }
+        let mut inner: Box<dyn Write + Send> = match writer {
+            ArchiveWriter::Tar(builder) => builder.into_inner()?,
+            ArchiveWriter::Zip(zip) => zip.finish().map_err(std::io::Error::other)?.into_inner(),
+        };
+        inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    ///書き込んだ内容を共有するバッファ。(アーカイブの書き込み先として使用する。)
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_output_sink_archive() {
+        for archive_format in [ArchiveFormat::Tar, ArchiveFormat::Zip, ArchiveFormat::Cbz] {
+            let buffer = SharedBuffer::default();
+            let sink = OutputSink::archive(
+                archive_format,
+                Path::new("out/doc"),
+                Box::new(buffer.clone()),
+            );
+            sink.write_file(Path::new("out/doc/p10.png"), 10, b"image")
+                .unwrap();
+            sink.write_file(Path::new("out/doc/manifest.jsonl"), u64::MAX, b"{}\n")
+                .unwrap();
+            sink.write_file(Path::new("out/doc/p9.png"), 9, b"image")
+                .unwrap();
+            sink.finish().unwrap();
+            assert!(sink.write_file(Path::new("out/doc/b.png"), 1, b"").is_err());
+            let data: Vec<u8> = buffer.0.lock().unwrap().clone();
+
+            //ファイルの更新日時は書き込んだ日時ではなく固定の日時になる。
+            let names: Vec<String> = match archive_format {
+                ArchiveFormat::Tar => tar::Archive::new(data.as_slice())
+                    .entries()
+                    .unwrap()
+                    .map(|entry| {
+                        let entry = entry.unwrap();
+                        assert_eq!(entry.header().mtime().unwrap(), ARCHIVE_ENTRY_MTIME);
+                        entry.path().unwrap().display().to_string()
+                    })
+                    .collect(),
+                ArchiveFormat::Zip | ArchiveFormat::Cbz => {
+                    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+                    (0..archive.len())
+                        .map(|i| {
+                            let file = archive.by_index(i).unwrap();
+                            assert_eq!(file.last_modified(), Some(zip::DateTime::default()));
+                            file.name().to_string()
+                        })
+                        .collect()
+                }
+            };
+            //CBZ形式の場合はページ順に格納される。
+            let expected: Vec<&str> = match archive_format {
+                ArchiveFormat::Cbz => vec!["p9.png", "p10.png", "manifest.jsonl"],
+                _ => vec!["p10.png", "manifest.jsonl", "p9.png"],
+            };
+            assert_eq!(names, expected, "{}", archive_format);
+        }
+    }
+}
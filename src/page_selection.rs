@@ -0,0 +1,154 @@
+use std::fmt;
+use std::str::FromStr;
+
+///処理するページの指定。(カンマ区切りで複数指定した場合は、いずれかに該当するページを処理する。)
+///* `N` - Nページ目。
+///* `N-M` - NページからMページまで。
+///* `N-` - Nページから最後のページまで。
+///* `-M` - 最初のページからMページまで。
+///* `odd`・`even` - 奇数ページ・偶数ページ。
+///* `last:N` - 最後のNページ。
+///
+/// # Examples
+/// ```
+/// use pdf_to_image_rs::PageSelection;
+///
+/// let selection: PageSelection = "1-3,10,last:2".parse().unwrap();
+/// assert_eq!(selection.pages(12), vec![1, 2, 3, 10, 11, 12]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageSelection {
+    pattern: String,
+    ranges: Vec<PageRange>,
+}
+
+///ページの指定の1つの要素。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PageRange {
+    ///開始ページから終了ページまで。(終了ページがNoneの場合は最後のページまで。)
+    Range(u64, Option<u64>),
+    Odd,
+    Even,
+    ///最後のNページ。
+    Last(u64),
+}
+
+impl PageRange {
+    ///ページが該当すればtrueを返す。
+    /// # Arguments
+    /// * `page` - ページ番号(1始まり)。
+    /// * `page_count` - PDFファイルのページ数。
+    fn contains(&self, page: u64, page_count: u64) -> bool {
+        match *self {
+            PageRange::Range(start, end) => start <= page && end.is_none_or(|end| page <= end),
+            PageRange::Odd => !page.is_multiple_of(2),
+            PageRange::Even => page.is_multiple_of(2),
+            PageRange::Last(count) => page > page_count.saturating_sub(count),
+        }
+    }
+}
+
+impl PageSelection {
+    ///処理するページ番号を取得する。(PDFファイルのページ数を超えるページは含まない。)
+    /// # Arguments
+    /// * `page_count` - PDFファイルのページ数。
+    /// # Returns
+    /// * 該当するページ番号(1始まり)を昇順に並べたもの。
+    pub fn pages(&self, page_count: u64) -> Vec<u64> {
+        (1..=page_count)
+            .filter(|page| {
+                self.ranges
+                    .iter()
+                    .any(|range| range.contains(*page, page_count))
+            })
+            .collect()
+    }
+}
+
+///ページ番号を解析する。(1以上の整数のみ受け付ける。)
+fn parse_page_number(s: &str, pattern: &str) -> Result<u64, String> {
+    match s.trim().parse::<u64>() {
+        Ok(page) if page > 0 => Ok(page),
+        _ => Err(format!("INVALID PAGE NUMBER: {} IN {}", s, pattern)),
+    }
+}
+
+impl FromStr for PageSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ranges: Vec<PageRange> = Vec::new();
+        for token in s.split(',').map(|token| token.trim()) {
+            let lower: String = token.to_ascii_lowercase();
+            let range: PageRange = if token.is_empty() {
+                return Err(format!("EMPTY PAGE RANGE IN {}", s));
+            } else if lower == "odd" {
+                PageRange::Odd
+            } else if lower == "even" {
+                PageRange::Even
+            } else if let Some(count) = lower.strip_prefix("last") {
+                PageRange::Last(parse_page_number(count.trim_start_matches(':'), s)?)
+            } else if let Some((start, end)) = token.split_once('-') {
+                let start: u64 = match start.trim() {
+                    "" => 1,
+                    start => parse_page_number(start, s)?,
+                };
+                let end: Option<u64> = match end.trim() {
+                    "" if start == 1 && token.trim_start().starts_with('-') => {
+                        return Err(format!("INVALID PAGE RANGE: {} IN {}", token, s));
+                    }
+                    "" => None,
+                    end => Some(parse_page_number(end, s)?),
+                };
+                if end.is_some_and(|end| end < start) {
+                    return Err(format!("INVALID PAGE RANGE: {} IN {}", token, s));
+                }
+                PageRange::Range(start, end)
+            } else {
+                let page: u64 = parse_page_number(token, s)?;
+                PageRange::Range(page, Some(page))
+            };
+            ranges.push(range);
+        }
+        Ok(PageSelection {
+            pattern: s.to_string(),
+            ranges,
+        })
+    }
+}
+
+impl fmt::Display for PageSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pages(pattern: &str, page_count: u64) -> Vec<u64> {
+        pattern.parse::<PageSelection>().unwrap().pages(page_count)
+    }
+
+    #[test]
+    fn test_page_selection() {
+        assert_eq!(pages("1-5,10,20-", 22), vec![1, 2, 3, 4, 5, 10, 20, 21, 22]);
+        assert_eq!(pages("-3", 10), vec![1, 2, 3]);
+        assert_eq!(pages("odd", 6), vec![1, 3, 5]);
+        assert_eq!(pages("EVEN", 5), vec![2, 4]);
+        assert_eq!(pages("last:3", 10), vec![8, 9, 10]);
+        assert_eq!(pages("last 2, 1", 10), vec![1, 9, 10]);
+        assert_eq!(pages("last:5", 3), vec![1, 2, 3]);
+        //ページ数を超えるページは含まない。
+        assert_eq!(pages("8-12", 9), vec![8, 9]);
+        assert!(pages("15", 9).is_empty());
+        for invalid in ["", "0", "1,,2", "5-3", "-", "a", "last:0", "1-x"] {
+            assert!(invalid.parse::<PageSelection>().is_err(), "{}", invalid);
+        }
+        assert_eq!(
+            "1-5,odd".parse::<PageSelection>().unwrap().to_string(),
+            "1-5,odd"
+        );
+    }
+}
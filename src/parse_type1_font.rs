@@ -0,0 +1,724 @@
+use pdf::error::PdfError;
+use std::collections::HashMap;
+use ttf_parser::OutlineBuilder;
+
+///eexec暗号化の鍵。
+const EEXEC_KEY: u16 = 55665;
+///CharStringの暗号化の鍵。
+const CHARSTRING_KEY: u16 = 4330;
+///サブルーチン呼び出しの最大の深さ。
+const MAX_CALL_DEPTH: usize = 10;
+
+///埋め込まれたType1フォント(FontFile)のグリフの情報。
+pub(crate) struct Type1Font {
+    ///グリフ空間からテキスト空間への変換行列。
+    pub(crate) font_matrix: [f32; 6],
+    ///フォント内蔵のエンコーディング(コードとグリフ名)。(StandardEncodingの場合はNone。)
+    encoding: Option<HashMap<u8, String>>,
+    ///グリフ名ごとの復号済みのCharString。
+    char_strings: HashMap<String, Vec<u8>>,
+    ///復号済みのサブルーチン。
+    subrs: Vec<Vec<u8>>,
+}
+
+impl Type1Font {
+    ///Type1フォントのデータを解析する。(PFB形式のセグメントヘッダにも対応する。)
+    /// # Arguments
+    /// * `data` - FontFileストリームのデータ。
+    /// # Returns
+    /// * 成功時:解析したフォント。
+    /// * 失敗時:PdfError(eexec暗号化部分もしくはCharStringsが見つからない)。
+    pub(crate) fn parse(data: &[u8]) -> Result<Self, PdfError> {
+        let data: Vec<u8> = strip_pfb_headers(data);
+        let eexec_pos: usize = find(&data, b"eexec", 0).ok_or_else(|| PdfError::Other {
+            msg: "COULD NOT FIND EEXEC SECTION IN TYPE1 FONT.".to_string(),
+        })?;
+        let clear_text: &[u8] = &data[..eexec_pos];
+        let mut encrypted_pos: usize = eexec_pos + 5;
+        while encrypted_pos < data.len() && data[encrypted_pos].is_ascii_whitespace() {
+            encrypted_pos += 1;
+        }
+        let private: Vec<u8> = decrypt(&to_binary(&data[encrypted_pos..]), EEXEC_KEY, 4);
+
+        let len_iv: i32 = find(&private, b"/lenIV", 0)
+            .and_then(|pos| Tokenizer::new(&private, pos + 6).next_number())
+            .map(|len_iv| len_iv as i32)
+            .unwrap_or(4);
+        let decrypt_char_string = |encrypted: &[u8]| -> Vec<u8> {
+            if len_iv < 0 {
+                encrypted.to_vec()
+            } else {
+                decrypt(encrypted, CHARSTRING_KEY, len_iv as usize)
+            }
+        };
+
+        let mut subrs: Vec<Vec<u8>> = Vec::new();
+        if let Some(pos) = find(&private, b"/Subrs", 0) {
+            let mut tokens = Tokenizer::new(&private, pos + 6);
+            let count: usize = tokens.next_number().unwrap_or(0.0) as usize;
+            subrs.resize(count.min(65536), Vec::new());
+            while tokens.next_token() == Some(b"dup".as_slice()) {
+                let index: Option<f32> = tokens.next_number();
+                let binary: Option<&[u8]> = tokens.next_binary();
+                if let (Some(index), Some(binary)) = (index, binary) {
+                    if let Some(subr) = subrs.get_mut(index as usize) {
+                        *subr = decrypt_char_string(binary);
+                    }
+                }
+                tokens.skip_until(&[b"NP", b"|", b"put"]);
+            }
+        }
+
+        let mut char_strings: HashMap<String, Vec<u8>> = HashMap::new();
+        let char_strings_pos: usize =
+            find(&private, b"/CharStrings", 0).ok_or_else(|| PdfError::Other {
+                msg: "COULD NOT FIND CHARSTRINGS IN TYPE1 FONT.".to_string(),
+            })?;
+        let mut tokens = Tokenizer::new(&private, char_strings_pos + 12);
+        tokens.skip_until(&[b"begin"]);
+        while let Some(token) = tokens.next_token() {
+            let name: &[u8] = match token.strip_prefix(b"/") {
+                Some(name) => name,
+                None => break,
+            };
+            let name: String = String::from_utf8_lossy(name).into_owned();
+            match tokens.next_binary() {
+                Some(binary) => {
+                    char_strings.insert(name, decrypt_char_string(binary));
+                }
+                None => break,
+            }
+            tokens.skip_until(&[b"ND", b"|-", b"def"]);
+        }
+
+        Ok(Type1Font {
+            font_matrix: parse_font_matrix(clear_text)
+                .unwrap_or([0.001, 0.0, 0.0, 0.001, 0.0, 0.0]),
+            encoding: parse_encoding(clear_text),
+            char_strings,
+            subrs,
+        })
+    }
+
+    ///フォント内蔵のエンコーディングでコードに対応するグリフ名を取得する。
+    ///(StandardEncodingのフォントの場合はNone。)
+    pub(crate) fn builtin_glyph_name(&self, code: u8) -> Option<Option<&str>> {
+        self.encoding
+            .as_ref()
+            .map(|encoding| encoding.get(&code).map(|name| name.as_str()))
+    }
+
+    ///グリフ名のグリフを持っていればtrueを返す。
+    pub(crate) fn has_glyph(&self, name: &str) -> bool {
+        self.char_strings.contains_key(name)
+    }
+
+    ///グリフの輪郭を出力する。
+    /// # Arguments
+    /// * `name` - グリフ名。
+    /// * `builder` - 輪郭の出力先(グリフ空間の座標で出力される)。
+    /// # Returns
+    /// * グリフの送り幅(グリフ空間の単位)。(グリフがない、もしくはCharStringが不正な場合はNone。)
+    pub(crate) fn outline(&self, name: &str, builder: &mut dyn OutlineBuilder) -> Option<f32> {
+        let char_string: &[u8] = self.char_strings.get(name)?;
+        let mut interpreter = CharStringInterpreter::new(self, builder);
+        interpreter.execute(char_string, 0).ok()?;
+        interpreter.finish();
+        Some(interpreter.width)
+    }
+}
+
+///Type1のCharStringを実行して輪郭を出力する。
+struct CharStringInterpreter<'a> {
+    font: &'a Type1Font,
+    builder: &'a mut dyn OutlineBuilder,
+    stack: Vec<f32>,
+    ///callothersubrの結果(popで取り出す値)。
+    ps_stack: Vec<f32>,
+    x: f32,
+    y: f32,
+    ///合成グリフ(seac)のアクセントの位置。
+    offset: (f32, f32),
+    width: f32,
+    path_open: bool,
+    ///Flexの制御点を収集中であればSome。
+    flex_points: Option<Vec<(f32, f32)>>,
+    finished: bool,
+}
+
+impl<'a> CharStringInterpreter<'a> {
+    fn new(font: &'a Type1Font, builder: &'a mut dyn OutlineBuilder) -> Self {
+        CharStringInterpreter {
+            font,
+            builder,
+            stack: Vec::new(),
+            ps_stack: Vec::new(),
+            x: 0.0,
+            y: 0.0,
+            offset: (0.0, 0.0),
+            width: 0.0,
+            path_open: false,
+            flex_points: None,
+            finished: false,
+        }
+    }
+
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+        //Flex中の移動は制御点の収集のみ行う。
+        if self.flex_points.is_none() {
+            self.close();
+            self.builder.move_to(x + self.offset.0, y + self.offset.1);
+            self.path_open = true;
+        }
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+        self.builder.line_to(x + self.offset.0, y + self.offset.1);
+    }
+
+    fn curve_to(&mut self, points: [(f32, f32); 3]) {
+        let (ox, oy) = self.offset;
+        self.builder.curve_to(
+            points[0].0 + ox,
+            points[0].1 + oy,
+            points[1].0 + ox,
+            points[1].1 + oy,
+            points[2].0 + ox,
+            points[2].1 + oy,
+        );
+        (self.x, self.y) = points[2];
+    }
+
+    ///現在の点からの相対座標で3次ベジェ曲線を出力する。
+    fn relative_curve_to(&mut self, d: [f32; 6]) {
+        let p1: (f32, f32) = (self.x + d[0], self.y + d[1]);
+        let p2: (f32, f32) = (p1.0 + d[2], p1.1 + d[3]);
+        let p3: (f32, f32) = (p2.0 + d[4], p2.1 + d[5]);
+        self.curve_to([p1, p2, p3]);
+    }
+
+    fn close(&mut self) {
+        if self.path_open {
+            self.builder.close();
+            self.path_open = false;
+        }
+    }
+
+    fn finish(&mut self) {
+        self.close();
+    }
+
+    ///スタックの先頭からn個の値を取得する。(不足している場合は0とする。)
+    fn args<const N: usize>(&self) -> [f32; N] {
+        let mut args: [f32; N] = [0.0; N];
+        let start: usize = self.stack.len().saturating_sub(N);
+        for (arg, value) in args.iter_mut().zip(self.stack[start..].iter()) {
+            *arg = *value;
+        }
+        args
+    }
+
+    fn execute(&mut self, data: &[u8], depth: usize) -> Result<(), ()> {
+        if depth > MAX_CALL_DEPTH {
+            return Err(());
+        }
+        let mut pos: usize = 0;
+        while pos < data.len() && !self.finished {
+            let v: u8 = data[pos];
+            pos += 1;
+            match v {
+                32..=246 => self.stack.push(v as f32 - 139.0),
+                247..=250 => {
+                    let w: u8 = *data.get(pos).ok_or(())?;
+                    pos += 1;
+                    self.stack
+                        .push(((v as i32 - 247) * 256 + w as i32 + 108) as f32);
+                }
+                251..=254 => {
+                    let w: u8 = *data.get(pos).ok_or(())?;
+                    pos += 1;
+                    self.stack
+                        .push((-(v as i32 - 251) * 256 - w as i32 - 108) as f32);
+                }
+                255 => {
+                    let bytes: &[u8] = data.get(pos..pos + 4).ok_or(())?;
+                    pos += 4;
+                    self.stack
+                        .push(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32);
+                }
+                //callsubr
+                10 => {
+                    let index: f32 = self.stack.pop().ok_or(())?;
+                    let subr: &[u8] = self.font.subrs.get(index as usize).ok_or(())?;
+                    self.execute(subr, depth + 1)?;
+                }
+                //return
+                11 => return Ok(()),
+                12 => {
+                    let escape: u8 = *data.get(pos).ok_or(())?;
+                    pos += 1;
+                    self.execute_escape(escape, depth)?;
+                }
+                _ => self.execute_operator(v)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn execute_operator(&mut self, operator: u8) -> Result<(), ()> {
+        match operator {
+            //hstem・vstem(ヒントは使用しない)
+            1 | 3 => {}
+            //vmoveto
+            4 => {
+                let [dy] = self.args();
+                self.move_to(self.x, self.y + dy);
+            }
+            //rlineto
+            5 => {
+                let [dx, dy] = self.args();
+                self.line_to(self.x + dx, self.y + dy);
+            }
+            //hlineto
+            6 => {
+                let [dx] = self.args();
+                self.line_to(self.x + dx, self.y);
+            }
+            //vlineto
+            7 => {
+                let [dy] = self.args();
+                self.line_to(self.x, self.y + dy);
+            }
+            //rrcurveto
+            8 => {
+                let d: [f32; 6] = self.args();
+                self.relative_curve_to(d);
+            }
+            //closepath
+            9 => self.close(),
+            //hsbw
+            13 => {
+                let [sbx, wx] = self.args();
+                self.x = sbx;
+                self.y = 0.0;
+                self.width = wx;
+            }
+            //endchar
+            14 => {
+                self.close();
+                self.finished = true;
+            }
+            //rmoveto
+            21 => {
+                let [dx, dy] = self.args();
+                self.move_to(self.x + dx, self.y + dy);
+            }
+            //hmoveto
+            22 => {
+                let [dx] = self.args();
+                self.move_to(self.x + dx, self.y);
+            }
+            //vhcurveto
+            30 => {
+                let [dy1, dx2, dy2, dx3] = self.args();
+                self.relative_curve_to([0.0, dy1, dx2, dy2, dx3, 0.0]);
+            }
+            //hvcurveto
+            31 => {
+                let [dx1, dx2, dy2, dy3] = self.args();
+                self.relative_curve_to([dx1, 0.0, dx2, dy2, 0.0, dy3]);
+            }
+            _ => return Err(()),
+        }
+        self.stack.clear();
+        Ok(())
+    }
+
+    fn execute_escape(&mut self, escape: u8, depth: usize) -> Result<(), ()> {
+        match escape {
+            //dotsection・vstem3・hstem3
+            0..=2 => {}
+            //seac(StandardEncodingの2つのグリフを合成する)
+            6 => {
+                let [asb, adx, ady, bchar, achar] = self.args();
+                let base: &str = standard_glyph_name(bchar)?;
+                let accent: &str = standard_glyph_name(achar)?;
+                let base_char_string: &[u8] = self.font.char_strings.get(base).ok_or(())?;
+                let accent_char_string: &[u8] = self.font.char_strings.get(accent).ok_or(())?;
+                let width: f32 = self.width;
+                self.stack.clear();
+                self.execute(base_char_string, depth + 1)?;
+                self.close();
+                self.finished = false;
+                self.stack.clear();
+                self.offset = (adx - asb, ady);
+                self.execute(accent_char_string, depth + 1)?;
+                self.close();
+                self.offset = (0.0, 0.0);
+                self.width = width;
+                self.finished = true;
+            }
+            //sbw
+            7 => {
+                let [sbx, sby, wx, _wy] = self.args();
+                self.x = sbx;
+                self.y = sby;
+                self.width = wx;
+            }
+            //div
+            12 => {
+                let b: f32 = self.stack.pop().ok_or(())?;
+                let a: f32 = self.stack.pop().ok_or(())?;
+                self.stack.push(if b == 0.0 { 0.0 } else { a / b });
+                return Ok(());
+            }
+            //callothersubr
+            16 => {
+                let other_subr: f32 = self.stack.pop().ok_or(())?;
+                let count: usize = self.stack.pop().ok_or(())? as usize;
+                let start: usize = self.stack.len().checked_sub(count).ok_or(())?;
+                let args: Vec<f32> = self.stack.split_off(start);
+                self.call_other_subr(other_subr as i32, args);
+                return Ok(());
+            }
+            //pop
+            17 => {
+                let value: f32 = self.ps_stack.pop().unwrap_or(0.0);
+                self.stack.push(value);
+                return Ok(());
+            }
+            //setcurrentpoint
+            33 => {
+                let [x, y] = self.args();
+                self.x = x;
+                self.y = y;
+            }
+            _ => return Err(()),
+        }
+        self.stack.clear();
+        Ok(())
+    }
+
+    ///OtherSubrs(Flexとヒントの置き換え)を実行する。
+    fn call_other_subr(&mut self, other_subr: i32, args: Vec<f32>) {
+        match other_subr {
+            //Flexの終了:収集した制御点で2つの曲線を出力する。
+            0 => {
+                if let Some(points) = self.flex_points.take() {
+                    if points.len() >= 7 {
+                        self.curve_to([points[1], points[2], points[3]]);
+                        self.curve_to([points[4], points[5], points[6]]);
+                    }
+                }
+                self.ps_stack.push(self.y);
+                self.ps_stack.push(self.x);
+            }
+            //Flexの開始(以降のrmovetoは輪郭を分けずに制御点として収集する)
+            1 => self.flex_points = Some(Vec::new()),
+            //Flexの制御点の追加
+            2 => {
+                let point: (f32, f32) = (self.x, self.y);
+                if let Some(points) = self.flex_points.as_mut() {
+                    points.push(point);
+                }
+            }
+            //ヒントの置き換え(サブルーチン番号をそのまま返す)
+            3 => self.ps_stack.push(3.0),
+            _ => self.ps_stack.extend(args.into_iter().rev()),
+        }
+    }
+}
+
+///StandardEncodingのコードに対応するグリフ名を取得する。
+fn standard_glyph_name(code: f32) -> Result<&'static str, ()> {
+    crate::font_encoding::STANDARD_ENCODING
+        .get(code as usize)
+        .copied()
+        .filter(|name| !name.is_empty())
+        .ok_or(())
+}
+
+///PFB形式のセグメントヘッダ(0x80 0x01・0x80 0x02)を取り除く。(PFB形式でない場合はそのまま返す。)
+fn strip_pfb_headers(data: &[u8]) -> Vec<u8> {
+    if data.first() != Some(&0x80) {
+        return data.to_vec();
+    }
+    let mut stripped: Vec<u8> = Vec::with_capacity(data.len());
+    let mut pos: usize = 0;
+    while pos + 6 <= data.len() && data[pos] == 0x80 && matches!(data[pos + 1], 1 | 2) {
+        let length: usize =
+            u32::from_le_bytes([data[pos + 2], data[pos + 3], data[pos + 4], data[pos + 5]])
+                as usize;
+        let start: usize = pos + 6;
+        let end: usize = (start + length).min(data.len());
+        stripped.extend_from_slice(&data[start..end]);
+        pos = end;
+    }
+    stripped
+}
+
+///eexec暗号化部分が16進数で記述されている場合はバイナリに変換する。
+fn to_binary(data: &[u8]) -> Vec<u8> {
+    let is_hex: bool = data.len() >= 4 && data[..4].iter().all(|b| b.is_ascii_hexdigit());
+    if !is_hex {
+        return data.to_vec();
+    }
+    let digits: Vec<u8> = data
+        .iter()
+        .filter(|b| b.is_ascii_hexdigit())
+        .map(|b| (*b as char).to_digit(16).unwrap_or(0) as u8)
+        .collect();
+    digits
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair.get(1).copied().unwrap_or(0))
+        .collect()
+}
+
+///Type1フォントの暗号化を復号する。
+/// # Arguments
+/// * `data` - 暗号化されたデータ。
+/// * `key` - 鍵(eexec:55665、CharString:4330)。
+/// * `skip` - 復号後に読み飛ばす先頭のバイト数。
+fn decrypt(data: &[u8], key: u16, skip: usize) -> Vec<u8> {
+    let mut r: u16 = key;
+    let decrypted: Vec<u8> = data
+        .iter()
+        .map(|cipher| {
+            let plain: u8 = cipher ^ (r >> 8) as u8;
+            r = (*cipher as u16)
+                .wrapping_add(r)
+                .wrapping_mul(52845)
+                .wrapping_add(22719);
+            plain
+        })
+        .collect();
+    decrypted.into_iter().skip(skip).collect()
+}
+
+///平文部分からFontMatrixを取得する。
+fn parse_font_matrix(clear_text: &[u8]) -> Option<[f32; 6]> {
+    let pos: usize = find(clear_text, b"/FontMatrix", 0)?;
+    let mut tokens = Tokenizer::new(clear_text, pos + 11);
+    let mut matrix: [f32; 6] = [0.0; 6];
+    if !matches!(tokens.next_token()?, b"[" | b"{") {
+        return None;
+    }
+    for value in matrix.iter_mut() {
+        *value = tokens.next_number()?;
+    }
+    Some(matrix)
+}
+
+///平文部分から内蔵のエンコーディングを取得する。(StandardEncodingの場合はNone。)
+fn parse_encoding(clear_text: &[u8]) -> Option<HashMap<u8, String>> {
+    let pos: usize = find(clear_text, b"/Encoding", 0)?;
+    let mut tokens = Tokenizer::new(clear_text, pos + 9);
+    let mut encoding: HashMap<u8, String> = HashMap::new();
+    while let Some(token) = tokens.next_token() {
+        match token {
+            b"StandardEncoding" => return None,
+            b"def" | b"readonly" if !encoding.is_empty() => break,
+            b"dup" => {
+                let code: Option<f32> = tokens.next_number();
+                let name: Option<&[u8]> = tokens.next_token();
+                if let (Some(code), Some(name)) = (code, name.and_then(|n| n.strip_prefix(b"/"))) {
+                    encoding.insert(code as u8, String::from_utf8_lossy(name).into_owned());
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(encoding)
+}
+
+///データ内で指定したバイト列が最初に現れる位置を取得する。
+fn find(data: &[u8], pattern: &[u8], from: usize) -> Option<usize> {
+    data.get(from..)?
+        .windows(pattern.len())
+        .position(|window| window == pattern)
+        .map(|pos| pos + from)
+}
+
+///Type1フォントのPostScript部分を字句に分割する。
+struct Tokenizer<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Tokenizer { data, pos }
+    }
+
+    fn is_delimiter(b: u8) -> bool {
+        matches!(
+            b,
+            b'[' | b']' | b'{' | b'}' | b'(' | b')' | b'<' | b'>' | b'/' | b'%'
+        )
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.data.len() {
+            match self.data[self.pos] {
+                b if b.is_ascii_whitespace() || b == 0 => self.pos += 1,
+                //コメントは行末まで読み飛ばす。
+                b'%' => {
+                    while self.pos < self.data.len()
+                        && !matches!(self.data[self.pos], b'\r' | b'\n')
+                    {
+                        self.pos += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    ///次の字句を取得する。(名前は先頭の"/"を含む。区切り文字は1文字の字句とする。)
+    fn next_token(&mut self) -> Option<&'a [u8]> {
+        self.skip_whitespace();
+        let start: usize = self.pos;
+        let first: u8 = *self.data.get(self.pos)?;
+        self.pos += 1;
+        if Self::is_delimiter(first) && first != b'/' {
+            return Some(&self.data[start..self.pos]);
+        }
+        while self.pos < self.data.len() {
+            let b: u8 = self.data[self.pos];
+            if b.is_ascii_whitespace() || Self::is_delimiter(b) {
+                break;
+            }
+            self.pos += 1;
+        }
+        Some(&self.data[start..self.pos])
+    }
+
+    ///次の字句を数値として取得する。
+    fn next_number(&mut self) -> Option<f32> {
+        let token: &[u8] = self.next_token()?;
+        std::str::from_utf8(token).ok()?.parse::<f32>().ok()
+    }
+
+    ///"<長さ> RD <バイナリ>"の形式のバイナリデータを取得する。
+    fn next_binary(&mut self) -> Option<&'a [u8]> {
+        let length: usize = self.next_number()? as usize;
+        //RDもしくは"-|"の後の空白1文字の次からバイナリデータが始まる。
+        self.next_token()?;
+        let start: usize = self.pos + 1;
+        let end: usize = start.checked_add(length)?;
+        let binary: &[u8] = self.data.get(start..end)?;
+        self.pos = end;
+        Some(binary)
+    }
+
+    ///指定した字句のいずれかが現れるまで読み飛ばす。
+    fn skip_until(&mut self, tokens: &[&[u8]]) {
+        while let Some(token) = self.next_token() {
+            if tokens.contains(&token) {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///テスト用に輪郭の命令を記録する。
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl OutlineBuilder for Recorder {
+        fn move_to(&mut self, x: f32, y: f32) {
+            self.0.push(format!("M{} {}", x, y));
+        }
+        fn line_to(&mut self, x: f32, y: f32) {
+            self.0.push(format!("L{} {}", x, y));
+        }
+        fn quad_to(&mut self, _x1: f32, _y1: f32, x: f32, y: f32) {
+            self.0.push(format!("Q{} {}", x, y));
+        }
+        fn curve_to(&mut self, _x1: f32, _y1: f32, _x2: f32, _y2: f32, x: f32, y: f32) {
+            self.0.push(format!("C{} {}", x, y));
+        }
+        fn close(&mut self) {
+            self.0.push("Z".to_string());
+        }
+    }
+
+    ///暗号化する。(decrypt()の逆の処理。)
+    fn encrypt(plain: &[u8], key: u16) -> Vec<u8> {
+        let mut r: u16 = key;
+        plain
+            .iter()
+            .map(|p| {
+                let cipher: u8 = p ^ (r >> 8) as u8;
+                r = (cipher as u16)
+                    .wrapping_add(r)
+                    .wrapping_mul(52845)
+                    .wrapping_add(22719);
+                cipher
+            })
+            .collect()
+    }
+
+    ///1000単位の正方形のグリフ"square"を持つType1フォントを作成する。
+    fn square_font() -> Vec<u8> {
+        //0 1000 hsbw 100 100 rmoveto 800 hlineto 800 vlineto -800 hlineto closepath endchar
+        let mut char_string: Vec<u8> = vec![0; 4];
+        char_string.extend_from_slice(&[139, 250, 124, 13]);
+        char_string.extend_from_slice(&[239, 239, 21]);
+        char_string.extend_from_slice(&[249, 180, 6, 249, 180, 7, 253, 180, 6, 9, 14]);
+        let encrypted: Vec<u8> = encrypt(&char_string, CHARSTRING_KEY);
+        let mut private: Vec<u8> = b"    dup /Private 8 dict dup begin /lenIV 4 def\n/Subrs 0 array\n/CharStrings 1 dict dup begin\n/square ".to_vec();
+        private.extend_from_slice(format!("{} RD ", encrypted.len()).as_bytes());
+        private.extend_from_slice(&encrypted);
+        private.extend_from_slice(b" ND\nend\n");
+        let mut font: Vec<u8> = b"%!FontType1-1.0: Test\n/FontMatrix [0.001 0 0 0.001 0 0] readonly def\n/Encoding 256 array\n0 1 255 {1 index exch /.notdef put} for\ndup 65 /square put\nreadonly def\ncurrentfile eexec\n".to_vec();
+        font.extend_from_slice(&encrypt(&private, EEXEC_KEY));
+        font
+    }
+
+    #[test]
+    fn test_parse_type1_font() {
+        let font = Type1Font::parse(&square_font()).unwrap();
+        assert_eq!(font.font_matrix, [0.001, 0.0, 0.0, 0.001, 0.0, 0.0]);
+        assert_eq!(font.builtin_glyph_name(65), Some(Some("square")));
+        assert_eq!(font.builtin_glyph_name(66), Some(None));
+        assert!(font.has_glyph("square"));
+        let mut recorder = Recorder::default();
+        let width = font.outline("square", &mut recorder).unwrap();
+        assert_eq!(width, 1000.0);
+        assert_eq!(
+            recorder.0,
+            vec!["M100 100", "L900 100", "L900 900", "L100 900", "Z"]
+        );
+    }
+
+    #[test]
+    fn test_decrypt_hex() {
+        let encrypted: Vec<u8> = encrypt(b"abcdhello", EEXEC_KEY);
+        let hex: String = encrypted.iter().map(|b| format!("{:02x}", b)).collect();
+        let binary: Vec<u8> = to_binary(hex.as_bytes());
+        assert_eq!(decrypt(&binary, EEXEC_KEY, 4), b"hello");
+    }
+
+    #[test]
+    fn test_strip_pfb_headers() {
+        let pfb: Vec<u8> = [
+            &[0x80, 0x01, 0x02, 0x00, 0x00, 0x00][..],
+            b"ab",
+            &[0x80, 0x02, 0x01, 0x00, 0x00, 0x00],
+            b"c",
+            &[0x80, 0x03],
+        ]
+        .concat();
+        assert_eq!(strip_pfb_headers(&pfb), b"abc");
+        assert_eq!(strip_pfb_headers(b"%!PS"), b"%!PS");
+    }
+}
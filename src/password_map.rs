@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+///暗号化されたPDFファイルを開くパスワード。
+///PDFファイルごとのパスワードと、一致するPDFファイルがない場合に使用する既定のパスワードを保持する。
+///
+/// # Examples
+/// ```
+/// use pdf_to_image_rs::PasswordMap;
+///
+/// let csv: &[u8] = b"report.pdf,secret\n/data/contract.pdf,p@ss,word\n";
+/// let password_map = PasswordMap::from_csv(csv).unwrap();
+/// assert_eq!(password_map.len(), 2);
+/// ```
+#[derive(Clone, Default)]
+pub struct PasswordMap {
+    ///PDFファイルのパスもしくはファイル名とパスワード。
+    passwords: HashMap<String, String>,
+    ///一致するPDFファイルがない場合に使用するパスワード。
+    default_password: Option<String>,
+}
+
+impl PasswordMap {
+    ///パスワードを保持しないPasswordMapを作成する。
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    ///PDFファイルのパスワードを追加する。
+    /// # Arguments
+    /// * `file_name` - PDFファイルのパスもしくはファイル名。(パスが一致しない場合はファイル名で判定する。)
+    /// * `password` - パスワード。
+    pub fn insert(&mut self, file_name: &str, password: &str) {
+        self.passwords
+            .insert(file_name.to_string(), password.to_string());
+    }
+
+    ///PDFファイルごとのパスワードの数を取得する。
+    pub fn len(&self) -> usize {
+        self.passwords.len()
+    }
+
+    ///PDFファイルごとのパスワードを1つも保持していなければtrueを返す。
+    pub fn is_empty(&self) -> bool {
+        self.passwords.is_empty()
+    }
+
+    ///"ファイル名,パスワード"の形式のCSV(ヘッダー行なし)からパスワードを読み込む。
+    ///(パスワードに","を含む場合は2列目以降を","で連結したものをパスワードとする。空行は無視する。)
+    /// # Arguments
+    /// * `reader` - CSVの読み込み元。
+    /// # Returns
+    /// * 成功時:読み込んだパスワード。
+    /// * 失敗時:std::io::Error(読み込み失敗もしくはパスワードのない行)。
+    pub fn from_csv<R: Read>(reader: R) -> std::io::Result<Self> {
+        let mut password_map = PasswordMap::new();
+        let mut csv_reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(reader);
+        for record in csv_reader.records() {
+            let record = record.map_err(std::io::Error::other)?;
+            let file_name: &str = record.get(0).unwrap_or_default().trim();
+            if record.len() <= 1 && file_name.is_empty() {
+                continue;
+            }
+            if record.len() < 2 || file_name.is_empty() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "INVALID PASSWORD MAP LINE. LINE: {}",
+                        record.position().map(|p| p.line()).unwrap_or_default()
+                    ),
+                ));
+            }
+            let password: String = record.iter().skip(1).collect::<Vec<&str>>().join(",");
+            password_map.insert(file_name, &password);
+        }
+        Ok(password_map)
+    }
+
+    ///CSVファイルからパスワードを読み込む。(形式はfrom_csv()を参照。)
+    pub fn load(csv_path: &Path) -> std::io::Result<Self> {
+        PasswordMap::from_csv(BufReader::new(File::open(csv_path)?))
+    }
+
+    ///一致するPDFファイルがない場合に使用するパスワードを設定する。
+    pub(crate) fn set_default_password(&mut self, password: &str) {
+        self.default_password = Some(password.to_string());
+    }
+
+    ///別のPasswordMapのパスワードを追加する。(同じファイル名・既定のパスワードは上書きする。)
+    pub(crate) fn extend(&mut self, other: PasswordMap) {
+        self.passwords.extend(other.passwords);
+        if other.default_password.is_some() {
+            self.default_password = other.default_password;
+        }
+    }
+
+    ///PDFファイルのパスワードを取得する。
+    /// # Arguments
+    /// * `pdf_path` - PDFファイルのパス。
+    /// # Returns
+    /// * パスが一致するパスワード、ファイル名が一致するパスワード、既定のパスワードの順に最初に見つかったもの。(いずれもない場合はNone。)
+    pub(crate) fn get(&self, pdf_path: &Path) -> Option<&str> {
+        let file_name: Option<String> = pdf_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string());
+        self.passwords
+            .get(pdf_path.to_string_lossy().as_ref())
+            .or_else(|| file_name.and_then(|name| self.passwords.get(&name)))
+            .or(self.default_password.as_ref())
+            .map(|password| password.as_str())
+    }
+}
+
+///パスワードがログなどに出力されないよう、ファイル名のみを出力する。
+impl fmt::Debug for PasswordMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut file_names: Vec<&String> = self.passwords.keys().collect();
+        file_names.sort();
+        f.debug_struct("PasswordMap")
+            .field("files", &file_names)
+            .field("has_default_password", &self.default_password.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password_map() {
+        let csv: &[u8] = b"a.pdf,secret\n\n\"dir/b.pdf\", with space \nc.pdf,p,w\n";
+        let mut password_map = PasswordMap::from_csv(csv).unwrap();
+        assert_eq!(password_map.len(), 3);
+        assert_eq!(password_map.get(Path::new("/x/a.pdf")), Some("secret"));
+        assert_eq!(
+            password_map.get(Path::new("dir/b.pdf")),
+            Some(" with space ")
+        );
+        assert_eq!(password_map.get(Path::new("c.pdf")), Some("p,w"));
+        assert_eq!(password_map.get(Path::new("d.pdf")), None);
+
+        password_map.set_default_password("default");
+        assert_eq!(password_map.get(Path::new("d.pdf")), Some("default"));
+        assert_eq!(password_map.get(Path::new("a.pdf")), Some("secret"));
+        assert!(!format!("{:?}", password_map).contains("secret"));
+
+        assert!(PasswordMap::from_csv(&b"a.pdf\n"[..]).is_err());
+        assert!(PasswordMap::from_csv(&b",secret\n"[..]).is_err());
+    }
+}
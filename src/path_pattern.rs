@@ -0,0 +1,76 @@
+use globset::{GlobBuilder, GlobMatcher};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+///PDFファイルの検索対象を絞り込むglobパターン。
+///"/"を含むパターンは入力ディレクトリからの相対パス("/"区切り)と、含まないパターンはファイル名・ディレクトリ名と比較する。
+///(`*`・`?`は"/"に一致しない。複数階層に一致させる場合は`**`を使用する。)
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    pattern: String,
+    matcher: GlobMatcher,
+}
+
+impl PathPattern {
+    ///入力ディレクトリからの相対パスがパターンに一致すればtrueを返す。
+    /// # Arguments
+    /// * `relative_path` - 入力ディレクトリからの相対パス。
+    pub(crate) fn is_match(&self, relative_path: &Path) -> bool {
+        if self.pattern.contains('/') {
+            let components: Vec<String> = relative_path
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect();
+            self.matcher.is_match(components.join("/"))
+        } else {
+            relative_path
+                .file_name()
+                .is_some_and(|name| self.matcher.is_match(name))
+        }
+    }
+}
+
+impl FromStr for PathPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("GLOB PATTERN IS EMPTY.".to_string());
+        }
+        let glob = GlobBuilder::new(s)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| format!("INVALID GLOB PATTERN: {} ERR: {}", s, e))?;
+        Ok(PathPattern {
+            pattern: s.to_string(),
+            matcher: glob.compile_matcher(),
+        })
+    }
+}
+
+impl fmt::Display for PathPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_pattern() {
+        let name: PathPattern = "report_*.pdf".parse().unwrap();
+        assert!(name.is_match(Path::new("2023/report_01.pdf")));
+        assert!(!name.is_match(Path::new("2023/invoice_01.pdf")));
+        let path: PathPattern = "2023/*.pdf".parse().unwrap();
+        assert!(path.is_match(Path::new("2023/report_01.pdf")));
+        assert!(!path.is_match(Path::new("2023/q1/report_01.pdf")));
+        let deep: PathPattern = "2023/**/*.pdf".parse().unwrap();
+        assert!(deep.is_match(Path::new("2023/q1/report_01.pdf")));
+        assert!("a[".parse::<PathPattern>().is_err());
+        assert!("".parse::<PathPattern>().is_err());
+        assert_eq!(deep.to_string(), "2023/**/*.pdf");
+    }
+}
@@ -0,0 +1,191 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage};
+use std::fmt;
+use std::str::FromStr;
+
+///近似した画像(再圧縮された同じ画像など)を判定する知覚ハッシュの種類。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PerceptualHash {
+    ///8x8に縮小した画像の各画素が平均より明るいかどうか(Average Hash)。
+    Ahash,
+    ///9x8に縮小した画像の各画素が右隣の画素より明るいかどうか(Difference Hash)。
+    #[default]
+    Dhash,
+    ///32x32に縮小した画像の離散コサイン変換の低周波成分が中央値より大きいかどうか(Perceptual Hash)。
+    Phash,
+}
+
+impl PerceptualHash {
+    ///画像の知覚ハッシュ(64bit)を計算する。
+    /// # Arguments
+    /// * `image` - 展開した画像。
+    pub(crate) fn compute(&self, image: &DynamicImage) -> u64 {
+        match self {
+            PerceptualHash::Ahash => {
+                let gray: GrayImage = resize_gray(image, 8, 8);
+                let sum: u32 = gray.pixels().map(|p| p[0] as u32).sum();
+                let mean: u32 = sum / 64;
+                to_bits(gray.pixels().map(|p| p[0] as u32 > mean))
+            }
+            PerceptualHash::Dhash => {
+                let gray: GrayImage = resize_gray(image, 9, 8);
+                to_bits(
+                    (0..8)
+                        .flat_map(|y| (0..8).map(move |x| (x, y)))
+                        .map(|(x, y)| gray.get_pixel(x, y)[0] > gray.get_pixel(x + 1, y)[0]),
+                )
+            }
+            PerceptualHash::Phash => {
+                let gray: GrayImage = resize_gray(image, 32, 32);
+                let coefficients: Vec<f32> = dct_low_frequencies(&gray, 8);
+                //直流成分を除いた中央値を閾値とする。
+                let mut ac: Vec<f32> = coefficients[1..].to_vec();
+                ac.sort_by(|a, b| a.total_cmp(b));
+                let median: f32 = ac[ac.len() / 2];
+                to_bits(coefficients.iter().map(|c| *c > median))
+            }
+        }
+    }
+}
+
+///2つの知覚ハッシュのハミング距離(異なるビットの数)を取得する。
+pub(crate) fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+///画像をグレースケールに変換して縮小する。
+fn resize_gray(image: &DynamicImage, width: u32, height: u32) -> GrayImage {
+    image::imageops::resize(&image.to_luma8(), width, height, FilterType::Triangle)
+}
+
+///判定結果を上位ビットから順に並べて64bitの値にする。
+fn to_bits(bits: impl Iterator<Item = bool>) -> u64 {
+    bits.take(64)
+        .fold(0u64, |hash, bit| (hash << 1) | (bit as u64))
+}
+
+///正方形の画像に2次元の離散コサイン変換(DCT-II)を行い、低周波成分を取得する。
+/// # Arguments
+/// * `gray` - グレースケールの正方形の画像。
+/// * `size` - 取得する低周波成分の縦横の数。
+/// # Returns
+/// * 行優先で並べたsize×size個の係数。
+fn dct_low_frequencies(gray: &GrayImage, size: usize) -> Vec<f32> {
+    let n: usize = gray.width() as usize;
+    let cosines: Vec<Vec<f32>> = (0..size)
+        .map(|u| {
+            (0..n)
+                .map(|x| {
+                    (std::f32::consts::PI * (2 * x + 1) as f32 * u as f32 / (2 * n) as f32).cos()
+                })
+                .collect()
+        })
+        .collect();
+    //行方向に変換してから列方向に変換する。
+    let rows: Vec<Vec<f32>> = (0..n)
+        .map(|y| {
+            (0..size)
+                .map(|u| {
+                    (0..n)
+                        .map(|x| gray.get_pixel(x as u32, y as u32)[0] as f32 * cosines[u][x])
+                        .sum()
+                })
+                .collect()
+        })
+        .collect();
+    cosines
+        .iter()
+        .flat_map(|cosine| {
+            let rows = &rows;
+            (0..size).map(move |u| {
+                rows.iter()
+                    .zip(cosine.iter())
+                    .map(|(row, c)| row[u] * c)
+                    .sum::<f32>()
+            })
+        })
+        .collect()
+}
+
+impl FromStr for PerceptualHash {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ahash" => Ok(PerceptualHash::Ahash),
+            "dhash" => Ok(PerceptualHash::Dhash),
+            "phash" => Ok(PerceptualHash::Phash),
+            _ => Err(format!(
+                "UNSUPPORTED PERCEPTUAL HASH: {} (ahash|dhash|phash)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PerceptualHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name: &str = match self {
+            PerceptualHash::Ahash => "ahash",
+            PerceptualHash::Dhash => "dhash",
+            PerceptualHash::Phash => "phash",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    ///左上から右下に明るくなるグラデーションに円を描いた画像を作成する。
+    fn sample_image(size: u32, brightness: i32) -> DynamicImage {
+        let image = RgbImage::from_fn(size, size, |x, y| {
+            let (fx, fy) = (x as f32 / size as f32, y as f32 / size as f32);
+            let mut value: i32 = ((fx + fy) * 100.0) as i32;
+            if (fx - 0.3).powi(2) + (fy - 0.6).powi(2) < 0.04 {
+                value = 240;
+            }
+            let value: u8 = (value + brightness).clamp(0, 255) as u8;
+            Rgb([value, value / 2, 255 - value])
+        });
+        DynamicImage::ImageRgb8(image)
+    }
+
+    #[test]
+    fn test_perceptual_hash_near_duplicate() {
+        let original = sample_image(128, 0);
+        //縮小して明るさを変えた画像は近似した画像と判定される。
+        let modified = sample_image(96, 6);
+        //左右を反転した画像は異なる画像と判定される。
+        let flipped = original.fliph();
+        for kind in [
+            PerceptualHash::Ahash,
+            PerceptualHash::Dhash,
+            PerceptualHash::Phash,
+        ] {
+            let hash = kind.compute(&original);
+            assert!(
+                hamming_distance(hash, kind.compute(&modified)) <= 5,
+                "{}",
+                kind
+            );
+            assert!(
+                hamming_distance(hash, kind.compute(&flipped)) > 10,
+                "{}",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn test_perceptual_hash_from_str() {
+        assert_eq!("aHash".parse::<PerceptualHash>(), Ok(PerceptualHash::Ahash));
+        assert_eq!("dhash".parse::<PerceptualHash>(), Ok(PerceptualHash::Dhash));
+        assert_eq!("PHASH".parse::<PerceptualHash>(), Ok(PerceptualHash::Phash));
+        assert!("whash".parse::<PerceptualHash>().is_err());
+        assert_eq!(PerceptualHash::Phash.to_string(), "phash");
+        assert_eq!(hamming_distance(0b1011, 0b0001), 2);
+    }
+}
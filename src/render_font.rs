@@ -0,0 +1,423 @@
+use crate::font_encoding::{base_encoding_table, glyph_name_to_unicode, STANDARD_ENCODING};
+use crate::parse_type1_font::Type1Font;
+use crate::standard_font::StandardFont;
+
+use pdf::encoding::BaseEncoding;
+use pdf::error::PdfError;
+use pdf::font::{Font, FontType, Widths};
+use pdf::object::Resolve;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+use tiny_skia::{Path, PathBuilder, Transform};
+use ttf_parser::{cff, Face, GlyphId, OutlineBuilder, PlatformId};
+
+///ページの描画に使用するグリフ。
+pub(crate) struct Glyph {
+    ///テキスト空間(1emを1とする)の輪郭。(空白などの輪郭を持たないグリフはNone。)
+    pub(crate) path: Option<Path>,
+    ///送り幅(テキスト空間の単位)。
+    pub(crate) advance: f32,
+}
+
+///埋め込まれたフォントプログラム。
+enum FontProgram {
+    ///TrueType・OpenTypeのフォント(FontFile2、もしくはFontFile3のOpenType)。
+    Sfnt(Arc<[u8]>),
+    ///CFFのフォント(FontFile3のType1C・CIDFontType0C)。
+    Cff {
+        data: Arc<[u8]>,
+        ///CID-keyedのCFFのCIDからグリフIDへの対応表。(CID-keyedでない場合はNone。)
+        cid_to_gid: Option<HashMap<u16, u16>>,
+    },
+    ///Type1のフォント(FontFile)。
+    Type1(Type1Font),
+    ///埋め込まれていない単純フォントの代わりに使用する標準フォント。
+    Standard(StandardFont),
+}
+
+impl FontProgram {
+    ///フォントプログラムの形式を先頭のデータから判定して解析する。
+    fn parse(data: Arc<[u8]>) -> Result<Self, PdfError> {
+        if data.starts_with(&[0x00, 0x01, 0x00, 0x00])
+            || data.starts_with(b"true")
+            || data.starts_with(b"OTTO")
+            || data.starts_with(b"ttcf")
+        {
+            Face::parse(&data, 0).map_err(|e| PdfError::Other {
+                msg: format!("COULD NOT PARSE TRUETYPE FONT. ERR: {}", e),
+            })?;
+            return Ok(FontProgram::Sfnt(data));
+        }
+        if data.starts_with(b"%!") || data.first() == Some(&0x80) {
+            return Type1Font::parse(&data).map(FontProgram::Type1);
+        }
+        if data.first() == Some(&1) {
+            let table: cff::Table = cff::Table::parse(&data).ok_or_else(|| PdfError::Other {
+                msg: "COULD NOT PARSE CFF FONT.".to_string(),
+            })?;
+            let cid_to_gid: HashMap<u16, u16> = (0..table.number_of_glyphs())
+                .filter_map(|gid| table.glyph_cid(GlyphId(gid)).map(|cid| (cid, gid)))
+                .collect();
+            let cid_to_gid: Option<HashMap<u16, u16>> = if cid_to_gid.is_empty() {
+                None
+            } else {
+                Some(cid_to_gid)
+            };
+            return Ok(FontProgram::Cff { data, cid_to_gid });
+        }
+        let top: Vec<String> = data.iter().take(4).map(|b| format!("{:02x}", b)).collect();
+        Err(PdfError::Other {
+            msg: format!("UNSUPPORTED FONT PROGRAM. TOP_4: {}", top.join("_")),
+        })
+    }
+}
+
+///ページの描画に使用するフォント。
+pub(crate) struct RenderFont {
+    program: FontProgram,
+    ///Type0フォント(2バイトのコードでCIDを指定する)であればtrue。
+    is_cid: bool,
+    ///単純フォントのコードごとのグリフ名。(Encodingが指定されていない場合はNone。)
+    names: Option<Vec<Option<String>>>,
+    ///Type0フォントのCIDからグリフIDへの対応表(CIDToGIDMap)。
+    cid_to_gid_map: Option<Vec<u16>>,
+    ///PDFに指定された文字幅。
+    widths: Option<Widths>,
+    ///作成済みのグリフ。(コードごと)
+    glyphs: RefCell<HashMap<u32, Rc<Glyph>>>,
+}
+
+impl RenderFont {
+    ///PDFのフォントから描画に使用するフォントを作成する。
+    /// # Arguments
+    /// * `font` - PDFのフォント。
+    /// * `resolve` - 埋め込まれたフォントプログラムの取得に使用するリゾルバ。
+    /// # Returns
+    /// * 成功時:描画に使用するフォント。
+    ///   (フォントプログラムが埋め込まれていない単純フォントは標準フォントで代替する。
+    ///   埋め込まれていないType0フォント・Type3フォントの場合はNone。)
+    /// * 失敗時:PdfError(フォントプログラムの取得もしくは解析失敗、Identity-H以外のCMapのType0フォント)。
+    pub(crate) fn load(font: &Font, resolve: &impl Resolve) -> Result<Option<Self>, PdfError> {
+        let is_cid: bool = font.subtype == FontType::Type0;
+        let program: FontProgram = match font.embedded_data(resolve) {
+            Some(data) => FontProgram::parse(data?)?,
+            None if is_cid || font.subtype == FontType::Type3 => return Ok(None),
+            None => FontProgram::Standard(StandardFont::select(
+                font.name.as_ref().map(|name| name.as_str()),
+            )),
+        };
+        //Type0フォントの文字列はIdentity-HのCMap(2バイトのコードがそのままCID)としてのみ解釈できる。
+        if is_cid {
+            match font.encoding().map(|encoding| &encoding.base) {
+                Some(BaseEncoding::IdentityH) => {}
+                base => {
+                    return Err(PdfError::Other {
+                        msg: format!("CMAP IS NOT SUPPORTED. ENCODING: {:?}", base),
+                    })
+                }
+            }
+        }
+        Ok(Some(RenderFont {
+            program,
+            is_cid,
+            names: if is_cid { None } else { encoding_names(font) },
+            cid_to_gid_map: font.cid_to_gid_map().map(|map| map.to_vec()),
+            widths: font.widths(resolve)?,
+            glyphs: RefCell::new(HashMap::new()),
+        }))
+    }
+
+    ///埋め込まれていないフォントを、文字幅の異なる書体で置き換えて描画する場合はtrueを返す。
+    ///(標準14フォントとArial・Times New Roman・Courier Newの場合はfalse。)
+    pub(crate) fn is_substituted(&self) -> bool {
+        matches!(self.program, FontProgram::Standard(ref standard) if !standard.exact)
+    }
+
+    ///Type0フォント(2バイトのコード)であればtrueを返す。
+    pub(crate) fn is_cid(&self) -> bool {
+        self.is_cid
+    }
+
+    ///文字列をコードに分割する。(Type0フォントは2バイトずつ、それ以外は1バイトずつ。)
+    pub(crate) fn codes(&self, text: &[u8]) -> Vec<u32> {
+        if self.is_cid {
+            text.chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]) as u32)
+                .collect()
+        } else {
+            text.iter().map(|b| *b as u32).collect()
+        }
+    }
+
+    ///コードに対応するグリフを取得する。(作成済みのグリフは再利用する。)
+    pub(crate) fn glyph(&self, code: u32) -> Rc<Glyph> {
+        if let Some(glyph) = self.glyphs.borrow().get(&code) {
+            return Rc::clone(glyph);
+        }
+        let glyph: Rc<Glyph> = Rc::new(self.create_glyph(code));
+        self.glyphs.borrow_mut().insert(code, Rc::clone(&glyph));
+        glyph
+    }
+
+    ///コードに対応するグリフの輪郭と送り幅を作成する。
+    fn create_glyph(&self, code: u32) -> Glyph {
+        let mut outline = PathOutline(PathBuilder::new());
+        //フォントプログラムの送り幅(グリフ空間の単位)と、グリフ空間からテキスト空間への変換行列。
+        let (advance, matrix): (Option<f32>, Transform) = match self.program {
+            FontProgram::Sfnt(ref data) => match Face::parse(data, 0) {
+                Ok(face) => {
+                    let scale: f32 = 1.0 / face.units_per_em().max(1) as f32;
+                    let glyph_id: GlyphId = self.sfnt_glyph_id(&face, code);
+                    face.outline_glyph(glyph_id, &mut outline);
+                    (
+                        face.glyph_hor_advance(glyph_id).map(|a| a as f32),
+                        Transform::from_scale(scale, scale),
+                    )
+                }
+                Err(_) => (None, Transform::identity()),
+            },
+            FontProgram::Cff {
+                ref data,
+                ref cid_to_gid,
+            } => match cff::Table::parse(data) {
+                Some(table) => {
+                    let glyph_id: Option<GlyphId> = match cid_to_gid {
+                        Some(cid_to_gid) => cid_to_gid.get(&(code as u16)).map(|gid| GlyphId(*gid)),
+                        None if self.is_cid => Some(GlyphId(code as u16)),
+                        None => self
+                            .glyph_name(code)
+                            .and_then(|name| table.glyph_index_by_name(name))
+                            .or_else(|| table.glyph_index(code as u8)),
+                    };
+                    let advance: Option<f32> = glyph_id.and_then(|glyph_id| {
+                        table.outline(glyph_id, &mut outline).ok();
+                        table.glyph_width(glyph_id).map(|w| w as f32)
+                    });
+                    let m = table.matrix();
+                    (
+                        advance,
+                        Transform::from_row(m.sx, m.ky, m.kx, m.sy, m.tx, m.ty),
+                    )
+                }
+                None => (None, Transform::identity()),
+            },
+            FontProgram::Type1(ref font) => {
+                //PDFのEncodingのグリフ名がフォントにない場合はフォント内蔵のエンコーディングを使用する。
+                let code: u8 = code as u8;
+                let name: Option<&str> = self
+                    .glyph_name(code as u32)
+                    .filter(|name| font.has_glyph(name))
+                    .or_else(|| match font.builtin_glyph_name(code) {
+                        Some(name) => name,
+                        None => Some(STANDARD_ENCODING[code as usize]),
+                    });
+                let m: [f32; 6] = font.font_matrix;
+                (
+                    name.and_then(|name| font.outline(name, &mut outline)),
+                    Transform::from_row(m[0], m[1], m[2], m[3], m[4], m[5]),
+                )
+            }
+            FontProgram::Standard(ref standard) => match Face::parse(standard.program(), 0) {
+                Ok(face) => {
+                    let name: Option<&str> = self.glyph_name(code);
+                    let glyph_id: Option<GlyphId> = standard
+                        .char(code as u8, name)
+                        .and_then(|c| face.glyph_index(c));
+                    let advance: Option<f32> = glyph_id.and_then(|glyph_id| {
+                        face.outline_glyph(glyph_id, &mut outline);
+                        face.glyph_hor_advance(glyph_id).map(|a| a as f32)
+                    });
+                    //代替フォントのグリフを、PDFもしくは標準フォントの文字幅に合わせて横方向に伸縮する。
+                    let scale: f32 = 1.0 / face.units_per_em().max(1) as f32;
+                    let width: Option<f32> = match self.widths {
+                        Some(ref widths) => Some(widths.get(code as usize)),
+                        None => standard.width(code as u8, name),
+                    };
+                    let fit: f32 = match (width, advance) {
+                        (Some(width), Some(advance)) if width > 0.0 && advance > 0.0 => {
+                            width / 1000.0 / (advance * scale)
+                        }
+                        _ => 1.0,
+                    };
+                    (advance, Transform::from_scale(scale * fit, scale))
+                }
+                Err(_) => (None, Transform::identity()),
+            },
+        };
+        let advance: f32 = match self.widths {
+            Some(ref widths) => widths.get(code as usize) / 1000.0,
+            None => advance.map(|a| a * matrix.sx).unwrap_or(0.0),
+        };
+        Glyph {
+            path: outline.0.finish().and_then(|path| path.transform(matrix)),
+            advance,
+        }
+    }
+
+    ///単純フォントのPDFのEncodingでコードに対応するグリフ名を取得する。
+    fn glyph_name(&self, code: u32) -> Option<&str> {
+        self.names.as_ref()?.get(code as usize)?.as_deref()
+    }
+
+    ///TrueType・OpenTypeのフォントのコードに対応するグリフIDを取得する。
+    fn sfnt_glyph_id(&self, face: &Face, code: u32) -> GlyphId {
+        if self.is_cid {
+            let glyph_id: u16 = match self.cid_to_gid_map {
+                Some(ref map) => map.get(code as usize).copied().unwrap_or(0),
+                None => code as u16,
+            };
+            return GlyphId(glyph_id);
+        }
+        let found = |glyph_id: Option<GlyphId>| glyph_id.filter(|glyph_id| glyph_id.0 != 0);
+        //グリフ名がわかる場合はpostテーブルの名前、Unicodeのcmapの順に探す。
+        if let Some(name) = self.glyph_name(code) {
+            let by_name = found(face.glyph_index_by_name(name))
+                .or_else(|| glyph_name_to_unicode(name).and_then(|c| found(face.glyph_index(c))));
+            if let Some(glyph_id) = by_name {
+                return glyph_id;
+            }
+        }
+        //シンボルフォントのcmap(3,0)はコードに0xF000を加えた値、Macintoshのcmap(1,0)はコードそのままで引く。
+        if let Some(cmap) = face.tables().cmap {
+            for subtable in cmap.subtables {
+                let glyph_id: Option<GlyphId> = match (subtable.platform_id, subtable.encoding_id) {
+                    (PlatformId::Windows, 0) => found(subtable.glyph_index(0xF000 | code))
+                        .or_else(|| found(subtable.glyph_index(code))),
+                    (PlatformId::Macintosh, 0) => found(subtable.glyph_index(code)),
+                    _ => None,
+                };
+                if let Some(glyph_id) = glyph_id {
+                    return glyph_id;
+                }
+            }
+        }
+        char::from_u32(code)
+            .and_then(|c| found(face.glyph_index(c)))
+            .unwrap_or(GlyphId(code as u16))
+    }
+}
+
+///単純フォントのEncodingからコードごとのグリフ名の表を作成する。
+///(Differencesで指定されていないコードは基底のエンコーディングのグリフ名とする。)
+fn encoding_names(font: &Font) -> Option<Vec<Option<String>>> {
+    let encoding = font.encoding()?;
+    let base: Option<&[&str; 256]> = base_encoding_table(&encoding.base);
+    if base.is_none() && encoding.differences.is_empty() {
+        return None;
+    }
+    let mut names: Vec<Option<String>> = (0..256)
+        .map(|code| {
+            base.map(|table| table[code])
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+        })
+        .collect();
+    for (code, name) in encoding.differences.iter() {
+        if let Some(slot) = names.get_mut(*code as usize) {
+            *slot = Some(name.as_str().to_string());
+        }
+    }
+    Some(names)
+}
+
+///フォントの輪郭をtiny-skiaのパスに変換する。
+struct PathOutline(PathBuilder);
+
+impl OutlineBuilder for PathOutline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.move_to(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.line_to(x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.0.quad_to(x1, y1, x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.0.cubic_to(x1, y1, x2, y2, x, y);
+    }
+
+    fn close(&mut self) {
+        self.0.close();
+    }
+}
+
+#[cfg(test)]
+impl RenderFont {
+    ///フォントプログラムから、PDFのEncoding・文字幅を持たない単純フォントを作成する。(テスト用)
+    pub(crate) fn from_program(data: Vec<u8>) -> Result<Self, PdfError> {
+        Ok(RenderFont {
+            program: FontProgram::parse(data.into())?,
+            is_cid: false,
+            names: None,
+            cid_to_gid_map: None,
+            widths: None,
+            glyphs: RefCell::new(HashMap::new()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_font_program_parse_unsupported() {
+        let data: Arc<[u8]> = Arc::from(&b"\xde\xad\xbe\xef"[..]);
+        assert!(FontProgram::parse(data).is_err());
+    }
+
+    #[test]
+    ///test_pdf/render_pdf/render.ttfは"A"が1emの正方形、"B"が下半分の長方形のグリフを持つ。
+    fn test_render_font_glyph() {
+        let font =
+            RenderFont::from_program(std::fs::read("test_pdf/render_pdf/render.ttf").unwrap())
+                .unwrap();
+        let bounds = |code: u32| {
+            let glyph: Rc<Glyph> = font.glyph(code);
+            let bounds = glyph.path.as_ref().unwrap().bounds();
+            (bounds.width(), bounds.height(), glyph.advance)
+        };
+        assert_eq!(bounds(0x41), (1.0, 1.0, 1.0));
+        assert_eq!(bounds(0x42), (1.0, 0.5, 1.0));
+        //cmapにない文字は輪郭を持たない.notdefのグリフになる。
+        assert!(font.glyph(0x43).path.is_none());
+    }
+
+    #[test]
+    ///埋め込まれていないHelveticaを代替フォントで描画し、Helveticaの文字幅に合わせることを確認する。
+    fn test_render_font_standard() {
+        let font = RenderFont {
+            program: FontProgram::Standard(StandardFont::select(Some("Helvetica"))),
+            is_cid: false,
+            names: None,
+            cid_to_gid_map: None,
+            widths: None,
+            glyphs: RefCell::new(HashMap::new()),
+        };
+        assert!(!font.is_substituted());
+        let glyph: Rc<Glyph> = font.glyph(b'A' as u32);
+        assert_eq!(glyph.advance, 0.667);
+        let bounds = glyph.path.as_ref().unwrap().bounds();
+        assert!(bounds.left() >= 0.0 && bounds.right() <= 0.667);
+        assert!(bounds.height() > 0.6 && bounds.height() < 0.8);
+        assert!(font.glyph(b' ' as u32).path.is_none());
+    }
+
+    #[test]
+    fn test_path_outline() {
+        let mut outline = PathOutline(PathBuilder::new());
+        outline.move_to(0.0, 0.0);
+        outline.line_to(10.0, 0.0);
+        outline.quad_to(10.0, 10.0, 0.0, 10.0);
+        outline.close();
+        let path = outline.0.finish().unwrap();
+        let bounds = path.bounds();
+        assert_eq!((bounds.width(), bounds.height()), (10.0, 10.0));
+    }
+}
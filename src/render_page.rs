@@ -0,0 +1,949 @@
+use crate::apply_image_mask::{apply_image_mask, has_image_mask};
+use crate::collect_page_images::normalize_inline_image;
+use crate::color_management::{is_inverted_decode, ImageColorSpace};
+use crate::decode_raw_image::{decode_image, decode_raw_samples, image_data, RawSamples};
+use crate::render_font::{Glyph, RenderFont};
+
+use image::{DynamicImage, RgbImage, RgbaImage};
+use log::{info, log_enabled, warn, Level};
+use pdf::content::{self, Color, Matrix, Op, TextDrawAdjusted, TextMode, Winding};
+use pdf::error::PdfError;
+use pdf::font::FontType;
+use pdf::object::{
+    ColorSpace, ImageXObject, MaybeRef, Page, PlainRef, RcRef, Resolve, Resources, XObject,
+};
+use pdf::primitive::Primitive;
+use std::collections::{BTreeSet, HashMap};
+use std::rc::Rc;
+use std::sync::Arc;
+use tiny_skia::{
+    BlendMode, FillRule, FilterQuality, IntSize, LineCap, LineJoin, Mask, Paint, Path, PathBuilder,
+    Pixmap, PixmapPaint, Stroke, StrokeDash, Transform,
+};
+
+///描画する画像の最大の画素数。(巨大なページや高いDPIの指定でメモリを使い果たさないため。)
+const MAX_RENDER_PIXELS: u64 = 100_000_000;
+///Form XObjectの入れ子の最大の深さ。
+const MAX_FORM_DEPTH: usize = 32;
+
+///ページを指定した解像度で描画する。
+///パス(塗りつぶし・線・クリッピング)、色空間、画像(SMask・Maskを含む)、Form XObject、
+///TrueType・OpenType・CFF・Type1フォントのテキストを描画する。(埋め込まれていない単純フォントは標準フォントで代替する。)
+///(シェーディング・パターン・Type3フォント・埋め込まれていないType0フォントなどは描画せず、
+///描画しなかった内容として返す。標準14フォント以外を別の書体で代替した場合も記録する。)
+/// # Arguments
+/// * `page` - PDFファイルのページ。
+/// * `resolve` - 間接参照の解決に使用するリゾルバ。
+/// * `dpi` - 描画する解像度(1インチあたりのピクセル数)。
+/// # Returns
+/// * 成功時:白い背景に描画したRGBの画像(ページの/Rotateに従って回転させる)と、描画しなかった内容の一覧。
+/// * 失敗時:PdfError(ページの大きさ・リソース・コンテンツストリームの取得失敗など)。
+pub(crate) fn render_page(
+    page: &Page,
+    resolve: &impl Resolve,
+    dpi: f32,
+) -> Result<(RgbImage, Vec<&'static str>), PdfError> {
+    //CropBoxが指定されていない場合はMediaBoxの範囲を描画する。
+    let page_box = page.crop_box().or_else(|_| page.media_box())?;
+    let left: f32 = page_box.left.min(page_box.right);
+    let bottom: f32 = page_box.bottom.min(page_box.top);
+    let box_width: f32 = (page_box.right - page_box.left).abs();
+    let box_height: f32 = (page_box.top - page_box.bottom).abs();
+    let scale: f32 = dpi / 72.0;
+    let rotate: i32 = page.rotate.rem_euclid(360);
+
+    let (width, height): (f32, f32) = match rotate {
+        90 | 270 => (box_height * scale, box_width * scale),
+        _ => (box_width * scale, box_height * scale),
+    };
+    let (width, height): (u32, u32) = (width.ceil().max(1.0) as u32, height.ceil().max(1.0) as u32);
+    if width as u64 * height as u64 > MAX_RENDER_PIXELS {
+        return Err(PdfError::Other {
+            msg: format!(
+                "PAGE IS TOO LARGE TO RENDER. WIDTH: {} HEIGHT: {} DPI: {}",
+                width, height, dpi
+            ),
+        });
+    }
+    let mut pixmap: Pixmap = Pixmap::new(width, height).ok_or_else(|| PdfError::Other {
+        msg: format!(
+            "COULD NOT CREATE PIXMAP. WIDTH: {} HEIGHT: {}",
+            width, height
+        ),
+    })?;
+    pixmap.fill(tiny_skia::Color::WHITE);
+
+    //ユーザー空間(左下原点・上向き)からピクセル座標(左上原点・下向き)への変換。
+    let (w, h): (f32, f32) = (box_width * scale, box_height * scale);
+    let rotation: Transform = match rotate {
+        90 => Transform::from_row(0.0, scale, scale, 0.0, 0.0, 0.0),
+        180 => Transform::from_row(-scale, 0.0, 0.0, scale, w, 0.0),
+        270 => Transform::from_row(0.0, -scale, -scale, 0.0, h, w),
+        _ => Transform::from_row(scale, 0.0, 0.0, -scale, 0.0, h),
+    };
+    let base_transform: Transform = rotation.pre_translate(-left, -bottom);
+
+    let resources: &MaybeRef<Resources> = page.resources()?;
+    let mut renderer = PageRenderer::new(resolve, pixmap, base_transform);
+    if let Some(ref contents) = page.contents {
+        let ops: Vec<Op> = contents.operations(resolve)?;
+        renderer.execute(&ops, resources);
+    }
+    Ok((
+        pixmap_to_rgb(&renderer.pixmap),
+        renderer.unsupported.into_iter().collect(),
+    ))
+}
+
+///塗りつぶし・線の色。
+#[derive(Clone)]
+struct PaintColor {
+    ///現在の色空間。(DeviceGray・DeviceRGB・DeviceCMYKの演算子で指定された場合はNone。)
+    color_space: Option<ColorSpace>,
+    ///sRGBの値(0.0-1.0)。
+    rgb: [f32; 3],
+    ///パターン色空間が指定されていればtrue。(パターンは描画せず、描画しなかった内容に記録する。)
+    pattern: bool,
+}
+
+impl PaintColor {
+    fn black() -> Self {
+        PaintColor {
+            color_space: None,
+            rgb: [0.0; 3],
+            pattern: false,
+        }
+    }
+}
+
+///グラフィックス状態。(q・Qで保存・復元される。)
+#[derive(Clone)]
+struct GraphicsState {
+    ctm: Transform,
+    clip: Option<Rc<Mask>>,
+    fill: PaintColor,
+    stroke: PaintColor,
+    fill_alpha: f32,
+    stroke_alpha: f32,
+    line_width: f32,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    miter_limit: f32,
+    dash: Option<StrokeDash>,
+    font: Option<Rc<RenderFont>>,
+    font_size: f32,
+    char_space: f32,
+    word_space: f32,
+    horiz_scale: f32,
+    leading: f32,
+    rise: f32,
+    render_mode: TextMode,
+}
+
+impl GraphicsState {
+    fn new(ctm: Transform) -> Self {
+        GraphicsState {
+            ctm,
+            clip: None,
+            fill: PaintColor::black(),
+            stroke: PaintColor::black(),
+            fill_alpha: 1.0,
+            stroke_alpha: 1.0,
+            line_width: 1.0,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            miter_limit: 10.0,
+            dash: None,
+            font: None,
+            font_size: 0.0,
+            char_space: 0.0,
+            word_space: 0.0,
+            horiz_scale: 1.0,
+            leading: 0.0,
+            rise: 0.0,
+            render_mode: TextMode::Fill,
+        }
+    }
+}
+
+///コンテンツストリームの命令を実行してページを描画する。
+struct PageRenderer<'a, R: Resolve> {
+    resolve: &'a R,
+    pixmap: Pixmap,
+    state: GraphicsState,
+    saved_states: Vec<GraphicsState>,
+    ///構築中のパス(ユーザー空間の座標)。
+    path: PathBuilder,
+    ///W・W*で指定され、次のパスの描画命令で適用するクリッピングの規則。
+    pending_clip: Option<FillRule>,
+    text_matrix: Transform,
+    text_line_matrix: Transform,
+    ///読み込み済みのフォント。(埋め込まれていない・読み込めないフォントはNone。)
+    fonts: HashMap<PlainRef, Option<Rc<RenderFont>>>,
+    ///実行中のForm XObject。(循環参照を防ぐため。)
+    forms: Vec<PlainRef>,
+    ///描画しなかった内容。("SHADING"・"PATTERN"・"TYPE3 FONT"など)
+    unsupported: BTreeSet<&'static str>,
+}
+
+impl<'a, R: Resolve> PageRenderer<'a, R> {
+    fn new(resolve: &'a R, pixmap: Pixmap, ctm: Transform) -> Self {
+        PageRenderer {
+            resolve,
+            pixmap,
+            state: GraphicsState::new(ctm),
+            saved_states: Vec::new(),
+            path: PathBuilder::new(),
+            pending_clip: None,
+            text_matrix: Transform::identity(),
+            text_line_matrix: Transform::identity(),
+            fonts: HashMap::new(),
+            forms: Vec::new(),
+            unsupported: BTreeSet::new(),
+        }
+    }
+
+    ///命令列を実行する。(描画できない命令はログを出力して無視する。)
+    fn execute(&mut self, ops: &[Op], resources: &Resources) {
+        for op in ops {
+            if let Err(e) = self.execute_op(op, resources) {
+                warn!("COULD NOT RENDER OPERATION. IT IGNORED. ERR: {}", e);
+                self.unsupported.insert("FAILED OPERATION");
+            }
+        }
+    }
+
+    fn execute_op(&mut self, op: &Op, resources: &Resources) -> Result<(), PdfError> {
+        match op {
+            Op::Save => self.saved_states.push(self.state.clone()),
+            Op::Restore => {
+                if let Some(state) = self.saved_states.pop() {
+                    self.state = state;
+                }
+            }
+            Op::Transform { matrix } => {
+                self.state.ctm = self.state.ctm.pre_concat(to_transform(matrix));
+            }
+            Op::LineWidth { width } => self.state.line_width = *width,
+            Op::LineCap { cap } => {
+                self.state.line_cap = match cap {
+                    content::LineCap::Butt => LineCap::Butt,
+                    content::LineCap::Round => LineCap::Round,
+                    content::LineCap::Square => LineCap::Square,
+                }
+            }
+            Op::LineJoin { join } => {
+                self.state.line_join = match join {
+                    content::LineJoin::Miter => LineJoin::Miter,
+                    content::LineJoin::Round => LineJoin::Round,
+                    content::LineJoin::Bevel => LineJoin::Bevel,
+                }
+            }
+            Op::MiterLimit { limit } => self.state.miter_limit = *limit,
+            Op::Dash { pattern, phase } => self.state.dash = to_stroke_dash(pattern, *phase),
+            Op::GraphicsState { name } => {
+                if let Some(parameters) = resources.graphics_states.get(name) {
+                    if let Some(line_width) = parameters.line_width {
+                        self.state.line_width = line_width;
+                    }
+                    if let Some(fill_alpha) = parameters.fill_alpha {
+                        self.state.fill_alpha = fill_alpha.clamp(0.0, 1.0);
+                    }
+                    if let Some(stroke_alpha) = parameters.stroke_alpha {
+                        self.state.stroke_alpha = stroke_alpha.clamp(0.0, 1.0);
+                    }
+                }
+            }
+
+            Op::FillColorSpace { name } => {
+                self.state.fill = initial_color(name.as_str(), resources)?;
+            }
+            Op::StrokeColorSpace { name } => {
+                self.state.stroke = initial_color(name.as_str(), resources)?;
+            }
+            Op::FillColor { color } => set_color(&mut self.state.fill, color)?,
+            Op::StrokeColor { color } => set_color(&mut self.state.stroke, color)?,
+
+            Op::MoveTo { p } => self.path.move_to(p.x, p.y),
+            Op::LineTo { p } => self.path.line_to(p.x, p.y),
+            Op::CurveTo { c1, c2, p } => self.path.cubic_to(c1.x, c1.y, c2.x, c2.y, p.x, p.y),
+            Op::Rect { rect } => {
+                //幅・高さが負の矩形もあるため、4点のパスとして追加する。
+                self.path.move_to(rect.x, rect.y);
+                self.path.line_to(rect.x + rect.width, rect.y);
+                self.path.line_to(rect.x + rect.width, rect.y + rect.height);
+                self.path.line_to(rect.x, rect.y + rect.height);
+                self.path.close();
+            }
+            Op::Close => self.path.close(),
+            Op::Clip { winding } => self.pending_clip = Some(to_fill_rule(winding)),
+            Op::Fill { winding } => self.paint_path(Some(to_fill_rule(winding)), false),
+            Op::Stroke => self.paint_path(None, true),
+            Op::FillAndStroke { winding } => self.paint_path(Some(to_fill_rule(winding)), true),
+            Op::EndPath => self.paint_path(None, false),
+            Op::Shade { name } => {
+                self.unsupported.insert("SHADING");
+                if log_enabled!(Level::Debug) {
+                    info!(
+                        "SHADING IS NOT SUPPORTED. IT IGNORED. NAME: {}",
+                        name.as_str()
+                    );
+                }
+            }
+
+            Op::BeginText => {
+                self.text_matrix = Transform::identity();
+                self.text_line_matrix = Transform::identity();
+            }
+            Op::CharSpacing { char_space } => self.state.char_space = *char_space,
+            Op::WordSpacing { word_space } => self.state.word_space = *word_space,
+            Op::TextScaling { horiz_scale } => self.state.horiz_scale = *horiz_scale / 100.0,
+            Op::Leading { leading } => self.state.leading = *leading,
+            Op::TextFont { name, size } => {
+                self.state.font_size = *size;
+                self.state.font = self.load_font(name.as_str(), resources)?;
+            }
+            Op::TextRenderMode { mode } => self.state.render_mode = *mode,
+            Op::TextRise { rise } => self.state.rise = *rise,
+            Op::MoveTextPosition { translation } => {
+                self.move_text_line(translation.x, translation.y)
+            }
+            Op::SetTextMatrix { matrix } => {
+                self.text_matrix = to_transform(matrix);
+                self.text_line_matrix = self.text_matrix;
+            }
+            Op::TextNewline => self.move_text_line(0.0, -self.state.leading),
+            Op::TextDraw { text } => self.show_text(text.as_bytes()),
+            Op::TextDrawAdjusted { array } => {
+                for item in array {
+                    match item {
+                        TextDrawAdjusted::Text(text) => self.show_text(text.as_bytes()),
+                        TextDrawAdjusted::Spacing(spacing) => {
+                            let tx: f32 =
+                                -spacing / 1000.0 * self.state.font_size * self.state.horiz_scale;
+                            self.text_matrix = self.text_matrix.pre_translate(tx, 0.0);
+                        }
+                    }
+                }
+            }
+
+            Op::XObject { name } => {
+                let r = match resources.xobjects.get(name) {
+                    Some(r) => *r,
+                    None => {
+                        return Err(PdfError::Other {
+                            msg: format!("XOBJECT NOT FOUND. NAME: {}", name.as_str()),
+                        })
+                    }
+                };
+                let xobject: RcRef<XObject> = self.resolve.get(r)?;
+                match *xobject {
+                    XObject::Image(ref image) => self.draw_image(image)?,
+                    XObject::Form(ref form) => {
+                        let form_ref: PlainRef = r.get_inner();
+                        if self.forms.contains(&form_ref) || self.forms.len() >= MAX_FORM_DEPTH {
+                            return Err(PdfError::Other {
+                                msg: format!("FORM XOBJECT IS RECURSIVE. NAME: {}", name.as_str()),
+                            });
+                        }
+                        let ops: Vec<Op> = form.operations(self.resolve)?;
+                        let form_resources: &Resources = match form.dict().resources {
+                            Some(ref form_resources) => form_resources,
+                            None => resources,
+                        };
+                        self.saved_states.push(self.state.clone());
+                        if let Some(matrix) = form.dict().matrix.as_ref().and_then(parse_matrix) {
+                            self.state.ctm = self.state.ctm.pre_concat(matrix);
+                        }
+                        let bbox = &form.dict().bbox;
+                        let mut bbox_path = PathBuilder::new();
+                        bbox_path.move_to(bbox.left, bbox.bottom);
+                        bbox_path.line_to(bbox.right, bbox.bottom);
+                        bbox_path.line_to(bbox.right, bbox.top);
+                        bbox_path.line_to(bbox.left, bbox.top);
+                        bbox_path.close();
+                        self.clip(bbox_path.finish().as_ref(), FillRule::Winding);
+                        self.forms.push(form_ref);
+                        self.execute(&ops, form_resources);
+                        self.forms.pop();
+                        if let Some(state) = self.saved_states.pop() {
+                            self.state = state;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Op::InlineImage { image } => {
+                let image: Arc<ImageXObject> = normalize_inline_image(image, resources);
+                self.draw_image(&image)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    ///構築中のパスを塗りつぶし・線の描画に使用し、保留中のクリッピングを適用する。
+    /// # Arguments
+    /// * `fill_rule` - 塗りつぶす場合の規則。(塗りつぶさない場合はNone。)
+    /// * `stroke` - 線を描画する場合はtrue。
+    fn paint_path(&mut self, fill_rule: Option<FillRule>, stroke: bool) {
+        let path: Option<Path> = std::mem::replace(&mut self.path, PathBuilder::new()).finish();
+        if let Some(ref path) = path {
+            if let Some(fill_rule) = fill_rule {
+                self.fill_path(path, fill_rule, self.state.ctm);
+            }
+            if stroke {
+                self.stroke_path(path, self.state.ctm);
+            }
+        }
+        if let Some(clip_rule) = self.pending_clip.take() {
+            self.clip(path.as_ref(), clip_rule);
+        }
+    }
+
+    fn fill_path(&mut self, path: &Path, fill_rule: FillRule, transform: Transform) {
+        if self.state.fill.pattern {
+            self.unsupported.insert("PATTERN");
+        }
+        if let Some(paint) = to_paint(&self.state.fill, self.state.fill_alpha) {
+            self.pixmap.fill_path(
+                path,
+                &paint,
+                fill_rule,
+                transform,
+                self.state.clip.as_deref(),
+            );
+        }
+    }
+
+    fn stroke_path(&mut self, path: &Path, transform: Transform) {
+        if self.state.stroke.pattern {
+            self.unsupported.insert("PATTERN");
+        }
+        if let Some(paint) = to_paint(&self.state.stroke, self.state.stroke_alpha) {
+            let stroke = Stroke {
+                width: self.state.line_width.max(0.0),
+                miter_limit: self.state.miter_limit,
+                line_cap: self.state.line_cap,
+                line_join: self.state.line_join,
+                dash: self.state.dash.clone(),
+            };
+            self.pixmap
+                .stroke_path(path, &paint, &stroke, transform, self.state.clip.as_deref());
+        }
+    }
+
+    ///クリッピング領域をパスとの共通部分に狭める。(パスが空の場合は全て描画されなくなる。)
+    fn clip(&mut self, path: Option<&Path>, fill_rule: FillRule) {
+        let mut mask: Mask = match self.state.clip {
+            Some(ref clip) => (**clip).clone(),
+            None => match Mask::new(self.pixmap.width(), self.pixmap.height()) {
+                Some(mut mask) => {
+                    if let Some(path) = path {
+                        mask.fill_path(path, fill_rule, true, self.state.ctm);
+                    }
+                    self.state.clip = Some(Rc::new(mask));
+                    return;
+                }
+                None => return,
+            },
+        };
+        match path {
+            Some(path) => mask.intersect_path(path, fill_rule, true, self.state.ctm),
+            None => mask.clear(),
+        }
+        self.state.clip = Some(Rc::new(mask));
+    }
+
+    ///テキスト行の位置を移動する。(Td・T*)
+    fn move_text_line(&mut self, tx: f32, ty: f32) {
+        self.text_line_matrix = self.text_line_matrix.pre_translate(tx, ty);
+        self.text_matrix = self.text_line_matrix;
+    }
+
+    ///リソースのフォントを読み込む。(同じフォントは読み込み済みのものを使用する。)
+    fn load_font(
+        &mut self,
+        name: &str,
+        resources: &Resources,
+    ) -> Result<Option<Rc<RenderFont>>, PdfError> {
+        let lazy = resources
+            .fonts
+            .iter()
+            .find(|(font_name, _)| font_name.as_str() == name)
+            .map(|(_, lazy)| lazy)
+            .ok_or_else(|| PdfError::Other {
+                msg: format!("FONT NOT FOUND. NAME: {}", name),
+            })?;
+        let font = lazy.load(self.resolve)?;
+        let font_ref: Option<PlainRef> = match font {
+            MaybeRef::Indirect(ref r) => Some(r.get_ref().get_inner()),
+            MaybeRef::Direct(_) => None,
+        };
+        if let Some(loaded) = font_ref.and_then(|r| self.fonts.get(&r)) {
+            return Ok(loaded.clone());
+        }
+        //Type3フォント(グリフをコンテンツストリームで定義する)は描画しない。
+        if font.subtype == FontType::Type3 {
+            self.unsupported.insert("TYPE3 FONT");
+            if log_enabled!(Level::Debug) {
+                info!(
+                    "TYPE3 FONT IS NOT SUPPORTED. TEXT IS NOT RENDERED. NAME: {}",
+                    name
+                );
+            }
+            return Ok(None);
+        }
+        let loaded: Option<Rc<RenderFont>> = match RenderFont::load(&font, self.resolve) {
+            Ok(Some(render_font)) => {
+                if render_font.is_substituted() {
+                    self.unsupported.insert("SUBSTITUTED FONT");
+                    if log_enabled!(Level::Debug) {
+                        info!(
+                            "FONT IS NOT EMBEDDED. TEXT IS RENDERED WITH SUBSTITUTE FONT. NAME: {}",
+                            name
+                        );
+                    }
+                }
+                Some(Rc::new(render_font))
+            }
+            Ok(None) => {
+                self.unsupported.insert("NON-EMBEDDED FONT");
+                if log_enabled!(Level::Debug) {
+                    info!("FONT IS NOT EMBEDDED. TEXT IS NOT RENDERED. NAME: {}", name);
+                }
+                None
+            }
+            Err(e) => {
+                self.unsupported.insert("UNSUPPORTED FONT");
+                warn!(
+                    "COULD NOT LOAD FONT. TEXT IS NOT RENDERED. NAME: {} ERR: {}",
+                    name, e
+                );
+                None
+            }
+        };
+        if let Some(r) = font_ref {
+            self.fonts.insert(r, loaded.clone());
+        }
+        Ok(loaded)
+    }
+
+    ///文字列を描画し、テキスト行列を送り幅だけ進める。(Tj・TJ)
+    fn show_text(&mut self, text: &[u8]) {
+        let font: Rc<RenderFont> = match self.state.font {
+            Some(ref font) => Rc::clone(font),
+            None => return,
+        };
+        let font_size: f32 = self.state.font_size;
+        let horiz_scale: f32 = self.state.horiz_scale;
+        let (fill, stroke): (bool, bool) = match self.state.render_mode {
+            TextMode::Invisible => (false, false),
+            TextMode::Stroke | TextMode::StrokeAndClip => (false, true),
+            TextMode::FillThenStroke => (true, true),
+            _ => (true, false),
+        };
+        for code in font.codes(text) {
+            let glyph: Rc<Glyph> = font.glyph(code);
+            if let Some(ref path) = glyph.path {
+                //テキスト空間からユーザー空間への変換。
+                let text_to_user: Transform = self.text_matrix.pre_concat(Transform::from_row(
+                    font_size * horiz_scale,
+                    0.0,
+                    0.0,
+                    font_size,
+                    0.0,
+                    self.state.rise,
+                ));
+                if let Some(path) = path.clone().transform(text_to_user) {
+                    if fill {
+                        self.fill_path(&path, FillRule::Winding, self.state.ctm);
+                    }
+                    if stroke {
+                        self.stroke_path(&path, self.state.ctm);
+                    }
+                }
+            }
+            let mut tx: f32 = glyph.advance * font_size + self.state.char_space;
+            //単語間隔は1バイトのコード32(空白)のみに適用する。
+            if code == 32 && !font.is_cid() {
+                tx += self.state.word_space;
+            }
+            self.text_matrix = self.text_matrix.pre_translate(tx * horiz_scale, 0.0);
+        }
+    }
+
+    ///画像XObjectもしくはインライン画像を単位正方形に描画する。
+    fn draw_image(&mut self, img: &ImageXObject) -> Result<(), PdfError> {
+        let (data, filter) = image_data(img, self.resolve)?;
+        let rgba: RgbaImage = if img.image_mask {
+            //ステンシルマスクは塗りつぶしの色で描画する。
+            if self.state.fill.pattern {
+                self.unsupported.insert("PATTERN");
+                return Ok(());
+            }
+            let samples: RawSamples = decode_raw_samples(img, &data, filter)?;
+            stencil_to_rgba(&samples, img, self.state.fill.rgb)
+        } else {
+            let base: DynamicImage = match decode_image(img, &data, filter)? {
+                Some(base) => base,
+                None => {
+                    return Err(PdfError::Other {
+                        msg: format!(
+                            "UNSUPPORTED IMAGE FILTER FOR RENDERING. FILTER: {:?}",
+                            filter
+                        ),
+                    })
+                }
+            };
+            let masked: Option<RgbaImage> = if has_image_mask(img) {
+                apply_image_mask(img, &base, &data, filter, self.resolve)?
+            } else {
+                None
+            };
+            masked.unwrap_or_else(|| base.to_rgba8())
+        };
+        let (width, height) = rgba.dimensions();
+        let image_pixmap: Pixmap = match rgba_to_pixmap(rgba) {
+            Some(image_pixmap) => image_pixmap,
+            None => return Ok(()),
+        };
+        //画像空間(左上原点)から単位正方形(左下原点)への変換。
+        let transform: Transform = self.state.ctm.pre_concat(Transform::from_row(
+            1.0 / width as f32,
+            0.0,
+            0.0,
+            -1.0 / height as f32,
+            0.0,
+            1.0,
+        ));
+        //拡大する場合は/Interpolateが指定されていなければ補間しない。
+        let device_width: f32 = (transform.sx.powi(2) + transform.ky.powi(2)).sqrt() * width as f32;
+        let quality: FilterQuality = if device_width > width as f32 && !img.interpolate {
+            FilterQuality::Nearest
+        } else {
+            FilterQuality::Bilinear
+        };
+        let paint = PixmapPaint {
+            opacity: self.state.fill_alpha,
+            blend_mode: BlendMode::SourceOver,
+            quality,
+        };
+        self.pixmap.draw_pixmap(
+            0,
+            0,
+            image_pixmap.as_ref(),
+            &paint,
+            transform,
+            self.state.clip.as_deref(),
+        );
+        Ok(())
+    }
+}
+
+///PDFの行列をtiny-skiaの変換に変換する。
+fn to_transform(matrix: &Matrix) -> Transform {
+    Transform::from_row(matrix.a, matrix.b, matrix.c, matrix.d, matrix.e, matrix.f)
+}
+
+///Form XObjectの/Matrix(6個の数値の配列)を変換に変換する。
+fn parse_matrix(matrix: &Primitive) -> Option<Transform> {
+    let values: Vec<f32> = matrix
+        .as_array()
+        .ok()?
+        .iter()
+        .map(|value| value.as_number().ok())
+        .collect::<Option<Vec<f32>>>()?;
+    match values.as_slice() {
+        [a, b, c, d, e, f] => Some(Transform::from_row(*a, *b, *c, *d, *e, *f)),
+        _ => None,
+    }
+}
+
+fn to_fill_rule(winding: &Winding) -> FillRule {
+    match winding {
+        Winding::NonZero => FillRule::Winding,
+        Winding::EvenOdd => FillRule::EvenOdd,
+    }
+}
+
+///破線のパターンを変換する。(実線の場合はNone。)
+fn to_stroke_dash(pattern: &[f32], phase: f32) -> Option<StrokeDash> {
+    if pattern.iter().all(|value| *value <= 0.0) {
+        return None;
+    }
+    //要素数が奇数のパターンは2回繰り返したものと同じ。
+    let mut dash: Vec<f32> = pattern.to_vec();
+    if dash.len() % 2 == 1 {
+        dash.extend_from_slice(pattern);
+    }
+    StrokeDash::new(dash, phase)
+}
+
+///塗りつぶし・線の色から描画に使用するペイントを作成する。(パターンの場合はNone。)
+fn to_paint(color: &PaintColor, alpha: f32) -> Option<Paint<'static>> {
+    if color.pattern {
+        return None;
+    }
+    let mut paint = Paint::default();
+    paint.set_color(tiny_skia::Color::from_rgba(
+        color.rgb[0].clamp(0.0, 1.0),
+        color.rgb[1].clamp(0.0, 1.0),
+        color.rgb[2].clamp(0.0, 1.0),
+        alpha.clamp(0.0, 1.0),
+    )?);
+    paint.anti_alias = true;
+    Some(paint)
+}
+
+///色空間の演算子(cs・CS)で指定された色空間と、その初期値の色を作成する。
+fn initial_color(name: &str, resources: &Resources) -> Result<PaintColor, PdfError> {
+    let color_space: ColorSpace = match name {
+        "DeviceGray" | "G" => ColorSpace::DeviceGray,
+        "DeviceRGB" | "RGB" => ColorSpace::DeviceRGB,
+        "DeviceCMYK" | "CMYK" => ColorSpace::DeviceCMYK,
+        "Pattern" => ColorSpace::Pattern,
+        _ => match resources
+            .color_spaces
+            .iter()
+            .find(|(color_space_name, _)| color_space_name.as_str() == name)
+        {
+            Some((_, color_space)) => color_space.clone(),
+            None => {
+                return Err(PdfError::Other {
+                    msg: format!("COLOR SPACE NOT FOUND. NAME: {}", name),
+                })
+            }
+        },
+    };
+    if matches!(color_space, ColorSpace::Pattern) {
+        return Ok(PaintColor {
+            color_space: Some(color_space),
+            rgb: [0.0; 3],
+            pattern: true,
+        });
+    }
+    //初期値はSeparation・DeviceNは濃度1.0、それ以外は全成分0とする。
+    let rgb: [f32; 3] = {
+        let converter: ImageColorSpace = ImageColorSpace::new(&color_space)?;
+        let initial: f32 = match converter {
+            ImageColorSpace::Tint { .. } => 1.0,
+            _ => 0.0,
+        };
+        converter.to_rgb(&vec![initial; converter.components()])?
+    };
+    Ok(PaintColor {
+        color_space: Some(color_space),
+        rgb,
+        pattern: false,
+    })
+}
+
+///色の演算子(g・rg・k・sc・scnなど)で指定された色を設定する。
+fn set_color(paint_color: &mut PaintColor, color: &Color) -> Result<(), PdfError> {
+    match color {
+        Color::Gray(gray) => {
+            *paint_color = PaintColor {
+                color_space: Some(ColorSpace::DeviceGray),
+                rgb: [*gray; 3],
+                pattern: false,
+            };
+        }
+        Color::Rgb(rgb) => {
+            *paint_color = PaintColor {
+                color_space: Some(ColorSpace::DeviceRGB),
+                rgb: [rgb.red, rgb.green, rgb.blue],
+                pattern: false,
+            };
+        }
+        Color::Cmyk(cmyk) => {
+            *paint_color = PaintColor {
+                color_space: Some(ColorSpace::DeviceCMYK),
+                rgb: ImageColorSpace::Cmyk.to_rgb(&[
+                    cmyk.cyan,
+                    cmyk.magenta,
+                    cmyk.yellow,
+                    cmyk.key,
+                ])?,
+                pattern: false,
+            };
+        }
+        Color::Other(values) => {
+            //パターン名を含む場合(scn)はパターンとして扱う。
+            if paint_color.pattern || values.iter().any(|v| matches!(v, Primitive::Name(_))) {
+                paint_color.pattern = true;
+                return Ok(());
+            }
+            let values: Vec<f32> = values
+                .iter()
+                .map(|value| value.as_number())
+                .collect::<Result<Vec<f32>, PdfError>>()?;
+            let converter: ImageColorSpace = match paint_color.color_space {
+                Some(ref color_space) => ImageColorSpace::new(color_space)?,
+                None => ImageColorSpace::from_components(values.len())?,
+            };
+            let mut values: Vec<f32> = values;
+            values.resize(converter.components().max(values.len()), 0.0);
+            paint_color.rgb = converter.to_rgb(&values)?;
+        }
+    }
+    Ok(())
+}
+
+///ステンシルマスクのサンプル値から塗りつぶしの色の画像を作成する。
+///(Decode配列が[0 1]の場合はサンプル値0、[1 0]の場合はサンプル値1の部分を塗りつぶす。)
+fn stencil_to_rgba(samples: &RawSamples, img: &ImageXObject, rgb: [f32; 3]) -> RgbaImage {
+    let painted_value: u16 = if is_inverted_decode(img.decode.as_deref()) {
+        1
+    } else {
+        0
+    };
+    let color: [u8; 3] = rgb.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    RgbaImage::from_fn(img.width, img.height, |x, y| {
+        let index: usize = y as usize * img.width as usize + x as usize;
+        let alpha: u8 = match samples.values.get(index) {
+            Some(value) if *value == painted_value => 255,
+            _ => 0,
+        };
+        image::Rgba([color[0], color[1], color[2], alpha])
+    })
+}
+
+///RGBAの画像をtiny-skiaのピクセルマップ(乗算済みアルファ)に変換する。
+fn rgba_to_pixmap(rgba: RgbaImage) -> Option<Pixmap> {
+    let size: IntSize = IntSize::from_wh(rgba.width(), rgba.height())?;
+    let mut data: Vec<u8> = rgba.into_raw();
+    for pixel in data.chunks_exact_mut(4) {
+        let alpha: u16 = pixel[3] as u16;
+        for c in pixel[..3].iter_mut() {
+            *c = ((*c as u16 * alpha + 127) / 255) as u8;
+        }
+    }
+    Pixmap::from_vec(data, size)
+}
+
+///描画結果のピクセルマップをRGBの画像に変換する。(背景が白で不透明なため、アルファ値は捨てる。)
+fn pixmap_to_rgb(pixmap: &Pixmap) -> RgbImage {
+    let data: Vec<u8> = pixmap
+        .data()
+        .chunks_exact(4)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+    RgbImage::from_raw(pixmap.width(), pixmap.height(), data)
+        .unwrap_or_else(|| RgbImage::new(pixmap.width(), pixmap.height()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_stroke_dash() {
+        assert!(to_stroke_dash(&[], 0.0).is_none());
+        assert!(to_stroke_dash(&[0.0, 0.0], 0.0).is_none());
+        assert!(to_stroke_dash(&[3.0], 0.0).is_some());
+        assert!(to_stroke_dash(&[3.0, 1.0], 1.0).is_some());
+    }
+
+    #[test]
+    fn test_set_color() {
+        let mut color = PaintColor::black();
+        set_color(
+            &mut color,
+            &Color::Cmyk(content::Cmyk {
+                cyan: 1.0,
+                magenta: 0.0,
+                yellow: 0.0,
+                key: 0.0,
+            }),
+        )
+        .unwrap();
+        assert_eq!(color.rgb, [0.0, 1.0, 1.0]);
+        //DeviceCMYKが設定された状態でscの4成分の値を指定する。
+        set_color(
+            &mut color,
+            &Color::Other(vec![
+                Primitive::Number(0.0),
+                Primitive::Number(1.0),
+                Primitive::Number(0.0),
+                Primitive::Number(0.0),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(color.rgb, [1.0, 0.0, 1.0]);
+        set_color(
+            &mut color,
+            &Color::Other(vec![Primitive::Name("P0".into())]),
+        )
+        .unwrap();
+        assert!(color.pattern);
+        assert!(to_paint(&color, 1.0).is_none());
+    }
+
+    #[test]
+    fn test_rgba_to_pixmap_premultiply() {
+        let rgba = RgbaImage::from_raw(2, 1, vec![255, 0, 0, 128, 10, 20, 30, 255]).unwrap();
+        let pixmap = rgba_to_pixmap(rgba).unwrap();
+        assert_eq!(pixmap.data(), &[128, 0, 0, 128, 10, 20, 30, 255]);
+    }
+
+    #[test]
+    fn test_draw_path_and_clip() {
+        let pixmap = Pixmap::new(20, 20).unwrap();
+        let mut renderer =
+            PageRenderer::new(&pdf::object::NoResolve, pixmap, Transform::identity());
+        renderer.state.fill.rgb = [1.0, 0.0, 0.0];
+        //左半分にクリッピングしてから全体を塗りつぶす。
+        renderer
+            .path
+            .push_rect(tiny_skia::Rect::from_xywh(0.0, 0.0, 10.0, 20.0).unwrap());
+        renderer.pending_clip = Some(FillRule::Winding);
+        renderer.paint_path(None, false);
+        renderer
+            .path
+            .push_rect(tiny_skia::Rect::from_xywh(0.0, 0.0, 20.0, 20.0).unwrap());
+        renderer.paint_path(Some(FillRule::Winding), false);
+        let image = pixmap_to_rgb(&renderer.pixmap);
+        assert_eq!(image.get_pixel(5, 5).0, [255, 0, 0]);
+        assert_eq!(image.get_pixel(15, 5).0, [0, 0, 0]);
+    }
+
+    #[test]
+    ///埋め込まれたTrueTypeフォントのテキストを描画し、パターンの塗りつぶしが記録されることを確認する。
+    ///(render.ttfは"A"が1emの正方形、"B"が下半分の長方形のグリフを持つ。)
+    fn test_draw_text_and_pattern() {
+        let mut pixmap = Pixmap::new(100, 40).unwrap();
+        pixmap.fill(tiny_skia::Color::WHITE);
+        let mut renderer = PageRenderer::new(
+            &pdf::object::NoResolve,
+            pixmap,
+            Transform::from_row(1.0, 0.0, 0.0, -1.0, 0.0, 40.0),
+        );
+        let font =
+            RenderFont::from_program(std::fs::read("test_pdf/render_pdf/render.ttf").unwrap())
+                .unwrap();
+        renderer.state.font = Some(Rc::new(font));
+        renderer.state.font_size = 20.0;
+        renderer.state.fill.rgb = [0.0, 0.0, 1.0];
+        renderer.move_text_line(10.0, 10.0);
+        renderer.show_text(b"ABA");
+        let image = pixmap_to_rgb(&renderer.pixmap);
+        assert_eq!(image.get_pixel(20, 20).0, [0, 0, 255]);
+        assert_eq!(image.get_pixel(40, 25).0, [0, 0, 255]);
+        assert_eq!(image.get_pixel(60, 20).0, [0, 0, 255]);
+        assert_eq!(image.get_pixel(40, 15).0, [255, 255, 255]);
+        assert_eq!(image.get_pixel(80, 20).0, [255, 255, 255]);
+        assert!(renderer.unsupported.is_empty());
+
+        renderer.state.fill.pattern = true;
+        renderer
+            .path
+            .push_rect(tiny_skia::Rect::from_xywh(0.0, 0.0, 100.0, 40.0).unwrap());
+        renderer.paint_path(Some(FillRule::Winding), false);
+        assert_eq!(pixmap_to_rgb(&renderer.pixmap), image);
+        assert_eq!(
+            renderer.unsupported.into_iter().collect::<Vec<_>>(),
+            vec!["PATTERN"]
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use log::{info, log_enabled, warn, Level};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+///保存先ディレクトリに作成する再開用マニフェストのファイル名。
+pub(crate) const RESUME_MANIFEST_FILE_NAME: &str = ".resume_manifest.tsv";
+
+///中断した処理を再開するため、保存が完了した画像を記録するマニフェスト。
+///1行に「画像のキー<TAB>ファイル名」を記録する。
+///(ファイル名は実行時刻を含み実行ごとに変わるため、ページ番号と埋め込みオブジェクト名から作成したキーで画像を識別する。)
+pub(crate) struct ResumeManifest {
+    ///前回までの実行で保存が完了した画像(キーとファイル名)。
+    completed: HashMap<String, String>,
+    ///追記用のマニフェストファイル。(複数のスレッドから書き込むためMutexで保護する。永続化しない場合はNone。)
+    writer: Option<Mutex<File>>,
+}
+
+impl ResumeManifest {
+    ///永続化しない空のマニフェストを作成する。(保存先ディレクトリを作成しない場合に使用する。)
+    pub(crate) fn new() -> Self {
+        ResumeManifest {
+            completed: HashMap::new(),
+            writer: None,
+        }
+    }
+
+    ///空のマニフェストを作成する。(既に存在する場合は空にする。)
+    /// # Arguments
+    /// * `dest_dir_path` - 画像の保存先ディレクトリ。
+    pub(crate) fn create(dest_dir_path: &Path) -> std::io::Result<Self> {
+        let writer: File = File::create(dest_dir_path.join(RESUME_MANIFEST_FILE_NAME))?;
+        Ok(ResumeManifest {
+            completed: HashMap::new(),
+            writer: Some(Mutex::new(writer)),
+        })
+    }
+
+    ///前回の実行で作成されたマニフェストを読み込む。
+    ///(書き込み途中で中断された不完全な行は無視し、有効な行のみでマニフェストを作成し直す。)
+    /// # Arguments
+    /// * `dest_dir_path` - 画像の保存先ディレクトリ。
+    /// # Returns
+    /// * マニフェストが存在する場合:読み込んだマニフェスト。
+    /// * マニフェストが存在しない場合:None。
+    pub(crate) fn open(dest_dir_path: &Path) -> std::io::Result<Option<Self>> {
+        let manifest_path: PathBuf = dest_dir_path.join(RESUME_MANIFEST_FILE_NAME);
+        if !manifest_path.is_file() {
+            return Ok(None);
+        }
+        let mut completed: HashMap<String, String> = HashMap::new();
+        let reader = BufReader::new(File::open(&manifest_path)?);
+        for line in reader.split(b'\n') {
+            let line: Vec<u8> = line?;
+            let line: &str = match std::str::from_utf8(&line) {
+                Ok(line) => line,
+                Err(_) => continue,
+            };
+            //書き込み途中の行(タブを含まない、もしくはファイルが存在しない行)は無視する。
+            if let Some((key, file_name)) = line.rsplit_once('\t') {
+                if !key.is_empty() && dest_dir_path.join(file_name).is_file() {
+                    completed.insert(key.to_string(), file_name.to_string());
+                }
+            }
+        }
+        let mut writer: File = File::create(&manifest_path)?;
+        for (key, file_name) in completed.iter() {
+            writeln!(writer, "{}\t{}", key, file_name)?;
+        }
+        writer.flush()?;
+        Ok(Some(ResumeManifest {
+            completed,
+            writer: Some(Mutex::new(writer)),
+        }))
+    }
+
+    ///前回までの実行で保存が完了していればtrueを返す。
+    pub(crate) fn contains(&self, key: &str) -> bool {
+        self.completed.contains_key(key)
+    }
+
+    ///前回までの実行で保存したファイルの名前を取得する。
+    pub(crate) fn file_name(&self, key: &str) -> Option<&str> {
+        self.completed.get(key).map(|s| s.as_str())
+    }
+
+    ///前回までの実行で保存が完了した画像の数を取得する。
+    pub(crate) fn len(&self) -> usize {
+        self.completed.len()
+    }
+
+    ///保存が完了した画像を記録する。
+    ///(1つの画像から複数のファイルを保存する場合は、全て書き込めた後にまとめて記録する。)
+    /// # Arguments
+    /// * `entries` - 画像のキーと保存したファイルのパス。
+    pub(crate) fn record(&self, entries: &[(String, PathBuf)]) -> std::io::Result<()> {
+        let writer = match self.writer {
+            Some(ref writer) => writer,
+            None => return Ok(()),
+        };
+        let mut lines: String = String::new();
+        for (key, path) in entries {
+            let file_name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+            lines.push_str(&format!("{}\t{}\n", key, file_name));
+        }
+        //パニックしたスレッドがロックを保持していた場合もマニフェストへの記録は続ける。
+        let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+        writer.write_all(lines.as_bytes())?;
+        writer.flush()
+    }
+
+    ///マニフェストに記録されていないファイル(中断により書き込みが完了しなかったファイル)を削除する。
+    /// # Arguments
+    /// * `dest_dir_path` - 画像の保存先ディレクトリ。
+    /// # Returns
+    /// * 成功時:削除したファイルの数。
+    pub(crate) fn remove_unrecorded_files(&self, dest_dir_path: &Path) -> std::io::Result<u64> {
+        let recorded: HashSet<&str> = self.completed.values().map(|s| s.as_str()).collect();
+        let mut removed: u64 = 0;
+        for entry in std::fs::read_dir(dest_dir_path)? {
+            let path: PathBuf = entry?.path();
+            let file_name: &str = match path.file_name().and_then(|s| s.to_str()) {
+                Some(file_name) => file_name,
+                None => continue,
+            };
+            if !path.is_file()
+                || file_name == RESUME_MANIFEST_FILE_NAME
+                || recorded.contains(file_name)
+            {
+                continue;
+            }
+            match std::fs::remove_file(&path) {
+                Ok(_) => {
+                    if log_enabled!(Level::Debug) {
+                        info!("INCOMPLETE FILE REMOVED. FILE: {}", path.display());
+                    }
+                    removed += 1;
+                }
+                Err(e) => {
+                    warn!(
+                        "COULD NOT REMOVE INCOMPLETE FILE. FILE: {} ERR: {}",
+                        path.display(),
+                        e
+                    );
+                }
+            }
+        }
+        Ok(removed)
+    }
+}
+
+///画像XObjectもしくはインライン画像を識別するキーを作成する。
+/// # Arguments
+/// * `page_count` - PDFのページ番号。
+/// * `object_name` - 埋め込みオブジェクト名。
+/// * `suffix` - ファイル名の接尾辞("_unmasked"など)。
+pub(crate) fn image_key(page_count: u64, object_name: &str, suffix: &str) -> String {
+    format!("image_{:06}_{}{}", page_count, object_name, suffix)
+}
+
+///描画したページを識別するキーを作成する。
+pub(crate) fn page_key(page_count: u64) -> String {
+    format!("page_{:06}", page_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, OpenOptions};
+
+    #[test]
+    fn test_resume_manifest() {
+        let dest_dir_path: PathBuf = std::env::temp_dir().join(format!(
+            "pdf_to_image_rs_resume_manifest_{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dest_dir_path).unwrap();
+        let manifest = ResumeManifest::create(&dest_dir_path).unwrap();
+        assert_eq!(manifest.len(), 0);
+        //保存が完了したファイルと、記録される前に中断されたファイルを作成する。
+        fs::write(dest_dir_path.join("a.png"), b"a").unwrap();
+        fs::write(dest_dir_path.join("b.png"), b"b").unwrap();
+        manifest
+            .record(&[(image_key(1, "Im1", ""), dest_dir_path.join("a.png"))])
+            .unwrap();
+        drop(manifest);
+        //書き込み途中の行を追加する。
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(dest_dir_path.join(RESUME_MANIFEST_FILE_NAME))
+            .unwrap();
+        file.write_all(b"image_000002_Im").unwrap();
+        drop(file);
+
+        let manifest = ResumeManifest::open(&dest_dir_path).unwrap().unwrap();
+        assert!(manifest.contains("image_000001_Im1"));
+        assert!(!manifest.contains(&image_key(2, "Im1", "")));
+        assert_eq!(manifest.len(), 1);
+        assert_eq!(manifest.remove_unrecorded_files(&dest_dir_path).unwrap(), 1);
+        let exists_a = dest_dir_path.join("a.png").is_file();
+        let exists_b = dest_dir_path.join("b.png").is_file();
+        fs::remove_dir_all(&dest_dir_path).unwrap();
+        assert!(exists_a);
+        assert!(!exists_b);
+        assert_eq!(page_key(3), "page_000003");
+        //永続化しないマニフェストは記録してもファイルを作成しない。
+        let in_memory = ResumeManifest::new();
+        assert!(in_memory
+            .record(&[(page_key(1), PathBuf::from("page_000001.png"))])
+            .is_ok());
+        assert_eq!(in_memory.len(), 0);
+    }
+}
@@ -0,0 +1,767 @@
+use crate::font_encoding::{glyph_name_to_unicode, STANDARD_ENCODING};
+
+///代替フォントのフォントプログラム。
+///(DejaVuフォントから標準14フォントのエンコーディングで使用する文字のみを抜き出したもの。fonts/LICENSEを参照。)
+static SANS: [&[u8]; 4] = [
+    include_bytes!("../fonts/DejaVuSans.ttf"),
+    include_bytes!("../fonts/DejaVuSans-Bold.ttf"),
+    include_bytes!("../fonts/DejaVuSans-Oblique.ttf"),
+    include_bytes!("../fonts/DejaVuSans-BoldOblique.ttf"),
+];
+static SERIF: [&[u8]; 4] = [
+    include_bytes!("../fonts/DejaVuSerif.ttf"),
+    include_bytes!("../fonts/DejaVuSerif-Bold.ttf"),
+    include_bytes!("../fonts/DejaVuSerif-Italic.ttf"),
+    include_bytes!("../fonts/DejaVuSerif-BoldItalic.ttf"),
+];
+static MONO: [&[u8]; 4] = [
+    include_bytes!("../fonts/DejaVuSansMono.ttf"),
+    include_bytes!("../fonts/DejaVuSansMono-Bold.ttf"),
+    include_bytes!("../fonts/DejaVuSansMono-Oblique.ttf"),
+    include_bytes!("../fonts/DejaVuSansMono-BoldOblique.ttf"),
+];
+
+///Courierの文字幅。(全てのグリフで共通。)
+const COURIER_WIDTH: u16 = 600;
+
+///標準14フォントの書体。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Family {
+    Courier,
+    Helvetica,
+    Times,
+    Symbol,
+    ZapfDingbats,
+}
+
+///埋め込まれていないフォントの代わりに使用する標準フォント。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct StandardFont {
+    family: Family,
+    bold: bool,
+    italic: bool,
+    ///標準14フォント、もしくは文字幅が同じフォント(Arial・Times New Roman・Courier New)であればtrue。
+    ///(それ以外のフォントを近い書体で置き換えた場合はfalse。)
+    pub(crate) exact: bool,
+}
+
+impl StandardFont {
+    ///フォント名(BaseFont)から代わりに使用する標準フォントを選択する。
+    ///(サブセットの接頭辞("ABCDEF+")を除き、名前に含まれる書体・太字・斜体の語で判定する。)
+    /// # Arguments
+    /// * `base_font` - PDFのフォントのBaseFont。(指定されていない場合はNone。)
+    /// # Returns
+    /// * 代わりに使用する標準フォント。(判定できない場合はHelvetica。)
+    pub(crate) fn select(base_font: Option<&str>) -> Self {
+        let base_font: &str = base_font.unwrap_or("");
+        let name: String = match base_font.split_once('+') {
+            Some((prefix, name)) if prefix.len() == 6 => name,
+            _ => base_font,
+        }
+        .to_ascii_lowercase();
+        let has = |words: &[&str]| words.iter().any(|word| name.contains(word));
+        let (family, exact): (Family, bool) = if has(&["zapfdingbats", "dingbats"]) {
+            (Family::ZapfDingbats, true)
+        } else if has(&["symbol"]) {
+            (Family::Symbol, true)
+        } else if has(&["courier"]) {
+            (Family::Courier, true)
+        } else if has(&["times"]) {
+            (Family::Times, true)
+        } else if has(&["helvetica", "arial"]) {
+            (Family::Helvetica, true)
+        } else if has(&["mono", "consol", "typewriter"]) {
+            (Family::Courier, false)
+        } else if has(&["serif", "roman", "mincho", "georgia", "garamond", "century"])
+            && !has(&["sans"])
+        {
+            (Family::Times, false)
+        } else {
+            (Family::Helvetica, false)
+        };
+        StandardFont {
+            family,
+            bold: has(&["bold", "black", "heavy"]),
+            italic: has(&["italic", "oblique"]),
+            exact,
+        }
+    }
+
+    ///代わりに描画するTrueTypeのフォントプログラムを取得する。
+    pub(crate) fn program(&self) -> &'static [u8] {
+        let faces: &[&[u8]; 4] = match self.family {
+            Family::Courier => &MONO,
+            Family::Times => &SERIF,
+            _ => &SANS,
+        };
+        //Symbol・ZapfDingbatsの文字はDejaVu Sansの標準の太さのみに含まれる。
+        match self.family {
+            Family::Symbol | Family::ZapfDingbats => faces[0],
+            _ => faces[self.bold as usize + 2 * self.italic as usize],
+        }
+    }
+
+    ///グリフ名もしくはコードに対応する文字の代替フォントでのUnicodeを取得する。
+    /// # Arguments
+    /// * `code` - 文字コード。
+    /// * `name` - PDFのEncodingで指定されたグリフ名。(指定されていない場合はNone。)
+    /// # Returns
+    /// * 対応する文字。(組み込みのエンコーディングで未定義のコードはNone。)
+    pub(crate) fn char(&self, code: u8, name: Option<&str>) -> Option<char> {
+        if let Some(c) = name.and_then(glyph_name_to_unicode) {
+            return Some(c);
+        }
+        let unicode: u16 = match self.family {
+            Family::Symbol => SYMBOL_UNICODE[code as usize],
+            Family::ZapfDingbats => ZAPF_DINGBATS_UNICODE[code as usize],
+            _ => return glyph_name_to_unicode(STANDARD_ENCODING[code as usize]),
+        };
+        char::from_u32(unicode as u32).filter(|_| unicode != 0)
+    }
+
+    ///グリフ名もしくはコードに対応する文字の標準フォントでの文字幅(1000分の1em単位)を取得する。
+    /// # Arguments
+    /// * `code` - 文字コード。
+    /// * `name` - PDFのEncodingで指定されたグリフ名。(指定されていない場合はNone。)
+    /// # Returns
+    /// * 文字幅。(標準フォントにない文字、もしくは置き換えたフォントの場合はNone。)
+    pub(crate) fn width(&self, code: u8, name: Option<&str>) -> Option<f32> {
+        if !self.exact {
+            return None;
+        }
+        let widths: &[u16; 315] = match (self.family, self.bold, self.italic) {
+            (Family::Courier, _, _) => return Some(COURIER_WIDTH as f32),
+            (Family::Symbol, _, _) => return builtin_width(&SYMBOL_WIDTHS, code),
+            (Family::ZapfDingbats, _, _) => return builtin_width(&ZAPF_DINGBATS_WIDTHS, code),
+            (Family::Helvetica, false, _) => &HELVETICA_WIDTHS,
+            (Family::Helvetica, true, _) => &HELVETICA_BOLD_WIDTHS,
+            (Family::Times, false, false) => &TIMES_ROMAN_WIDTHS,
+            (Family::Times, true, false) => &TIMES_BOLD_WIDTHS,
+            (Family::Times, false, true) => &TIMES_ITALIC_WIDTHS,
+            (Family::Times, true, true) => &TIMES_BOLD_ITALIC_WIDTHS,
+        };
+        let name: &str = name.unwrap_or(STANDARD_ENCODING[code as usize]);
+        LATIN_GLYPH_NAMES
+            .binary_search(&name)
+            .ok()
+            .map(|index| widths[index] as f32)
+    }
+}
+
+///組み込みのエンコーディングのコードごとの文字幅の表から文字幅を取得する。(未定義のコードはNone。)
+fn builtin_width(widths: &[u16; 256], code: u8) -> Option<f32> {
+    Some(widths[code as usize] as f32).filter(|width| *width > 0.0)
+}
+
+//以下の文字幅はAdobeのCore14 AFMファイルから作成した。
+
+///ラテン文字の標準フォント(Courier・Helvetica・Times)のグリフ名。(文字幅の表の並び順。名前順に並べる。)
+static LATIN_GLYPH_NAMES: [&str; 315] = [
+    "A",
+    "AE",
+    "Aacute",
+    "Abreve",
+    "Acircumflex",
+    "Adieresis",
+    "Agrave",
+    "Amacron",
+    "Aogonek",
+    "Aring",
+    "Atilde",
+    "B",
+    "C",
+    "Cacute",
+    "Ccaron",
+    "Ccedilla",
+    "D",
+    "Dcaron",
+    "Dcroat",
+    "Delta",
+    "E",
+    "Eacute",
+    "Ecaron",
+    "Ecircumflex",
+    "Edieresis",
+    "Edotaccent",
+    "Egrave",
+    "Emacron",
+    "Eogonek",
+    "Eth",
+    "Euro",
+    "F",
+    "G",
+    "Gbreve",
+    "Gcommaaccent",
+    "H",
+    "I",
+    "Iacute",
+    "Icircumflex",
+    "Idieresis",
+    "Idotaccent",
+    "Igrave",
+    "Imacron",
+    "Iogonek",
+    "J",
+    "K",
+    "Kcommaaccent",
+    "L",
+    "Lacute",
+    "Lcaron",
+    "Lcommaaccent",
+    "Lslash",
+    "M",
+    "N",
+    "Nacute",
+    "Ncaron",
+    "Ncommaaccent",
+    "Ntilde",
+    "O",
+    "OE",
+    "Oacute",
+    "Ocircumflex",
+    "Odieresis",
+    "Ograve",
+    "Ohungarumlaut",
+    "Omacron",
+    "Oslash",
+    "Otilde",
+    "P",
+    "Q",
+    "R",
+    "Racute",
+    "Rcaron",
+    "Rcommaaccent",
+    "S",
+    "Sacute",
+    "Scaron",
+    "Scedilla",
+    "Scommaaccent",
+    "T",
+    "Tcaron",
+    "Tcommaaccent",
+    "Thorn",
+    "U",
+    "Uacute",
+    "Ucircumflex",
+    "Udieresis",
+    "Ugrave",
+    "Uhungarumlaut",
+    "Umacron",
+    "Uogonek",
+    "Uring",
+    "V",
+    "W",
+    "X",
+    "Y",
+    "Yacute",
+    "Ydieresis",
+    "Z",
+    "Zacute",
+    "Zcaron",
+    "Zdotaccent",
+    "a",
+    "aacute",
+    "abreve",
+    "acircumflex",
+    "acute",
+    "adieresis",
+    "ae",
+    "agrave",
+    "amacron",
+    "ampersand",
+    "aogonek",
+    "aring",
+    "asciicircum",
+    "asciitilde",
+    "asterisk",
+    "at",
+    "atilde",
+    "b",
+    "backslash",
+    "bar",
+    "braceleft",
+    "braceright",
+    "bracketleft",
+    "bracketright",
+    "breve",
+    "brokenbar",
+    "bullet",
+    "c",
+    "cacute",
+    "caron",
+    "ccaron",
+    "ccedilla",
+    "cedilla",
+    "cent",
+    "circumflex",
+    "colon",
+    "comma",
+    "commaaccent",
+    "copyright",
+    "currency",
+    "d",
+    "dagger",
+    "daggerdbl",
+    "dcaron",
+    "dcroat",
+    "degree",
+    "dieresis",
+    "divide",
+    "dollar",
+    "dotaccent",
+    "dotlessi",
+    "e",
+    "eacute",
+    "ecaron",
+    "ecircumflex",
+    "edieresis",
+    "edotaccent",
+    "egrave",
+    "eight",
+    "ellipsis",
+    "emacron",
+    "emdash",
+    "endash",
+    "eogonek",
+    "equal",
+    "eth",
+    "exclam",
+    "exclamdown",
+    "f",
+    "fi",
+    "five",
+    "fl",
+    "florin",
+    "four",
+    "fraction",
+    "g",
+    "gbreve",
+    "gcommaaccent",
+    "germandbls",
+    "grave",
+    "greater",
+    "greaterequal",
+    "guillemotleft",
+    "guillemotright",
+    "guilsinglleft",
+    "guilsinglright",
+    "h",
+    "hungarumlaut",
+    "hyphen",
+    "i",
+    "iacute",
+    "icircumflex",
+    "idieresis",
+    "igrave",
+    "imacron",
+    "iogonek",
+    "j",
+    "k",
+    "kcommaaccent",
+    "l",
+    "lacute",
+    "lcaron",
+    "lcommaaccent",
+    "less",
+    "lessequal",
+    "logicalnot",
+    "lozenge",
+    "lslash",
+    "m",
+    "macron",
+    "minus",
+    "mu",
+    "multiply",
+    "n",
+    "nacute",
+    "ncaron",
+    "ncommaaccent",
+    "nine",
+    "notequal",
+    "ntilde",
+    "numbersign",
+    "o",
+    "oacute",
+    "ocircumflex",
+    "odieresis",
+    "oe",
+    "ogonek",
+    "ograve",
+    "ohungarumlaut",
+    "omacron",
+    "one",
+    "onehalf",
+    "onequarter",
+    "onesuperior",
+    "ordfeminine",
+    "ordmasculine",
+    "oslash",
+    "otilde",
+    "p",
+    "paragraph",
+    "parenleft",
+    "parenright",
+    "partialdiff",
+    "percent",
+    "period",
+    "periodcentered",
+    "perthousand",
+    "plus",
+    "plusminus",
+    "q",
+    "question",
+    "questiondown",
+    "quotedbl",
+    "quotedblbase",
+    "quotedblleft",
+    "quotedblright",
+    "quoteleft",
+    "quoteright",
+    "quotesinglbase",
+    "quotesingle",
+    "r",
+    "racute",
+    "radical",
+    "rcaron",
+    "rcommaaccent",
+    "registered",
+    "ring",
+    "s",
+    "sacute",
+    "scaron",
+    "scedilla",
+    "scommaaccent",
+    "section",
+    "semicolon",
+    "seven",
+    "six",
+    "slash",
+    "space",
+    "sterling",
+    "summation",
+    "t",
+    "tcaron",
+    "tcommaaccent",
+    "thorn",
+    "three",
+    "threequarters",
+    "threesuperior",
+    "tilde",
+    "trademark",
+    "two",
+    "twosuperior",
+    "u",
+    "uacute",
+    "ucircumflex",
+    "udieresis",
+    "ugrave",
+    "uhungarumlaut",
+    "umacron",
+    "underscore",
+    "uogonek",
+    "uring",
+    "v",
+    "w",
+    "x",
+    "y",
+    "yacute",
+    "ydieresis",
+    "yen",
+    "z",
+    "zacute",
+    "zcaron",
+    "zdotaccent",
+    "zero",
+];
+
+///Helveticaの文字幅。(LATIN_GLYPH_NAMESの順。1000分の1em単位。)
+static HELVETICA_WIDTHS: [u16; 315] = [
+    667, 1000, 667, 667, 667, 667, 667, 667, 667, 667, 667, 667, 722, 722, 722, 722, 722, 722, 722,
+    612, 667, 667, 667, 667, 667, 667, 667, 667, 667, 722, 556, 611, 778, 778, 778, 722, 278, 278,
+    278, 278, 278, 278, 278, 278, 500, 667, 667, 556, 556, 556, 556, 556, 833, 722, 722, 722, 722,
+    722, 778, 1000, 778, 778, 778, 778, 778, 778, 778, 778, 667, 778, 722, 722, 722, 722, 667, 667,
+    667, 667, 667, 611, 611, 611, 667, 722, 722, 722, 722, 722, 722, 722, 722, 722, 667, 944, 667,
+    667, 667, 667, 611, 611, 611, 611, 556, 556, 556, 556, 333, 556, 889, 556, 556, 667, 556, 556,
+    469, 584, 389, 1015, 556, 556, 278, 260, 334, 334, 278, 278, 333, 260, 350, 500, 500, 333, 500,
+    500, 333, 556, 333, 278, 278, 250, 737, 556, 556, 556, 556, 643, 556, 400, 333, 584, 556, 333,
+    278, 556, 556, 556, 556, 556, 556, 556, 556, 1000, 556, 1000, 556, 556, 584, 556, 278, 333,
+    278, 500, 556, 500, 556, 556, 167, 556, 556, 556, 611, 333, 584, 549, 556, 556, 333, 333, 556,
+    333, 333, 222, 278, 278, 278, 278, 278, 222, 222, 500, 500, 222, 222, 299, 222, 584, 549, 584,
+    471, 222, 833, 333, 584, 556, 584, 556, 556, 556, 556, 556, 549, 556, 556, 556, 556, 556, 556,
+    944, 333, 556, 556, 556, 556, 834, 834, 333, 370, 365, 611, 556, 556, 537, 333, 333, 476, 889,
+    278, 278, 1000, 584, 584, 556, 556, 611, 355, 333, 333, 333, 222, 222, 222, 191, 333, 333, 453,
+    333, 333, 737, 333, 500, 500, 500, 500, 500, 556, 278, 556, 556, 278, 278, 556, 600, 278, 317,
+    278, 556, 556, 834, 333, 333, 1000, 556, 333, 556, 556, 556, 556, 556, 556, 556, 556, 556, 556,
+    500, 722, 500, 500, 500, 500, 556, 500, 500, 500, 500, 556,
+];
+
+///Helvetica-Boldの文字幅。(LATIN_GLYPH_NAMESの順。1000分の1em単位。)
+static HELVETICA_BOLD_WIDTHS: [u16; 315] = [
+    722, 1000, 722, 722, 722, 722, 722, 722, 722, 722, 722, 722, 722, 722, 722, 722, 722, 722, 722,
+    612, 667, 667, 667, 667, 667, 667, 667, 667, 667, 722, 556, 611, 778, 778, 778, 722, 278, 278,
+    278, 278, 278, 278, 278, 278, 556, 722, 722, 611, 611, 611, 611, 611, 833, 722, 722, 722, 722,
+    722, 778, 1000, 778, 778, 778, 778, 778, 778, 778, 778, 667, 778, 722, 722, 722, 722, 667, 667,
+    667, 667, 667, 611, 611, 611, 667, 722, 722, 722, 722, 722, 722, 722, 722, 722, 667, 944, 667,
+    667, 667, 667, 611, 611, 611, 611, 556, 556, 556, 556, 333, 556, 889, 556, 556, 722, 556, 556,
+    584, 584, 389, 975, 556, 611, 278, 280, 389, 389, 333, 333, 333, 280, 350, 556, 556, 333, 556,
+    556, 333, 556, 333, 333, 278, 250, 737, 556, 611, 556, 556, 743, 611, 400, 333, 584, 556, 333,
+    278, 556, 556, 556, 556, 556, 556, 556, 556, 1000, 556, 1000, 556, 556, 584, 611, 333, 333,
+    333, 611, 556, 611, 556, 556, 167, 611, 611, 611, 611, 333, 584, 549, 556, 556, 333, 333, 611,
+    333, 333, 278, 278, 278, 278, 278, 278, 278, 278, 556, 556, 278, 278, 400, 278, 584, 549, 584,
+    494, 278, 889, 333, 584, 611, 584, 611, 611, 611, 611, 556, 549, 611, 556, 611, 611, 611, 611,
+    944, 333, 611, 611, 611, 556, 834, 834, 333, 370, 365, 611, 611, 611, 556, 333, 333, 494, 889,
+    278, 278, 1000, 584, 584, 611, 611, 611, 474, 500, 500, 500, 278, 278, 278, 238, 389, 389, 549,
+    389, 389, 737, 333, 556, 556, 556, 556, 556, 556, 333, 556, 556, 278, 278, 556, 600, 333, 389,
+    333, 611, 556, 834, 333, 333, 1000, 556, 333, 611, 611, 611, 611, 611, 611, 611, 556, 611, 611,
+    556, 778, 556, 556, 556, 556, 556, 500, 500, 500, 500, 556,
+];
+
+///Times-Romanの文字幅。(LATIN_GLYPH_NAMESの順。1000分の1em単位。)
+static TIMES_ROMAN_WIDTHS: [u16; 315] = [
+    722, 889, 722, 722, 722, 722, 722, 722, 722, 722, 722, 667, 667, 667, 667, 667, 722, 722, 722,
+    612, 611, 611, 611, 611, 611, 611, 611, 611, 611, 722, 500, 556, 722, 722, 722, 722, 333, 333,
+    333, 333, 333, 333, 333, 333, 389, 722, 722, 611, 611, 611, 611, 611, 889, 722, 722, 722, 722,
+    722, 722, 889, 722, 722, 722, 722, 722, 722, 722, 722, 556, 722, 667, 667, 667, 667, 556, 556,
+    556, 556, 556, 611, 611, 611, 556, 722, 722, 722, 722, 722, 722, 722, 722, 722, 722, 944, 722,
+    722, 722, 722, 611, 611, 611, 611, 444, 444, 444, 444, 333, 444, 667, 444, 444, 778, 444, 444,
+    469, 541, 500, 921, 444, 500, 278, 200, 480, 480, 333, 333, 333, 200, 350, 444, 444, 333, 444,
+    444, 333, 500, 333, 278, 250, 250, 760, 500, 500, 500, 500, 588, 500, 400, 333, 564, 500, 333,
+    278, 444, 444, 444, 444, 444, 444, 444, 500, 1000, 444, 1000, 500, 444, 564, 500, 333, 333,
+    333, 556, 500, 556, 500, 500, 167, 500, 500, 500, 500, 333, 564, 549, 500, 500, 333, 333, 500,
+    333, 333, 278, 278, 278, 278, 278, 278, 278, 278, 500, 500, 278, 278, 344, 278, 564, 549, 564,
+    471, 278, 778, 333, 564, 500, 564, 500, 500, 500, 500, 500, 549, 500, 500, 500, 500, 500, 500,
+    722, 333, 500, 500, 500, 500, 750, 750, 300, 276, 310, 500, 500, 500, 453, 333, 333, 476, 833,
+    250, 250, 1000, 564, 564, 500, 444, 444, 408, 444, 444, 444, 333, 333, 333, 180, 333, 333, 453,
+    333, 333, 760, 333, 389, 389, 389, 389, 389, 500, 278, 500, 500, 278, 250, 500, 600, 278, 326,
+    278, 500, 500, 750, 300, 333, 980, 500, 300, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+    500, 722, 500, 500, 500, 500, 500, 444, 444, 444, 444, 500,
+];
+
+///Times-Boldの文字幅。(LATIN_GLYPH_NAMESの順。1000分の1em単位。)
+static TIMES_BOLD_WIDTHS: [u16; 315] = [
+    722, 1000, 722, 722, 722, 722, 722, 722, 722, 722, 722, 667, 722, 722, 722, 722, 722, 722, 722,
+    612, 667, 667, 667, 667, 667, 667, 667, 667, 667, 722, 500, 611, 778, 778, 778, 778, 389, 389,
+    389, 389, 389, 389, 389, 389, 500, 778, 778, 667, 667, 667, 667, 667, 944, 722, 722, 722, 722,
+    722, 778, 1000, 778, 778, 778, 778, 778, 778, 778, 778, 611, 778, 722, 722, 722, 722, 556, 556,
+    556, 556, 556, 667, 667, 667, 611, 722, 722, 722, 722, 722, 722, 722, 722, 722, 722, 1000, 722,
+    722, 722, 722, 667, 667, 667, 667, 500, 500, 500, 500, 333, 500, 722, 500, 500, 833, 500, 500,
+    581, 520, 500, 930, 500, 556, 278, 220, 394, 394, 333, 333, 333, 220, 350, 444, 444, 333, 444,
+    444, 333, 500, 333, 333, 250, 250, 747, 500, 556, 500, 500, 672, 556, 400, 333, 570, 500, 333,
+    278, 444, 444, 444, 444, 444, 444, 444, 500, 1000, 444, 1000, 500, 444, 570, 500, 333, 333,
+    333, 556, 500, 556, 500, 500, 167, 500, 500, 500, 556, 333, 570, 549, 500, 500, 333, 333, 556,
+    333, 333, 278, 278, 278, 278, 278, 278, 278, 333, 556, 556, 278, 278, 394, 278, 570, 549, 570,
+    494, 278, 833, 333, 570, 556, 570, 556, 556, 556, 556, 500, 549, 556, 500, 500, 500, 500, 500,
+    722, 333, 500, 500, 500, 500, 750, 750, 300, 300, 330, 500, 500, 556, 540, 333, 333, 494, 1000,
+    250, 250, 1000, 570, 570, 556, 500, 500, 555, 500, 500, 500, 333, 333, 333, 278, 444, 444, 549,
+    444, 444, 747, 333, 389, 389, 389, 389, 389, 500, 333, 500, 500, 278, 250, 500, 600, 333, 416,
+    333, 556, 500, 750, 300, 333, 1000, 500, 300, 556, 556, 556, 556, 556, 556, 556, 500, 556, 556,
+    500, 722, 500, 500, 500, 500, 500, 444, 444, 444, 444, 500,
+];
+
+///Times-Italicの文字幅。(LATIN_GLYPH_NAMESの順。1000分の1em単位。)
+static TIMES_ITALIC_WIDTHS: [u16; 315] = [
+    611, 889, 611, 611, 611, 611, 611, 611, 611, 611, 611, 611, 667, 667, 667, 667, 722, 722, 722,
+    612, 611, 611, 611, 611, 611, 611, 611, 611, 611, 722, 500, 611, 722, 722, 722, 722, 333, 333,
+    333, 333, 333, 333, 333, 333, 444, 667, 667, 556, 556, 611, 556, 556, 833, 667, 667, 667, 667,
+    667, 722, 944, 722, 722, 722, 722, 722, 722, 722, 722, 611, 722, 611, 611, 611, 611, 500, 500,
+    500, 500, 500, 556, 556, 556, 611, 722, 722, 722, 722, 722, 722, 722, 722, 722, 611, 833, 611,
+    556, 556, 556, 556, 556, 556, 556, 500, 500, 500, 500, 333, 500, 667, 500, 500, 778, 500, 500,
+    422, 541, 500, 920, 500, 500, 278, 275, 400, 400, 389, 389, 333, 275, 350, 444, 444, 333, 444,
+    444, 333, 500, 333, 333, 250, 250, 760, 500, 500, 500, 500, 544, 500, 400, 333, 675, 500, 333,
+    278, 444, 444, 444, 444, 444, 444, 444, 500, 889, 444, 889, 500, 444, 675, 500, 333, 389, 278,
+    500, 500, 500, 500, 500, 167, 500, 500, 500, 500, 333, 675, 549, 500, 500, 333, 333, 500, 333,
+    333, 278, 278, 278, 278, 278, 278, 278, 278, 444, 444, 278, 278, 300, 278, 675, 549, 675, 471,
+    278, 722, 333, 675, 500, 675, 500, 500, 500, 500, 500, 549, 500, 500, 500, 500, 500, 500, 667,
+    333, 500, 500, 500, 500, 750, 750, 300, 276, 310, 500, 500, 500, 523, 333, 333, 476, 833, 250,
+    250, 1000, 675, 675, 500, 500, 500, 420, 556, 556, 556, 333, 333, 333, 214, 389, 389, 453, 389,
+    389, 760, 333, 389, 389, 389, 389, 389, 500, 333, 500, 500, 278, 250, 500, 600, 278, 300, 278,
+    500, 500, 750, 300, 333, 980, 500, 300, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 444,
+    667, 444, 444, 444, 444, 500, 389, 389, 389, 389, 500,
+];
+
+///Times-BoldItalicの文字幅。(LATIN_GLYPH_NAMESの順。1000分の1em単位。)
+static TIMES_BOLD_ITALIC_WIDTHS: [u16; 315] = [
+    667, 944, 667, 667, 667, 667, 667, 667, 667, 667, 667, 667, 667, 667, 667, 667, 722, 722, 722,
+    612, 667, 667, 667, 667, 667, 667, 667, 667, 667, 722, 500, 667, 722, 722, 722, 778, 389, 389,
+    389, 389, 389, 389, 389, 389, 500, 667, 667, 611, 611, 611, 611, 611, 889, 722, 722, 722, 722,
+    722, 722, 944, 722, 722, 722, 722, 722, 722, 722, 722, 611, 722, 667, 667, 667, 667, 556, 556,
+    556, 556, 556, 611, 611, 611, 611, 722, 722, 722, 722, 722, 722, 722, 722, 722, 667, 889, 667,
+    611, 611, 611, 611, 611, 611, 611, 500, 500, 500, 500, 333, 500, 722, 500, 500, 778, 500, 500,
+    570, 570, 500, 832, 500, 500, 278, 220, 348, 348, 333, 333, 333, 220, 350, 444, 444, 333, 444,
+    444, 333, 500, 333, 333, 250, 250, 747, 500, 500, 500, 500, 608, 500, 400, 333, 570, 500, 333,
+    278, 444, 444, 444, 444, 444, 444, 444, 500, 1000, 444, 1000, 500, 444, 570, 500, 389, 389,
+    333, 556, 500, 556, 500, 500, 167, 500, 500, 500, 500, 333, 570, 549, 500, 500, 333, 333, 556,
+    333, 333, 278, 278, 278, 278, 278, 278, 278, 278, 500, 500, 278, 278, 382, 278, 570, 549, 606,
+    494, 278, 778, 333, 606, 576, 570, 556, 556, 556, 556, 500, 549, 556, 500, 500, 500, 500, 500,
+    722, 333, 500, 500, 500, 500, 750, 750, 300, 266, 300, 500, 500, 500, 500, 333, 333, 494, 833,
+    250, 250, 1000, 570, 570, 500, 500, 500, 555, 500, 500, 500, 333, 333, 333, 278, 389, 389, 549,
+    389, 389, 747, 333, 389, 389, 389, 389, 389, 500, 333, 500, 500, 278, 250, 500, 600, 278, 366,
+    278, 500, 500, 750, 300, 333, 1000, 500, 300, 556, 556, 556, 556, 556, 556, 556, 500, 556, 556,
+    444, 667, 500, 444, 444, 444, 500, 389, 389, 389, 389, 500,
+];
+
+///Symbolの組み込みのエンコーディングでコードに対応するUnicode。(未定義のコードは0。)
+static SYMBOL_UNICODE: [u16; 256] = [
+    0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0020, 0x0021, 0x2200, 0x0023,
+    0x2203, 0x0025, 0x0026, 0x220B, 0x0028, 0x0029, 0x2217, 0x002B, 0x002C, 0x2212, 0x002E, 0x002F,
+    0x0030, 0x0031, 0x0032, 0x0033, 0x0034, 0x0035, 0x0036, 0x0037, 0x0038, 0x0039, 0x003A, 0x003B,
+    0x003C, 0x003D, 0x003E, 0x003F, 0x2245, 0x0391, 0x0392, 0x03A7, 0x2206, 0x0395, 0x03A6, 0x0393,
+    0x0397, 0x0399, 0x03D1, 0x039A, 0x039B, 0x039C, 0x039D, 0x039F, 0x03A0, 0x0398, 0x03A1, 0x03A3,
+    0x03A4, 0x03A5, 0x03C2, 0x2126, 0x039E, 0x03A8, 0x0396, 0x005B, 0x2234, 0x005D, 0x22A5, 0x005F,
+    0xF8E5, 0x03B1, 0x03B2, 0x03C7, 0x03B4, 0x03B5, 0x03C6, 0x03B3, 0x03B7, 0x03B9, 0x03D5, 0x03BA,
+    0x03BB, 0x00B5, 0x03BD, 0x03BF, 0x03C0, 0x03B8, 0x03C1, 0x03C3, 0x03C4, 0x03C5, 0x03D6, 0x03C9,
+    0x03BE, 0x03C8, 0x03B6, 0x007B, 0x007C, 0x007D, 0x223C, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x0000, 0x0000, 0x20AC, 0x03D2, 0x2032, 0x2264, 0x2044, 0x221E, 0x0192, 0x2663,
+    0x2666, 0x2665, 0x2660, 0x2194, 0x2190, 0x2191, 0x2192, 0x2193, 0x00B0, 0x00B1, 0x2033, 0x2265,
+    0x00D7, 0x221D, 0x2202, 0x2022, 0x00F7, 0x2260, 0x2261, 0x2248, 0x2026, 0xF8E6, 0xF8E7, 0x21B5,
+    0x2135, 0x2111, 0x211C, 0x2118, 0x2297, 0x2295, 0x2205, 0x2229, 0x222A, 0x2283, 0x2287, 0x2284,
+    0x2282, 0x2286, 0x2208, 0x2209, 0x2220, 0x2207, 0xF6DA, 0xF6D9, 0xF6DB, 0x220F, 0x221A, 0x22C5,
+    0x00AC, 0x2227, 0x2228, 0x21D4, 0x21D0, 0x21D1, 0x21D2, 0x21D3, 0x25CA, 0x2329, 0xF8E8, 0xF8E9,
+    0xF8EA, 0x2211, 0xF8EB, 0xF8EC, 0xF8ED, 0xF8EE, 0xF8EF, 0xF8F0, 0xF8F1, 0xF8F2, 0xF8F3, 0xF8F4,
+    0x0000, 0x232A, 0x222B, 0x2320, 0xF8F5, 0x2321, 0xF8F6, 0xF8F7, 0xF8F8, 0xF8F9, 0xF8FA, 0xF8FB,
+    0xF8FC, 0xF8FD, 0xF8FE, 0x0000,
+];
+
+///Symbolのコードごとの文字幅。(1000分の1em単位。未定義のコードは0。)
+static SYMBOL_WIDTHS: [u16; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    250, 333, 713, 500, 549, 833, 778, 439, 333, 333, 500, 549, 250, 549, 250, 278, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 278, 278, 549, 549, 549, 444, 549, 722, 667, 722, 612, 611,
+    763, 603, 722, 333, 631, 722, 686, 889, 722, 722, 768, 741, 556, 592, 611, 690, 439, 768, 645,
+    795, 611, 333, 863, 333, 658, 500, 500, 631, 549, 549, 494, 439, 521, 411, 603, 329, 603, 549,
+    549, 576, 521, 549, 549, 521, 549, 603, 439, 576, 713, 686, 493, 686, 494, 480, 200, 480, 549,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 750, 620, 247, 549, 167, 713, 500, 753, 753, 753, 753, 1042, 987, 603, 987, 603, 400, 549,
+    411, 549, 549, 713, 494, 460, 549, 549, 549, 549, 1000, 603, 1000, 658, 823, 686, 795, 987,
+    768, 768, 823, 768, 768, 713, 713, 713, 713, 713, 713, 713, 768, 713, 790, 790, 890, 823, 549,
+    250, 713, 603, 603, 1042, 987, 603, 987, 603, 494, 329, 790, 790, 786, 713, 384, 384, 384, 384,
+    384, 384, 494, 494, 494, 494, 0, 329, 274, 686, 686, 686, 384, 384, 384, 384, 384, 384, 494,
+    494, 494, 0,
+];
+
+///ZapfDingbatsの組み込みのエンコーディングでコードに対応するUnicode。(未定義のコードは0。)
+static ZAPF_DINGBATS_UNICODE: [u16; 256] = [
+    0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0020, 0x2701, 0x2702, 0x2703,
+    0x2704, 0x260E, 0x2706, 0x2707, 0x2708, 0x2709, 0x261B, 0x261E, 0x270C, 0x270D, 0x270E, 0x270F,
+    0x2710, 0x2711, 0x2712, 0x2713, 0x2714, 0x2715, 0x2716, 0x2717, 0x2718, 0x2719, 0x271A, 0x271B,
+    0x271C, 0x271D, 0x271E, 0x271F, 0x2720, 0x2721, 0x2722, 0x2723, 0x2724, 0x2725, 0x2726, 0x2727,
+    0x2605, 0x2729, 0x272A, 0x272B, 0x272C, 0x272D, 0x272E, 0x272F, 0x2730, 0x2731, 0x2732, 0x2733,
+    0x2734, 0x2735, 0x2736, 0x2737, 0x2738, 0x2739, 0x273A, 0x273B, 0x273C, 0x273D, 0x273E, 0x273F,
+    0x2740, 0x2741, 0x2742, 0x2743, 0x2744, 0x2745, 0x2746, 0x2747, 0x2748, 0x2749, 0x274A, 0x274B,
+    0x25CF, 0x274D, 0x25A0, 0x274F, 0x2750, 0x2751, 0x2752, 0x25B2, 0x25BC, 0x25C6, 0x2756, 0x25D7,
+    0x2758, 0x2759, 0x275A, 0x275B, 0x275C, 0x275D, 0x275E, 0x0000, 0x2768, 0x2769, 0x276A, 0x276B,
+    0x276C, 0x276D, 0x276E, 0x276F, 0x2770, 0x2771, 0x2772, 0x2773, 0x2774, 0x2775, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,
+    0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x2761, 0x2762, 0x2763, 0x2764, 0x2765, 0x2766, 0x2767,
+    0x2663, 0x2666, 0x2665, 0x2660, 0x2460, 0x2461, 0x2462, 0x2463, 0x2464, 0x2465, 0x2466, 0x2467,
+    0x2468, 0x2469, 0x2776, 0x2777, 0x2778, 0x2779, 0x277A, 0x277B, 0x277C, 0x277D, 0x277E, 0x277F,
+    0x2780, 0x2781, 0x2782, 0x2783, 0x2784, 0x2785, 0x2786, 0x2787, 0x2788, 0x2789, 0x278A, 0x278B,
+    0x278C, 0x278D, 0x278E, 0x278F, 0x2790, 0x2791, 0x2792, 0x2793, 0x2794, 0x2192, 0x2194, 0x2195,
+    0x2798, 0x2799, 0x279A, 0x279B, 0x279C, 0x279D, 0x279E, 0x279F, 0x27A0, 0x27A1, 0x27A2, 0x27A3,
+    0x27A4, 0x27A5, 0x27A6, 0x27A7, 0x27A8, 0x27A9, 0x27AA, 0x27AB, 0x27AC, 0x27AD, 0x27AE, 0x27AF,
+    0x0000, 0x27B1, 0x27B2, 0x27B3, 0x27B4, 0x27B5, 0x27B6, 0x27B7, 0x27B8, 0x27B9, 0x27BA, 0x27BB,
+    0x27BC, 0x27BD, 0x27BE, 0x0000,
+];
+
+///ZapfDingbatsのコードごとの文字幅。(1000分の1em単位。未定義のコードは0。)
+static ZAPF_DINGBATS_WIDTHS: [u16; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    278, 974, 961, 974, 980, 719, 789, 790, 791, 690, 960, 939, 549, 855, 911, 933, 911, 945, 974,
+    755, 846, 762, 761, 571, 677, 763, 760, 759, 754, 494, 552, 537, 577, 692, 786, 788, 788, 790,
+    793, 794, 816, 823, 789, 841, 823, 833, 816, 831, 923, 744, 723, 749, 790, 792, 695, 776, 768,
+    792, 759, 707, 708, 682, 701, 826, 815, 789, 789, 707, 687, 696, 689, 786, 787, 713, 791, 785,
+    791, 873, 761, 762, 762, 759, 759, 892, 892, 788, 784, 438, 138, 277, 415, 392, 392, 668, 668,
+    0, 390, 390, 317, 317, 276, 276, 509, 509, 410, 410, 234, 234, 334, 334, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 732, 544, 544, 910, 667, 760, 760, 776, 595, 694, 626, 788,
+    788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788,
+    788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788,
+    788, 894, 838, 1016, 458, 748, 924, 748, 918, 927, 928, 928, 834, 873, 828, 924, 924, 917, 930,
+    931, 463, 883, 836, 836, 867, 867, 696, 696, 874, 0, 874, 760, 946, 771, 865, 771, 888, 967,
+    888, 831, 873, 927, 970, 918, 0,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ttf_parser::Face;
+
+    #[test]
+    fn test_select() {
+        let font = StandardFont::select(Some("Helvetica-BoldOblique"));
+        assert_eq!(
+            (font.family, font.bold, font.italic, font.exact),
+            (Family::Helvetica, true, true, true)
+        );
+        let font = StandardFont::select(Some("ABCDEF+TimesNewRomanPS-ItalicMT"));
+        assert_eq!(
+            (font.family, font.bold, font.italic, font.exact),
+            (Family::Times, false, true, true)
+        );
+        let font = StandardFont::select(Some("Courier"));
+        assert_eq!((font.family, font.exact), (Family::Courier, true));
+        let font = StandardFont::select(Some("Symbol"));
+        assert_eq!((font.family, font.exact), (Family::Symbol, true));
+        let font = StandardFont::select(Some("Georgia,Bold"));
+        assert_eq!(
+            (font.family, font.bold, font.exact),
+            (Family::Times, true, false)
+        );
+        let font = StandardFont::select(None);
+        assert_eq!((font.family, font.exact), (Family::Helvetica, false));
+    }
+
+    #[test]
+    fn test_width() {
+        assert!(LATIN_GLYPH_NAMES.windows(2).all(|pair| pair[0] < pair[1]));
+        let helvetica = StandardFont::select(Some("Helvetica"));
+        assert_eq!(helvetica.width(b'A', None), Some(667.0));
+        assert_eq!(helvetica.width(b' ', None), Some(278.0));
+        assert_eq!(helvetica.width(0xE9, Some("eacute")), Some(556.0));
+        let times = StandardFont::select(Some("Times-Roman"));
+        assert_eq!(times.width(b'A', None), Some(722.0));
+        let courier = StandardFont::select(Some("Courier-Bold"));
+        assert_eq!(courier.width(b'i', None), Some(600.0));
+        let symbol = StandardFont::select(Some("Symbol"));
+        assert_eq!(symbol.width(b'a', None), Some(631.0));
+        assert_eq!(symbol.char(b'a', None), Some('α'));
+        let dingbats = StandardFont::select(Some("ZapfDingbats"));
+        assert_eq!(dingbats.char(0x21, None), Some('\u{2701}'));
+        //置き換えたフォントの文字幅は不明。
+        assert_eq!(
+            StandardFont::select(Some("Verdana")).width(b'A', None),
+            None
+        );
+    }
+
+    #[test]
+    ///代替フォントの全てのフォントプログラムで、標準14フォントの文字の輪郭を取得できることを確認する。
+    fn test_program() {
+        let mut fonts: Vec<StandardFont> = Vec::new();
+        for name in ["Helvetica", "Times", "Courier"] {
+            for style in ["", "-Bold", "-Italic", "-BoldItalic"] {
+                fonts.push(StandardFont::select(Some(&format!("{}{}", name, style))));
+            }
+        }
+        fonts.push(StandardFont::select(Some("Symbol")));
+        fonts.push(StandardFont::select(Some("ZapfDingbats")));
+        for font in fonts {
+            let face = Face::parse(font.program(), 0).unwrap();
+            for (code, name) in [(b'A', None), (0xE9, Some("eacute")), (b'a', None)] {
+                let c: char = font.char(code, name).unwrap();
+                let glyph_id = face.glyph_index(c).unwrap();
+                assert!(
+                    face.glyph_bounding_box(glyph_id).is_some(),
+                    "{:?} {}",
+                    font,
+                    c
+                );
+            }
+        }
+    }
+}